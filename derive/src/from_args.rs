@@ -0,0 +1,275 @@
+use crate::util::{attr_name, rustpython_path, Options};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Expr, Field, Fields, Ident, Result};
+
+/// How the value of a field is passed.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum ParameterKind {
+    PositionalOnly,
+    PositionalOrKeyword,
+    KeywordOnly,
+    /// The positional arguments no parameter takes, like `*args`.
+    Args,
+    /// The keyword arguments no parameter takes, like `**kwargs`.
+    KwArgs,
+}
+
+impl ParameterKind {
+    fn from_ident(ident: &Ident) -> Option<Self> {
+        match ident.to_string().as_str() {
+            "positional_only" => Some(ParameterKind::PositionalOnly),
+            "positional_or_keyword" => Some(ParameterKind::PositionalOrKeyword),
+            "keyword_only" => Some(ParameterKind::KeywordOnly),
+            "args" => Some(ParameterKind::Args),
+            "kwargs" => Some(ParameterKind::KwArgs),
+            _ => None,
+        }
+    }
+
+    fn is_positional(self) -> bool {
+        self == ParameterKind::PositionalOnly || self == ParameterKind::PositionalOrKeyword
+    }
+}
+
+/// What a missing argument turns into.
+enum DefaultValue {
+    /// It has to be given.
+    Required,
+    /// `OptionalArg::Missing`, and `OptionalArg::Present` for a given one.
+    Optional,
+    Value(TokenStream2),
+}
+
+struct Parameter<'a> {
+    field: &'a Field,
+    kind: ParameterKind,
+    default: DefaultValue,
+}
+
+impl<'a> Parameter<'a> {
+    fn from_field(field: &'a Field) -> Result<Self> {
+        let mut kind = None;
+        let mut default = DefaultValue::Required;
+        for attr in &field.attrs {
+            if attr_name(attr).map_or(true, |name| name != "pyarg") {
+                continue;
+            }
+            let options = Options::from_attr(
+                attr,
+                &[
+                    "positional_only",
+                    "positional_or_keyword",
+                    "keyword_only",
+                    "args",
+                    "kwargs",
+                    "optional",
+                    "default",
+                ],
+                &["default"],
+            )?;
+            for word in options.words() {
+                if let Some(word_kind) = ParameterKind::from_ident(word) {
+                    if kind.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            word,
+                            "only one kind of parameter can be given",
+                        ));
+                    }
+                    kind = Some(word_kind);
+                }
+            }
+            if options.has("optional") {
+                default = DefaultValue::Optional;
+            } else if options.has("default") {
+                default = DefaultValue::Value(quote!(::std::default::Default::default()));
+            } else if let Some(expr) = options.value("default") {
+                let expr: Expr = expr.parse()?;
+                default = DefaultValue::Value(quote!(#expr));
+            }
+        }
+        let parameter = Parameter {
+            field,
+            kind: kind.unwrap_or(ParameterKind::PositionalOrKeyword),
+            default,
+        };
+        if parameter.is_variadic() && !parameter.is_required() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`args` and `kwargs` can't have a default",
+            ));
+        }
+        Ok(parameter)
+    }
+
+    fn is_variadic(&self) -> bool {
+        self.kind == ParameterKind::Args || self.kind == ParameterKind::KwArgs
+    }
+
+    fn is_required(&self) -> bool {
+        match self.default {
+            DefaultValue::Required => true,
+            _ => false,
+        }
+    }
+}
+
+pub fn impl_from_args(input: DeriveInput) -> Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "FromArgs can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromArgs can only be derived for structs",
+            ))
+        }
+    };
+    let mut function_name = None;
+    for attr in &input.attrs {
+        if attr_name(attr).map_or(true, |name| name != "pyarg") {
+            continue;
+        }
+        function_name = Options::from_attr(attr, &[], &["name"])?.name();
+    }
+    let parameters = fields
+        .iter()
+        .map(Parameter::from_field)
+        .collect::<Result<Vec<_>>>()?;
+
+    // The positional parameters follow python's rules for their order.
+    let mut last_positional: Option<&Parameter> = None;
+    let mut varargs = false;
+    let mut varkwargs = false;
+    for parameter in &parameters {
+        match parameter.kind {
+            ParameterKind::Args if varargs => {
+                return Err(syn::Error::new_spanned(
+                    parameter.field,
+                    "only one field can take `args`",
+                ))
+            }
+            ParameterKind::Args => varargs = true,
+            ParameterKind::KwArgs if varkwargs => {
+                return Err(syn::Error::new_spanned(
+                    parameter.field,
+                    "only one field can take `kwargs`",
+                ))
+            }
+            ParameterKind::KwArgs => varkwargs = true,
+            kind if kind.is_positional() => {
+                if let Some(last) = last_positional {
+                    if last.kind > kind {
+                        return Err(syn::Error::new_spanned(
+                            parameter.field,
+                            "positional only parameters must come before the other positional ones",
+                        ));
+                    }
+                    if !last.is_required() && parameter.is_required() {
+                        return Err(syn::Error::new_spanned(
+                            parameter.field,
+                            "a required positional parameter can't follow one with a default",
+                        ));
+                    }
+                }
+                last_positional = Some(parameter);
+            }
+            _ => {}
+        }
+    }
+
+    let rustpython_vm = rustpython_path();
+    let named: Vec<_> = parameters
+        .iter()
+        .filter(|parameter| !parameter.is_variadic())
+        .collect();
+    let descriptions = named.iter().map(|parameter| {
+        let name = parameter.field.ident.as_ref().unwrap().to_string();
+        let kind = match parameter.kind {
+            ParameterKind::PositionalOnly => quote!(PositionalOnly),
+            ParameterKind::PositionalOrKeyword => quote!(PositionalOrKeyword),
+            _ => quote!(KeywordOnly),
+        };
+        let required = parameter.is_required();
+        quote! {
+            #rustpython_vm::function::Parameter {
+                name: #name,
+                kind: #rustpython_vm::function::ParameterKind::#kind,
+                required: #required,
+            }
+        }
+    });
+    let positional_count = named
+        .iter()
+        .filter(|parameter| parameter.kind.is_positional())
+        .count();
+    let required_positional_count = named
+        .iter()
+        .filter(|parameter| parameter.kind.is_positional() && parameter.is_required())
+        .count();
+
+    let field_values = parameters.iter().map(|parameter| {
+        let ident = &parameter.field.ident;
+        if parameter.is_variadic() {
+            return quote! {
+                #ident: #rustpython_vm::function::FromArgs::from_args(vm, args)?
+            };
+        }
+        let convert = quote! {
+            #rustpython_vm::pyobject::TryFromObject::try_from_object(vm, value)?
+        };
+        let (given, missing) = match &parameter.default {
+            DefaultValue::Required => (convert, quote!(unreachable!())),
+            DefaultValue::Optional => (
+                quote!(#rustpython_vm::function::OptionalArg::Present(#convert)),
+                quote!(#rustpython_vm::function::OptionalArg::Missing),
+            ),
+            DefaultValue::Value(default) => (convert, default.clone()),
+        };
+        quote! {
+            #ident: match values.next().unwrap() {
+                Some(value) => #given,
+                None => #missing,
+            }
+        }
+    });
+
+    let function_name = match function_name {
+        Some(function_name) => quote!(Some(#function_name)),
+        None => quote!(None),
+    };
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #rustpython_vm::function::FromArgs for #name #ty_generics #where_clause {
+            fn arity() -> ::std::ops::RangeInclusive<usize> {
+                #required_positional_count..=#positional_count
+            }
+
+            fn from_args(
+                vm: &#rustpython_vm::vm::VirtualMachine,
+                args: &mut #rustpython_vm::function::PyFuncArgs
+            ) -> Result<Self, #rustpython_vm::function::ArgumentError> {
+                const PARAMETERS: &[#rustpython_vm::function::Parameter] = &[#(#descriptions),*];
+                #[allow(unused_mut, unused_variables)]
+                let mut values = #rustpython_vm::function::bind_parameters(
+                    vm,
+                    args,
+                    #function_name,
+                    PARAMETERS,
+                    #varargs,
+                    #varkwargs,
+                )?
+                .into_iter();
+                Ok(#name { #(#field_values,)* })
+            }
+        }
+    })
+}
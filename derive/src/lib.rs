@@ -1,48 +1,66 @@
+#![recursion_limit = "128"]
+
 extern crate proc_macro;
 
+mod from_args;
+mod pyclass;
+mod util;
+
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
-use syn::{Data, DeriveInput, Fields};
+use syn::{parse_macro_input, AttributeArgs, DeriveInput, Item};
 
-#[proc_macro_derive(FromArgs)]
+/// Bind the arguments of a native function to the fields of a struct, which
+/// are python parameters named after them. How each of them is passed is set
+/// with `#[pyarg(...)]`:
+///
+/// - `positional_only`, `positional_or_keyword` (the default) or
+///   `keyword_only`; the positional parameters are taken in the order of the
+///   fields.
+/// - `args` or `kwargs` for an `Args` or `KwArgs` field taking the arguments
+///   no other parameter takes, like `*args` and `**kwargs`.
+/// - `default = "expression"` for the value of a missing argument, or just
+///   `default` for `Default::default()`.
+/// - `optional` for an `OptionalArg` field.
+///
+/// Arguments that can't be bound raise the same `TypeError`s python does,
+/// which start with `f() ` when the struct has `#[pyarg(name = "f")]`.
+#[proc_macro_derive(FromArgs, attributes(pyarg))]
 pub fn derive_from_args(input: TokenStream) -> TokenStream {
-    let ast: DeriveInput = syn::parse(input).unwrap();
+    let input = parse_macro_input!(input as DeriveInput);
+    result_to_tokens(from_args::impl_from_args(input))
+}
 
-    let gen = impl_from_args(&ast);
-    gen.to_string().parse().unwrap()
+/// Make a struct the payload of a python class, named after the struct unless
+/// given with `#[pyclass(name = "...")]`. Its doc comment becomes the
+/// docstring of the class.
+///
+/// The struct still needs a `PyValue` impl, and the class gets its methods
+/// from a `#[pymethods]` block.
+#[proc_macro_attribute]
+pub fn pyclass(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr = parse_macro_input!(attr as AttributeArgs);
+    let item = parse_macro_input!(item as Item);
+    result_to_tokens(pyclass::impl_pyclass(attr, item))
 }
 
-fn impl_from_args(input: &DeriveInput) -> TokenStream2 {
-    // FIXME: This references types using `crate` instead of `rustpython_vm`
-    //        so that it can be used in the latter. How can we support both?
-    let fields = match input.data {
-        Data::Struct(ref data) => {
-            match data.fields {
-                Fields::Named(ref fields) => fields.named.iter().map(|field| {
-                    let name = &field.ident;
-                    quote! {
-                        #name: crate::pyobject::TryFromObject::try_from_object(
-                            vm,
-                            args.take_keyword(stringify!(#name)).unwrap_or_else(|| vm.ctx.none())
-                        )?,
-                    }
-                }),
-                Fields::Unnamed(_) | Fields::Unit => unimplemented!(), // TODO: better error message
-            }
-        }
-        Data::Enum(_) | Data::Union(_) => unimplemented!(), // TODO: better error message
-    };
+/// Collect the python methods and properties of a `#[pyclass]` from an
+/// `impl` block, for `PyClassImpl::make_class` to create the class with:
+///
+/// - `#[pymethod]` makes a method, named `name = "..."` or after the
+///   function, with `magic` adding the double underscores. Slots like
+///   `__new__` and `__add__` are methods as well.
+/// - `#[pyproperty]` makes the function the getter of a property, and
+///   `#[pyproperty(setter)]` a `set_<name>` function its setter.
+///
+/// The block can be on the payload or on its `PyRef`.
+#[proc_macro_attribute]
+pub fn pymethods(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr = parse_macro_input!(attr as AttributeArgs);
+    let item = parse_macro_input!(item as Item);
+    result_to_tokens(pyclass::impl_pymethods(attr, item))
+}
 
-    let name = &input.ident;
-    quote! {
-        impl crate::function::FromArgs for #name {
-            fn from_args(
-                vm: &crate::vm::VirtualMachine,
-                args: &mut crate::function::PyFuncArgs
-            ) -> Result<Self, crate::function::ArgumentError> {
-                Ok(#name { #(#fields)* })
-            }
-        }
-    }
+fn result_to_tokens(result: syn::Result<TokenStream2>) -> TokenStream {
+    result.unwrap_or_else(|err| err.to_compile_error()).into()
 }
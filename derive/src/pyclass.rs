@@ -0,0 +1,194 @@
+use crate::util::{attr_name, rustpython_path, Options};
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use std::collections::HashMap;
+use syn::{Attribute, AttributeArgs, Ident, ImplItem, Item, Lit, Meta, Result};
+
+/// The docstring made of the `///` comments of an item.
+fn doc_string(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr_name(attr).map_or(false, |name| name == "doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(name_value)) => match name_value.lit {
+                Lit::Str(line) => Some(line.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| {
+            if line.starts_with(' ') {
+                line[1..].to_string()
+            } else {
+                line
+            }
+        })
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+pub fn impl_pyclass(attr: AttributeArgs, item: Item) -> Result<TokenStream2> {
+    let (ident, generics, attrs) = match &item {
+        Item::Struct(item) => (&item.ident, &item.generics, &item.attrs),
+        Item::Enum(item) => (&item.ident, &item.generics, &item.attrs),
+        item => {
+            return Err(syn::Error::new_spanned(
+                item,
+                "#[pyclass] can only be used on a struct or an enum",
+            ))
+        }
+    };
+    let options = Options::parse(&attr, &[], &["name"])?;
+    let name = options.name().unwrap_or_else(|| ident.to_string());
+    let doc = match doc_string(attrs) {
+        Some(doc) => quote!(Some(#doc)),
+        None => quote!(None),
+    };
+
+    let rustpython_vm = rustpython_path();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        #item
+
+        impl #impl_generics #rustpython_vm::pyobject::PyClassDef for #ident #ty_generics #where_clause {
+            const NAME: &'static str = #name;
+            const DOC: Option<&'static str> = #doc;
+        }
+    })
+}
+
+#[derive(Default)]
+struct Property {
+    getter: Option<Ident>,
+    setter: Option<Ident>,
+}
+
+pub fn impl_pymethods(attr: AttributeArgs, item: Item) -> Result<TokenStream2> {
+    if let Some(meta) = attr.first() {
+        return Err(syn::Error::new_spanned(
+            meta,
+            "#[pymethods] doesn't take options",
+        ));
+    }
+    let mut imp = match item {
+        Item::Impl(imp) => imp,
+        item => {
+            return Err(syn::Error::new_spanned(
+                item,
+                "#[pymethods] can only be used on an impl block",
+            ))
+        }
+    };
+
+    let rustpython_vm = rustpython_path();
+    let mut methods = vec![];
+    // Properties in the order their getter or setter first appeared.
+    let mut property_names: Vec<String> = vec![];
+    let mut properties: HashMap<String, Property> = HashMap::new();
+    for item in imp.items.iter_mut() {
+        let method = match item {
+            ImplItem::Method(method) => method,
+            _ => continue,
+        };
+        let fn_name = method.sig.ident.clone();
+        let mut kept_attrs = vec![];
+        for attr in method.attrs.drain(..) {
+            match attr_name(&attr).as_ref().map(String::as_str) {
+                Some("pymethod") => {
+                    let options = Options::from_attr(&attr, &["magic"], &["name"])?;
+                    let name = match options.name() {
+                        Some(name) => name,
+                        None if options.has("magic") => format!("__{}__", fn_name),
+                        None => fn_name.to_string(),
+                    };
+                    methods.push(quote! {
+                        ctx.set_attr(class, #name, ctx.new_rustfunc(Self::#fn_name));
+                    });
+                }
+                Some("pyproperty") => {
+                    let options = Options::from_attr(&attr, &["setter"], &["name"])?;
+                    let setter = options.has("setter");
+                    let name = match options.name() {
+                        Some(name) => name,
+                        None if !setter => fn_name.to_string(),
+                        None => {
+                            let fn_name = fn_name.to_string();
+                            if !fn_name.starts_with("set_") {
+                                return Err(syn::Error::new_spanned(
+                                    &method.sig.ident,
+                                    "the name of a property setter must start with `set_` \
+                                     unless it is given with `name = \"...\"`",
+                                ));
+                            }
+                            fn_name["set_".len()..].to_string()
+                        }
+                    };
+                    if !properties.contains_key(&name) {
+                        property_names.push(name.clone());
+                    }
+                    let property = properties.entry(name.clone()).or_default();
+                    let slot = if setter {
+                        &mut property.setter
+                    } else {
+                        &mut property.getter
+                    };
+                    if slot.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            &method.sig.ident,
+                            format!("property `{}` is defined twice", name),
+                        ));
+                    }
+                    *slot = Some(fn_name.clone());
+                }
+                _ => kept_attrs.push(attr),
+            }
+        }
+        method.attrs = kept_attrs;
+    }
+
+    for name in property_names {
+        let property = &properties[&name];
+        let getter = match &property.getter {
+            Some(getter) => getter,
+            None => {
+                return Err(syn::Error::new(
+                    Span::call_site(),
+                    format!("property `{}` has a setter but no getter", name),
+                ))
+            }
+        };
+        let setter = property
+            .setter
+            .as_ref()
+            .map(|setter| quote!(.add_setter(Self::#setter)));
+        methods.push(quote! {
+            ctx.set_attr(
+                class,
+                #name,
+                #rustpython_vm::obj::objproperty::PropertyBuilder::new(ctx)
+                    .add_getter(Self::#getter)
+                    #setter
+                    .create(),
+            );
+        });
+    }
+
+    let ty = &imp.self_ty;
+    let (impl_generics, _, where_clause) = imp.generics.split_for_impl();
+    Ok(quote! {
+        #imp
+
+        impl #impl_generics #rustpython_vm::pyobject::PyClassImpl for #ty #where_clause {
+            fn impl_extend_class(
+                ctx: &#rustpython_vm::pyobject::PyContext,
+                class: &#rustpython_vm::obj::objtype::PyClassRef,
+            ) {
+                #(#methods)*
+            }
+        }
+    })
+}
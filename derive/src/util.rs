@@ -0,0 +1,103 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Attribute, Ident, Lit, LitStr, Meta, NestedMeta, Result};
+
+/// The path of the `rustpython_vm` crate, which is `crate` inside of it.
+pub fn rustpython_path() -> TokenStream2 {
+    let inside_vm = std::env::var("CARGO_PKG_NAME")
+        .map(|name| name == "rustpython_vm")
+        .unwrap_or(false);
+    if inside_vm {
+        quote!(crate)
+    } else {
+        quote!(::rustpython_vm)
+    }
+}
+
+pub fn attr_name(attr: &Attribute) -> Option<String> {
+    if attr.path.segments.len() == 1 {
+        Some(attr.path.segments[0].ident.to_string())
+    } else {
+        None
+    }
+}
+
+/// The `key = "..."` and bare word options of a `#[py...(...)]` attribute.
+#[derive(Default)]
+pub struct Options {
+    values: Vec<(Ident, LitStr)>,
+    words: Vec<Ident>,
+}
+
+impl Options {
+    pub fn parse(
+        nested: &[NestedMeta],
+        allowed_words: &[&str],
+        allowed_keys: &[&str],
+    ) -> Result<Self> {
+        let mut options = Options::default();
+        for meta in nested {
+            match meta {
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if allowed_keys.contains(&name_value.ident.to_string().as_str()) =>
+                {
+                    match &name_value.lit {
+                        Lit::Str(value) => options
+                            .values
+                            .push((name_value.ident.clone(), value.clone())),
+                        lit => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                format!("`{}` must be a string", name_value.ident),
+                            ))
+                        }
+                    }
+                }
+                NestedMeta::Meta(Meta::Word(word))
+                    if allowed_words.contains(&word.to_string().as_str()) =>
+                {
+                    options.words.push(word.clone())
+                }
+                meta => return Err(syn::Error::new_spanned(meta, "unknown option")),
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn from_attr(
+        attr: &Attribute,
+        allowed_words: &[&str],
+        allowed_keys: &[&str],
+    ) -> Result<Self> {
+        match attr.parse_meta()? {
+            Meta::Word(_) => Ok(Options::default()),
+            Meta::List(list) => {
+                let nested: Vec<_> = list.nested.into_iter().collect();
+                Options::parse(&nested, allowed_words, allowed_keys)
+            }
+            meta @ Meta::NameValue(_) => {
+                Err(syn::Error::new_spanned(meta, "expected a list of options"))
+            }
+        }
+    }
+
+    pub fn has(&self, word: &str) -> bool {
+        self.words.iter().any(|ident| ident == word)
+    }
+
+    pub fn words(&self) -> &[Ident] {
+        &self.words
+    }
+
+    pub fn value(&self, key: &str) -> Option<&LitStr> {
+        self.values
+            .iter()
+            .find(|(ident, _)| ident == key)
+            .map(|(_, value)| value)
+    }
+
+    /// The `name = "..."` option.
+    pub fn name(&self) -> Option<String> {
+        self.value("name").map(LitStr::value)
+    }
+}
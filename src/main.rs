@@ -10,14 +10,38 @@ extern crate rustyline;
 use clap::{App, Arg};
 use rustpython_parser::error::ParseError;
 use rustpython_vm::{
-    compile, error::CompileError, frame::Scope, import, obj::objstr, print_exception,
-    pyobject::PyResult, util, VirtualMachine,
+    compile,
+    error::CompileError,
+    frame::Scope,
+    import,
+    obj::{objstr, objtype},
+    print_exception,
+    profiler::Profiler,
+    pyobject::{DictProtocol, IdProtocol, PyObjectRef, PyResult, TryFromObject},
+    signal, util, write_stderr, Settings, VirtualMachine, THREAD_STACK_SIZE,
 };
 use rustyline::{error::ReadlineError, Editor};
+use std::fs::File;
 use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::thread;
 
 fn main() {
+    // The main thread's stack may be too small for python code to reach the
+    // recursion limit, so the interpreter runs in a thread of its own.
+    let interpreter = thread::Builder::new()
+        .stack_size(THREAD_STACK_SIZE)
+        .spawn(run)
+        .expect("the interpreter thread starts");
+    signal::block_interrupt_signals();
+    let exit_code = interpreter.join().unwrap_or(101);
+    process::exit(exit_code);
+}
+
+fn run() -> i32 {
     env_logger::init();
+    signal::install_interrupt_handler();
     let matches = App::new("RustPython")
         .version(crate_version!())
         .author(crate_authors!())
@@ -41,53 +65,186 @@ fn main() {
                 .takes_value(true)
                 .help("run library module as script"),
         )
+        .arg(
+            Arg::with_name("optimize")
+                .short("O")
+                .multiple(true)
+                .help("remove assert statements and set __debug__ to False"),
+        )
+        .arg(
+            Arg::with_name("ignore_environment")
+                .short("E")
+                .help("ignore PYTHON* environment variables such as PYTHONPATH"),
+        )
+        .arg(
+            Arg::with_name("debug")
+                .long("debug")
+                .help("start the program in the interactive debugger"),
+        )
+        .arg(
+            Arg::with_name("flamegraph")
+                .long("flamegraph")
+                .takes_value(true)
+                .value_name("FILE")
+                .help(
+                    "profile the program and write its call stacks to FILE, for flamegraph tools",
+                ),
+        )
         .arg(Arg::from_usage("[pyargs] 'args for python'").multiple(true))
         .get_matches();
 
     // Construct vm:
-    let vm = VirtualMachine::new();
+    let vm = VirtualMachine::new_with_settings(create_settings(&matches));
+    if matches.is_present("debug") {
+        vm.set_trace();
+    }
+    let flamegraph = matches.value_of("flamegraph");
+    if flamegraph.is_some() {
+        vm.set_profiler(Some(Rc::new(Profiler::new())));
+    }
+
+    let scope = create_main_module(&vm);
 
     // Figure out if a -c option was given:
     let result = if let Some(command) = matches.value_of("c") {
-        run_command(&vm, command.to_string())
+        run_command(&vm, scope, command.to_string())
     } else if let Some(module) = matches.value_of("m") {
         run_module(&vm, module)
     } else {
         // Figure out if a script was passed:
         match matches.value_of("script") {
-            None => run_shell(&vm),
-            Some(filename) => run_script(&vm, filename),
+            None => run_shell(&vm, scope),
+            Some(filename) => run_script(&vm, scope, filename),
         }
     };
 
+    if let Some(path) = flamegraph {
+        write_flamegraph(&vm, path);
+    }
+
     // See if any exception leaked out:
-    handle_exception(&vm, result);
+    let exit_code = handle_exception(&vm, result);
+    vm.finalize();
+    exit_code
+}
+
+fn create_settings(matches: &clap::ArgMatches) -> Settings {
+    // sys.argv[0] is the script, "-c" for a command or the module name.
+    // Along with those, the first argument ends up in the script slot.
+    let mut argv = if matches.is_present("c") || matches.is_present("m") {
+        let argv0 = matches.value_of("m").unwrap_or("-c");
+        let mut argv = vec![argv0.to_string()];
+        argv.extend(matches.value_of("script").map(str::to_string));
+        argv
+    } else {
+        vec![matches.value_of("script").unwrap_or("").to_string()]
+    };
+    if let Some(pyargs) = matches.values_of("pyargs") {
+        argv.extend(pyargs.map(str::to_string));
+    }
+
+    Settings {
+        argv,
+        ignore_environment: matches.is_present("ignore_environment"),
+        optimize: matches.occurrences_of("optimize") as u8,
+        ..Default::default()
+    }
+}
+
+/// Create the `__main__` module, whose namespace the command, the script or
+/// the shell runs in.
+fn create_main_module(vm: &VirtualMachine) -> Scope {
+    let scope = vm.ctx.new_scope();
+    scope
+        .globals
+        .set_item(&vm.ctx, "__name__", vm.new_str("__main__".to_string()));
+    let module = vm.ctx.new_module("__main__", scope.globals.clone());
+    if let Ok(modules) = vm.get_attribute(vm.sys_module.clone(), "modules") {
+        modules.set_item(&vm.ctx, "__main__", module);
+    }
+    scope
 }
 
-fn _run_string(vm: &VirtualMachine, source: &str, source_path: String) -> PyResult {
+fn _run_string(vm: &VirtualMachine, scope: Scope, source: &str, source_path: String) -> PyResult {
     let code_obj =
         compile::compile(vm, source, &compile::Mode::Exec, source_path).map_err(|err| {
             let syntax_error = vm.context().exceptions.syntax_error.clone();
             vm.new_exception(syntax_error, err.to_string())
         })?;
     // trace!("Code object: {:?}", code_obj.borrow());
-    let vars = vm.ctx.new_scope(); // Keep track of local variables
-    vm.run_code_obj(code_obj, vars)
+    vm.run_code_obj(code_obj, scope)
+}
+
+/// Write the call stacks the profiler saw, in the collapsed format.
+fn write_flamegraph(vm: &VirtualMachine, path: &str) {
+    let profiler = match vm.set_profiler(None) {
+        Some(profiler) => profiler,
+        None => return,
+    };
+    profiler.flush();
+    let written = File::create(path).and_then(|mut file| profiler.write_collapsed(&mut file));
+    if let Err(err) = written {
+        eprintln!("Failed writing profile to {}: {}", path, err);
+    }
+}
+
+/// Report the exception that ended the program, if any, and pick the exit
+/// status of the process.
+fn handle_exception(vm: &VirtualMachine, result: PyResult) -> i32 {
+    let exc = match result {
+        Ok(_) => return 0,
+        Err(exc) => exc,
+    };
+    if objtype::isinstance(&exc, &vm.ctx.exceptions.system_exit) {
+        return system_exit_code(vm, &exc);
+    }
+    print_exception(vm, &exc);
+    if objtype::isinstance(&exc, &vm.ctx.exceptions.keyboard_interrupt) {
+        // What a shell reports for a process killed by SIGINT.
+        130
+    } else {
+        1
+    }
 }
 
-fn handle_exception(vm: &VirtualMachine, result: PyResult) {
-    if let Err(err) = result {
-        print_exception(vm, &err);
-        std::process::exit(1);
+/// The exit status `SystemExit` asks for: the low 8 bits of its code when
+/// that is an int, all the OS keeps of it anyway, 0 for None, and otherwise 1,
+/// after writing the code to `sys.stderr`.
+fn system_exit_code(vm: &VirtualMachine, exc: &PyObjectRef) -> i32 {
+    let code = match vm.get_attribute(exc.clone(), "code") {
+        Ok(code) => code,
+        Err(_) => return 1,
+    };
+    if code.is(&vm.get_none()) {
+        return 0;
+    }
+    if objtype::isinstance(&code, &vm.ctx.int_type()) {
+        return vm
+            .call_method(&code, "__and__", vec![vm.ctx.new_int(0xff)])
+            .and_then(|status| i32::try_from_object(vm, status))
+            .unwrap_or(1);
     }
+    let message = vm
+        .to_str(&code)
+        .map(|message| message.value.clone())
+        .unwrap_or_else(|_| "<exception str() failed>".to_string());
+    let written = vm
+        .get_attribute(vm.sys_module.clone(), "stderr")
+        .and_then(|stderr| {
+            vm.call_method(&stderr, "write", vec![vm.new_str(format!("{}\n", message))])
+        });
+    if written.is_err() {
+        eprintln!("{}", message);
+    }
+    1
 }
 
-fn run_command(vm: &VirtualMachine, mut source: String) -> PyResult {
+fn run_command(vm: &VirtualMachine, scope: Scope, mut source: String) -> PyResult {
     debug!("Running command {}", source);
 
     // This works around https://github.com/RustPython/RustPython/issues/17
     source.push('\n');
-    _run_string(vm, &source, "<stdin>".to_string())
+    _run_string(vm, scope, &source, "<stdin>".to_string())
 }
 
 fn run_module(vm: &VirtualMachine, module: &str) -> PyResult {
@@ -96,27 +253,37 @@ fn run_module(vm: &VirtualMachine, module: &str) -> PyResult {
     import::import_module(vm, current_path, module)
 }
 
-fn run_script(vm: &VirtualMachine, script_file: &str) -> PyResult {
+fn run_script(vm: &VirtualMachine, scope: Scope, script_file: &str) -> PyResult {
     debug!("Running file {}", script_file);
     // Parse an ast from it:
     let file_path = Path::new(script_file);
     match util::read_file(file_path) {
-        Ok(source) => _run_string(vm, &source, file_path.to_str().unwrap().to_string()),
+        Ok(source) => _run_string(vm, scope, &source, file_path.to_str().unwrap().to_string()),
         Err(err) => {
-            error!("Failed reading file: {:?}", err.kind());
-            std::process::exit(1);
+            eprintln!("rustpython: can't open file '{}': {}", script_file, err);
+            std::process::exit(2);
         }
     }
 }
 
-fn shell_exec(vm: &VirtualMachine, source: &str, scope: Scope) -> Result<(), CompileError> {
+/// Run a statement typed in the shell. A `SystemExit` it raises ends the
+/// shell, other exceptions are printed.
+fn shell_exec(
+    vm: &VirtualMachine,
+    source: &str,
+    scope: Scope,
+) -> Result<PyResult<()>, CompileError> {
     match compile::compile(vm, source, &compile::Mode::Single, "<stdin>".to_string()) {
-        Ok(code) => {
-            if let Err(err) = vm.run_code_obj(code, scope) {
+        Ok(code) => match vm.run_code_obj(code, scope) {
+            Err(err) => {
+                if objtype::isinstance(&err, &vm.ctx.exceptions.system_exit) {
+                    return Ok(Err(err));
+                }
                 print_exception(vm, &err);
+                Ok(Ok(()))
             }
-            Ok(())
-        }
+            Ok(_) => Ok(Ok(())),
+        },
         // Don't inject syntax errors for line continuation
         Err(err @ CompileError::Parse(ParseError::EOF(_))) => Err(err),
         Err(err) => {
@@ -151,12 +318,11 @@ fn get_prompt(vm: &VirtualMachine, prompt_name: &str) -> String {
         .unwrap_or_else(String::new)
 }
 
-fn run_shell(vm: &VirtualMachine) -> PyResult {
+fn run_shell(vm: &VirtualMachine, scope: Scope) -> PyResult {
     println!(
         "Welcome to the magnificent Rust Python {} interpreter \u{1f631} \u{1f596}",
         crate_version!()
     );
-    let vars = vm.ctx.new_scope();
 
     // Read a single line:
     let mut input = String::new();
@@ -191,19 +357,25 @@ fn run_shell(vm: &VirtualMachine) -> PyResult {
                     }
                 }
 
-                match shell_exec(vm, &input, vars.clone()) {
+                match shell_exec(vm, &input, scope.clone()) {
                     Err(CompileError::Parse(ParseError::EOF(_))) => {
                         continuing = true;
                         continue;
                     }
+                    Ok(Err(system_exit)) => {
+                        repl.save_history(repl_history_path_str).unwrap();
+                        return Err(system_exit);
+                    }
                     _ => {
                         input = String::new();
                     }
                 }
             }
             Err(ReadlineError::Interrupted) => {
-                // TODO: Raise a real KeyboardInterrupt exception
-                println!("^C");
+                // Ctrl-C while editing discards the input, like CPython
+                // does when it raises KeyboardInterrupt at the prompt.
+                write_stderr(vm, "KeyboardInterrupt\n");
+                input = String::new();
                 continuing = false;
                 continue;
             }
@@ -0,0 +1,396 @@
+from abc import ABC, ABCMeta, abstractmethod, get_cache_token
+from collections.abc import (
+    Callable, Container, Hashable, ItemsView, Iterable, Iterator, KeysView,
+    Mapping, MappingView, MutableMapping, MutableSequence, MutableSet,
+    Sequence, Set, Sized, ValuesView,
+)
+from testutils import assertRaises
+
+
+class Shape(ABC):
+    @abstractmethod
+    def area(self):
+        pass
+
+    @abstractmethod
+    def perimeter(self):
+        pass
+
+    def describe(self):
+        return "shape"
+
+
+assert isinstance(Shape, ABCMeta)
+assert Shape.__abstractmethods__ == frozenset(["area", "perimeter"])
+with assertRaises(TypeError):
+    Shape()
+
+try:
+    Shape()
+except TypeError as e:
+    assert "area" in str(e)
+    assert "perimeter" in str(e)
+
+
+class Partial(Shape):
+    def area(self):
+        return 4
+
+
+assert Partial.__abstractmethods__ == frozenset(["perimeter"])
+with assertRaises(TypeError):
+    Partial()
+
+
+class Square(Partial):
+    def perimeter(self):
+        return 8
+
+
+s = Square()
+assert s.area() == 4
+assert s.perimeter() == 8
+assert s.describe() == "shape"
+assert isinstance(s, Shape)
+assert Square.__abstractmethods__ == frozenset()
+
+
+class WithDescriptors(metaclass=ABCMeta):
+    @classmethod
+    @abstractmethod
+    def make(cls):
+        pass
+
+    @staticmethod
+    @abstractmethod
+    def helper():
+        pass
+
+    @property
+    @abstractmethod
+    def value(self):
+        pass
+
+
+assert WithDescriptors.__abstractmethods__ == frozenset(["make", "helper", "value"])
+
+
+# Virtual subclasses
+class Drawable(ABC):
+    pass
+
+
+class Circle:
+    pass
+
+
+assert not issubclass(Circle, Drawable)
+token = get_cache_token()
+assert Drawable.register(Circle) is Circle
+assert get_cache_token() != token
+assert issubclass(Circle, Drawable)
+assert isinstance(Circle(), Drawable)
+assert Drawable not in Circle.__mro__
+
+
+class Ellipse(Circle):
+    pass
+
+
+assert issubclass(Ellipse, Drawable)
+
+with assertRaises(TypeError):
+    Drawable.register(3)
+
+with assertRaises(RuntimeError):
+    class Base(ABC):
+        pass
+    class Derived(Base):
+        pass
+    Derived.register(Base)
+
+
+# __subclasshook__
+class Quacker(ABC):
+    @classmethod
+    def __subclasshook__(cls, C):
+        if cls is Quacker:
+            return hasattr(C, "quack")
+        return NotImplemented
+
+
+class Duck:
+    def quack(self):
+        pass
+
+
+assert issubclass(Duck, Quacker)
+assert isinstance(Duck(), Quacker)
+assert not issubclass(int, Quacker)
+assert not isinstance(1, Quacker)
+
+
+# collections.abc
+assert isinstance([], Sequence)
+assert isinstance([], MutableSequence)
+assert isinstance((), Sequence)
+assert not isinstance((), MutableSequence)
+assert isinstance("abc", Sequence)
+assert isinstance({}, Mapping)
+assert isinstance({}, MutableMapping)
+assert not isinstance([], Mapping)
+assert not isinstance({}, Sequence)
+assert isinstance(set(), MutableSet)
+assert isinstance(frozenset(), Set)
+assert not isinstance(frozenset(), MutableSet)
+assert isinstance(range(3), Sequence)
+
+for obj in ([], (), "", {}, set(), range(1)):
+    assert isinstance(obj, Iterable)
+    assert isinstance(obj, Sized)
+    assert isinstance(obj, Container)
+
+assert isinstance(iter([]), Iterator)
+assert not isinstance([], Iterator)
+assert isinstance(len, Callable)
+assert isinstance(lambda: 0, Callable)
+assert isinstance(s.area, Callable)
+assert isinstance(int, Callable)
+assert not isinstance(1, Callable)
+assert isinstance(1, Hashable)
+assert isinstance("abc", Hashable)
+assert isinstance((), Hashable)
+for obj in ([], {}, set(), bytearray()):
+    assert not isinstance(obj, Hashable)
+    with assertRaises(TypeError):
+        hash(obj)
+assert not isinstance(1, Iterable)
+
+
+class Countdown:
+    def __init__(self, start):
+        self.current = start
+
+    def __iter__(self):
+        return self
+
+    def __next__(self):
+        if self.current <= 0:
+            raise StopIteration
+        self.current -= 1
+        return self.current
+
+
+assert isinstance(Countdown(3), Iterator)
+assert isinstance(Countdown(3), Iterable)
+assert not isinstance(Countdown(3), Sized)
+
+
+class NotIterable:
+    __iter__ = None
+
+
+assert not isinstance(NotIterable(), Iterable)
+
+
+class Word(Sequence):
+    def __init__(self, letters):
+        self.letters = letters
+
+    def __getitem__(self, index):
+        return self.letters[index]
+
+    def __len__(self):
+        return len(self.letters)
+
+
+w = Word("hello")
+assert list(w) == ["h", "e", "l", "l", "o"]
+assert list(reversed(w)) == ["o", "l", "l", "e", "h"]
+assert "e" in w
+assert "z" not in w
+assert w.index("l") == 2
+assert w.count("l") == 2
+assert w.index("l", 3) == 3
+assert w.index("l", -2) == 3
+with assertRaises(ValueError):
+    w.index("l", 0, 2)
+with assertRaises(ValueError):
+    w.index("z")
+assert isinstance(w, Iterable)
+assert issubclass(Word, Sized)
+
+with assertRaises(TypeError):
+    class Incomplete(Sequence):
+        pass
+    Incomplete()
+
+
+class Stack(MutableSequence):
+    def __init__(self):
+        self.items = []
+
+    def __getitem__(self, index):
+        return self.items[index]
+
+    def __setitem__(self, index, value):
+        self.items[index] = value
+
+    def __delitem__(self, index):
+        if index < 0:
+            index += len(self.items)
+        self.items = self.items[:index] + self.items[index + 1:]
+
+    def __len__(self):
+        return len(self.items)
+
+    def insert(self, index, value):
+        self.items.insert(index, value)
+
+
+st = Stack()
+st.append(1)
+st.extend([2, 3, 4])
+assert st.items == [1, 2, 3, 4]
+assert st.pop() == 4
+assert st.pop(0) == 1
+st.remove(2)
+assert st.items == [3]
+st += [5, 6]
+assert st.items == [3, 5, 6]
+st.reverse()
+assert st.items == [6, 5, 3]
+st.clear()
+assert st.items == []
+
+
+class Registry(MutableMapping):
+    def __init__(self):
+        self.data = {}
+
+    def __getitem__(self, key):
+        return self.data[key]
+
+    def __setitem__(self, key, value):
+        self.data[key] = value
+
+    def __delitem__(self, key):
+        del self.data[key]
+
+    def __iter__(self):
+        return iter(self.data)
+
+    def __len__(self):
+        return len(self.data)
+
+
+r = Registry()
+r["a"] = 1
+assert "a" in r
+assert "b" not in r
+assert r.get("a") == 1
+assert r.get("b") is None
+assert r.get("b", 2) == 2
+assert r.setdefault("c", 3) == 3
+assert r["c"] == 3
+assert r.pop("a") == 1
+assert r.pop("a", 5) == 5
+with assertRaises(KeyError):
+    r.pop("a")
+assert len(r) == 1
+assert isinstance(r, Mapping)
+assert not isinstance(r, Sequence)
+
+r.update({"d": 4}, e=5)
+r.update([("f", 6)])
+assert sorted(r.keys()) == ["c", "d", "e", "f"]
+assert isinstance(r.keys(), KeysView)
+assert isinstance(r.items(), ItemsView)
+assert isinstance(r.values(), ValuesView)
+assert isinstance(r.keys(), MappingView)
+assert len(r.items()) == 4
+assert ("d", 4) in r.items()
+assert ("d", 5) not in r.items()
+assert 6 in r.values()
+assert 7 not in r.values()
+assert "c" in r.keys()
+assert sorted(r.items()) == [("c", 3), ("d", 4), ("e", 5), ("f", 6)]
+assert sorted(r.values()) == [3, 4, 5, 6]
+assert r.keys() & {"c", "z"} == {"c"}
+assert r.keys() - {"c", "d"} == {"e", "f"}
+
+other = Registry()
+other.update(r)
+assert other == r
+other["c"] = 0
+assert other != r
+assert r != {}
+assert not isinstance(r, Hashable)
+
+key, value = r.popitem()
+assert key not in r
+assert len(r) == 3
+r.clear()
+assert len(r) == 0
+with assertRaises(KeyError):
+    r.popitem()
+
+
+class ListSet(MutableSet):
+    def __init__(self, items=()):
+        self.items = []
+        for item in items:
+            self.add(item)
+
+    def __contains__(self, item):
+        return item in self.items
+
+    def __iter__(self):
+        return iter(self.items)
+
+    def __len__(self):
+        return len(self.items)
+
+    def add(self, item):
+        if item not in self.items:
+            self.items.append(item)
+
+    def discard(self, item):
+        if item in self.items:
+            self.items.remove(item)
+
+
+a = ListSet([1, 2, 3])
+b = ListSet([2, 3, 4])
+assert isinstance(a & b, ListSet)
+assert sorted(a & b) == [2, 3]
+assert sorted(a | b) == [1, 2, 3, 4]
+assert sorted(a - b) == [1]
+assert sorted(a ^ b) == [1, 4]
+assert sorted(a & [3]) == [3]
+assert a == ListSet([3, 2, 1])
+assert a != b
+assert ListSet([1]) < a
+assert ListSet([1]) <= a
+assert a <= a
+assert not a < a
+assert a > ListSet([2])
+assert a >= b - ListSet([4])
+assert not a.isdisjoint(b)
+assert a.isdisjoint([7, 8])
+assert not isinstance(a, Hashable)
+
+a |= [5]
+assert sorted(a) == [1, 2, 3, 5]
+a &= ListSet([1, 2, 5])
+assert sorted(a) == [1, 2, 5]
+a -= [1]
+assert sorted(a) == [2, 5]
+a ^= ListSet([5, 6])
+assert sorted(a) == [2, 6]
+popped = a.pop()
+assert popped in (2, 6) and popped not in a
+a.clear()
+assert len(a) == 0
+with assertRaises(KeyError):
+    a.pop()
@@ -0,0 +1,210 @@
+import asyncio
+import socket
+from types import coroutine
+
+from testutils import assertRaises
+
+
+loop = asyncio.new_event_loop()
+asyncio.set_event_loop(loop)
+
+# Plain callbacks:
+order = []
+loop.call_later(0.02, order.append, 'later')
+loop.call_soon(order.append, 'soon')
+handle = loop.call_soon(order.append, 'cancelled')
+handle.cancel()
+loop.call_later(0.03, loop.stop)
+loop.run_forever()
+assert order == ['soon', 'later']
+assert not loop.is_running()
+
+# Futures:
+fut = loop.create_future()
+assert not fut.done()
+with assertRaises(asyncio.InvalidStateError):
+    fut.result()
+done = []
+fut.add_done_callback(done.append)
+loop.call_soon(fut.set_result, 42)
+assert loop.run_until_complete(fut) == 42
+assert fut.done()
+assert fut.result() == 42
+assert fut.exception() is None
+assert done == [fut]
+with assertRaises(asyncio.InvalidStateError):
+    fut.set_result(1)
+
+fut = loop.create_future()
+fut.set_exception(KeyError)
+with assertRaises(KeyError):
+    loop.run_until_complete(fut)
+assert isinstance(fut.exception(), KeyError)
+
+
+# Coroutines are generators waiting on futures:
+log = []
+
+
+@coroutine
+def add(a, b, delay):
+    yield from asyncio.sleep(delay)
+    log.append(a + b)
+    return a + b
+
+
+assert loop.run_until_complete(add(1, 2, 0)) == 3
+
+
+@coroutine
+def concurrent():
+    results = yield from asyncio.gather(
+        add(1, 1, 0.03), add(2, 2, 0.01), add(3, 3, 0.02))
+    return results
+
+
+log = []
+assert loop.run_until_complete(concurrent()) == [2, 4, 6]
+assert log == [4, 6, 2]
+
+
+@coroutine
+def fail(delay):
+    yield from asyncio.sleep(delay)
+    raise ValueError('boom')
+
+
+@coroutine
+def catch():
+    try:
+        yield from fail(0)
+    except ValueError:
+        return 'caught'
+
+
+assert loop.run_until_complete(catch()) == 'caught'
+with assertRaises(ValueError):
+    loop.run_until_complete(fail(0))
+
+
+@coroutine
+def gather_errors():
+    results = yield from asyncio.gather(
+        add(1, 2, 0), fail(0), return_exceptions=True)
+    return results
+
+
+results = loop.run_until_complete(gather_errors())
+assert results[0] == 3
+assert isinstance(results[1], ValueError)
+
+
+# Tasks can be cancelled:
+@coroutine
+def forever(log):
+    try:
+        yield from asyncio.sleep(10)
+    except asyncio.CancelledError as exc:
+        log.append('cancelled')
+        raise exc
+
+
+@coroutine
+def cancel_it():
+    cancelled = []
+    task = asyncio.ensure_future(forever(cancelled))
+    yield from asyncio.sleep(0.01)
+    assert not task.done()
+    assert task.cancel()
+    try:
+        yield from task
+    except asyncio.CancelledError:
+        pass
+    assert task.cancelled()
+    return cancelled
+
+
+assert loop.run_until_complete(cancel_it()) == ['cancelled']
+
+
+# Timeouts:
+@coroutine
+def timeouts():
+    result = yield from asyncio.wait_for(add(5, 5, 0), 1)
+    try:
+        yield from asyncio.wait_for(asyncio.sleep(1), 0.01)
+    except asyncio.TimeoutError:
+        return result
+
+
+assert loop.run_until_complete(timeouts()) == 10
+
+
+@coroutine
+def who():
+    yield from asyncio.sleep(0)
+    return asyncio.current_task()
+
+
+task = loop.create_task(who())
+assert loop.run_until_complete(task) is task
+
+
+# Sockets, served concurrently in one thread:
+server = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+server.bind(('127.0.0.1', 0))
+server.listen(5)
+server.setblocking(False)
+address = server.getsockname()
+
+
+@coroutine
+def handle(conn):
+    data = yield from loop.sock_recv(conn, 1024)
+    yield from loop.sock_sendall(conn, data)
+    conn.close()
+
+
+@coroutine
+def serve(count):
+    handlers = []
+    for _ in range(count):
+        conn, _ = yield from loop.sock_accept(server)
+        handlers.append(asyncio.ensure_future(handle(conn)))
+    yield from asyncio.gather(*handlers)
+
+
+@coroutine
+def client(message):
+    sock = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+    sock.setblocking(False)
+    yield from loop.sock_connect(sock, address)
+    yield from loop.sock_sendall(sock, message)
+    reply = yield from loop.sock_recv(sock, 1024)
+    sock.close()
+    return reply
+
+
+@coroutine
+def echo():
+    replies = yield from asyncio.gather(
+        serve(3), client(b'a'), client(b'bb'), client(b'ccc'))
+    return replies[1:]
+
+
+assert loop.run_until_complete(echo()) == [b'a', b'bb', b'ccc']
+server.close()
+
+loop.close()
+assert loop.is_closed()
+with assertRaises(RuntimeError):
+    loop.run_until_complete(add(1, 1, 0))
+
+
+@coroutine
+def nested():
+    result = yield from add(2, 3, 0)
+    return result
+
+
+assert asyncio.run(nested()) == 5
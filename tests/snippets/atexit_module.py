@@ -0,0 +1,62 @@
+import atexit
+
+calls = []
+
+def record(*args, **kwargs):
+    calls.append((args, kwargs))
+
+def first():
+    calls.append("first")
+
+atexit._clear()
+assert atexit._ncallbacks() == 0
+
+assert atexit.register(first) is first
+atexit.register(record, 1, 2, key="value")
+assert atexit._ncallbacks() == 2
+
+# The callbacks run last registered first, and are forgotten once they ran.
+atexit._run_exitfuncs()
+assert len(calls) == 2, calls
+assert calls[0][0] == (1, 2)
+assert calls[0][1]["key"] == "value"
+assert calls[1] == "first"
+assert atexit._ncallbacks() == 0
+
+# Every registration of a function is removed.
+calls.clear()
+atexit.register(first)
+atexit.register(record, "kept")
+atexit.register(first)
+atexit.unregister(first)
+atexit.unregister(first)
+atexit._run_exitfuncs()
+assert len(calls) == 1, calls
+assert calls[0][0] == ("kept",)
+
+# A failing callback doesn't keep the others from running.
+def fail():
+    raise ValueError("from an atexit callback")
+
+calls.clear()
+atexit.register(first)
+atexit.register(fail)
+atexit._run_exitfuncs()
+assert calls == ["first"], calls
+
+try:
+    atexit.register(1)
+except TypeError:
+    pass
+else:
+    assert False, "registering a non-callable should fail"
+
+try:
+    atexit.register()
+except TypeError:
+    pass
+else:
+    assert False, "register needs a function"
+
+# This one runs when the interpreter exits.
+atexit.register(print, "atexit callback ran")
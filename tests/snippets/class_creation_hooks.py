@@ -0,0 +1,166 @@
+def assert_raises(exc_type, func, *args, **kwargs):
+    try:
+        func(*args, **kwargs)
+    except exc_type:
+        pass
+    else:
+        assert False, "{} not raised".format(exc_type)
+
+
+# __init_subclass__ runs for the subclasses, with the class keywords.
+class Plugin:
+    registry = []
+
+    def __init_subclass__(cls, name=None, **kwargs):
+        super().__init_subclass__(**kwargs)
+        cls.plugin_name = name
+        Plugin.registry.append(cls)
+
+
+class First(Plugin, name="first"):
+    pass
+
+
+class Second(First):
+    pass
+
+
+assert Plugin.registry == [First, Second]
+assert First.plugin_name == "first"
+assert Second.plugin_name is None
+assert not hasattr(Plugin, "plugin_name")
+
+# object.__init_subclass__ takes no keywords.
+assert_raises(TypeError, type, "Bad", (), {}, unexpected=1)
+
+
+def make_bad():
+    class Bad(unexpected=1):
+        pass
+
+
+assert_raises(TypeError, make_bad)
+
+
+# The class keywords also reach __prepare__ and the metaclass.
+seen = []
+
+
+class Meta(type):
+    @classmethod
+    def __prepare__(mcs, name, bases, **kwargs):
+        seen.append(("prepare", name, kwargs.get("flag")))
+        return {}
+
+    def __new__(mcs, name, bases, namespace, **kwargs):
+        seen.append(("new", name, kwargs.get("flag")))
+        return type.__new__(mcs, name, bases, namespace)
+
+    def __init__(cls, name, bases, namespace, **kwargs):
+        type.__init__(cls, name, bases, namespace)
+
+
+class WithMeta(metaclass=Meta, flag=True):
+    pass
+
+
+assert seen == [("prepare", "WithMeta", True), ("new", "WithMeta", True)], seen
+
+
+# __set_name__ tells descriptors their owner and name.
+class Field:
+    def __set_name__(self, owner, name):
+        self.owner = owner
+        self.name = name
+
+    def __get__(self, obj, owner=None):
+        if obj is None:
+            return self
+        return obj.values[self.name]
+
+
+class Record:
+    id = Field()
+    title = Field()
+
+    def __init__(self, **values):
+        self.values = values
+
+
+assert Record.id.owner is Record
+assert Record.id.name == "id"
+assert Record.title.name == "title"
+assert Record(id=1, title="t").title == "t"
+
+
+# They are told in the order the class body defines them.
+named = []
+
+
+class Column:
+    def __set_name__(self, owner, name):
+        named.append(name)
+
+
+class Table:
+    zeta = Column()
+    alpha = Column()
+    mid = Column()
+    alpha2 = Column()
+    beta = Column()
+    omega = Column()
+
+
+assert named == ["zeta", "alpha", "mid", "alpha2", "beta", "omega"], named
+
+# Deleting a name from the class body doesn't reorder the others.
+named.clear()
+
+
+class Pruned:
+    first = Column()
+    gone = Column()
+    second = Column()
+    third = Column()
+    del gone
+
+
+assert named == ["first", "second", "third"], named
+
+
+# Descriptors added later don't get __set_name__ called.
+late = Field()
+Record.late = late
+assert not hasattr(late, "name")
+
+
+# __class_getitem__ makes the class subscriptable.
+class Box:
+    def __class_getitem__(cls, item):
+        return (cls, item)
+
+
+assert Box[int] == (Box, int)
+
+
+class IntBox(Box):
+    pass
+
+
+assert IntBox["x"] == (IntBox, "x")
+
+
+# A metaclass __getitem__ takes precedence.
+class GetItemMeta(type):
+    def __getitem__(cls, item):
+        return "meta"
+
+
+class Both(metaclass=GetItemMeta):
+    def __class_getitem__(cls, item):
+        return "class"
+
+
+assert Both[0] == "meta"
+
+assert_raises(TypeError, lambda: Record[0])
@@ -34,3 +34,14 @@ res = set()
 for key in a.keys():
 	res.add(key)
 assert res == set(['a','b'])
+
+# Dicts keep insertion order, also once keys are deleted
+d = {'a': 1, 'b': 2, 'c': 3, 'd': 4}
+del d['a']
+assert list(d) == ['b', 'c', 'd']
+del d['c']
+assert list(d.items()) == [('b', 2), ('d', 4)]
+d['a'] = 5
+assert list(d.keys()) == ['b', 'd', 'a']
+assert list(d.values()) == [2, 4, 5]
+assert {'a': 1, 'a': 2}['a'] == 2
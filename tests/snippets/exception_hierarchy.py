@@ -0,0 +1,169 @@
+import os
+
+from testutils import assert_raises
+
+# The class hierarchy:
+assert issubclass(SystemExit, BaseException)
+assert not issubclass(SystemExit, Exception)
+assert not issubclass(KeyboardInterrupt, Exception)
+assert not issubclass(GeneratorExit, Exception)
+assert issubclass(KeyError, LookupError)
+assert issubclass(IndexError, LookupError)
+assert issubclass(LookupError, Exception)
+assert issubclass(UnicodeDecodeError, UnicodeError)
+assert issubclass(UnicodeEncodeError, UnicodeError)
+assert issubclass(UnicodeTranslateError, UnicodeError)
+assert issubclass(UnicodeError, ValueError)
+assert issubclass(EOFError, Exception)
+assert issubclass(RecursionError, RuntimeError)
+assert issubclass(NotImplementedError, RuntimeError)
+assert issubclass(UnboundLocalError, NameError)
+assert issubclass(ConnectionRefusedError, ConnectionError)
+assert issubclass(ConnectionResetError, ConnectionError)
+assert issubclass(ConnectionAbortedError, ConnectionError)
+assert issubclass(BrokenPipeError, ConnectionError)
+assert issubclass(ConnectionError, OSError)
+assert issubclass(TimeoutError, OSError)
+assert issubclass(IsADirectoryError, OSError)
+assert issubclass(FileNotFoundError, OSError)
+assert issubclass(TabError, IndentationError)
+assert issubclass(IndentationError, SyntaxError)
+assert issubclass(DeprecationWarning, Warning)
+assert issubclass(ResourceWarning, Warning)
+assert issubclass(Warning, Exception)
+assert IOError is OSError
+assert EnvironmentError is OSError
+
+try:
+    {}['missing']
+except LookupError as exc:
+    assert isinstance(exc, KeyError)
+
+try:
+    [][1]
+except LookupError as exc:
+    assert isinstance(exc, IndexError)
+
+# args, str and repr:
+exc = ValueError()
+assert exc.args == ()
+assert str(exc) == ''
+assert repr(exc) == 'ValueError()'
+
+exc = ValueError('bad value')
+assert exc.args == ('bad value',)
+assert str(exc) == 'bad value'
+assert repr(exc) == "ValueError('bad value')"
+
+exc = ValueError(1, 'two')
+assert exc.args == (1, 'two')
+assert str(exc) == "(1, 'two')"
+assert repr(exc) == "ValueError(1, 'two')"
+
+assert str(KeyError('key')) == "'key'"
+assert str(KeyError()) == ''
+
+
+class NoSuper(Exception):
+    def __init__(self, value):
+        self.value = value
+
+
+try:
+    raise NoSuper(3)
+except NoSuper as exc:
+    assert exc.value == 3
+    assert exc.args == (3,)
+
+# OSError and its attributes:
+exc = OSError(2, 'No such file or directory')
+assert exc.errno == 2
+assert exc.strerror == 'No such file or directory'
+assert exc.filename is None
+assert str(exc) == '[Errno 2] No such file or directory'
+
+exc = OSError(2, 'No such file or directory', 'missing.txt')
+assert exc.args == (2, 'No such file or directory')
+assert exc.filename == 'missing.txt'
+assert str(exc) == "[Errno 2] No such file or directory: 'missing.txt'"
+
+exc = OSError('just a message')
+assert exc.errno is None
+assert str(exc) == 'just a message'
+
+try:
+    os.open('/this/file/does/not/exist', 0)
+except OSError as exc:
+    assert type(exc) is FileNotFoundError
+    assert exc.errno == 2
+    assert exc.filename == '/this/file/does/not/exist'
+else:
+    assert False, 'os.open did not fail'
+
+# Exception specific attributes:
+assert SystemExit().code is None
+assert SystemExit(3).code == 3
+assert SystemExit('bye').code == 'bye'
+
+exc = ImportError('no module', name='spam', path='spam.py')
+assert exc.name == 'spam'
+assert exc.path == 'spam.py'
+assert exc.args == ('no module',)
+assert ImportError().name is None
+
+invalid = bytes([97, 98, 255])
+exc = UnicodeDecodeError('utf-8', invalid, 2, 3, 'invalid start byte')
+assert exc.encoding == 'utf-8'
+assert exc.object == invalid
+assert exc.start == 2
+assert exc.end == 3
+assert exc.reason == 'invalid start byte'
+assert str(exc) == "'utf-8' codec can't decode byte 0xff in position 2: invalid start byte"
+# The attributes may be replaced with anything.
+exc.object = 'not bytes'
+assert str(exc).startswith("'utf-8' codec can't decode")
+
+exc = UnicodeEncodeError('ascii', 'abc', 1, 3, 'ordinal not in range(128)')
+assert str(exc) == "'ascii' codec can't encode characters in position 1-2: ordinal not in range(128)"
+exc.object = b'not str'
+assert str(exc).startswith("'ascii' codec can't encode")
+
+exc = UnicodeTranslateError('abc', 0, 1, 'no mapping')
+assert str(exc) == "can't translate character '\\x61' in position 0: no mapping"
+
+# with_traceback and chaining:
+exc = RuntimeError('traced')
+assert exc.with_traceback(None) is exc
+assert exc.__traceback__ is None
+assert exc.__cause__ is None
+assert exc.__context__ is None
+assert exc.__suppress_context__ is False
+
+try:
+    try:
+        1 / 0
+    except ZeroDivisionError as inner:
+        raise ValueError('outer') from inner
+except ValueError as exc:
+    assert isinstance(exc.__cause__, ZeroDivisionError)
+    assert exc.__suppress_context__ is True
+
+try:
+    raise ValueError from KeyError
+except ValueError as exc:
+    assert type(exc.__cause__) is KeyError
+
+try:
+    raise ValueError('no cause') from None
+except ValueError as exc:
+    assert exc.__cause__ is None
+    assert exc.__suppress_context__ is True
+
+
+def raise_from_int():
+    raise ValueError from 1
+
+
+assert_raises(TypeError, raise_from_int)
+
+assert_raises(TypeError, BaseException.__new__)
@@ -0,0 +1,113 @@
+import sys
+import threading
+
+log = []
+
+
+class Resource:
+    def __init__(self, name):
+        self.name = name
+
+    def __del__(self):
+        log.append(self.name)
+
+
+r = Resource('global')
+del r
+assert log == ['global']
+
+
+def use_resource():
+    r = Resource('local')
+    assert log == ['global']
+
+
+use_resource()
+assert log == ['global', 'local']
+
+r = Resource('rebound')
+r = None
+assert log == ['global', 'local', 'rebound']
+
+
+# Subclasses inherit the finalizer:
+class SubResource(Resource):
+    pass
+
+
+SubResource('sub')
+assert log[-1] == 'sub'
+
+
+# Exceptions raised by a finalizer are printed and ignored:
+class Broken:
+    def __del__(self):
+        log.append('broken')
+        raise ValueError('finalizer failed')
+
+
+b = Broken()
+del b
+assert log[-1] == 'broken'
+
+
+# A finalizer may resurrect its object, but only runs once:
+saved = []
+
+
+class Phoenix:
+    def __del__(self):
+        log.append('phoenix')
+        saved.append(self)
+
+
+p = Phoenix()
+p.feathers = 3
+del p
+assert log[-1] == 'phoenix'
+assert saved[0].feathers == 3
+saved.clear()
+assert log.count('phoenix') == 1
+
+
+# Subclasses of exceptions are finalized like other classes.
+class FailedResource(Exception):
+    def __del__(self):
+        log.append('exception')
+
+
+FailedResource()
+assert log[-1] == 'exception'
+
+
+# A thread finalizes what it drops before it exits.
+def drop_in_thread():
+    Resource('thread')
+
+
+t = threading.Thread(target=drop_in_thread)
+t.start()
+t.join()
+assert log[-1] == 'thread'
+
+
+# The payload of subclasses of other builtin types can't be kept around for
+# __del__, so it isn't called for them, unlike in CPython.
+class FinalizedList(list):
+    def __del__(self):
+        log.append('list')
+
+
+FinalizedList([1, 2])
+if sys.implementation.name == 'rustpython':
+    assert log[-1] == 'thread'
+else:
+    assert log[-1] == 'list'
+
+
+class AtExit:
+    def __del__(self):
+        print('finalized at exit')
+
+
+keep_until_exit = AtExit()
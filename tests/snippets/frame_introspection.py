@@ -0,0 +1,94 @@
+import sys
+
+
+def current():
+    return sys._getframe()
+
+
+def caller():
+    return sys._getframe(1)
+
+
+def outer():
+    frame = current()
+    assert frame.f_code.co_name == 'current'
+    assert frame.f_back.f_code.co_name == 'outer'
+    assert caller() is sys._getframe()
+    return frame
+
+
+frame = outer()
+# Still valid once the frames have returned:
+assert frame.f_code.co_name == 'current'
+assert frame.f_back.f_code.co_name == 'outer'
+assert frame.f_back.f_back is sys._getframe()
+assert frame.f_lineno == 5
+
+here = sys._getframe()
+assert here.f_globals is globals()
+assert here.f_globals['here'] is here
+assert here.f_builtins['len'] is len
+assert isinstance(here.f_lasti, int)
+assert here.f_lasti >= 0
+
+
+def lines():
+    frame = sys._getframe()
+    first = frame.f_lineno
+    second = frame.f_lineno
+    return first, second
+
+
+assert lines() == (37, 38)
+
+
+def lasti():
+    frame = sys._getframe()
+    first = frame.f_lasti
+    second = frame.f_lasti
+    return first, second
+
+
+first, second = lasti()
+assert first < second
+
+
+def chain():
+    frame = sys._getframe()
+    names = []
+    while frame is not None:
+        names.append(frame.f_code.co_name)
+        frame = frame.f_back
+    return names
+
+
+def middle():
+    return chain()
+
+
+names = middle()
+assert names[:3] == ['chain', 'middle', '<module>']
+assert names[-1] == '<module>'
+
+
+def gen():
+    yield sys._getframe()
+    yield sys._getframe().f_back
+
+
+g = gen()
+gen_frame = next(g)
+assert gen_frame.f_code.co_name == 'gen'
+# A suspended generator has no caller:
+assert gen_frame.f_back is None
+
+
+def resume():
+    return next(g)
+
+
+assert resume().f_code.co_name == 'resume'
+assert gen_frame.f_lineno == 76
+
+# A frame refers to the code object of its function, not a copy.
+assert outer().f_back.f_code is outer.__code__
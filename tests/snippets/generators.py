@@ -35,3 +35,209 @@ r = list(g3())
 # print(r)
 assert r == [23, 1, 2, 3, 44]
 
+
+
+from testutils import assertRaises
+
+
+def g4():
+    x = yield from g5()
+    yield x
+
+
+def g5():
+    y = yield 1
+    return y * 2
+
+
+i = g4()
+assert next(i) == 1
+assert i.send(21) == 42
+with assertRaises(StopIteration):
+    next(i)
+with assertRaises(StopIteration):
+    next(i)
+
+
+def g6():
+    return 'done'
+    yield
+
+
+try:
+    next(g6())
+except StopIteration as stop:
+    assert stop.value == 'done'
+else:
+    assert False
+assert StopIteration().value is None
+assert StopIteration(5).value == 5
+
+
+# throw() raises at the yield point:
+log = []
+
+
+def g7():
+    while True:
+        try:
+            yield
+        except ValueError as exc:
+            log.append(exc)
+
+
+i = g7()
+next(i)
+one = ValueError('one')
+three = ValueError('three')
+assert i.throw(one) is None
+assert i.throw(ValueError, 'two') is None
+assert i.throw(ValueError, three) is None
+assert len(log) == 3
+assert log[0] is one
+assert type(log[1]) is ValueError
+assert log[2] is three
+with assertRaises(KeyError):
+    i.throw(KeyError)
+with assertRaises(StopIteration):
+    next(i)
+with assertRaises(TypeError):
+    g7().throw(42)
+
+# throw() on a generator that did not start yet just raises:
+i = g7()
+with assertRaises(ValueError):
+    i.throw(ValueError)
+assert len(log) == 3
+
+
+# close() runs finally blocks:
+def g8():
+    try:
+        yield 1
+        yield 2
+    finally:
+        log.append('cleanup')
+
+
+log = []
+i = g8()
+assert next(i) == 1
+i.close()
+assert log == ['cleanup']
+with assertRaises(StopIteration):
+    next(i)
+i.close()
+g8().close()
+assert log == ['cleanup']
+
+
+def g9():
+    try:
+        yield
+    except GeneratorExit as exc:
+        log.append('exit')
+        raise exc
+
+
+log = []
+i = g9()
+next(i)
+i.close()
+assert log == ['exit']
+
+
+def stubborn():
+    try:
+        yield
+    except GeneratorExit:
+        pass
+    yield
+
+
+i = stubborn()
+next(i)
+with assertRaises(RuntimeError):
+    i.close()
+
+
+# throw() and close() are passed on through yield from:
+def outer():
+    try:
+        result = yield from g7()
+    finally:
+        log.append('outer')
+
+
+log = []
+i = outer()
+next(i)
+inner = ValueError('inner')
+i.throw(inner)
+assert log == [inner]
+i.close()
+assert log == [inner, 'outer']
+
+
+def g10():
+    try:
+        yield 1
+    except ValueError:
+        return 'handled'
+
+
+def outer2():
+    result = yield from g10()
+    yield result
+
+
+i = outer2()
+assert next(i) == 1
+assert i.throw(ValueError) == 'handled'
+
+
+# An abandoned generator is closed:
+log = []
+i = g8()
+next(i)
+del i
+assert log == ['cleanup']
+
+
+# Raising StopIteration inside a generator is an error:
+def g11():
+    raise StopIteration
+    yield
+
+
+with assertRaises(RuntimeError):
+    next(g11())
+
+
+# Introspection:
+def g12():
+    assert i.gi_running
+    yield
+
+
+i = g12()
+assert not i.gi_running
+assert i.gi_frame is not None
+assert i.gi_code.co_name == 'g12'
+assert i.gi_code is i.gi_code
+assert i.gi_code is g12.__code__
+next(i)
+assert not i.gi_running
+with assertRaises(StopIteration):
+    next(i)
+assert i.gi_frame is None
+
+
+def g13():
+    next(i)
+    yield
+
+
+i = g13()
+with assertRaises(ValueError):
+    next(i)
@@ -0,0 +1,146 @@
+import builtins
+import _weakref
+
+# Attribute lookups run repeatedly from the same instructions, so the caches of
+# `LoadAttr` and `LoadName` get filled and have to notice changes.
+
+
+class Base:
+    def greet(self):
+        return 'base'
+
+
+class Child(Base):
+    pass
+
+
+def greet_all(objects):
+    return [obj.greet() for obj in objects]
+
+
+child = Child()
+assert greet_all([child, child]) == ['base', 'base']
+
+# Changing a base class invalidates what was cached about its subclasses.
+Base.greet = lambda self: 'patched'
+assert greet_all([child]) == ['patched']
+
+Child.greet = lambda self: 'child'
+assert greet_all([child, Base()]) == ['child', 'patched']
+
+# The instance dict shadows methods, but not data descriptors.
+child.greet = lambda: 'instance'
+assert greet_all([child]) == ['instance']
+
+
+class WithProperty:
+    def __init__(self):
+        self.__dict__['value'] = 'from dict'
+
+    @property
+    def value(self):
+        return 'from property'
+
+
+def get_value(obj):
+    return obj.value
+
+
+with_property = WithProperty()
+assert get_value(with_property) == 'from property'
+del WithProperty.value
+assert get_value(with_property) == 'from dict'
+
+
+# A descriptor type turning into a data descriptor:
+class Descriptor:
+    def __get__(self, obj, cls):
+        return 'descriptor'
+
+
+class Owner:
+    attr = Descriptor()
+
+
+owner = Owner()
+owner.__dict__['attr'] = 'instance'
+
+
+def get_attr(obj):
+    return obj.attr
+
+
+assert get_attr(owner) == 'instance'
+Descriptor.__set__ = lambda self, obj, value: None
+assert get_attr(owner) == 'descriptor'
+
+
+# Customized attribute access set up after the lookup was cached:
+class Dynamic:
+    def __init__(self):
+        self.x = 1
+
+
+def get_x(obj):
+    return obj.x
+
+
+dynamic = Dynamic()
+assert get_x(dynamic) == 1
+Dynamic.__getattribute__ = lambda self, name: 42
+assert get_x(dynamic) == 42
+
+
+class Fallback:
+    def __getattr__(self, name):
+        return 'fallback ' + name
+
+
+assert get_x(Fallback()) == 'fallback x'
+
+
+# Globals and builtins:
+def length():
+    return len('abc')
+
+
+assert length() == 3
+len = lambda obj: 'global'
+assert length() == 'global'
+del len
+assert length() == 3
+
+original_len = builtins.len
+builtins.len = lambda obj: 'builtin'
+assert length() == 'builtin'
+builtins.len = original_len
+assert length() == 3
+
+counter = 0
+
+
+def read_counter():
+    return counter
+
+
+for expected in range(3):
+    counter = expected
+    assert read_counter() == expected
+
+
+# Caches don't keep objects alive:
+class Thing:
+    pass
+
+
+thing = Thing()
+thing_ref = _weakref.ref(thing)
+
+
+def read_thing():
+    return thing
+
+
+assert read_thing() is thing
+del thing
+assert thing_ref() is None
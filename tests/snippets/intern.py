@@ -0,0 +1,60 @@
+import sys
+
+# Building the strings at runtime keeps them from being constants.
+a = ''.join(['not yet ', 'interned'])
+b = ''.join(['not yet in', 'terned'])
+assert a == b
+assert a is not b
+
+interned = sys.intern(a)
+assert interned is a
+assert sys.intern(b) is a
+assert sys.intern(a) is a
+
+# Names used in code are interned.
+name = sys.intern(''.join(['some', '_name']))
+some_name = 1
+assert sys.intern('some_name') is name
+
+
+class Point:
+    def __init__(self):
+        self.coordinate = 1
+
+
+key = list(Point().__dict__.keys())[0]
+assert key is sys.intern(''.join(['coord', 'inate']))
+
+# Dicts find keys equal to interned ones.
+d = {}
+d[a] = 1
+assert d[b] == 1
+d[b] = 2
+assert d[a] == 2
+assert len(d) == 1
+assert b in d
+del d[b]
+assert a not in d
+
+attr = ''.join(['dyn', 'amic'])
+p = Point()
+setattr(p, attr, 3)
+assert p.dynamic == 3
+assert getattr(p, ''.join(['dyna', 'mic'])) == 3
+
+
+def assert_raises(exc_type, func, *args):
+    try:
+        func(*args)
+    except exc_type:
+        pass
+    else:
+        assert False, '{} not raised'.format(exc_type)
+
+
+class MyStr(str):
+    pass
+
+
+assert_raises(TypeError, sys.intern, 1)
+assert_raises(TypeError, sys.intern, MyStr('x'))
@@ -1,4 +1,5 @@
 import json
+from testutils import assertRaises
 
 def round_trip_test(obj):
     # serde_json and Python's json module produce slightly differently spaced
@@ -12,6 +13,11 @@ assert "1.0" == json.dumps(1.0)
 assert "true" == json.dumps(True)
 assert "false" == json.dumps(False)
 assert 'null' == json.dumps(None)
+assert '18446744073709551616' == json.dumps(2 ** 64)
+assert '-170141183460469231731687303715884105728' == json.dumps(-2 ** 127)
+
+with assertRaises(TypeError):
+    json.dumps(b'bytes')
 
 assert '[]' == json.dumps([])
 assert '[1]' == json.dumps([1])
@@ -0,0 +1,127 @@
+# Calls like `obj.method(...)` look the method up without binding it first,
+# which has to behave like calling the bound method.
+
+
+class Greeter:
+    greeting = 'hello'
+
+    def greet(self, name, punctuation='!'):
+        return self.greeting + ' ' + name + punctuation
+
+    @classmethod
+    def create(cls):
+        return cls()
+
+    @staticmethod
+    def static(value):
+        return value * 2
+
+    @property
+    def getter(self):
+        return lambda: 'from property'
+
+
+g = Greeter()
+assert g.greet('you') == 'hello you!'
+assert g.greet('you', '?') == 'hello you?'
+assert g.greet('you', punctuation='.') == 'hello you.'
+assert g.greet(*['you']) == 'hello you!'
+assert g.greet(**{'name': 'you'}) == 'hello you!'
+assert isinstance(g.create(), Greeter)
+assert Greeter.create().greet('x') == 'hello x!'
+assert g.static(2) == 4
+assert g.getter() == 'from property'
+
+# Calling through the class passes self explicitly.
+assert Greeter.greet(g, 'class') == 'hello class!'
+
+# The instance dict shadows methods.
+g.greet = lambda name: 'shadowed ' + name
+assert g.greet('you') == 'shadowed you'
+del g.greet
+assert g.greet('you') == 'hello you!'
+
+# Methods replaced on the class are picked up.
+Greeter.greet = lambda self, name: 'replaced ' + name
+assert g.greet('you') == 'replaced you'
+
+
+# Callable objects stored on the class aren't bound.
+class Callable:
+    def __call__(self, *args):
+        return args
+
+
+class Holder:
+    call = Callable()
+
+
+assert Holder().call(1, 2) == (1, 2)
+
+
+# Custom attribute access:
+class Dynamic:
+    def __getattr__(self, name):
+        return lambda *args: (name, args)
+
+
+assert Dynamic().anything(1) == ('anything', (1,))
+
+
+class Intercepting:
+    def method(self):
+        return 'method'
+
+    def __getattribute__(self, name):
+        return lambda: 'intercepted ' + name
+
+
+assert Intercepting().method() == 'intercepted method'
+
+
+# super() and inheritance:
+class Base:
+    def describe(self):
+        return 'base'
+
+
+class Derived(Base):
+    def describe(self):
+        return 'derived ' + super().describe()
+
+
+assert Derived().describe() == 'derived base'
+
+# Methods of builtin types and modules:
+assert 'abc'.upper() == 'ABC'
+assert [3, 1, 2].index(1) == 1
+assert {'a': 1}.get('a') == 1
+import math
+assert math.sqrt(4) == 2
+
+# The object is evaluated before the arguments, and errors come from the
+# lookup before the arguments are evaluated.
+order = []
+
+
+def record(value):
+    order.append(value)
+    return value
+
+
+class Recorder:
+    def method(self, *args):
+        return args
+
+
+record(Recorder()).method(record(1), record(2))
+assert order[1:] == [1, 2]
+
+order = []
+try:
+    record(Greeter()).missing(record(1))
+except AttributeError:
+    pass
+else:
+    assert False, 'expected AttributeError'
+assert len(order) == 1
@@ -9,3 +9,5 @@ try:
     print('test', end=None, sep=None, flush=None)
 except:
     assert False, 'Expected None passed to end, sep, and flush to not raise errors'
+
+assert_raises(TypeError, lambda: print('test', fil=None), 'unexpected keyword argument')
@@ -0,0 +1,117 @@
+import cProfile
+import profile
+import pstats
+
+
+class Output:
+    def __init__(self):
+        self.text = ''
+
+    def write(self, text):
+        self.text += text
+
+
+def fib(n):
+    if n < 2:
+        return n
+    return fib(n - 1) + fib(n - 2)
+
+
+def work():
+    return fib(6) + helper()
+
+
+def helper():
+    return 1
+
+
+prof = cProfile.Profile(builtins=False)
+assert prof.runcall(work) == 9
+stats = pstats.Stats(prof)
+# fib(6) makes 25 calls, one of them not recursive.
+assert stats.total_calls == 27
+assert stats.prim_calls == 3
+assert stats.total_tt >= 0
+
+out = Output()
+stats = pstats.Stats(prof, stream=out)
+assert stats.strip_dirs() is stats
+assert stats.sort_stats('cumulative') is stats
+assert stats.print_stats() is stats
+lines = out.text.splitlines()
+assert lines[0].startswith('         27 function calls (3 primitive calls) in '), lines[0]
+assert lines[0].endswith(' seconds')
+assert lines[2] == '   Ordered by: cumulative time'
+assert lines[4] == '   ncalls  tottime  percall  cumtime  percall filename:lineno(function)'
+assert lines[5].strip().startswith('1 ')
+assert lines[5].endswith('profiling.py:20(work)'), lines[5]
+fib_line = [line for line in lines if line.endswith('(fib)')][0]
+assert fib_line.strip().startswith('25/1 ')
+
+out = Output()
+pstats.Stats(prof, stream=out).sort_stats(pstats.SortKey.NAME).print_stats(1)
+lines = out.text.splitlines()
+assert lines[2] == '   Ordered by: function name'
+assert lines[3] == '   List reduced from 3 to 1 due to restriction <1>'
+assert lines[6].endswith('(fib)'), lines
+
+out = Output()
+pstats.Stats(prof, stream=out).sort_stats('calls').print_stats('helper')
+assert out.text.count('(helper)') == 1
+assert '(fib)' not in out.text
+assert "due to restriction <'helper'>" in out.text
+
+profile_stats = pstats.Stats(prof).get_stats_profile()
+assert profile_stats.func_profiles['fib'].ncalls == '25/1'
+assert profile_stats.func_profiles['helper'].ncalls == '1'
+assert profile_stats.func_profiles['work'].line_number == 20
+
+# Enabling and disabling by hand, and as a context manager:
+prof = cProfile.Profile(builtins=False)
+prof.enable()
+helper()
+helper()
+prof.disable()
+helper()
+assert pstats.Stats(prof).total_calls == 2
+
+with cProfile.Profile(builtins=False) as prof:
+    helper()
+assert pstats.Stats(prof).get_stats_profile().func_profiles['helper'].ncalls == '1'
+
+prof.clear()
+prof.runcall(helper)
+assert pstats.Stats(prof).total_calls == 1
+
+# Stats from several profiles add up:
+first = cProfile.Profile(builtins=False)
+first.runcall(helper)
+second = cProfile.Profile(builtins=False)
+second.runcall(helper)
+assert pstats.Stats(first, second).total_calls == 2
+assert pstats.Stats(first).add(second).total_calls == 2
+
+prof = profile.Profile()
+prof.runcall(fib, 3)
+assert pstats.Stats(prof).get_stats_profile().func_profiles['fib'].ncalls == '5/1'
+
+
+# A profiler only sees the thread it was enabled in.
+import threading
+
+
+def in_thread():
+    return helper()
+
+
+prof = cProfile.Profile(builtins=False)
+prof.enable()
+thread = threading.Thread(target=in_thread)
+thread.start()
+thread.join()
+work()
+prof.disable()
+out = Output()
+pstats.Stats(prof, stream=out).print_stats()
+assert '(work)' in out.text
+assert '(in_thread)' not in out.text
@@ -0,0 +1,53 @@
+import sys
+
+assert sys.getrecursionlimit() == 1000
+
+
+def recurse():
+    recurse()
+
+
+try:
+    recurse()
+except RecursionError as exc:
+    assert str(exc) == 'maximum recursion depth exceeded'
+else:
+    assert False, 'RecursionError not raised'
+
+
+# The stack unwound, calls work again.
+def depth(n):
+    return depth(n + 1) if n < 500 else n
+
+
+assert depth(0) == 500
+
+sys.setrecursionlimit(100)
+assert sys.getrecursionlimit() == 100
+try:
+    depth(0)
+except RecursionError:
+    pass
+else:
+    assert False, 'RecursionError not raised'
+sys.setrecursionlimit(1000)
+
+try:
+    sys.setrecursionlimit(0)
+except ValueError:
+    pass
+else:
+    assert False, 'ValueError not raised'
+
+
+class Recursive:
+    def __getattr__(self, name):
+        return getattr(self, name)
+
+
+try:
+    Recursive().attribute
+except RecursionError:
+    pass
+else:
+    assert False, 'RecursionError not raised'
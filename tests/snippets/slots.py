@@ -0,0 +1,174 @@
+from _weakref import ref
+
+
+def assert_raises(exc_type, func, *args):
+    try:
+        func(*args)
+    except exc_type:
+        pass
+    else:
+        assert False, "{} not raised".format(exc_type)
+
+
+class Point:
+    __slots__ = ("x", "y")
+
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+
+
+p = Point(1, 2)
+assert p.x == 1 and p.y == 2
+p.x = 10
+assert p.x == 10
+
+# No __dict__, so unknown attributes can't be set.
+assert_raises(AttributeError, setattr, p, "z", 3)
+assert not hasattr(p, "__dict__")
+assert_raises(AttributeError, lambda: p.__dict__)
+assert_raises(TypeError, ref, p)
+
+# Unset and deleted slots are missing.
+del p.y
+assert not hasattr(p, "y")
+assert_raises(AttributeError, delattr, p, "y")
+p.y = 5
+assert p.y == 5
+
+# The slots are member descriptors in the class.
+assert repr(Point.x) == "<member 'x' of 'Point' objects>"
+assert Point.x.__name__ == "x"
+assert Point.x.__objclass__ is Point
+assert Point.x.__get__(p, Point) == 10
+assert Point.__slots__ == ("x", "y")
+
+class Other:
+    __slots__ = "x"
+
+assert_raises(TypeError, Point.x.__get__, Other(), Other)
+
+
+# A subclass without __slots__ gets a __dict__ and weak references.
+class Point3(Point):
+    pass
+
+
+q = Point3(1, 2)
+q.z = 3
+assert q.__dict__["z"] == 3
+assert q.x == 1
+assert ref(q)() is q
+
+
+# A subclass with __slots__ adds its own.
+class Labeled(Point):
+    __slots__ = ["label"]
+
+    def __init__(self, x, y, label):
+        super().__init__(x, y)
+        self.label = label
+
+
+l = Labeled(1, 2, "a")
+assert (l.x, l.y, l.label) == (1, 2, "a")
+assert_raises(AttributeError, setattr, l, "other", 1)
+
+
+# __dict__ and __weakref__ can be asked for.
+class WithDict:
+    __slots__ = ("a", "__dict__", "__weakref__")
+
+
+w = WithDict()
+w.a = 1
+w.b = 2
+assert w.a == 1 and w.b == 2
+assert "a" not in w.__dict__
+assert ref(w)() is w
+
+assert_raises(TypeError, type, "Twice", (WithDict,), {"__slots__": ("__dict__",)})
+assert_raises(TypeError, type, "Bad", (), {"__slots__": (1,)})
+assert_raises(TypeError, type, "Bad", (), {"__slots__": ("not an identifier",)})
+assert_raises(ValueError, type, "Bad", (), {"__slots__": ("a",), "a": 1})
+
+
+# Bases with slots of their own can't be combined.
+class A:
+    __slots__ = ("a",)
+
+
+class B:
+    __slots__ = ("b",)
+
+
+assert_raises(TypeError, type, "AB", (A, B), {})
+
+
+# Unless the slots of one extend those of the other, or one has none.
+class SubA(A):
+    __slots__ = ("c",)
+
+
+class Mixin:
+    pass
+
+
+class Combined(SubA, A, Mixin):
+    pass
+
+
+c = Combined()
+c.a = 1
+c.c = 2
+c.d = 3
+assert (c.a, c.c, c.d) == (1, 2, 3)
+
+
+# Empty __slots__ give instances no attributes at all.
+class Empty:
+    __slots__ = ()
+
+
+assert_raises(AttributeError, setattr, Empty(), "a", 1)
+
+
+# Exception instances keep their __dict__.
+class Error(Exception):
+    __slots__ = ("code",)
+
+
+e = Error("message")
+e.code = 1
+e.note = "kept"
+assert e.code == 1 and e.note == "kept"
+
+
+# The slots survive until __del__ runs.
+seen = []
+
+
+class Finalized:
+    __slots__ = ("value",)
+
+    def __del__(self):
+        seen.append(self.value)
+
+
+f = Finalized()
+f.value = 42
+del f
+assert seen == [42]
+
+
+# The instances of native types have no room for slots, but exceptions do.
+def subclass(base, slots):
+    return type("Sub", (base,), {"__slots__": slots})
+
+
+for base in (int, bytes, tuple):
+    assert_raises(TypeError, subclass, base, ("extra",))
+    assert_raises(TypeError, subclass, subclass(base, ()), ("extra",))
+    subclass(base, ())
+
+subclass(subclass(ValueError, ("extra",)), ("more",))
@@ -0,0 +1,106 @@
+# Ints that fit in a machine word take fast paths, which have to give the
+# same results as arbitrary precision arithmetic.
+
+big = 2 ** 63
+assert 2 ** 62 + 2 ** 62 == big
+assert (big - 1) + 1 == big
+assert -(big - 1) - 2 == -big - 1
+assert 3037000500 * 3037000500 == 9223372037000250000
+assert big - 1 - (big - 1) == 0
+assert big // big == 1
+
+x = 9223372036854775807
+x += 1
+assert x == big
+x -= 1
+assert x == big - 1
+
+# Floor division and modulo round towards negative infinity.
+assert -7 // 2 == -4
+assert 7 // -2 == -4
+assert -7 // -2 == 3
+assert -7 % 2 == 1
+assert 7 % -2 == -1
+assert -7 % -2 == -1
+assert divmod(-7, 2) == (-4, 1)
+assert divmod(7, -2) == (-4, -1)
+assert (-big) // -1 == big
+assert (-big) % -1 == 0
+
+try:
+    1 // 0
+except ZeroDivisionError:
+    pass
+else:
+    assert False, 'expected ZeroDivisionError'
+
+try:
+    1 % 0
+except ZeroDivisionError:
+    pass
+else:
+    assert False, 'expected ZeroDivisionError'
+
+# Shifts:
+assert 1 << 62 == 4611686018427387904
+assert 1 << 63 == big
+assert 1 << 100 == 1267650600228229401496703205376
+assert -1 << 64 == -18446744073709551616
+assert 0 << 1000 == 0
+assert 5 >> 1 == 2
+assert -5 >> 1 == -3
+assert 5 >> 100 == 0
+assert -5 >> 100 == -1
+assert (1 << 100) >> 99 == 2
+
+try:
+    1 << -1
+except ValueError:
+    pass
+else:
+    assert False, 'expected ValueError'
+
+# Bitwise operators and comparisons:
+assert 12 & 10 == 8
+assert 12 | 10 == 14
+assert 12 ^ 10 == 6
+assert ~5 == -6
+assert -big < big - 1 < big
+assert big > 1
+assert not (big == big - 1)
+assert abs(-big) == big
+assert -(-big) == big
+
+# Bools aren't exact ints.
+assert True + True == 2
+assert (True < 2) is True
+
+# Subclasses of int keep their operators.
+
+
+class MyInt(int):
+    def __add__(self, other):
+        return 'added'
+
+    def __lt__(self, other):
+        return 'less'
+
+
+assert MyInt(1) + 2 == 'added'
+assert (MyInt(1) < 2) == 'less'
+assert 1 + MyInt(2) == 3
+
+# Small ints are shared.
+a = 100
+b = 99
+b += 1
+assert a is b
+assert int('100') is a
+
+# The hash of an int doesn't depend on how it was computed.
+big = 2 ** 70
+assert hash(big // 2 ** 10) == hash(2 ** 60)
+assert hash(big - big + 5) == hash(5)
+assert hash(-2 ** 63) == hash(-(2 ** 62) * 2)
+assert hash(2 ** 64) == hash(big // 2 ** 6)
+assert len({2 ** 60, big // 2 ** 10, 5, big - big + 5}) == 2
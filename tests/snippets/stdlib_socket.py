@@ -102,3 +102,34 @@ with assertRaises(OSError):
 
 with assertRaises(OSError):
 	socket.socket(socket.AF_INET, 1000)
+
+# Blocking calls let other threads run
+import threading
+import time
+
+listener = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+listener.bind(("127.0.0.1", 0))
+listener.listen(1)
+
+accepted = []
+acceptor = threading.Thread(target=lambda: accepted.append(listener.accept()))
+acceptor.start()
+time.sleep(0.05)
+# The other thread waits in accept() by now.
+connector = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+connector.connect(("127.0.0.1", listener.getsockname()[1]))
+acceptor.join()
+(connection, addr) = accepted[0]
+assert addr == connector.getsockname()
+
+received = []
+receiver = threading.Thread(target=lambda: received.append(connection.recv(len(MESSAGE_A))))
+receiver.start()
+time.sleep(0.05)
+connector.send(MESSAGE_A)
+receiver.join()
+assert received == [MESSAGE_A]
+
+connection.close()
+connector.close()
+listener.close()
@@ -0,0 +1,77 @@
+import io
+import sys
+
+from testutils import assert_raises
+
+assert sys.__stdout__ is sys.stdout
+assert sys.__stderr__ is sys.stderr
+assert sys.__stdin__ is sys.stdin
+assert sys.__excepthook__ is sys.excepthook
+assert sys.stdout.writable()
+assert sys.stdin.readable()
+assert not sys.stdout.readable()
+assert sys.stdout.name == '<stdout>'
+assert sys.stderr.name == '<stderr>'
+assert sys.stdin.mode == 'r'
+assert sys.stdout.fileno() == 1
+assert not sys.stdout.closed
+assert sys.stdout.write('') == 0
+
+# print writes to sys.stdout, whatever it is at the time.
+output = io.StringIO()
+sys.stdout = output
+print('hello', 42)
+print('a', 'b', sep='-', end='!', flush=True)
+sys.stdout = sys.__stdout__
+assert output.getvalue() == 'hello 42\na-b!'
+
+# print(file=...) writes to the given file instead.
+output = io.StringIO()
+print('to a file', file=output)
+print('file=None prints to sys.stdout', file=None)
+assert output.getvalue() == 'to a file\n'
+
+# Anything with a write method will do.
+class Recorder:
+    def __init__(self):
+        self.parts = []
+
+    def write(self, text):
+        self.parts.append(text)
+
+recorder = Recorder()
+print(1, 2, file=recorder)
+assert ''.join(recorder.parts) == '1 2\n'
+
+# Output to a None sys.stdout is dropped.
+sys.stdout = None
+print('dropped')
+sys.stdout = sys.__stdout__
+
+# input reads a line from sys.stdin and writes the prompt to sys.stdout.
+output = io.StringIO()
+sys.stdin = io.StringIO('first line\nsecond line\n')
+sys.stdout = output
+try:
+    first = input('> ')
+    second = input()
+    assert_raises(EOFError, input)
+finally:
+    sys.stdin = sys.__stdin__
+    sys.stdout = sys.__stdout__
+assert first == 'first line'
+assert second == 'second line'
+assert output.getvalue() == '> '
+
+# sys.excepthook writes the traceback to sys.stderr.
+errors = io.StringIO()
+sys.stderr = errors
+try:
+    try:
+        raise ValueError('bad value')
+    except ValueError as err:
+        sys.excepthook(type(err), err, err.__traceback__)
+finally:
+    sys.stderr = sys.__stderr__
+assert errors.getvalue().startswith('Traceback (most recent call last):\n')
+assert errors.getvalue().endswith('ValueError: bad value\n')
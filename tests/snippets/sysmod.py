@@ -2,3 +2,89 @@ import sys
 
 print(sys.argv)
 assert sys.argv[0].endswith('.py')
+
+import io
+
+from testutils import assert_raises
+
+assert sys.version_info >= (3, 5)
+assert sys.version_info[:2] == (sys.version_info.major, sys.version_info.minor)
+assert type(sys.version_info[:2]) is tuple
+assert isinstance(sys.version_info, tuple)
+assert len(sys.version_info) == 5
+assert sys.version_info.releaselevel in ('alpha', 'beta', 'candidate', 'final')
+assert sys.version.startswith(str(sys.version_info.major) + '.')
+assert isinstance(sys.hexversion, int)
+assert sys.hexversion >> 24 == sys.version_info.major
+assert isinstance(sys.implementation.name, str)
+assert isinstance(sys.implementation.version, tuple)
+assert isinstance(sys.platform, str)
+assert sys.byteorder in ('little', 'big')
+assert isinstance(sys.executable, str)
+assert sys.flags.optimize == 0
+assert sys.flags.ignore_environment in (0, 1)
+assert sys.float_info.mant_dig == 53
+assert sys.float_info.radix == 2
+assert sys.float_info.epsilon > 0
+assert 'sys' in sys.builtin_module_names
+assert 'builtins' in sys.builtin_module_names
+assert sys.warnoptions == []
+
+# exc_info describes the exception being handled.
+assert sys.exc_info() == (None, None, None)
+try:
+    raise KeyError('key')
+except KeyError as err:
+    exc_type, exc_value, exc_tb = sys.exc_info()
+    assert exc_type is KeyError
+    assert exc_value is err
+
+    def inner():
+        return sys.exc_info()[1]
+    assert inner() is err
+
+    try:
+        raise
+    except KeyError as reraised:
+        assert reraised is err
+assert sys.exc_info() == (None, None, None)
+
+def handled_in_callee():
+    try:
+        raise ValueError
+    except ValueError:
+        return sys.exc_info()[0]
+assert handled_in_callee() is ValueError
+assert sys.exc_info() == (None, None, None)
+
+for i in range(2):
+    try:
+        raise ValueError
+    except ValueError:
+        break
+assert sys.exc_info() == (None, None, None)
+
+assert_raises(RuntimeError, lambda: exec('raise'))
+
+# exit raises SystemExit.
+try:
+    sys.exit(3)
+except SystemExit as err:
+    assert err.code == 3
+try:
+    sys.exit()
+except SystemExit as err:
+    assert err.code is None
+
+# displayhook prints the repr of a value and keeps it as builtins._
+import builtins
+output = io.StringIO()
+sys.stdout = output
+try:
+    sys.displayhook('value')
+    sys.displayhook(None)
+finally:
+    sys.stdout = sys.__stdout__
+assert output.getvalue() == "'value'\n"
+assert builtins._ == 'value'
+assert sys.__displayhook__ is sys.displayhook
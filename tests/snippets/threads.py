@@ -0,0 +1,174 @@
+import _thread
+import threading
+import time
+
+from testutils import assertRaises
+
+
+# Locks:
+lock = threading.Lock()
+assert not lock.locked()
+assert lock.acquire()
+assert lock.locked()
+assert not lock.acquire(False)
+assert not lock.acquire(timeout=0.01)
+lock.release()
+assert not lock.locked()
+with assertRaises(RuntimeError):
+    lock.release()
+with assertRaises(ValueError):
+    lock.acquire(False, 1)
+with lock:
+    assert lock.locked()
+assert not lock.locked()
+
+rlock = threading.RLock()
+with rlock:
+    with rlock:
+        assert rlock.acquire(False)
+        rlock.release()
+assert rlock.acquire()
+rlock.release()
+with assertRaises(RuntimeError):
+    rlock.release()
+
+
+# Threads sharing state through a lock:
+counter = [0]
+counter_lock = threading.Lock()
+
+
+def count(n):
+    for _ in range(n):
+        with counter_lock:
+            counter[0] += 1
+
+
+threads = [threading.Thread(target=count, args=(50,)) for _ in range(4)]
+for t in threads:
+    assert not t.is_alive()
+    t.start()
+for t in threads:
+    t.join()
+    assert not t.is_alive()
+assert counter == [200]
+
+with assertRaises(RuntimeError):
+    threads[0].start()
+with assertRaises(RuntimeError):
+    threading.Thread().join()
+
+
+# Subclassing Thread and keyword arguments:
+results = []
+
+
+class Worker(threading.Thread):
+    def __init__(self, value):
+        super().__init__(name='worker-' + str(value))
+        self.value = value
+
+    def run(self):
+        results.append((threading.current_thread().name, self.value))
+
+
+w = Worker(7)
+assert w.name == 'worker-7'
+assert w.ident is None
+w.start()
+w.join()
+assert isinstance(w.ident, int)
+assert results == [('worker-7', 7)]
+
+
+def record(value=None):
+    results.append(value)
+
+
+t = threading.Thread(target=record, kwargs={'value': 'kw'}, daemon=True)
+assert t.daemon
+t.start()
+t.join()
+assert results[-1] == 'kw'
+
+assert threading.current_thread() is threading.main_thread()
+assert threading.current_thread().name == 'MainThread'
+assert threading.get_ident() == _thread.get_ident()
+
+
+# A busy thread gets preempted, so the main thread can stop it:
+stop = []
+spins = [0]
+
+
+def spin():
+    while not stop:
+        spins[0] += 1
+
+
+spinner = threading.Thread(target=spin)
+spinner.start()
+time.sleep(0.05)
+stop.append(True)
+spinner.join()
+assert spins[0] > 0
+
+
+# Events and conditions:
+event = threading.Event()
+assert not event.is_set()
+assert not event.wait(0.01)
+setter = threading.Thread(target=event.set)
+setter.start()
+assert event.wait(5.0)
+setter.join()
+event.clear()
+assert not event.is_set()
+
+items = []
+cond = threading.Condition()
+
+
+def produce():
+    for i in range(3):
+        with cond:
+            items.append(i)
+            cond.notify()
+
+
+def consume(n):
+    got = []
+    with cond:
+        while len(got) < n:
+            cond.wait_for(lambda: items)
+            got.append(items.pop(0))
+    results.append(got)
+
+
+consumer = threading.Thread(target=consume, args=(3,))
+consumer.start()
+produce()
+consumer.join()
+assert results[-1] == [0, 1, 2]
+
+with cond:
+    assert not cond.wait(0.01)
+with assertRaises(RuntimeError):
+    cond.notify()
+
+
+# Low level threads:
+done = _thread.allocate_lock()
+done.acquire()
+box = []
+
+
+def low_level(a, b):
+    box.append(a + b)
+    done.release()
+
+
+ident = _thread.start_new_thread(low_level, (1, 2))
+assert isinstance(ident, int)
+assert done.acquire(timeout=5.0)
+assert box == [3]
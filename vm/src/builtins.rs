@@ -3,7 +3,6 @@
 //! Implements functions listed here: https://docs.python.org/3/library/builtins.html
 
 use std::char;
-use std::io::{self, Write};
 use std::path::PathBuf;
 
 use num_traits::{Signed, ToPrimitive};
@@ -22,6 +21,7 @@ use crate::function::{Args, OptionalArg, PyFuncArgs};
 use crate::pyobject::{
     DictProtocol, IdProtocol, PyContext, PyObjectRef, PyResult, TryFromObject, TypeProtocol,
 };
+use crate::sysmodule;
 use crate::vm::VirtualMachine;
 
 use crate::obj::objcode::PyCodeRef;
@@ -71,18 +71,20 @@ fn builtin_bin(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let s = if n.is_negative() {
         format!("-0b{:b}", n.abs())
     } else {
-        format!("0b{:b}", n)
+        format!("0b{:b}", &*n)
     };
 
     Ok(vm.new_str(s))
 }
 
-// builtin_breakpoint
+/// Stop in the debugger at the next line.
+fn builtin_breakpoint(vm: &VirtualMachine) {
+    vm.set_trace();
+}
 
 fn builtin_callable(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(vm, args, required = [(obj, None)]);
-    let is_callable = objtype::class_has_attr(&obj.class(), "__call__");
-    Ok(vm.new_bool(is_callable))
+    Ok(vm.new_bool(vm.is_callable(obj)))
 }
 
 fn builtin_chr(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
@@ -308,7 +310,7 @@ fn builtin_hasattr(obj: PyObjectRef, attr: PyStringRef, vm: &VirtualMachine) ->
 fn builtin_hash(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(vm, args, required = [(obj, None)]);
 
-    vm.call_method(obj, "__hash__", vec![])
+    vm._hash(obj)
 }
 
 // builtin_help
@@ -320,7 +322,7 @@ fn builtin_hex(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let s = if n.is_negative() {
         format!("-0x{:x}", n.abs())
     } else {
-        format!("0x{:x}", n)
+        format!("0x{:x}", &*n)
     };
 
     Ok(vm.new_str(s))
@@ -332,7 +334,28 @@ fn builtin_id(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     Ok(vm.context().new_int(obj.get_id()))
 }
 
-// builtin_input
+fn builtin_input(prompt: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult<String> {
+    let stdin = sysmodule::get_stream(vm, "stdin")?
+        .ok_or_else(|| vm.new_runtime_error("input(): lost sys.stdin".to_string()))?;
+    let stdout = sysmodule::get_stream(vm, "stdout")?
+        .ok_or_else(|| vm.new_runtime_error("input(): lost sys.stdout".to_string()))?;
+    if let OptionalArg::Present(prompt) = prompt {
+        let prompt = vm.to_str(&prompt)?;
+        vm.call_method(&stdout, "write", vec![prompt.into_object()])?;
+    }
+    vm.call_method(&stdout, "flush", vec![])?;
+
+    let line = vm.call_method(&stdin, "readline", vec![])?;
+    let line = PyStringRef::try_from_object(vm, line)
+        .map_err(|_| vm.new_type_error("object.readline() returned non-string".to_string()))?;
+    match line.value.as_str() {
+        "" => Err(vm.new_exception(
+            vm.ctx.exceptions.eof_error.clone(),
+            "EOF when reading a line".to_string(),
+        )),
+        line => Ok(line.trim_end_matches('\n').to_string()),
+    }
+}
 
 fn builtin_isinstance(obj: PyObjectRef, typ: PyClassRef, vm: &VirtualMachine) -> PyResult<bool> {
     vm.isinstance(&obj, &typ)
@@ -498,7 +521,7 @@ fn builtin_oct(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let s = if n.is_negative() {
         format!("-0o{:o}", n.abs())
     } else {
-        format!("0o{:o}", n)
+        format!("0o{:o}", &*n)
     };
 
     Ok(vm.new_str(s))
@@ -553,35 +576,46 @@ fn builtin_pow(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
 
 #[derive(Debug, FromArgs)]
 pub struct PrintOptions {
+    #[pyarg(keyword_only, default = "None")]
     sep: Option<PyStringRef>,
+    #[pyarg(keyword_only, default = "None")]
     end: Option<PyStringRef>,
+    #[pyarg(keyword_only, default = "None")]
+    file: Option<PyObjectRef>,
+    #[pyarg(keyword_only, default = "false")]
     flush: bool,
 }
 
 pub fn builtin_print(objects: Args, options: PrintOptions, vm: &VirtualMachine) -> PyResult<()> {
-    let stdout = io::stdout();
-    let mut stdout_lock = stdout.lock();
+    // The file gets the whole text in a single `write`.
+    let mut text = String::new();
     let mut first = true;
     for object in objects {
         if first {
             first = false;
         } else if let Some(ref sep) = options.sep {
-            write!(stdout_lock, "{}", sep.value).unwrap();
+            text.push_str(&sep.value);
         } else {
-            write!(stdout_lock, " ").unwrap();
+            text.push(' ');
         }
-        let s = &vm.to_str(&object)?.value;
-        write!(stdout_lock, "{}", s).unwrap();
+        text.push_str(&vm.to_str(&object)?.value);
     }
 
-    if let Some(end) = options.end {
-        write!(stdout_lock, "{}", end.value).unwrap();
-    } else {
-        writeln!(stdout_lock).unwrap();
+    match options.end {
+        Some(end) => text.push_str(&end.value),
+        None => text.push('\n'),
     }
 
+    let file = match options.file {
+        Some(file) => file,
+        None => match sysmodule::get_stream(vm, "stdout")? {
+            Some(stdout) => stdout,
+            None => return Ok(()),
+        },
+    };
+    vm.call_method(&file, "write", vec![vm.new_str(text)])?;
     if options.flush {
-        stdout_lock.flush().unwrap();
+        vm.call_method(&file, "flush", vec![])?;
     }
 
     Ok(())
@@ -618,7 +652,7 @@ fn builtin_round(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     } else {
         // without a parameter, the result type is coerced to int
         let rounded = &vm.call_method(number, "__round__", vec![])?;
-        Ok(vm.ctx.new_int(objint::get_value(rounded).clone()))
+        Ok(vm.ctx.new_int(objint::get_value(rounded).into_owned()))
     }
 }
 
@@ -693,6 +727,7 @@ pub fn make_module(ctx: &PyContext) -> PyObjectRef {
         "any" => ctx.new_rustfunc(builtin_any),
         "bin" => ctx.new_rustfunc(builtin_bin),
         "bool" => ctx.bool_type(),
+        "breakpoint" => ctx.new_rustfunc(builtin_breakpoint),
         "bytearray" => ctx.bytearray_type(),
         "bytes" => ctx.bytes_type(),
         "callable" => ctx.new_rustfunc(builtin_callable),
@@ -717,6 +752,7 @@ pub fn make_module(ctx: &PyContext) -> PyObjectRef {
         "hash" => ctx.new_rustfunc(builtin_hash),
         "hex" => ctx.new_rustfunc(builtin_hex),
         "id" => ctx.new_rustfunc(builtin_id),
+        "input" => ctx.new_rustfunc(builtin_input),
         "int" => ctx.int_type(),
         "isinstance" => ctx.new_rustfunc(builtin_isinstance),
         "issubclass" => ctx.new_rustfunc(builtin_issubclass),
@@ -754,28 +790,23 @@ pub fn make_module(ctx: &PyContext) -> PyObjectRef {
 
         // Constants
         "NotImplemented" => ctx.not_implemented(),
-
-        // Exceptions:
-        "BaseException" => ctx.exceptions.base_exception_type.clone(),
-        "Exception" => ctx.exceptions.exception_type.clone(),
-        "ArithmeticError" => ctx.exceptions.arithmetic_error.clone(),
-        "AssertionError" => ctx.exceptions.assertion_error.clone(),
-        "AttributeError" => ctx.exceptions.attribute_error.clone(),
-        "NameError" => ctx.exceptions.name_error.clone(),
-        "OverflowError" => ctx.exceptions.overflow_error.clone(),
-        "RuntimeError" => ctx.exceptions.runtime_error.clone(),
-        "NotImplementedError" => ctx.exceptions.not_implemented_error.clone(),
-        "TypeError" => ctx.exceptions.type_error.clone(),
-        "ValueError" => ctx.exceptions.value_error.clone(),
-        "IndexError" => ctx.exceptions.index_error.clone(),
-        "ImportError" => ctx.exceptions.import_error.clone(),
-        "FileNotFoundError" => ctx.exceptions.file_not_found_error.clone(),
-        "StopIteration" => ctx.exceptions.stop_iteration.clone(),
-        "ZeroDivisionError" => ctx.exceptions.zero_division_error.clone(),
-        "KeyError" => ctx.exceptions.key_error.clone(),
-        "OSError" => ctx.exceptions.os_error.clone(),
     });
 
+    // Exceptions:
+    for (name, exc_type) in ctx.exceptions.builtins() {
+        ctx.set_attr(&py_mod, name, exc_type.clone().into_object());
+    }
+    ctx.set_attr(
+        &py_mod,
+        "EnvironmentError",
+        ctx.exceptions.os_error.clone().into_object(),
+    );
+    ctx.set_attr(
+        &py_mod,
+        "IOError",
+        ctx.exceptions.os_error.clone().into_object(),
+    );
+
     #[cfg(not(target_arch = "wasm32"))]
     ctx.set_attr(&py_mod, "open", ctx.new_rustfunc(io_open));
 
@@ -801,21 +832,32 @@ pub fn builtin_build_class_(vm: &VirtualMachine, mut args: PyFuncArgs) -> PyResu
     }
 
     let bases = vm.context().new_tuple(bases);
+    // The other keyword arguments of the class statement are passed to
+    // `__prepare__` and the metaclass, which hands them to `__init_subclass__`.
+    let kwargs: Vec<_> = args
+        .kwargs
+        .into_iter()
+        .filter(|(name, _)| name != "metaclass")
+        .collect();
 
     // Prepare uses full __getattribute__ resolution chain.
     let prepare = vm.get_attribute(metaclass.clone().into_object(), "__prepare__")?;
-    let namespace = vm.invoke(prepare, vec![name_arg.clone(), bases.clone()])?;
+    let prepare_args = PyFuncArgs {
+        args: vec![name_arg.clone(), bases.clone()],
+        kwargs: kwargs.clone(),
+    };
+    let namespace = vm.invoke(prepare, prepare_args)?;
 
     let namespace: PyDictRef = TryFromObject::try_from_object(vm, namespace)?;
 
     let cells = vm.ctx.new_dict();
 
     vm.invoke_with_locals(function, cells.clone(), namespace.clone())?;
-    let class = vm.call_method(
-        metaclass.as_object(),
-        "__call__",
-        vec![name_arg, bases, namespace.into_object()],
-    )?;
+    let class_args = PyFuncArgs {
+        args: vec![name_arg, bases, namespace.into_object()],
+        kwargs,
+    };
+    let class = vm.call_method(metaclass.as_object(), "__call__", class_args)?;
     cells.set_item(&vm.ctx, "__class__", class.clone());
     Ok(class)
 }
@@ -77,6 +77,11 @@ pub enum Instruction {
     LoadAttr {
         name: String,
     },
+    /// Look up a method to call with `CallMethod`, without binding it when
+    /// it is a function found on the class.
+    LoadMethod {
+        name: String,
+    },
     CompareOperation {
         op: ComparisonOperator,
     },
@@ -104,6 +109,9 @@ pub enum Instruction {
     CallFunction {
         typ: CallType,
     },
+    CallMethod {
+        typ: CallType,
+    },
     ForIter {
         target: Label,
     },
@@ -124,6 +132,8 @@ pub enum Instruction {
         end: Label,
     },
     PopBlock,
+    /// Leave the `except` clause that handled an exception.
+    PopException,
     Raise {
         argc: usize,
     },
@@ -305,6 +315,20 @@ impl fmt::Display for CodeObject {
 }
 
 impl Instruction {
+    /// The identifier of the variable or attribute the instruction accesses.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            LoadName { name }
+            | StoreName { name }
+            | DeleteName { name }
+            | LoadAttr { name }
+            | LoadMethod { name }
+            | StoreAttr { name }
+            | DeleteAttr { name } => Some(name),
+            _ => None,
+        }
+    }
+
     fn fmt_dis(&self, f: &mut fmt::Formatter, label_map: &HashMap<Label, usize>) -> fmt::Result {
         macro_rules! w {
             ($variant:ident) => {
@@ -332,6 +356,7 @@ impl Instruction {
             UnaryOperation { op } => w!(UnaryOperation, format!("{:?}", op)),
             BinaryOperation { op, inplace } => w!(BinaryOperation, format!("{:?}", op), inplace),
             LoadAttr { name } => w!(LoadAttr, name),
+            LoadMethod { name } => w!(LoadMethod, name),
             CompareOperation { op } => w!(CompareOperation, format!("{:?}", op)),
             Pop => w!(Pop),
             Rotate { amount } => w!(Rotate, amount),
@@ -345,6 +370,7 @@ impl Instruction {
             JumpIfFalse { target } => w!(JumpIfFalse, label_map[target]),
             MakeFunction { flags } => w!(MakeFunction, format!("{:?}", flags)),
             CallFunction { typ } => w!(CallFunction, format!("{:?}", typ)),
+            CallMethod { typ } => w!(CallMethod, format!("{:?}", typ)),
             ForIter { target } => w!(ForIter, label_map[target]),
             ReturnValue => w!(ReturnValue),
             YieldValue => w!(YieldValue),
@@ -354,6 +380,7 @@ impl Instruction {
             SetupWith { end } => w!(SetupWith, end),
             CleanupWith { end } => w!(CleanupWith, end),
             PopBlock => w!(PopBlock),
+            PopException => w!(PopException),
             Raise { argc } => w!(Raise, argc),
             BuildString { size } => w!(BuildString, size),
             BuildTuple { size, unpack } => w!(BuildTuple, size, unpack),
@@ -21,6 +21,7 @@ struct Compiler {
     current_source_location: ast::Location,
     in_loop: bool,
     in_function_def: bool,
+    optimize: u8,
 }
 
 /// Compile a given sourcecode into a bytecode object.
@@ -30,7 +31,7 @@ pub fn compile(
     mode: &Mode,
     source_path: String,
 ) -> Result<PyCodeRef, CompileError> {
-    let mut compiler = Compiler::new();
+    let mut compiler = Compiler::new(vm.settings.optimize);
     compiler.source_path = Some(source_path);
     compiler.push_new_code_object("<module>".to_string());
 
@@ -51,7 +52,7 @@ pub fn compile(
 
     let code = compiler.pop_code_object();
     trace!("Compilation completed: {:?}", code);
-    Ok(objcode::PyCode::new(code).into_ref(vm))
+    Ok(objcode::PyCode::new(&vm.ctx, code).into_ref(vm))
 }
 
 pub enum Mode {
@@ -69,7 +70,7 @@ enum EvalContext {
 type Label = usize;
 
 impl Compiler {
-    fn new() -> Self {
+    fn new(optimize: u8) -> Self {
         Compiler {
             code_object_stack: Vec::new(),
             nxt_label: 0,
@@ -77,6 +78,7 @@ impl Compiler {
             current_source_location: ast::Location::default(),
             in_loop: false,
             in_function_def: false,
+            optimize,
         }
     }
 
@@ -313,7 +315,10 @@ impl Compiler {
                 decorator_list,
             } => self.compile_class_def(name, body, bases, keywords, decorator_list)?,
             ast::Statement::Assert { test, msg } => {
-                // TODO: if some flag, ignore all assert statements!
+                // Like `-O`, leave out asserts from optimization level 1 on.
+                if self.optimize > 0 {
+                    return Ok(());
+                }
 
                 let end_label = self.new_label();
                 self.compile_test(test, Some(end_label), None, EvalContext::Statement)?;
@@ -543,6 +548,7 @@ impl Compiler {
 
             // Handler code:
             self.compile_statements(&handler.body)?;
+            self.emit(Instruction::PopException);
             self.emit(Instruction::Jump {
                 target: finally_label,
             });
@@ -594,6 +600,7 @@ impl Compiler {
         // remember to restore self.in_loop to the original after the function is compiled
         let was_in_loop = self.in_loop;
         let was_in_function_def = self.in_function_def;
+        let location = self.current_source_location.clone();
         self.in_loop = false;
         self.in_function_def = true;
         let mut flags = self.enter_function(name, args)?;
@@ -605,6 +612,8 @@ impl Compiler {
         });
         self.emit(Instruction::ReturnValue);
         let code = self.pop_code_object();
+        // The rest belongs to the def statement again:
+        self.set_source_location(&location);
 
         self.prepare_decorators(decorator_list)?;
 
@@ -676,6 +685,7 @@ impl Compiler {
         decorator_list: &[ast::Expression],
     ) -> Result<(), CompileError> {
         let was_in_loop = self.in_loop;
+        let location = self.current_source_location.clone();
         self.in_loop = false;
         self.prepare_decorators(decorator_list)?;
         self.emit(Instruction::LoadBuildClass);
@@ -696,6 +706,7 @@ impl Compiler {
         self.emit(Instruction::ReturnValue);
 
         let code = self.pop_code_object();
+        self.set_source_location(&location);
         self.emit(Instruction::LoadConst {
             value: bytecode::Constant::Code {
                 code: Box::new(code),
@@ -1164,7 +1175,17 @@ impl Compiler {
         args: &[ast::Expression],
         keywords: &[ast::Keyword],
     ) -> Result<(), CompileError> {
-        self.compile_expression(function)?;
+        // Methods are looked up without binding them to the object.
+        let method_call = if let ast::Expression::Attribute { value, name } = function {
+            self.compile_expression(value)?;
+            self.emit(Instruction::LoadMethod {
+                name: name.to_string(),
+            });
+            true
+        } else {
+            self.compile_expression(function)?;
+            false
+        };
         let count = args.len() + keywords.len();
 
         // Normal arguments:
@@ -1205,13 +1226,9 @@ impl Compiler {
                     unpack: has_double_star,
                 });
 
-                self.emit(Instruction::CallFunction {
-                    typ: CallType::Ex(true),
-                });
+                self.emit_call(method_call, CallType::Ex(true));
             } else {
-                self.emit(Instruction::CallFunction {
-                    typ: CallType::Ex(false),
-                });
+                self.emit_call(method_call, CallType::Ex(false));
             }
         } else {
             // Keyword arguments:
@@ -1234,18 +1251,22 @@ impl Compiler {
                         elements: kwarg_names,
                     },
                 });
-                self.emit(Instruction::CallFunction {
-                    typ: CallType::Keyword(count),
-                });
+                self.emit_call(method_call, CallType::Keyword(count));
             } else {
-                self.emit(Instruction::CallFunction {
-                    typ: CallType::Positional(count),
-                });
+                self.emit_call(method_call, CallType::Positional(count));
             }
         }
         Ok(())
     }
 
+    fn emit_call(&mut self, method_call: bool, typ: CallType) {
+        if method_call {
+            self.emit(Instruction::CallMethod { typ });
+        } else {
+            self.emit(Instruction::CallFunction { typ });
+        }
+    }
+
     // Given a vector of expr / star expr generate code which gives either
     // a list of expressions on the stack, or a list of tuples.
     fn gather_elements(&mut self, elements: &[ast::Expression]) -> Result<bool, CompileError> {
@@ -1405,6 +1426,13 @@ impl Compiler {
             self.emit(Instruction::PopBlock);
         }
 
+        if let ast::ComprehensionKind::GeneratorExpression { .. } = kind {
+            // Generator expressions have nothing to return:
+            self.emit(Instruction::LoadConst {
+                value: bytecode::Constant::None,
+            });
+        }
+
         // Return freshly filled list:
         self.emit(Instruction::ReturnValue);
 
@@ -1520,7 +1548,7 @@ mod tests {
     use rustpython_parser::parser;
 
     fn compile_exec(source: &str) -> CodeObject {
-        let mut compiler = Compiler::new();
+        let mut compiler = Compiler::new(0);
         compiler.source_path = Some("source_path".to_string());
         compiler.push_new_code_object("<module>".to_string());
         let ast = parser::parse_program(&source.to_string()).unwrap();
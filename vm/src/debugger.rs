@@ -0,0 +1,493 @@
+/*! An interactive, pdb like, step debugger.
+
+The frame evaluation loop reports every line it starts executing to the
+debugger while it is enabled. When a line matches a breakpoint, or the
+current stepping command asks for it, execution is suspended and commands
+are read from the stdin of the VM until one of them resumes it. Like pdb, the
+debugger talks on the standard streams of the program: `vm.stdin` and
+`vm.stdout`.
+
+*/
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use rustpython_parser::ast;
+
+use crate::bytecode;
+use crate::compile;
+use crate::frame::FrameRef;
+use crate::pyobject::{PyObjectRef, PyResult, TypeProtocol};
+use crate::vm::VirtualMachine;
+
+const HELP: &str = "\
+Commands:
+  s(tep)              run until the next line, entering called functions
+  n(ext)              run until the next line in the current function
+  r(eturn)            run until the current function returns
+  c(ont(inue))        run until a breakpoint is hit
+  b(reak) [file:]line set a breakpoint, or list them without an argument
+  cl(ear) [number]    delete a breakpoint, or all of them
+  p expression        print the value of an expression
+  l(ist)              show the source around the current line
+  w(here)             print the call stack
+  u(p) / d(own)       move to the calling / called frame
+  q(uit)              stop the program
+  !statement          run a statement in the current frame
+Anything else is run as a statement in the current frame as well.
+An empty line repeats the last command.";
+
+/// `println!` to the stdout of the VM.
+macro_rules! output {
+    ($vm:expr) => {{
+        let _ = writeln!($vm.stdout());
+    }};
+    ($vm:expr, $($arg:tt)*) => {{
+        let _ = writeln!($vm.stdout(), $($arg)*);
+    }};
+}
+
+/// How far to run before stopping again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StepMode {
+    /// Only stop at breakpoints.
+    Continue,
+    /// Stop at the next line in any frame.
+    Step,
+    /// Stop at the next line in a frame at most this deep.
+    Next(usize),
+    /// Stop at the next line in a frame less deep than this.
+    Return(usize),
+}
+
+#[derive(Debug)]
+struct Breakpoint {
+    file: String,
+    line: usize,
+}
+
+impl Breakpoint {
+    fn matches(&self, source_path: &str, line: usize) -> bool {
+        self.line == line
+            && (self.file == source_path || Path::new(source_path).ends_with(&self.file))
+    }
+}
+
+/// What to do after a debugger command ran.
+enum Action {
+    Prompt,
+    Resume(StepMode),
+}
+
+#[derive(Debug)]
+pub struct Debugger {
+    enabled: Cell<bool>,
+    /// Set while the prompt is shown, so the code run by its commands is not
+    /// traced itself.
+    stopped: Cell<bool>,
+    mode: Cell<StepMode>,
+    breakpoints: RefCell<Vec<Breakpoint>>,
+    last_command: RefCell<String>,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Debugger {
+            enabled: Cell::new(false),
+            stopped: Cell::new(false),
+            mode: Cell::new(StepMode::Continue),
+            breakpoints: RefCell::new(vec![]),
+            last_command: RefCell::new(String::new()),
+        }
+    }
+}
+
+impl Debugger {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get() && !self.stopped.get()
+    }
+
+    /// Whether the debugger may need to hear about lines, even if it is
+    /// stopped at the prompt right now.
+    pub fn may_trace(&self) -> bool {
+        self.enabled.get()
+    }
+
+    /// Enable the debugger and stop at the next line that gets executed, see
+    /// `VirtualMachine::set_trace`.
+    pub(crate) fn set_trace(&self) {
+        self.mode.set(StepMode::Step);
+        self.enabled.set(true);
+    }
+
+    /// Called by the innermost frame whenever it starts executing a new line.
+    pub fn line_event(&self, vm: &VirtualMachine, line: usize) -> PyResult<()> {
+        let frames = vm.frames.borrow().clone();
+        let depth = frames.len();
+        let frame = match frames.last() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        let source_path = &frame.code.source_path;
+        let stop = match self.mode.get() {
+            StepMode::Step => true,
+            StepMode::Next(max_depth) => depth <= max_depth,
+            StepMode::Return(max_depth) => depth < max_depth,
+            StepMode::Continue => false,
+        } || self
+            .breakpoints
+            .borrow()
+            .iter()
+            .any(|breakpoint| breakpoint.matches(source_path, line));
+        if !stop {
+            return Ok(());
+        }
+        self.stopped.set(true);
+        let result = self.interact(vm, &frames);
+        self.stopped.set(false);
+        result
+    }
+
+    fn interact(&self, vm: &VirtualMachine, frames: &[FrameRef]) -> PyResult<()> {
+        let mut selected = frames.len() - 1;
+        print_location(vm, frames, selected, ">");
+        loop {
+            {
+                let mut stdout = vm.stdout();
+                let _ = write!(stdout, "(Pdb) ");
+                let _ = stdout.flush();
+            }
+            let mut input = String::new();
+            // Another thread may be reading stdin, and not be done until the
+            // program is, so give up on it as well then.
+            let read = match vm.stdin_reader.try_take_turn() {
+                Ok(_turn) => {
+                    let mut stdin = vm.stdin.borrow_mut();
+                    vm.allow_threads(|| stdin.read_line(&mut input)).ok()
+                }
+                Err(_) => None,
+            };
+            let command = match read {
+                Some(0) | None => {
+                    // Nobody is left to tell us what to do.
+                    output!(vm);
+                    self.enabled.set(false);
+                    return Ok(());
+                }
+                Some(_) => input.trim().to_string(),
+            };
+            let command = if command.is_empty() {
+                self.last_command.borrow().clone()
+            } else {
+                self.last_command.replace(command.clone());
+                command
+            };
+            match self.run_command(vm, frames, &mut selected, &command)? {
+                Action::Prompt => {}
+                Action::Resume(mode) => {
+                    self.mode.set(mode);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn run_command(
+        &self,
+        vm: &VirtualMachine,
+        frames: &[FrameRef],
+        selected: &mut usize,
+        command: &str,
+    ) -> PyResult<Action> {
+        let (name, arg) = match command.find(char::is_whitespace) {
+            Some(index) => (&command[..index], command[index..].trim()),
+            None => (command, ""),
+        };
+        let depth = frames.len();
+        let action = match name {
+            "" => Action::Prompt,
+            "h" | "help" => {
+                output!(vm, "{}", HELP);
+                Action::Prompt
+            }
+            "s" | "step" => Action::Resume(StepMode::Step),
+            "n" | "next" => Action::Resume(StepMode::Next(depth)),
+            "r" | "return" => Action::Resume(StepMode::Return(depth)),
+            "c" | "cont" | "continue" => Action::Resume(StepMode::Continue),
+            "q" | "quit" | "exit" => {
+                self.enabled.set(false);
+                let system_exit = vm.ctx.exceptions.system_exit.clone();
+                return Err(vm.new_empty_exception(system_exit)?);
+            }
+            "b" | "break" => {
+                self.set_breakpoint(vm, &frames[*selected], arg);
+                Action::Prompt
+            }
+            "cl" | "clear" => {
+                self.clear_breakpoint(vm, arg);
+                Action::Prompt
+            }
+            "l" | "list" => {
+                self.list_source(vm, &frames[*selected], frame_line(frames, *selected));
+                Action::Prompt
+            }
+            "w" | "where" | "bt" => {
+                for index in 0..frames.len() {
+                    let marker = if index == *selected { ">" } else { " " };
+                    print_location(vm, frames, index, marker);
+                }
+                Action::Prompt
+            }
+            "u" | "up" => {
+                if *selected == 0 {
+                    output!(vm, "*** Oldest frame");
+                } else {
+                    *selected -= 1;
+                    print_location(vm, frames, *selected, ">");
+                }
+                Action::Prompt
+            }
+            "d" | "down" => {
+                if *selected + 1 == frames.len() {
+                    output!(vm, "*** Newest frame");
+                } else {
+                    *selected += 1;
+                    print_location(vm, frames, *selected, ">");
+                }
+                Action::Prompt
+            }
+            "p" | "print" => {
+                match run_source(vm, &frames[*selected], arg, &compile::Mode::Eval) {
+                    Ok(value) => match vm.to_repr(&value) {
+                        Ok(repr) => output!(vm, "{}", repr.value),
+                        Err(exc) => print_error(vm, &exc),
+                    },
+                    Err(exc) => print_error(vm, &exc),
+                }
+                Action::Prompt
+            }
+            _ => {
+                let statement = if command.starts_with('!') {
+                    &command[1..]
+                } else {
+                    command
+                };
+                if let Err(exc) =
+                    run_source(vm, &frames[*selected], statement, &compile::Mode::Single)
+                {
+                    print_error(vm, &exc);
+                }
+                Action::Prompt
+            }
+        };
+        Ok(action)
+    }
+
+    fn set_breakpoint(&self, vm: &VirtualMachine, frame: &FrameRef, arg: &str) {
+        let mut breakpoints = self.breakpoints.borrow_mut();
+        if arg.is_empty() {
+            for (index, breakpoint) in breakpoints.iter().enumerate() {
+                output!(vm, "{}: {}:{}", index + 1, breakpoint.file, breakpoint.line);
+            }
+            return;
+        }
+        let (file, line) = match arg.rfind(':') {
+            Some(index) => (arg[..index].to_string(), &arg[index + 1..]),
+            None => (frame.code.source_path.clone(), arg),
+        };
+        match line.parse::<usize>() {
+            Ok(line) if line > 0 => {
+                output!(
+                    vm,
+                    "Breakpoint {} at {}:{}",
+                    breakpoints.len() + 1,
+                    file,
+                    line
+                );
+                breakpoints.push(Breakpoint { file, line });
+            }
+            _ => output!(vm, "*** Invalid line number: {}", line),
+        }
+    }
+
+    fn clear_breakpoint(&self, vm: &VirtualMachine, arg: &str) {
+        let mut breakpoints = self.breakpoints.borrow_mut();
+        if arg.is_empty() {
+            breakpoints.clear();
+            output!(vm, "Deleted all breakpoints");
+            return;
+        }
+        match arg.parse::<usize>() {
+            Ok(number) if number > 0 && number <= breakpoints.len() => {
+                let breakpoint = breakpoints.remove(number - 1);
+                output!(
+                    vm,
+                    "Deleted breakpoint {} at {}:{}",
+                    number,
+                    breakpoint.file,
+                    breakpoint.line
+                );
+            }
+            _ => output!(vm, "*** No breakpoint numbered {}", arg),
+        }
+    }
+
+    /// Show the lines around `line`, marking the ones that have code, the
+    /// current one and those with a breakpoint.
+    fn list_source(&self, vm: &VirtualMachine, frame: &FrameRef, line: usize) {
+        let source_path = &frame.code.source_path;
+        let source = match fs::read_to_string(source_path) {
+            Ok(source) => source,
+            Err(_) => {
+                output!(vm, "*** Could not read source of {}", source_path);
+                return;
+            }
+        };
+        let mut code_lines = HashSet::new();
+        collect_lines(&frame.code, &mut code_lines);
+        let first = line.saturating_sub(5).max(1);
+        let breakpoints = self.breakpoints.borrow();
+        for (index, text) in source.lines().enumerate().skip(first - 1).take(11) {
+            let number = index + 1;
+            let breakpoint = breakpoints
+                .iter()
+                .any(|breakpoint| breakpoint.matches(source_path, number));
+            let marker = match (number == line, breakpoint) {
+                (true, _) => "->",
+                (false, true) => "B ",
+                (false, false) if code_lines.contains(&number) => " .",
+                (false, false) => "  ",
+            };
+            output!(vm, "{:4} {} {}", number, marker, text);
+        }
+    }
+}
+
+/// The lines that have code, including those of nested functions and classes.
+fn collect_lines(code: &bytecode::CodeObject, lines: &mut HashSet<usize>) {
+    lines.extend(code.locations.iter().map(ast::Location::get_row));
+    for instruction in &code.instructions {
+        if let bytecode::Instruction::LoadConst {
+            value: bytecode::Constant::Code { code },
+        } = instruction
+        {
+            collect_lines(code, lines);
+        }
+    }
+}
+
+/// The line a frame is at: the innermost one is about to run its current
+/// instruction, the others are in the middle of a call.
+fn frame_line(frames: &[FrameRef], index: usize) -> usize {
+    let frame = &frames[index];
+    if index + 1 == frames.len() {
+        frame.get_lineno().get_row()
+    } else {
+        frame.current_location().get_row()
+    }
+}
+
+fn print_location(vm: &VirtualMachine, frames: &[FrameRef], index: usize, marker: &str) {
+    let frame = &frames[index];
+    let line = frame_line(frames, index);
+    output!(
+        vm,
+        "{} {}({}){}()",
+        marker,
+        frame.code.source_path,
+        line,
+        frame.code.obj_name
+    );
+    if let Ok(source) = fs::read_to_string(&frame.code.source_path) {
+        if let Some(text) = source.lines().nth(line.saturating_sub(1)) {
+            output!(vm, "-> {}", text.trim());
+        }
+    }
+}
+
+fn run_source(
+    vm: &VirtualMachine,
+    frame: &FrameRef,
+    source: &str,
+    mode: &compile::Mode,
+) -> PyResult {
+    let source = format!("{}\n", source);
+    let code = compile::compile(vm, &source, mode, "<debugger>".to_string()).map_err(|err| {
+        let syntax_error = vm.ctx.exceptions.syntax_error.clone();
+        vm.new_exception(syntax_error, err.to_string())
+    })?;
+    vm.run_code_obj(code, frame.scope.clone())
+}
+
+fn print_error(vm: &VirtualMachine, exc: &PyObjectRef) {
+    match vm.to_str(exc) {
+        Ok(ref text) if !text.value.is_empty() => {
+            output!(vm, "*** {}: {}", exc.class().name, text.value)
+        }
+        _ => output!(vm, "*** {}", exc.class().name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::env;
+    use std::fs;
+    use std::io::{self, Cursor, Write};
+    use std::process;
+    use std::rc::Rc;
+
+    use crate::compile;
+    use crate::vm::VirtualMachine;
+
+    /// A stdout for the VM whose output the test can read.
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_debug_session() {
+        let source = "def double(n):\n    return n * 2\nx = 1\ny = double(x)\nz = y + 1\n";
+        let path = env::temp_dir().join(format!("debugged_{}.py", process::id()));
+        fs::write(&path, source).unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        let vm = VirtualMachine::new();
+        let output = Output::default();
+        vm.stdin
+            .replace(Box::new(Cursor::new("b 5\nn\nn\np x\nc\np y\nc\n")));
+        vm.stdout.replace(Box::new(output.clone()));
+        let code = compile::compile(&vm, source, &compile::Mode::Exec, path.clone()).unwrap();
+        vm.set_trace();
+        let result = vm.run_code_obj(code, vm.ctx.new_scope());
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_ok());
+
+        let output = String::from_utf8(output.0.borrow().clone()).unwrap();
+        let expected = format!(
+            "> {path}(1)<module>()\n-> def double(n):\n\
+             (Pdb) Breakpoint 1 at {path}:5\n\
+             (Pdb) > {path}(3)<module>()\n-> x = 1\n\
+             (Pdb) > {path}(4)<module>()\n-> y = double(x)\n\
+             (Pdb) 1\n\
+             (Pdb) > {path}(5)<module>()\n-> z = y + 1\n\
+             (Pdb) 2\n\
+             (Pdb) ",
+            path = path
+        );
+        assert_eq!(output, expected);
+    }
+}
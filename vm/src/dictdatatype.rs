@@ -83,8 +83,7 @@ impl Dict {
                 panic!("Lookup returned invalid index into entries!");
             }
         } else {
-            let key_repr = vm.to_pystr(key)?;
-            Err(vm.new_value_error(format!("Key not found: {}", key_repr)))
+            Err(vm.new_key_error(key.clone()))
         }
     }
 
@@ -95,8 +94,7 @@ impl Dict {
             self.size -= 1;
             Ok(())
         } else {
-            let key_repr = vm.to_pystr(key)?;
-            Err(vm.new_value_error(format!("Key not found: {}", key_repr)))
+            Err(vm.new_key_error(key.clone()))
         }
     }
 
@@ -170,16 +168,12 @@ enum LookupResult {
 }
 
 fn calc_hash(vm: &VirtualMachine, key: &PyObjectRef) -> PyResult<usize> {
-    let hash = vm.call_method(key, "__hash__", vec![])?;
+    let hash = vm._hash(key)?;
     Ok(objint::get_value(&hash).to_usize().unwrap())
 }
 
 /// Invoke __eq__ on two keys
-fn do_eq(
-    vm: &VirtualMachine,
-    key1: &PyObjectRef,
-    key2: &PyObjectRef,
-) -> Result<bool, PyObjectRef> {
+fn do_eq(vm: &VirtualMachine, key1: &PyObjectRef, key2: &PyObjectRef) -> Result<bool, PyObjectRef> {
     let result = vm._eq(key1, key2.clone())?;
     Ok(objbool::get_value(&result))
 }
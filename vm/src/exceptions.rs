@@ -1,9 +1,70 @@
+use std::io::{self, Write};
+use std::str::Utf8Error;
+
 use crate::function::PyFuncArgs;
+use crate::native_panic;
+use crate::obj::objbytes::PyBytes;
+use crate::obj::objint::PyInt;
+use crate::obj::objobject::{self, PyInstance};
 use crate::obj::objsequence;
+use crate::obj::objstr::PyString;
+use crate::obj::objtuple::PyTupleRef;
 use crate::obj::objtype;
 use crate::obj::objtype::PyClassRef;
-use crate::pyobject::{create_type, PyContext, PyObjectRef, PyResult, TypeProtocol};
+use crate::pyobject::{
+    create_type, IdProtocol, PyContext, PyObject, PyObjectRef, PyRef, PyResult, TryFromObject,
+    TypeProtocol,
+};
 use crate::vm::VirtualMachine;
+use num_traits::ToPrimitive;
+
+fn exception_new(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    if args.args.is_empty() {
+        return Err(vm.new_type_error("BaseException.__new__(): not enough arguments".to_string()));
+    }
+    let zelf = objobject::new_instance(vm, args.clone())?;
+    set_exception_attributes(vm, &zelf, &args.args[1..]);
+    Ok(zelf)
+}
+
+/// Set the attributes every exception has. Done in `__new__` rather than in
+/// `__init__`, so subclasses that do not call the base initializer still get
+/// them.
+fn set_exception_attributes(vm: &VirtualMachine, zelf: &PyObjectRef, args: &[PyObjectRef]) {
+    vm.ctx
+        .set_attr(zelf, "args", vm.ctx.new_tuple(args.to_vec()));
+    vm.ctx
+        .set_attr(zelf, "msg", vm.new_str("No msg".to_string()));
+    vm.ctx
+        .set_attr(zelf, "__traceback__", vm.ctx.new_list(Vec::new()));
+    vm.ctx.set_attr(zelf, "__cause__", vm.get_none());
+    vm.ctx.set_attr(zelf, "__context__", vm.get_none());
+    vm.ctx
+        .set_attr(zelf, "__suppress_context__", vm.new_bool(false));
+}
+
+/// Create an exception of a builtin type with the given arguments, without
+/// calling the type, which can't fail. Only the attributes of
+/// `BaseException` are set, the caller sets the ones the type's `__init__`
+/// would.
+pub fn new_builtin_exception(
+    vm: &VirtualMachine,
+    exc_type: PyClassRef,
+    args: Vec<PyObjectRef>,
+) -> PyObjectRef {
+    let dict = if exc_type.layout.has_dict {
+        Some(vm.ctx.new_dict())
+    } else {
+        None
+    };
+    let instance = PyInstance::new(exc_type.layout.slot_count);
+    let zelf = PyObject::new(instance, exc_type, dict);
+    set_exception_attributes(vm, &zelf, &args);
+    if let Some(msg) = args.into_iter().next() {
+        vm.ctx.set_attr(&zelf, "msg", msg);
+    }
+    zelf
+}
 
 fn exception_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let zelf = args.args[0].clone();
@@ -12,16 +73,417 @@ fn exception_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     } else {
         vm.new_str("No msg".to_string())
     };
-    let traceback = vm.ctx.new_list(Vec::new());
+    vm.ctx
+        .set_attr(&zelf, "args", vm.ctx.new_tuple(args.args[1..].to_vec()));
     vm.ctx.set_attr(&zelf, "msg", msg);
-    vm.ctx.set_attr(&zelf, "__traceback__", traceback);
     Ok(vm.get_none())
 }
 
-// Print exception including traceback:
+fn exception_args(vm: &VirtualMachine, exc: &PyObjectRef) -> PyResult<Vec<PyObjectRef>> {
+    let args = PyTupleRef::try_from_object(vm, vm.get_attribute(exc.clone(), "args")?)?;
+    let elements = args.elements.borrow().clone();
+    Ok(elements)
+}
+
+fn stop_iteration_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    exception_init(vm, args.clone())?;
+    let value = args.args.get(1).cloned().unwrap_or_else(|| vm.get_none());
+    vm.ctx.set_attr(&args.args[0], "value", value);
+    Ok(vm.get_none())
+}
+
+fn system_exit_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    exception_init(vm, args.clone())?;
+    let code = match args.args.len() {
+        1 => vm.get_none(),
+        2 => args.args[1].clone(),
+        _ => vm.ctx.new_tuple(args.args[1..].to_vec()),
+    };
+    vm.ctx.set_attr(&args.args[0], "code", code);
+    Ok(vm.get_none())
+}
+
+fn import_error_init(vm: &VirtualMachine, mut args: PyFuncArgs) -> PyResult {
+    let name = args.take_keyword("name").unwrap_or_else(|| vm.get_none());
+    let path = args.take_keyword("path").unwrap_or_else(|| vm.get_none());
+    exception_init(vm, args.clone())?;
+    vm.ctx.set_attr(&args.args[0], "name", name);
+    vm.ctx.set_attr(&args.args[0], "path", path);
+    Ok(vm.get_none())
+}
+
+/// `OSError(errno, strerror[, filename[, winerror[, filename2]]])`
+fn os_error_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    exception_init(vm, args.clone())?;
+    set_os_error_attributes(vm, &args.args[0], &args.args[1..]);
+    Ok(vm.get_none())
+}
+
+/// Set the attributes of an `OSError` from its arguments.
+fn set_os_error_attributes(vm: &VirtualMachine, zelf: &PyObjectRef, params: &[PyObjectRef]) {
+    let param = |index: usize| params.get(index).cloned().unwrap_or_else(|| vm.get_none());
+    let (errno, strerror, filename, filename2) = if params.len() >= 2 && params.len() <= 5 {
+        (param(0), param(1), param(2), param(4))
+    } else {
+        (vm.get_none(), vm.get_none(), vm.get_none(), vm.get_none())
+    };
+    if !filename.is(&vm.get_none()) {
+        // Only errno and strerror count as the arguments then.
+        vm.ctx
+            .set_attr(zelf, "args", vm.ctx.new_tuple(params[..2].to_vec()));
+    }
+    vm.ctx.set_attr(zelf, "errno", errno);
+    vm.ctx.set_attr(zelf, "strerror", strerror);
+    vm.ctx.set_attr(zelf, "filename", filename);
+    vm.ctx.set_attr(zelf, "filename2", filename2);
+}
+
+fn unicode_error_attributes(
+    vm: &VirtualMachine,
+    zelf: &PyObjectRef,
+    names: &[&str],
+    values: &[&PyObjectRef],
+) {
+    for (name, value) in names.iter().zip(values) {
+        vm.ctx.set_attr(zelf, name, (*value).clone());
+    }
+}
+
+fn unicode_decode_error_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(
+        vm,
+        args,
+        required = [
+            (zelf, None),
+            (encoding, Some(vm.ctx.str_type())),
+            (object, Some(vm.ctx.bytes_type())),
+            (start, Some(vm.ctx.int_type())),
+            (end, Some(vm.ctx.int_type())),
+            (reason, Some(vm.ctx.str_type()))
+        ]
+    );
+    exception_init(vm, args.clone())?;
+    let names = ["encoding", "object", "start", "end", "reason"];
+    unicode_error_attributes(vm, zelf, &names, &[encoding, object, start, end, reason]);
+    Ok(vm.get_none())
+}
+
+fn unicode_encode_error_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(
+        vm,
+        args,
+        required = [
+            (zelf, None),
+            (encoding, Some(vm.ctx.str_type())),
+            (object, Some(vm.ctx.str_type())),
+            (start, Some(vm.ctx.int_type())),
+            (end, Some(vm.ctx.int_type())),
+            (reason, Some(vm.ctx.str_type()))
+        ]
+    );
+    exception_init(vm, args.clone())?;
+    let names = ["encoding", "object", "start", "end", "reason"];
+    unicode_error_attributes(vm, zelf, &names, &[encoding, object, start, end, reason]);
+    Ok(vm.get_none())
+}
+
+fn unicode_translate_error_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(
+        vm,
+        args,
+        required = [
+            (zelf, None),
+            (object, Some(vm.ctx.str_type())),
+            (start, Some(vm.ctx.int_type())),
+            (end, Some(vm.ctx.int_type())),
+            (reason, Some(vm.ctx.str_type()))
+        ]
+    );
+    exception_init(vm, args.clone())?;
+    let names = ["object", "start", "end", "reason"];
+    unicode_error_attributes(vm, zelf, &names, &[object, start, end, reason]);
+    Ok(vm.get_none())
+}
+
+/// A `UnicodeDecodeError` for bytes that are not valid utf-8.
+pub fn utf8_decode_error(vm: &VirtualMachine, bytes: Vec<u8>, err: &Utf8Error) -> PyObjectRef {
+    let start = err.valid_up_to();
+    let (end, reason) = match err.error_len() {
+        // Bytes which can start a sequence of two to four bytes.
+        Some(len) if (0xc2..=0xf4).contains(&bytes[start]) => {
+            (start + len, "invalid continuation byte")
+        }
+        Some(len) => (start + len, "invalid start byte"),
+        None => (bytes.len(), "unexpected end of data"),
+    };
+    let args = vec![
+        vm.new_str("utf-8".to_string()),
+        vm.ctx.new_bytes(bytes),
+        vm.ctx.new_int(start),
+        vm.ctx.new_int(end),
+        vm.new_str(reason.to_string()),
+    ];
+    let exc_type = vm.ctx.exceptions.unicode_decode_error.clone();
+    let exc = new_builtin_exception(vm, exc_type, args.clone());
+    let names = ["encoding", "object", "start", "end", "reason"];
+    let values: Vec<_> = args.iter().collect();
+    unicode_error_attributes(vm, &exc, &names, &values);
+    exc
+}
+
+/// An `OSError`, or the subclass matching the error, with `errno` and
+/// `strerror` set from `args`.
+pub fn new_os_error(
+    vm: &VirtualMachine,
+    exc_type: PyClassRef,
+    args: Vec<PyObjectRef>,
+) -> PyObjectRef {
+    let exc = new_builtin_exception(vm, exc_type, args.clone());
+    set_os_error_attributes(vm, &exc, &args);
+    exc
+}
+
+/// The subclass of `OSError` an I/O error is raised as, chosen by its errno
+/// or else by its kind.
+pub fn io_error_type(vm: &VirtualMachine, err: &io::Error) -> PyClassRef {
+    let exceptions = &vm.ctx.exceptions;
+    #[cfg(unix)]
+    {
+        match err.raw_os_error() {
+            Some(libc::EISDIR) => return exceptions.is_a_directory_error.clone(),
+            Some(libc::ENOTDIR) => return exceptions.not_a_directory_error.clone(),
+            Some(libc::ECHILD) => return exceptions.child_process_error.clone(),
+            Some(libc::ESRCH) => return exceptions.process_lookup_error.clone(),
+            _ => {}
+        }
+    }
+    match err.kind() {
+        io::ErrorKind::NotFound => exceptions.file_not_found_error.clone(),
+        io::ErrorKind::PermissionDenied => exceptions.permission_error.clone(),
+        io::ErrorKind::AlreadyExists => exceptions.file_exists_error.clone(),
+        io::ErrorKind::ConnectionRefused => exceptions.connection_refused_error.clone(),
+        io::ErrorKind::ConnectionReset => exceptions.connection_reset_error.clone(),
+        io::ErrorKind::ConnectionAborted => exceptions.connection_aborted_error.clone(),
+        io::ErrorKind::BrokenPipe => exceptions.broken_pipe_error.clone(),
+        io::ErrorKind::WouldBlock => exceptions.blocking_io_error.clone(),
+        io::ErrorKind::TimedOut => exceptions.timeout_error.clone(),
+        io::ErrorKind::Interrupted => exceptions.interrupted_error.clone(),
+        _ => exceptions.os_error.clone(),
+    }
+}
+
+fn exception_str(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(
+        vm,
+        args,
+        required = [(exc, Some(vm.ctx.exceptions.base_exception_type.clone()))]
+    );
+    let args = exception_args(vm, exc)?;
+    match args.len() {
+        0 => Ok(vm.new_str("".to_string())),
+        1 => vm.to_str(&args[0]).map(PyRef::into_object),
+        _ => vm.to_str(&vm.ctx.new_tuple(args)).map(PyRef::into_object),
+    }
+}
+
+fn exception_repr(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(
+        vm,
+        args,
+        required = [(exc, Some(vm.ctx.exceptions.base_exception_type.clone()))]
+    );
+    let mut reprs = vec![];
+    for arg in exception_args(vm, exc)? {
+        reprs.push(vm.to_repr(&arg)?.value.clone());
+    }
+    Ok(vm.new_str(format!("{}({})", exc.class().name, reprs.join(", "))))
+}
+
+fn exception_with_traceback(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(
+        vm,
+        args,
+        required = [
+            (exc, Some(vm.ctx.exceptions.base_exception_type.clone())),
+            (traceback, None)
+        ]
+    );
+    vm.ctx.set_attr(exc, "__traceback__", traceback.clone());
+    Ok(exc.clone())
+}
+
+fn key_error_str(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(
+        vm,
+        args,
+        required = [(exc, Some(vm.ctx.exceptions.key_error.clone()))]
+    );
+    let args = exception_args(vm, exc)?;
+    if args.len() == 1 {
+        // The missing key itself, which might be an empty string.
+        vm.to_repr(&args[0]).map(PyRef::into_object)
+    } else {
+        exception_str(vm, vec![exc.clone()].into())
+    }
+}
+
+fn os_error_str(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(
+        vm,
+        args,
+        required = [(exc, Some(vm.ctx.exceptions.os_error.clone()))]
+    );
+    let none = vm.get_none();
+    let errno = vm.get_attribute(exc.clone(), "errno")?;
+    let strerror = vm.get_attribute(exc.clone(), "strerror")?;
+    let filename = vm.get_attribute(exc.clone(), "filename")?;
+    let filename2 = vm.get_attribute(exc.clone(), "filename2")?;
+    if errno.is(&none) && strerror.is(&none) {
+        return exception_str(vm, vec![exc.clone()].into());
+    }
+    let mut s = format!(
+        "[Errno {}] {}",
+        vm.to_pystr(&errno)?,
+        vm.to_pystr(&strerror)?
+    );
+    if !filename.is(&none) {
+        s.push_str(&format!(": {}", vm.to_repr(&filename)?.value));
+        if !filename2.is(&none) {
+            s.push_str(&format!(" -> {}", vm.to_repr(&filename2)?.value));
+        }
+    }
+    Ok(vm.new_str(s))
+}
+
+/// The position of the offending part of the object, like "position 3" or
+/// "position 3-5".
+fn unicode_error_range(vm: &VirtualMachine, exc: &PyObjectRef) -> PyResult<(usize, usize)> {
+    let position = |name: &str| {
+        let value = vm.get_attribute(exc.clone(), name)?;
+        match value.payload::<PyInt>() {
+            Some(int) => int
+                .as_bigint()
+                .to_usize()
+                .ok_or_else(|| vm.new_overflow_error("position out of range".to_string())),
+            None => Err(vm.new_type_error(format!("{} attribute must be int", name))),
+        }
+    };
+    Ok((position("start")?, position("end")?))
+}
+
+fn unicode_error_position(start: usize, end: usize) -> String {
+    if end == start + 1 {
+        format!("position {}", start)
+    } else {
+        format!("position {}-{}", start, end.saturating_sub(1))
+    }
+}
+
+fn escape_char(c: char) -> String {
+    let code = c as u32;
+    if code <= 0xff {
+        format!("'\\x{:02x}'", code)
+    } else if code <= 0xffff {
+        format!("'\\u{:04x}'", code)
+    } else {
+        format!("'\\U{:08x}'", code)
+    }
+}
+
+fn unicode_decode_error_str(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(vm, args, required = [(exc, None)]);
+    let encoding = vm.to_pystr(&vm.get_attribute(exc.clone(), "encoding")?)?;
+    let reason = vm.to_pystr(&vm.get_attribute(exc.clone(), "reason")?)?;
+    let object = vm.get_attribute(exc.clone(), "object")?;
+    let (start, end) = unicode_error_range(vm, exc)?;
+    // The attributes may have been set to anything since.
+    let byte = object
+        .payload::<PyBytes>()
+        .and_then(|bytes| bytes.get(start).cloned());
+    let s = match byte {
+        Some(byte) if end == start + 1 => format!(
+            "'{}' codec can't decode byte 0x{:02x} in position {}: {}",
+            encoding, byte, start, reason
+        ),
+        _ => format!(
+            "'{}' codec can't decode bytes in {}: {}",
+            encoding,
+            unicode_error_position(start, end),
+            reason
+        ),
+    };
+    Ok(vm.new_str(s))
+}
+
+fn unicode_encode_error_str(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(vm, args, required = [(exc, None)]);
+    let encoding = vm.to_pystr(&vm.get_attribute(exc.clone(), "encoding")?)?;
+    let s = unicode_char_error(vm, exc, &format!("'{}' codec can't encode", encoding))?;
+    Ok(vm.new_str(s))
+}
+
+fn unicode_translate_error_str(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(vm, args, required = [(exc, None)]);
+    let s = unicode_char_error(vm, exc, "can't translate")?;
+    Ok(vm.new_str(s))
+}
+
+fn unicode_char_error(vm: &VirtualMachine, exc: &PyObjectRef, action: &str) -> PyResult<String> {
+    let reason = vm.to_pystr(&vm.get_attribute(exc.clone(), "reason")?)?;
+    let object = vm.get_attribute(exc.clone(), "object")?;
+    let (start, end) = unicode_error_range(vm, exc)?;
+    let c = object
+        .payload::<PyString>()
+        .and_then(|value| value.value.chars().nth(start));
+    Ok(match c {
+        Some(c) if end == start + 1 => format!(
+            "{} character {} in position {}: {}",
+            action,
+            escape_char(c),
+            start,
+            reason
+        ),
+        _ => format!(
+            "{} characters in {}: {}",
+            action,
+            unicode_error_position(start, end),
+            reason
+        ),
+    })
+}
+
+// Print exception including traceback, with sys.excepthook:
 pub fn print_exception(vm: &VirtualMachine, exc: &PyObjectRef) {
+    let traceback = vm
+        .get_attribute(exc.clone(), "__traceback__")
+        .unwrap_or_else(|_| vm.get_none());
+    let result = vm
+        .get_attribute(vm.sys_module.clone(), "excepthook")
+        .and_then(|hook| {
+            vm.invoke(
+                hook,
+                vec![exc.class().into_object(), exc.clone(), traceback],
+            )
+        });
+    if let Err(hook_exc) = result {
+        // Failing to write to the stderr of the VM leaves nowhere to report
+        // it.
+        let mut report = b"Error in sys.excepthook:\n".to_vec();
+        let _ = write_exception(&mut report, vm, &hook_exc);
+        report.extend_from_slice(b"\nOriginal exception was:\n");
+        let _ = write_exception(&mut report, vm, exc);
+        let _ = vm.stderr().write_all(&report);
+    }
+}
+
+// Write exception including traceback to the given output:
+pub fn write_exception<W: Write>(
+    output: &mut W,
+    vm: &VirtualMachine,
+    exc: &PyObjectRef,
+) -> io::Result<()> {
     if let Ok(tb) = vm.get_attribute(exc.clone(), "__traceback__") {
-        println!("Traceback (most recent call last):");
+        writeln!(output, "Traceback (most recent call last):")?;
         if objtype::isinstance(&tb, &vm.ctx.list_type()) {
             let mut elements = objsequence::get_elements(&tb).to_vec();
             elements.reverse();
@@ -46,124 +508,394 @@ pub fn print_exception(vm: &VirtualMachine, exc: &PyObjectRef) {
                         "<error>".to_string()
                     };
 
-                    println!("  File {}, line {}, in {}", filename, lineno, obj_name);
+                    writeln!(
+                        output,
+                        "  File {}, line {}, in {}",
+                        filename, lineno, obj_name
+                    )?;
                 } else {
-                    println!("  File ??");
+                    writeln!(output, "  File ??")?;
                 }
             }
         }
     } else {
-        println!("No traceback set on exception");
+        writeln!(output, "No traceback set on exception")?;
     }
 
     match vm.to_str(exc) {
-        Ok(txt) => println!("{}", txt.value),
-        Err(err) => println!("Error during error {:?}", err),
+        Ok(ref txt) if txt.value.is_empty() => writeln!(output, "{}", exc.class().name),
+        Ok(txt) => writeln!(output, "{}: {}", exc.class().name, txt.value),
+        Err(err) => writeln!(output, "Error during error {:?}", err),
     }
 }
 
-fn exception_str(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(
-        vm,
-        args,
-        required = [(exc, Some(vm.ctx.exceptions.exception_type.clone()))]
-    );
-    let msg = if let Ok(m) = vm.get_attribute(exc.clone(), "msg") {
-        match vm.to_pystr(&m) {
-            Ok(msg) => msg,
-            _ => "<exception str() failed>".to_string(),
-        }
-    } else {
-        panic!("Error message must be set");
-    };
-    let s = format!("{}: {}", exc.class().name, msg);
-    Ok(vm.new_str(s))
-}
-
 #[derive(Debug)]
 pub struct ExceptionZoo {
     pub arithmetic_error: PyClassRef,
     pub assertion_error: PyClassRef,
     pub attribute_error: PyClassRef,
     pub base_exception_type: PyClassRef,
+    pub blocking_io_error: PyClassRef,
+    pub broken_pipe_error: PyClassRef,
+    pub buffer_error: PyClassRef,
+    pub bytes_warning: PyClassRef,
+    pub child_process_error: PyClassRef,
+    pub connection_aborted_error: PyClassRef,
+    pub connection_error: PyClassRef,
+    pub connection_refused_error: PyClassRef,
+    pub connection_reset_error: PyClassRef,
+    pub deprecation_warning: PyClassRef,
+    pub eof_error: PyClassRef,
     pub exception_type: PyClassRef,
+    pub file_exists_error: PyClassRef,
     pub file_not_found_error: PyClassRef,
+    pub floating_point_error: PyClassRef,
+    pub future_warning: PyClassRef,
+    pub generator_exit: PyClassRef,
     pub import_error: PyClassRef,
+    pub import_warning: PyClassRef,
+    pub indentation_error: PyClassRef,
     pub index_error: PyClassRef,
+    pub interrupted_error: PyClassRef,
+    pub is_a_directory_error: PyClassRef,
     pub key_error: PyClassRef,
+    pub keyboard_interrupt: PyClassRef,
+    pub lookup_error: PyClassRef,
+    pub memory_error: PyClassRef,
     pub module_not_found_error: PyClassRef,
     pub name_error: PyClassRef,
+    pub not_a_directory_error: PyClassRef,
     pub not_implemented_error: PyClassRef,
     pub os_error: PyClassRef,
     pub overflow_error: PyClassRef,
+    pub pending_deprecation_warning: PyClassRef,
     pub permission_error: PyClassRef,
+    pub process_lookup_error: PyClassRef,
+    pub recursion_error: PyClassRef,
+    pub reference_error: PyClassRef,
+    pub resource_warning: PyClassRef,
     pub runtime_error: PyClassRef,
+    pub runtime_warning: PyClassRef,
+    pub stop_async_iteration: PyClassRef,
     pub stop_iteration: PyClassRef,
     pub syntax_error: PyClassRef,
+    pub syntax_warning: PyClassRef,
+    pub system_error: PyClassRef,
+    pub system_exit: PyClassRef,
+    pub tab_error: PyClassRef,
+    pub timeout_error: PyClassRef,
     pub type_error: PyClassRef,
+    pub unbound_local_error: PyClassRef,
+    pub unicode_decode_error: PyClassRef,
+    pub unicode_encode_error: PyClassRef,
+    pub unicode_error: PyClassRef,
+    pub unicode_translate_error: PyClassRef,
+    pub unicode_warning: PyClassRef,
+    pub user_warning: PyClassRef,
     pub value_error: PyClassRef,
+    pub warning: PyClassRef,
     pub zero_division_error: PyClassRef,
 }
 
 impl ExceptionZoo {
     pub fn new(type_type: &PyClassRef, object_type: &PyClassRef) -> Self {
-        // Sorted By Hierarchy then alphabetized.
+        // Ordered like the hierarchy in the python docs.
         let base_exception_type = create_type("BaseException", &type_type, &object_type);
+        let system_exit = create_type("SystemExit", &type_type, &base_exception_type);
+        let keyboard_interrupt = create_type("KeyboardInterrupt", &type_type, &base_exception_type);
+        let generator_exit = create_type("GeneratorExit", &type_type, &base_exception_type);
         let exception_type = create_type("Exception", &type_type, &base_exception_type);
+        let stop_iteration = create_type("StopIteration", &type_type, &exception_type);
+        let stop_async_iteration = create_type("StopAsyncIteration", &type_type, &exception_type);
         let arithmetic_error = create_type("ArithmeticError", &type_type, &exception_type);
+        let floating_point_error = create_type("FloatingPointError", &type_type, &arithmetic_error);
+        let overflow_error = create_type("OverflowError", &type_type, &arithmetic_error);
+        let zero_division_error = create_type("ZeroDivisionError", &type_type, &arithmetic_error);
         let assertion_error = create_type("AssertionError", &type_type, &exception_type);
         let attribute_error = create_type("AttributeError", &type_type, &exception_type);
+        let buffer_error = create_type("BufferError", &type_type, &exception_type);
+        let eof_error = create_type("EOFError", &type_type, &exception_type);
         let import_error = create_type("ImportError", &type_type, &exception_type);
-        let index_error = create_type("IndexError", &type_type, &exception_type);
-        let key_error = create_type("KeyError", &type_type, &exception_type);
+        let module_not_found_error = create_type("ModuleNotFoundError", &type_type, &import_error);
+        let lookup_error = create_type("LookupError", &type_type, &exception_type);
+        let index_error = create_type("IndexError", &type_type, &lookup_error);
+        let key_error = create_type("KeyError", &type_type, &lookup_error);
+        let memory_error = create_type("MemoryError", &type_type, &exception_type);
         let name_error = create_type("NameError", &type_type, &exception_type);
+        let unbound_local_error = create_type("UnboundLocalError", &type_type, &name_error);
         let os_error = create_type("OSError", &type_type, &exception_type);
+        let blocking_io_error = create_type("BlockingIOError", &type_type, &os_error);
+        let child_process_error = create_type("ChildProcessError", &type_type, &os_error);
+        let connection_error = create_type("ConnectionError", &type_type, &os_error);
+        let broken_pipe_error = create_type("BrokenPipeError", &type_type, &connection_error);
+        let connection_aborted_error =
+            create_type("ConnectionAbortedError", &type_type, &connection_error);
+        let connection_refused_error =
+            create_type("ConnectionRefusedError", &type_type, &connection_error);
+        let connection_reset_error =
+            create_type("ConnectionResetError", &type_type, &connection_error);
+        let file_exists_error = create_type("FileExistsError", &type_type, &os_error);
+        let file_not_found_error = create_type("FileNotFoundError", &type_type, &os_error);
+        let interrupted_error = create_type("InterruptedError", &type_type, &os_error);
+        let is_a_directory_error = create_type("IsADirectoryError", &type_type, &os_error);
+        let not_a_directory_error = create_type("NotADirectoryError", &type_type, &os_error);
+        let permission_error = create_type("PermissionError", &type_type, &os_error);
+        let process_lookup_error = create_type("ProcessLookupError", &type_type, &os_error);
+        let timeout_error = create_type("TimeoutError", &type_type, &os_error);
+        let reference_error = create_type("ReferenceError", &type_type, &exception_type);
         let runtime_error = create_type("RuntimeError", &type_type, &exception_type);
-        let stop_iteration = create_type("StopIteration", &type_type, &exception_type);
+        let not_implemented_error = create_type("NotImplementedError", &type_type, &runtime_error);
+        let recursion_error = create_type("RecursionError", &type_type, &runtime_error);
         let syntax_error = create_type("SyntaxError", &type_type, &exception_type);
+        let indentation_error = create_type("IndentationError", &type_type, &syntax_error);
+        let tab_error = create_type("TabError", &type_type, &indentation_error);
+        let system_error = create_type("SystemError", &type_type, &exception_type);
         let type_error = create_type("TypeError", &type_type, &exception_type);
         let value_error = create_type("ValueError", &type_type, &exception_type);
-        let overflow_error = create_type("OverflowError", &type_type, &arithmetic_error);
-        let zero_division_error = create_type("ZeroDivisionError", &type_type, &arithmetic_error);
-        let module_not_found_error = create_type("ModuleNotFoundError", &type_type, &import_error);
-        let not_implemented_error = create_type("NotImplementedError", &type_type, &runtime_error);
-        let file_not_found_error = create_type("FileNotFoundError", &type_type, &os_error);
-        let permission_error = create_type("PermissionError", &type_type, &os_error);
+        let unicode_error = create_type("UnicodeError", &type_type, &value_error);
+        let unicode_decode_error = create_type("UnicodeDecodeError", &type_type, &unicode_error);
+        let unicode_encode_error = create_type("UnicodeEncodeError", &type_type, &unicode_error);
+        let unicode_translate_error =
+            create_type("UnicodeTranslateError", &type_type, &unicode_error);
+        let warning = create_type("Warning", &type_type, &exception_type);
+        let deprecation_warning = create_type("DeprecationWarning", &type_type, &warning);
+        let pending_deprecation_warning =
+            create_type("PendingDeprecationWarning", &type_type, &warning);
+        let runtime_warning = create_type("RuntimeWarning", &type_type, &warning);
+        let syntax_warning = create_type("SyntaxWarning", &type_type, &warning);
+        let user_warning = create_type("UserWarning", &type_type, &warning);
+        let future_warning = create_type("FutureWarning", &type_type, &warning);
+        let import_warning = create_type("ImportWarning", &type_type, &warning);
+        let unicode_warning = create_type("UnicodeWarning", &type_type, &warning);
+        let bytes_warning = create_type("BytesWarning", &type_type, &warning);
+        let resource_warning = create_type("ResourceWarning", &type_type, &warning);
 
         ExceptionZoo {
             arithmetic_error,
             assertion_error,
             attribute_error,
             base_exception_type,
+            blocking_io_error,
+            broken_pipe_error,
+            buffer_error,
+            bytes_warning,
+            child_process_error,
+            connection_aborted_error,
+            connection_error,
+            connection_refused_error,
+            connection_reset_error,
+            deprecation_warning,
+            eof_error,
             exception_type,
+            file_exists_error,
             file_not_found_error,
+            floating_point_error,
+            future_warning,
+            generator_exit,
             import_error,
+            import_warning,
+            indentation_error,
             index_error,
+            interrupted_error,
+            is_a_directory_error,
             key_error,
+            keyboard_interrupt,
+            lookup_error,
+            memory_error,
             module_not_found_error,
             name_error,
+            not_a_directory_error,
             not_implemented_error,
             os_error,
             overflow_error,
+            pending_deprecation_warning,
             permission_error,
+            process_lookup_error,
+            recursion_error,
+            reference_error,
+            resource_warning,
             runtime_error,
+            runtime_warning,
+            stop_async_iteration,
             stop_iteration,
             syntax_error,
+            syntax_warning,
+            system_error,
+            system_exit,
+            tab_error,
+            timeout_error,
             type_error,
+            unbound_local_error,
+            unicode_decode_error,
+            unicode_encode_error,
+            unicode_error,
+            unicode_translate_error,
+            unicode_warning,
+            user_warning,
             value_error,
+            warning,
             zero_division_error,
         }
     }
+
+    /// The exceptions that are available as builtins, by name.
+    pub fn builtins(&self) -> Vec<(&'static str, &PyClassRef)> {
+        vec![
+            ("BaseException", &self.base_exception_type),
+            ("SystemExit", &self.system_exit),
+            ("KeyboardInterrupt", &self.keyboard_interrupt),
+            ("GeneratorExit", &self.generator_exit),
+            ("Exception", &self.exception_type),
+            ("StopIteration", &self.stop_iteration),
+            ("StopAsyncIteration", &self.stop_async_iteration),
+            ("ArithmeticError", &self.arithmetic_error),
+            ("FloatingPointError", &self.floating_point_error),
+            ("OverflowError", &self.overflow_error),
+            ("ZeroDivisionError", &self.zero_division_error),
+            ("AssertionError", &self.assertion_error),
+            ("AttributeError", &self.attribute_error),
+            ("BufferError", &self.buffer_error),
+            ("EOFError", &self.eof_error),
+            ("ImportError", &self.import_error),
+            ("ModuleNotFoundError", &self.module_not_found_error),
+            ("LookupError", &self.lookup_error),
+            ("IndexError", &self.index_error),
+            ("KeyError", &self.key_error),
+            ("MemoryError", &self.memory_error),
+            ("NameError", &self.name_error),
+            ("UnboundLocalError", &self.unbound_local_error),
+            ("OSError", &self.os_error),
+            ("BlockingIOError", &self.blocking_io_error),
+            ("ChildProcessError", &self.child_process_error),
+            ("ConnectionError", &self.connection_error),
+            ("BrokenPipeError", &self.broken_pipe_error),
+            ("ConnectionAbortedError", &self.connection_aborted_error),
+            ("ConnectionRefusedError", &self.connection_refused_error),
+            ("ConnectionResetError", &self.connection_reset_error),
+            ("FileExistsError", &self.file_exists_error),
+            ("FileNotFoundError", &self.file_not_found_error),
+            ("InterruptedError", &self.interrupted_error),
+            ("IsADirectoryError", &self.is_a_directory_error),
+            ("NotADirectoryError", &self.not_a_directory_error),
+            ("PermissionError", &self.permission_error),
+            ("ProcessLookupError", &self.process_lookup_error),
+            ("TimeoutError", &self.timeout_error),
+            ("ReferenceError", &self.reference_error),
+            ("RuntimeError", &self.runtime_error),
+            ("NotImplementedError", &self.not_implemented_error),
+            ("RecursionError", &self.recursion_error),
+            ("SyntaxError", &self.syntax_error),
+            ("IndentationError", &self.indentation_error),
+            ("TabError", &self.tab_error),
+            ("SystemError", &self.system_error),
+            ("TypeError", &self.type_error),
+            ("ValueError", &self.value_error),
+            ("UnicodeError", &self.unicode_error),
+            ("UnicodeDecodeError", &self.unicode_decode_error),
+            ("UnicodeEncodeError", &self.unicode_encode_error),
+            ("UnicodeTranslateError", &self.unicode_translate_error),
+            ("Warning", &self.warning),
+            ("DeprecationWarning", &self.deprecation_warning),
+            (
+                "PendingDeprecationWarning",
+                &self.pending_deprecation_warning,
+            ),
+            ("RuntimeWarning", &self.runtime_warning),
+            ("SyntaxWarning", &self.syntax_warning),
+            ("UserWarning", &self.user_warning),
+            ("FutureWarning", &self.future_warning),
+            ("ImportWarning", &self.import_warning),
+            ("UnicodeWarning", &self.unicode_warning),
+            ("BytesWarning", &self.bytes_warning),
+            ("ResourceWarning", &self.resource_warning),
+        ]
+    }
 }
 
 pub fn init(context: &PyContext) {
-    let base_exception_type = &context.exceptions.base_exception_type;
-    extend_class!(context, base_exception_type, {
-        "__init__" => context.new_rustfunc(exception_init)
+    let exceptions = &context.exceptions;
+
+    extend_class!(context, &exceptions.base_exception_type, {
+        "__new__" => context.new_rustfunc(exception_new),
+        "__init__" => context.new_rustfunc(exception_init),
+        "__str__" => context.new_rustfunc(exception_str),
+        "__repr__" => context.new_rustfunc(exception_repr),
+        "with_traceback" => context.new_rustfunc(exception_with_traceback)
     });
 
-    let exception_type = &context.exceptions.exception_type;
-    extend_class!(context, exception_type, {
-        "__str__" => context.new_rustfunc(exception_str)
+    extend_class!(context, &exceptions.stop_iteration, {
+        "__init__" => context.new_rustfunc(stop_iteration_init),
+        "value" => context.none()
     });
+
+    extend_class!(context, &exceptions.system_exit, {
+        "__init__" => context.new_rustfunc(system_exit_init),
+        "code" => context.none()
+    });
+
+    extend_class!(context, &exceptions.system_error, {
+        "native_backtrace" => context.new_property(native_panic::native_backtrace)
+    });
+
+    extend_class!(context, &exceptions.import_error, {
+        "__init__" => context.new_rustfunc(import_error_init),
+        "name" => context.none(),
+        "path" => context.none()
+    });
+
+    extend_class!(context, &exceptions.key_error, {
+        "__str__" => context.new_rustfunc(key_error_str)
+    });
+
+    extend_class!(context, &exceptions.os_error, {
+        "__init__" => context.new_rustfunc(os_error_init),
+        "__str__" => context.new_rustfunc(os_error_str),
+        "errno" => context.none(),
+        "strerror" => context.none(),
+        "filename" => context.none(),
+        "filename2" => context.none()
+    });
+
+    extend_class!(context, &exceptions.unicode_decode_error, {
+        "__init__" => context.new_rustfunc(unicode_decode_error_init),
+        "__str__" => context.new_rustfunc(unicode_decode_error_str)
+    });
+
+    extend_class!(context, &exceptions.unicode_encode_error, {
+        "__init__" => context.new_rustfunc(unicode_encode_error_init),
+        "__str__" => context.new_rustfunc(unicode_encode_error_str)
+    });
+
+    extend_class!(context, &exceptions.unicode_translate_error, {
+        "__init__" => context.new_rustfunc(unicode_translate_error_init),
+        "__str__" => context.new_rustfunc(unicode_translate_error_str)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::utf8_decode_error;
+    use crate::obj::objint;
+    use crate::vm::VirtualMachine;
+
+    /// The reason, start and end of the error decoding `bytes`.
+    fn decode_error(vm: &VirtualMachine, bytes: &[u8]) -> (String, i32, i32) {
+        let err = std::str::from_utf8(bytes).unwrap_err();
+        let exc = utf8_decode_error(vm, bytes.to_vec(), &err);
+        let get = |name| vm.get_attribute(exc.clone(), name).unwrap();
+        let reason = vm.to_pystr(&get("reason")).unwrap();
+        let position = |name| objint::get_value(&get(name)).to_string().parse().unwrap();
+        (reason, position("start"), position("end"))
+    }
+
+    #[test]
+    fn test_utf8_decode_error() {
+        let vm = VirtualMachine::new();
+        let invalid_start = ("invalid start byte".to_string(), 1, 2);
+        assert_eq!(decode_error(&vm, b"a\xffb"), invalid_start);
+        let invalid_continuation = ("invalid continuation byte".to_string(), 1, 2);
+        assert_eq!(decode_error(&vm, b"a\xe4b"), invalid_continuation);
+        let truncated = ("unexpected end of data".to_string(), 1, 3);
+        assert_eq!(decode_error(&vm, b"a\xe4\xb8"), truncated);
+    }
 }
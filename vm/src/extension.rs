@@ -0,0 +1,275 @@
+/*! Native extension modules loaded from shared libraries.
+
+An extension module is a `cdylib` crate that depends on `rustpython_vm` and
+declares the function that makes its module with `py_extension_module!`:
+
+```
+#[macro_use]
+extern crate rustpython_vm;
+
+use rustpython_vm::pyobject::{PyContext, PyObjectRef};
+
+fn make_module(ctx: &PyContext) -> PyObjectRef {
+    py_module!(ctx, "answers", {
+        "everything" => ctx.new_int(42),
+    })
+}
+
+py_extension_module!(make_module);
+# fn main() {}
+```
+
+Renamed to `answers.so` and put in a directory of `sys.path`, the library is
+loaded by `import answers`.
+
+This is not a stable ABI. An extension module works with the objects of the
+interpreter through the rust types of the library's own copy of
+`rustpython_vm`, which has to be the very same build of the crate as the
+interpreter's, down to the rust compiler and the features of every
+dependency, as the payloads of objects are told apart by their `TypeId`s. An
+extension has to be rebuilt along with the interpreter, for instance in the
+same cargo invocation, like `cargo build --all` in a workspace that has both.
+Only the `ExtensionModuleDef` header the library exports is laid out the C
+way, so that loading a library built for another `ABI_VERSION`, or against
+another build of the crate, raises an `ImportError` rather than crashing.
+
+The library's copy of the crate is pointed at the state the interpreter keeps
+for the process (see `globals`), and panics in it are caught by its own copy
+of std, raising a `SystemError` like those of any native function.
+*/
+
+use std::any::TypeId;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::globals::{self, globals};
+use crate::native_panic;
+use crate::pyobject::{PyContext, PyObjectRef, PyResult};
+use crate::vm::VirtualMachine;
+
+pub use crate::globals::Globals;
+
+/// The version of the layout of `ExtensionModuleDef`, raised whenever it
+/// changes. It only versions that header, not the rest of the crate.
+pub const ABI_VERSION: u32 = 2;
+
+/// The version of `rustpython_vm` an extension module was built against,
+/// nul terminated so that any build can read it.
+pub const VM_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// The file name suffixes of extension modules, tried before the ones of
+/// source files.
+#[cfg(unix)]
+pub const SUFFIXES: &[&str] = &[".so"];
+#[cfg(not(unix))]
+pub const SUFFIXES: &[&str] = &[];
+
+/// The name of the symbol an extension module exports its definition as.
+const SYMBOL: &str = "RUSTPYTHON_EXTENSION_MODULE";
+
+/// Make the module of an extension, raising a `SystemError` if it panics.
+pub type MakeModule = fn(&VirtualMachine) -> PyResult;
+
+/// What an extension module exports, made with `py_extension_module!`.
+#[repr(C)]
+pub struct ExtensionModuleDef {
+    /// Comes first, so it can be checked before anything else is read.
+    pub abi_version: u32,
+    /// Points to the extension's `VM_VERSION`.
+    pub vm_version: *const u8,
+    /// Tells the build of `rustpython_vm` apart, see `build_id`.
+    pub build_id: extern "C" fn() -> u64,
+    // The rest is only used once the build is known to be the same.
+    pub share_globals: fn(&'static Globals),
+    pub make_module: MakeModule,
+}
+
+// Only ever points to a string constant.
+unsafe impl Sync for ExtensionModuleDef {}
+
+/// Export the function that makes the module of an extension module crate.
+#[macro_export]
+macro_rules! py_extension_module {
+    ($make_module:expr) => {
+        #[no_mangle]
+        pub static RUSTPYTHON_EXTENSION_MODULE: $crate::extension::ExtensionModuleDef =
+            $crate::extension::ExtensionModuleDef {
+                abi_version: $crate::extension::ABI_VERSION,
+                vm_version: $crate::extension::VM_VERSION.as_ptr(),
+                build_id: $crate::extension::build_id,
+                share_globals: $crate::extension::share_globals,
+                make_module: |vm| $crate::extension::run_make_module(vm, $make_module),
+            };
+    };
+}
+
+/// A type's id differs between builds of the crate that defines it, and so
+/// do the ids of the payloads of objects. It is hashed to pass it the C way.
+pub extern "C" fn build_id() -> u64 {
+    let mut hasher = DefaultHasher::new();
+    TypeId::of::<ExtensionModuleDef>().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Point the extension's copy of the crate at the interpreter's state.
+pub fn share_globals(interpreter_globals: &'static Globals) {
+    globals::share(interpreter_globals);
+}
+
+/// Call the function of `py_extension_module!`, catching its panics in the
+/// extension's own copy of the crate, see `PyBuiltinFunction::new`.
+#[doc(hidden)]
+pub fn run_make_module(
+    vm: &VirtualMachine,
+    make_module: fn(&PyContext) -> PyObjectRef,
+) -> PyResult {
+    native_panic::catch_panic(vm, || Ok(make_module(&vm.ctx)))
+}
+
+pub fn is_extension(path: &Path) -> bool {
+    let file_name = path.to_string_lossy();
+    SUFFIXES.iter().any(|suffix| file_name.ends_with(suffix))
+}
+
+lazy_static! {
+    /// The libraries of the extension modules loaded so far, by path.
+    pub(crate) static ref LOADED: Mutex<HashMap<PathBuf, (Library, MakeModule)>> =
+        Mutex::new(HashMap::new());
+}
+
+/// A handle of a library, which is closed when dropped. That only happens to
+/// the ones that fail to load: the code of an extension module runs for as
+/// long as any object it made is alive, so loaded ones stay in `LOADED` until
+/// the process exits, and importing them again reuses the handle.
+pub(crate) struct Library {
+    #[cfg(unix)]
+    handle: *mut libc::c_void,
+}
+
+// The handle can be used and closed from any thread.
+unsafe impl Send for Library {}
+
+#[cfg(unix)]
+impl Drop for Library {
+    fn drop(&mut self) {
+        unsafe {
+            libc::dlclose(self.handle);
+        }
+    }
+}
+
+/// Load the extension module at `path`, giving the function that makes its
+/// module.
+#[cfg(unix)]
+pub fn load(path: &Path) -> Result<MakeModule, String> {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    fn last_error() -> String {
+        let error = unsafe { libc::dlerror() };
+        if error.is_null() {
+            "unknown error".to_string()
+        } else {
+            unsafe { CStr::from_ptr(error) }
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    // Without a slash in it, dlopen would look for the file in the library
+    // search path instead.
+    let absolute_path = path.canonicalize().map_err(|err| err.to_string())?;
+    let mut loaded = globals().extensions.lock().unwrap();
+    if let Some((_, make_module)) = loaded.get(&absolute_path) {
+        return Ok(*make_module);
+    }
+
+    let c_path = CString::new(absolute_path.as_os_str().as_bytes())
+        .map_err(|_| format!("{} contains a nul byte", path.display()))?;
+    let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if handle.is_null() {
+        return Err(last_error());
+    }
+    let library = Library { handle };
+    let symbol = CString::new(SYMBOL).unwrap();
+    let def = unsafe { libc::dlsym(library.handle, symbol.as_ptr()) } as *const ExtensionModuleDef;
+    if def.is_null() {
+        return Err(format!(
+            "{} is not a rustpython extension module, it has no {}",
+            path.display(),
+            SYMBOL
+        ));
+    }
+    let abi_version = unsafe { (*def).abi_version };
+    if abi_version != ABI_VERSION {
+        return Err(format!(
+            "{} was built for extension ABI version {}, not {}",
+            path.display(),
+            abi_version,
+            ABI_VERSION
+        ));
+    }
+    let def = unsafe { &*def };
+    let vm_version = unsafe { CStr::from_ptr(def.vm_version as *const libc::c_char) };
+    if vm_version.to_bytes_with_nul() != VM_VERSION.as_bytes() {
+        return Err(format!(
+            "{} was built against rustpython_vm {}, not {}",
+            path.display(),
+            vm_version.to_string_lossy(),
+            VM_VERSION.trim_end_matches('\0')
+        ));
+    }
+    if (def.build_id)() != build_id() {
+        return Err(format!(
+            "{} was built against another build of rustpython_vm {}",
+            path.display(),
+            VM_VERSION.trim_end_matches('\0')
+        ));
+    }
+    (def.share_globals)(globals());
+    loaded.insert(absolute_path, (library, def.make_module));
+    Ok(def.make_module)
+}
+
+#[cfg(not(unix))]
+pub fn load(path: &Path) -> Result<MakeModule, String> {
+    Err(format!(
+        "can't load {}, extension modules are not supported on this platform",
+        path.display()
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use crate::obj::objtype;
+    use crate::vm::VirtualMachine;
+
+    #[test]
+    fn test_not_an_extension() {
+        let dir = env::temp_dir().join(format!("rustpython_extension_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("bogus.so"), b"not a library").unwrap();
+
+        let vm = VirtualMachine::new();
+        let sys_path = vm.get_attribute(vm.sys_module.clone(), "path").unwrap();
+        vm.call_method(
+            &sys_path,
+            "append",
+            vec![vm.new_str(dir.to_string_lossy().into_owned())],
+        )
+        .unwrap();
+        let exc = vm.import("bogus").err().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(objtype::isinstance(&exc, &vm.ctx.exceptions.import_error));
+        assert!(!objtype::isinstance(
+            &exc,
+            &vm.ctx.exceptions.module_not_found_error
+        ));
+    }
+}
@@ -1,5 +1,6 @@
 use std::cell::RefCell;
 use std::fmt;
+use std::iter;
 use std::rc::Rc;
 
 use num_bigint::BigInt;
@@ -13,18 +14,59 @@ use crate::obj::objbool;
 use crate::obj::objbuiltinfunc::PyBuiltinFunction;
 use crate::obj::objcode::PyCodeRef;
 use crate::obj::objdict::{self, PyDictRef};
-use crate::obj::objint::PyInt;
+use crate::obj::objint::{self, PyInt};
 use crate::obj::objiter;
 use crate::obj::objlist;
 use crate::obj::objslice::PySlice;
-use crate::obj::objstr;
+use crate::obj::objstr::{self, PyStringRef};
 use crate::obj::objtype;
-use crate::obj::objtype::PyClassRef;
+use crate::obj::objtype::{PyClass, PyClassRef};
 use crate::pyobject::{
     DictProtocol, IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject,
     TypeProtocol,
 };
-use crate::vm::VirtualMachine;
+use crate::signal;
+use crate::vm::{self, VirtualMachine};
+
+/// The values of two ints stored inline, when neither is an instance of a
+/// subclass of int, which could override the operators.
+fn exact_small_ints(vm: &VirtualMachine, a: &PyObjectRef, b: &PyObjectRef) -> Option<(i64, i64)> {
+    let int_type = &vm.ctx.int_type;
+    if !a.class().is(int_type) || !b.class().is(int_type) {
+        return None;
+    }
+    Some((objint::get_int(a).as_i64()?, objint::get_int(b).as_i64()?))
+}
+
+/// `a <op> b` on inline ints, unless the result doesn't fit or needs the
+/// generic implementation to raise or return a float.
+fn small_int_binop(op: &bytecode::BinaryOperator, a: i64, b: i64) -> Option<i64> {
+    match *op {
+        bytecode::BinaryOperator::Add => a.checked_add(b),
+        bytecode::BinaryOperator::Subtract => a.checked_sub(b),
+        bytecode::BinaryOperator::Multiply => a.checked_mul(b),
+        bytecode::BinaryOperator::FloorDivide => objint::floor_div(a, b),
+        bytecode::BinaryOperator::Modulo => objint::floor_mod(a, b),
+        bytecode::BinaryOperator::Lshift => objint::small_lshift(a, b),
+        bytecode::BinaryOperator::Rshift if b >= 0 => Some(a >> b.min(63)),
+        bytecode::BinaryOperator::And => Some(a & b),
+        bytecode::BinaryOperator::Or => Some(a | b),
+        bytecode::BinaryOperator::Xor => Some(a ^ b),
+        _ => None,
+    }
+}
+
+fn small_int_compare(op: &bytecode::ComparisonOperator, a: i64, b: i64) -> Option<bool> {
+    match *op {
+        bytecode::ComparisonOperator::Equal => Some(a == b),
+        bytecode::ComparisonOperator::NotEqual => Some(a != b),
+        bytecode::ComparisonOperator::Less => Some(a < b),
+        bytecode::ComparisonOperator::LessOrEqual => Some(a <= b),
+        bytecode::ComparisonOperator::Greater => Some(a > b),
+        bytecode::ComparisonOperator::GreaterOrEqual => Some(a >= b),
+        _ => None,
+    }
+}
 
 /*
  * So a scope is a linked list of scopes.
@@ -120,6 +162,14 @@ impl Scope {
     pub fn child_scope(&self, ctx: &PyContext) -> Scope {
         self.child_scope_with_locals(ctx.new_dict())
     }
+
+    /// Look a name up in the local scopes, innermost first.
+    fn load_local_name(&self, name: &PyStringRef) -> Option<PyObjectRef> {
+        self.locals
+            .iter()
+            .filter_map(|dict| objdict::content_get_key(&dict.entries(), name))
+            .next()
+    }
 }
 
 pub trait NameProtocol {
@@ -131,13 +181,14 @@ pub trait NameProtocol {
 
 impl NameProtocol for Scope {
     fn load_name(&self, vm: &VirtualMachine, name: &str) -> Option<PyObjectRef> {
-        for dict in self.locals.iter() {
-            if let Some(value) = dict.get_item(name) {
-                return Some(value);
-            }
-        }
-
-        if let Some(value) = self.globals.get_item(name) {
+        // The name is hashed once for all the dicts it is looked up in.
+        let key = objdict::StrKey::new(name);
+        let value = self
+            .locals
+            .iter()
+            .chain(iter::once(&self.globals))
+            .find_map(|dict| dict.entries().get(&key).cloned());
+        if let Some(value) = value {
             return Some(value);
         }
 
@@ -183,17 +234,22 @@ enum BlockType {
         end: bytecode::Label,
         context_manager: PyObjectRef,
     },
+    /// An `except` clause is handling `exception`.
+    ExceptHandler {
+        exception: PyObjectRef,
+    },
 }
 
 pub type FrameRef = PyRef<Frame>;
 
 pub struct Frame {
-    pub code: bytecode::CodeObject,
+    pub code: PyCodeRef,
     // We need 1 stack per frame
     stack: RefCell<Vec<PyObjectRef>>, // The main data frame of the stack machine
     blocks: RefCell<Vec<Block>>,      // Block frames, for controlling loops and exceptions
     pub scope: Scope,                 // Variables
     pub lasti: RefCell<usize>,        // index of last instruction ran
+    pub back: RefCell<Option<FrameRef>>, // the calling frame
 }
 
 impl PyValue for Frame {
@@ -225,62 +281,180 @@ impl Frame {
         // locals.extend(callargs);
 
         Frame {
-            code: code.code.clone(),
+            code,
             stack: RefCell::new(vec![]),
             blocks: RefCell::new(vec![]),
             // save the callargs as locals
             // globals: locals.clone(),
             scope,
             lasti: RefCell::new(0),
+            back: RefCell::new(None),
         }
     }
 
     pub fn run(&self, vm: &VirtualMachine) -> Result<ExecutionResult, PyObjectRef> {
-        let filename = &self.code.source_path.to_string();
-
-        // This is the name of the object being run:
-        let run_obj_name = &self.code.obj_name.to_string();
-
+        let mut traced_line = 0;
         // Execute until return or exception:
         loop {
             let lineno = self.get_lineno();
-            let result = self.execute_instruction(vm);
+            let result = if !vm::eval_break_requested() {
+                self.execute_instruction(vm)
+            } else {
+                // Objects released by the previous instruction get finalized now:
+                vm.run_finalizers();
+                vm.check_thread_switch();
+                if let Err(exception) = signal::check_signals(vm) {
+                    Err(exception)
+                } else if vm.is_tracing() && lineno.get_row() != traced_line {
+                    traced_line = lineno.get_row();
+                    vm.trace_line(self, traced_line)
+                        .and_then(|()| self.execute_instruction(vm))
+                } else {
+                    self.execute_instruction(vm)
+                }
+            };
             match result {
                 Ok(None) => {}
                 Ok(Some(value)) => {
                     break Ok(value);
                 }
                 Err(exception) => {
-                    // unwind block stack on exception and find any handlers.
-                    // Add an entry in the traceback:
-                    assert!(objtype::isinstance(
-                        &exception,
-                        &vm.ctx.exceptions.base_exception_type
-                    ));
-                    let traceback = vm
-                        .get_attribute(exception.clone(), "__traceback__")
-                        .unwrap();
-                    trace!("Adding to traceback: {:?} {:?}", traceback, lineno);
-                    let pos = vm.ctx.new_tuple(vec![
-                        vm.ctx.new_str(filename.clone()),
-                        vm.ctx.new_int(lineno.get_row()),
-                        vm.ctx.new_str(run_obj_name.clone()),
-                    ]);
-                    objlist::PyListRef::try_from_object(vm, traceback)?.append(pos, vm);
-                    // exception.__trace
-                    match self.unwind_exception(vm, exception) {
-                        None => {}
-                        Some(exception) => {
-                            // TODO: append line number to traceback?
-                            // traceback.append();
-                            break Err(exception);
-                        }
+                    if let Err(exception) = self.handle_exception(vm, exception, &lineno) {
+                        break Err(exception);
                     }
                 }
             }
         }
     }
 
+    /// Turn the operand of a `raise` into an exception instance, instantiating
+    /// it first when it is an exception class.
+    fn normalize_exception(&self, vm: &VirtualMachine, exception: PyObjectRef) -> PyResult {
+        if objtype::isinstance(&exception, &vm.ctx.exceptions.base_exception_type) {
+            Ok(exception)
+        } else if let Ok(exception) = PyClassRef::try_from_object(vm, exception) {
+            if objtype::issubclass(&exception, &vm.ctx.exceptions.base_exception_type) {
+                vm.new_empty_exception(exception)
+            } else {
+                let msg = format!(
+                    "Can only raise BaseException derived types, not {}",
+                    exception
+                );
+                let type_error_type = vm.ctx.exceptions.type_error.clone();
+                Err(vm.new_exception(type_error_type, msg))
+            }
+        } else {
+            Err(vm.new_type_error("exceptions must derive from BaseException".to_string()))
+        }
+    }
+
+    /// Record where `exception` passed through this frame, then unwind the
+    /// block stack to a handler. Returns the exception if none was found.
+    fn handle_exception(
+        &self,
+        vm: &VirtualMachine,
+        exception: PyObjectRef,
+        lineno: &ast::Location,
+    ) -> PyResult<()> {
+        // Add an entry in the traceback:
+        assert!(objtype::isinstance(
+            &exception,
+            &vm.ctx.exceptions.base_exception_type
+        ));
+        let traceback = match vm.get_attribute(exception.clone(), "__traceback__") {
+            Ok(ref traceback) if objtype::isinstance(traceback, &vm.ctx.list_type()) => {
+                traceback.clone()
+            }
+            _ => {
+                let traceback = vm.ctx.new_list(vec![]);
+                vm.ctx
+                    .set_attr(&exception, "__traceback__", traceback.clone());
+                traceback
+            }
+        };
+        trace!("Adding to traceback: {:?} {:?}", traceback, lineno);
+        let pos = vm.ctx.new_tuple(vec![
+            vm.ctx.new_str(self.code.source_path.to_string()),
+            vm.ctx.new_int(lineno.get_row()),
+            vm.ctx.new_str(self.code.obj_name.to_string()),
+        ]);
+        objlist::PyListRef::try_from_object(vm, traceback)?.append(pos, vm);
+        // unwind block stack on exception and find any handlers.
+        match self.unwind_exception(vm, exception) {
+            None => Ok(()),
+            Some(exception) => Err(exception),
+        }
+    }
+
+    /// Resume a suspended generator frame by raising `exception` where it
+    /// yielded. If it is suspended in a `yield from`, the exception is first
+    /// thrown into the iterator being delegated to.
+    pub fn throw(
+        &self,
+        vm: &VirtualMachine,
+        exception: PyObjectRef,
+    ) -> Result<ExecutionResult, PyObjectRef> {
+        let exception = match self.yield_from_target() {
+            Some(iterator) => match self.throw_into_delegate(vm, &iterator, exception) {
+                Ok(Some(value)) => return Ok(ExecutionResult::Yield(value)),
+                Ok(None) => return self.run(vm),
+                Err(exception) => exception,
+            },
+            None => exception,
+        };
+        let lineno = self.current_location();
+        self.handle_exception(vm, exception, &lineno)?;
+        self.run(vm)
+    }
+
+    /// The iterator a `yield from` this frame is suspended in delegates to.
+    fn yield_from_target(&self) -> Option<PyObjectRef> {
+        match self.code.instructions.get(*self.lasti.borrow()) {
+            Some(bytecode::Instruction::YieldFrom) => Some(self.last_value()),
+            _ => None,
+        }
+    }
+
+    /// Returns the value to yield if the iterator yielded one, `None` if it
+    /// finished and its result is pushed, or the exception to raise here.
+    fn throw_into_delegate(
+        &self,
+        vm: &VirtualMachine,
+        iterator: &PyObjectRef,
+        exception: PyObjectRef,
+    ) -> PyResult<Option<PyObjectRef>> {
+        let finish_delegation = || {
+            self.pop_value();
+            *self.lasti.borrow_mut() += 1;
+        };
+        if objtype::isinstance(&exception, &vm.ctx.exceptions.generator_exit) {
+            finish_delegation();
+            if let Ok(close) = vm.get_attribute(iterator.clone(), "close") {
+                vm.invoke(close, vec![])?;
+            }
+            return Err(exception);
+        }
+        let throw = match vm.get_attribute(iterator.clone(), "throw") {
+            Ok(throw) => throw,
+            Err(_) => {
+                finish_delegation();
+                return Err(exception);
+            }
+        };
+        match vm.invoke(throw, vec![exception]) {
+            Ok(value) => Ok(Some(value)),
+            Err(exception) => {
+                finish_delegation();
+                if objtype::isinstance(&exception, &vm.ctx.exceptions.stop_iteration) {
+                    self.push_value(objiter::stop_iteration_value(vm, &exception)?);
+                    Ok(None)
+                } else {
+                    Err(exception)
+                }
+            }
+        }
+    }
+
     pub fn fetch_instruction(&self) -> &bytecode::Instruction {
         let ins2 = &self.code.instructions[*self.lasti.borrow()];
         *self.lasti.borrow_mut() += 1;
@@ -313,8 +487,8 @@ impl Frame {
                 ref symbol,
             } => self.import(vm, name, symbol),
             bytecode::Instruction::ImportStar { ref name } => self.import_star(vm, name),
-            bytecode::Instruction::LoadName { ref name } => self.load_name(vm, name),
-            bytecode::Instruction::StoreName { ref name } => self.store_name(vm, name),
+            bytecode::Instruction::LoadName { .. } => self.load_name(vm, self.instruction_name()),
+            bytecode::Instruction::StoreName { .. } => self.store_name(vm, self.instruction_name()),
             bytecode::Instruction::DeleteName { ref name } => self.delete_name(vm, name),
             bytecode::Instruction::StoreSubscript => self.execute_store_subscript(vm),
             bytecode::Instruction::DeleteSubscript => self.execute_delete_subscript(vm),
@@ -386,19 +560,22 @@ impl Frame {
             }
             bytecode::Instruction::BuildMap { size, unpack } => {
                 let map_obj = vm.ctx.new_dict().into_object();
+                // Insert the items in the order they were pushed, so that the
+                // dict keeps it, and later duplicates win.
+                let mut items = vec![];
                 for _x in 0..*size {
                     let obj = self.pop_value();
                     if *unpack {
                         // Take all key-value pairs from the dict:
-                        let dict_elements = objdict::get_key_value_pairs(&obj);
-                        for (key, value) in dict_elements.iter() {
-                            objdict::set_item(&map_obj, vm, key, value);
-                        }
+                        items.push(objdict::get_key_value_pairs(&obj));
                     } else {
                         let key = self.pop_value();
-                        objdict::set_item(&map_obj, vm, &key, &obj);
+                        items.push(vec![(key, obj)]);
                     }
                 }
+                for (key, value) in items.iter().rev().flatten() {
+                    objdict::set_item(&map_obj, vm, key, value);
+                }
                 self.push_value(map_obj);
                 Ok(None)
             }
@@ -412,7 +589,7 @@ impl Frame {
                         if x.is(&vm.ctx.none()) {
                             None
                         } else if let Some(i) = x.payload::<PyInt>() {
-                            Some(i.as_bigint().clone())
+                            Some(i.as_bigint().into_owned())
                         } else {
                             panic!("Expect Int or None as BUILD_SLICE arguments")
                         }
@@ -449,9 +626,14 @@ impl Frame {
             bytecode::Instruction::BinaryOperation { ref op, inplace } => {
                 self.execute_binop(vm, op, *inplace)
             }
-            bytecode::Instruction::LoadAttr { ref name } => self.load_attr(vm, name),
-            bytecode::Instruction::StoreAttr { ref name } => self.store_attr(vm, name),
-            bytecode::Instruction::DeleteAttr { ref name } => self.delete_attr(vm, name),
+            bytecode::Instruction::LoadAttr { .. } => self.load_attr(vm, self.instruction_name()),
+            bytecode::Instruction::LoadMethod { .. } => {
+                self.load_method(vm, self.instruction_name())
+            }
+            bytecode::Instruction::StoreAttr { .. } => self.store_attr(vm, self.instruction_name()),
+            bytecode::Instruction::DeleteAttr { .. } => {
+                self.delete_attr(vm, self.instruction_name())
+            }
             bytecode::Instruction::UnaryOperation { ref op } => self.execute_unop(vm, op),
             bytecode::Instruction::CompareOperation { ref op } => self.execute_compare(vm, op),
             bytecode::Instruction::ReturnValue => {
@@ -468,21 +650,30 @@ impl Frame {
             }
             bytecode::Instruction::YieldFrom => {
                 // Value send into iterator:
-                self.pop_value();
+                let value = self.pop_value();
 
                 let top_of_stack = self.last_value();
-                let next_obj = objiter::get_next_object(vm, &top_of_stack)?;
+                let next_obj = if value.is(&vm.get_none()) {
+                    objiter::call_next(vm, &top_of_stack)
+                } else {
+                    vm.call_method(&top_of_stack, "send", vec![value])
+                };
 
                 match next_obj {
-                    Some(value) => {
+                    Ok(value) => {
                         // Set back program counter:
                         *self.lasti.borrow_mut() -= 1;
                         Ok(Some(ExecutionResult::Yield(value)))
                     }
-                    None => {
-                        // Pop iterator from stack:
-                        self.pop_value();
-                        Ok(None)
+                    Err(exc) => {
+                        if objtype::isinstance(&exc, &vm.ctx.exceptions.stop_iteration) {
+                            // Replace the iterator with its result:
+                            self.pop_value();
+                            self.push_value(objiter::stop_iteration_value(vm, &exc)?);
+                            Ok(None)
+                        } else {
+                            Err(exc)
+                        }
                     }
                 }
             }
@@ -529,6 +720,14 @@ impl Frame {
                 self.pop_block().expect("no pop to block");
                 Ok(None)
             }
+            bytecode::Instruction::PopException => {
+                let block = self.pop_block().expect("no pop to block");
+                if let BlockType::ExceptHandler { .. } = block.typ {
+                    Ok(None)
+                } else {
+                    unreachable!("Block stack is incorrect, expected an except handler block");
+                }
+            }
             bytecode::Instruction::GetIter => {
                 let iterated_obj = self.pop_value();
                 let iter_obj = objiter::get_iter(vm, &iterated_obj)?;
@@ -591,48 +790,22 @@ impl Frame {
                 Ok(None)
             }
             bytecode::Instruction::CallFunction { typ } => {
-                let args = match typ {
-                    bytecode::CallType::Positional(count) => {
-                        let args: Vec<PyObjectRef> = self.pop_multiple(*count);
-                        PyFuncArgs {
-                            args,
-                            kwargs: vec![],
-                        }
-                    }
-                    bytecode::CallType::Keyword(count) => {
-                        let kwarg_names = self.pop_value();
-                        let args: Vec<PyObjectRef> = self.pop_multiple(*count);
-
-                        let kwarg_names = vm
-                            .extract_elements(&kwarg_names)?
-                            .iter()
-                            .map(|pyobj| objstr::get_value(pyobj))
-                            .collect();
-                        PyFuncArgs::new(args, kwarg_names)
-                    }
-                    bytecode::CallType::Ex(has_kwargs) => {
-                        let kwargs = if *has_kwargs {
-                            let kw_dict = self.pop_value();
-                            let dict_elements = objdict::get_elements(&kw_dict).clone();
-                            dict_elements
-                                .into_iter()
-                                .map(|elem| (elem.0, (elem.1).1))
-                                .collect()
-                        } else {
-                            vec![]
-                        };
-                        let args = self.pop_value();
-                        let args = vm.extract_elements(&args)?;
-                        PyFuncArgs { args, kwargs }
-                    }
-                };
-
-                // Call function:
+                let args = self.pop_call_args(vm, typ)?;
                 let func_ref = self.pop_value();
                 let value = vm.invoke(func_ref, args)?;
                 self.push_value(value);
                 Ok(None)
             }
+            bytecode::Instruction::CallMethod { typ } => {
+                let args = self.pop_call_args(vm, typ)?;
+                let func_ref = self.pop_value();
+                let is_method = objbool::get_value(&self.pop_value());
+                let target = self.pop_value();
+                let args = if is_method { args.insert(target) } else { args };
+                let value = vm.invoke(func_ref, args)?;
+                self.push_value(value);
+                Ok(None)
+            }
             bytecode::Instruction::Jump { target } => {
                 self.jump(*target);
                 Ok(None)
@@ -657,30 +830,33 @@ impl Frame {
 
             bytecode::Instruction::Raise { argc } => {
                 let exception = match argc {
-                    1 => self.pop_value(),
-                    0 | 2 | 3 => panic!("Not implemented!"),
-                    _ => panic!("Invalid parameter for RAISE_VARARGS, must be between 0 to 3"),
-                };
-                if objtype::isinstance(&exception, &vm.ctx.exceptions.base_exception_type) {
-                    info!("Exception raised: {:?}", exception);
-                    Err(exception)
-                } else if let Ok(exception) = PyClassRef::try_from_object(vm, exception) {
-                    if objtype::issubclass(&exception, &vm.ctx.exceptions.base_exception_type) {
-                        let exception = vm.new_empty_exception(exception)?;
-                        info!("Exception raised: {:?}", exception);
-                        Err(exception)
-                    } else {
-                        let msg = format!(
-                            "Can only raise BaseException derived types, not {}",
-                            exception
-                        );
-                        let type_error_type = vm.ctx.exceptions.type_error.clone();
-                        let type_error = vm.new_exception(type_error_type, msg);
-                        Err(type_error)
+                    1 => self.normalize_exception(vm, self.pop_value())?,
+                    2 => {
+                        let cause = self.pop_value();
+                        let exception = self.normalize_exception(vm, self.pop_value())?;
+                        let cause = if cause.is(&vm.get_none()) {
+                            cause
+                        } else {
+                            self.normalize_exception(vm, cause)?
+                        };
+                        vm.ctx.set_attr(&exception, "__cause__", cause);
+                        vm.ctx
+                            .set_attr(&exception, "__suppress_context__", vm.new_bool(true));
+                        exception
                     }
-                } else {
-                    Err(vm.new_type_error("exceptions must derive from BaseException".to_string()))
-                }
+                    // Re-raise the exception being handled.
+                    0 => vm.current_exception().ok_or_else(|| {
+                        vm.new_runtime_error("No active exception to reraise".to_string())
+                    })?,
+                    _ => {
+                        return Err(vm.new_system_error(format!(
+                            "invalid argument count for Raise: {}",
+                            argc
+                        )));
+                    }
+                };
+                info!("Exception raised: {:?}", exception);
+                Err(exception)
             }
 
             bytecode::Instruction::Break => {
@@ -708,13 +884,10 @@ impl Frame {
             }
             bytecode::Instruction::PrintExpr => {
                 let expr = self.pop_value();
-                if !expr.is(&vm.get_none()) {
-                    let repr = vm.to_repr(&expr)?;
-                    // TODO: implement sys.displayhook
-                    if let Ok(print) = vm.get_attribute(vm.builtins.clone(), "print") {
-                        vm.invoke(print, vec![repr.into_object()])?;
-                    }
-                }
+                let displayhook = vm
+                    .get_attribute(vm.sys_module.clone(), "displayhook")
+                    .map_err(|_| vm.new_runtime_error("lost sys.displayhook".to_string()))?;
+                vm.invoke(displayhook, vec![expr])?;
                 Ok(None)
             }
             bytecode::Instruction::LoadBuildClass => {
@@ -851,7 +1024,7 @@ impl Frame {
         while let Some(block) = self.pop_block() {
             match block.typ {
                 BlockType::Loop { .. } => {}
-                BlockType::TryExcept { .. } => {
+                BlockType::TryExcept { .. } | BlockType::ExceptHandler { .. } => {
                     // TODO: execute finally handler
                 }
                 BlockType::With {
@@ -876,7 +1049,7 @@ impl Frame {
             let block = self.current_block().expect("not in a loop");
             match block.typ {
                 BlockType::Loop { .. } => break block,
-                BlockType::TryExcept { .. } => {
+                BlockType::TryExcept { .. } | BlockType::ExceptHandler { .. } => {
                     // TODO: execute finally handler
                 }
                 BlockType::With {
@@ -898,6 +1071,9 @@ impl Frame {
         while let Some(block) = self.pop_block() {
             match block.typ {
                 BlockType::TryExcept { handler } => {
+                    self.push_block(BlockType::ExceptHandler {
+                        exception: exc.clone(),
+                    });
                     self.push_value(exc);
                     self.jump(handler);
                     return None;
@@ -930,7 +1106,7 @@ impl Frame {
                         }
                     }
                 }
-                BlockType::Loop { .. } => {}
+                BlockType::Loop { .. } | BlockType::ExceptHandler { .. } => {}
             }
         }
         Some(exc)
@@ -961,9 +1137,53 @@ impl Frame {
         vm.call_method(context_manager, "__exit__", args)
     }
 
-    fn store_name(&self, vm: &VirtualMachine, name: &str) -> FrameResult {
+    /// Pop the arguments of a `CallFunction` or `CallMethod` instruction.
+    fn pop_call_args(&self, vm: &VirtualMachine, typ: &bytecode::CallType) -> PyResult<PyFuncArgs> {
+        Ok(match typ {
+            bytecode::CallType::Positional(count) => {
+                let args: Vec<PyObjectRef> = self.pop_multiple(*count);
+                PyFuncArgs {
+                    args,
+                    kwargs: vec![],
+                }
+            }
+            bytecode::CallType::Keyword(count) => {
+                let kwarg_names = self.pop_value();
+                let args: Vec<PyObjectRef> = self.pop_multiple(*count);
+
+                let kwarg_names = vm
+                    .extract_elements(&kwarg_names)?
+                    .iter()
+                    .map(|pyobj| objstr::get_value(pyobj))
+                    .collect();
+                PyFuncArgs::new(args, kwarg_names)
+            }
+            bytecode::CallType::Ex(has_kwargs) => {
+                let kwargs = if *has_kwargs {
+                    let kw_dict = self.pop_value();
+                    let dict_elements = objdict::get_elements(&kw_dict).clone();
+                    dict_elements
+                        .into_iter()
+                        .map(|(key, value)| (key.as_str().to_string(), value))
+                        .collect()
+                } else {
+                    vec![]
+                };
+                let args = self.pop_value();
+                let args = vm.extract_elements(&args)?;
+                PyFuncArgs { args, kwargs }
+            }
+        })
+    }
+
+    fn store_name(&self, vm: &VirtualMachine, name: &PyStringRef) -> FrameResult {
         let obj = self.pop_value();
-        self.scope.store_name(&vm, name, obj);
+        objdict::set_item(
+            self.scope.get_locals().as_object(),
+            vm,
+            name.as_object(),
+            &obj,
+        );
         Ok(None)
     }
 
@@ -972,8 +1192,23 @@ impl Frame {
         Ok(None)
     }
 
-    fn load_name(&self, vm: &VirtualMachine, name: &str) -> FrameResult {
-        match self.scope.load_name(&vm, name) {
+    /// The index of the instruction being executed.
+    fn current_index(&self) -> usize {
+        *self.lasti.borrow() - 1
+    }
+
+    /// The interned name the instruction being executed refers to.
+    fn instruction_name(&self) -> &PyStringRef {
+        self.code.names[self.current_index()].as_ref().unwrap()
+    }
+
+    fn load_name(&self, vm: &VirtualMachine, name: &PyStringRef) -> FrameResult {
+        let value = self.scope.load_local_name(name).or_else(|| {
+            self.code
+                .caches
+                .load_global(vm, self.current_index(), &self.scope.globals, &name.value)
+        });
+        match value {
             Some(value) => {
                 self.push_value(value);
                 Ok(None)
@@ -988,6 +1223,16 @@ impl Frame {
     }
 
     fn subscript(&self, vm: &VirtualMachine, a: PyObjectRef, b: PyObjectRef) -> PyResult {
+        // A class without a `__getitem__` from its metaclass may be
+        // subscripted through `__class_getitem__`, see PEP 560.
+        if let Ok(class) = a.clone().downcast::<PyClass>() {
+            if !objtype::class_has_attr(&class.class(), "__getitem__")
+                && objtype::class_has_attr(&class, "__class_getitem__")
+            {
+                let class_getitem = vm.get_attribute(a.clone(), "__class_getitem__")?;
+                return vm.invoke(class_getitem, vec![b]);
+            }
+        }
         vm.call_method(&a, "__getitem__", vec![b])
     }
 
@@ -1020,6 +1265,12 @@ impl Frame {
     ) -> FrameResult {
         let b_ref = self.pop_value();
         let a_ref = self.pop_value();
+        if let Some((a, b)) = exact_small_ints(vm, &a_ref, &b_ref) {
+            if let Some(value) = small_int_binop(op, a, b) {
+                self.push_value(vm.ctx.new_int(value));
+                return Ok(None);
+            }
+        }
         let value = match *op {
             bytecode::BinaryOperator::Subtract if inplace => vm._isub(a_ref, b_ref),
             bytecode::BinaryOperator::Subtract => vm._sub(a_ref, b_ref),
@@ -1125,6 +1376,12 @@ impl Frame {
     ) -> FrameResult {
         let b = self.pop_value();
         let a = self.pop_value();
+        if let Some((a, b)) = exact_small_ints(vm, &a, &b) {
+            if let Some(value) = small_int_compare(op, a, b) {
+                self.push_value(vm.ctx.new_bool(value));
+                return Ok(None);
+            }
+        }
         let value = match *op {
             bytecode::ComparisonOperator::Equal => vm._eq(a, b)?,
             bytecode::ComparisonOperator::NotEqual => vm._ne(a, b)?,
@@ -1142,27 +1399,51 @@ impl Frame {
         Ok(None)
     }
 
-    fn load_attr(&self, vm: &VirtualMachine, attr_name: &str) -> FrameResult {
+    fn load_attr(&self, vm: &VirtualMachine, attr_name: &PyStringRef) -> FrameResult {
         let parent = self.pop_value();
-        let obj = vm.get_attribute(parent, attr_name)?;
+        let obj = self
+            .code
+            .caches
+            .load_attr(vm, self.current_index(), parent, attr_name)?;
         self.push_value(obj);
         Ok(None)
     }
 
-    fn store_attr(&self, vm: &VirtualMachine, attr_name: &str) -> FrameResult {
+    /// Push what `CallMethod` needs: the object the method was looked up on,
+    /// whether it has to be passed as the first argument, and the callable.
+    fn load_method(&self, vm: &VirtualMachine, name: &PyStringRef) -> FrameResult {
+        let obj = self.pop_value();
+        let (callable, target) =
+            self.code
+                .caches
+                .load_method(vm, self.current_index(), obj, name)?;
+        let is_method = target.is_some();
+        self.push_value(target.unwrap_or_else(|| vm.get_none()));
+        self.push_value(vm.ctx.new_bool(is_method));
+        self.push_value(callable);
+        Ok(None)
+    }
+
+    fn store_attr(&self, vm: &VirtualMachine, attr_name: &PyStringRef) -> FrameResult {
         let parent = self.pop_value();
         let value = self.pop_value();
-        vm.set_attr(&parent, vm.new_str(attr_name.to_string()), value)?;
+        vm.set_attr(&parent, attr_name.clone().into_object(), value)?;
         Ok(None)
     }
 
-    fn delete_attr(&self, vm: &VirtualMachine, attr_name: &str) -> FrameResult {
+    fn delete_attr(&self, vm: &VirtualMachine, attr_name: &PyStringRef) -> FrameResult {
         let parent = self.pop_value();
-        let name = vm.ctx.new_str(attr_name.to_string());
-        vm.del_attr(&parent, name)?;
+        vm.del_attr(&parent, attr_name.clone().into_object())?;
         Ok(None)
     }
 
+    /// The location of the instruction that ran last, or of the first one
+    /// when the frame has not started yet.
+    pub fn current_location(&self) -> ast::Location {
+        let lasti = *self.lasti.borrow();
+        self.code.locations[lasti.saturating_sub(1)].clone()
+    }
+
     pub fn get_lineno(&self) -> ast::Location {
         self.code.locations[*self.lasti.borrow()].clone()
     }
@@ -1184,6 +1465,19 @@ impl Frame {
         self.blocks.borrow().last().cloned()
     }
 
+    /// The exception the innermost `except` clause running in this frame
+    /// handles.
+    pub fn handled_exception(&self) -> Option<PyObjectRef> {
+        self.blocks
+            .borrow()
+            .iter()
+            .rev()
+            .find_map(|block| match block.typ {
+                BlockType::ExceptHandler { ref exception } => Some(exception.clone()),
+                _ => None,
+            })
+    }
+
     pub fn push_value(&self, obj: PyObjectRef) {
         self.stack.borrow_mut().push(obj);
     }
@@ -1232,7 +1526,7 @@ impl fmt::Debug for Frame {
             .map(|elem| format!("\n  > {:?}", elem))
             .collect::<String>();
         let dict = self.scope.get_locals();
-        let local_str = objdict::get_key_value_pairs_from_content(&dict.entries.borrow())
+        let local_str = objdict::get_key_value_pairs_from_content(&dict.entries())
             .iter()
             .map(|elem| format!("\n  {:?} = {:?}", elem.0, elem.1))
             .collect::<String>();
@@ -1,3 +1,4 @@
+use std::cmp;
 use std::collections::HashMap;
 use std::ops::RangeInclusive;
 
@@ -143,21 +144,41 @@ impl PyFuncArgs {
     ///
     /// If the given `FromArgs` includes any conversions, exceptions raised
     /// during the conversion will halt the binding and return the error.
-    pub fn bind<T: FromArgs>(mut self, vm: &VirtualMachine) -> PyResult<T> {
+    pub fn bind<T: FromArgs>(self, vm: &VirtualMachine) -> PyResult<T> {
+        self.bind_with(vm, T::arity(), T::from_args)
+    }
+
+    /// Binds these arguments to a tuple of parameters, each taking the
+    /// arguments it binds in turn, like the parameters of a native function.
+    pub fn bind_params<T: FromArgsTuple>(self, vm: &VirtualMachine) -> PyResult<T> {
+        self.bind_with(vm, T::arity(), T::from_args_tuple)
+    }
+
+    /// Like `bind`, for the parameters `from_args` binds, which accept `arity`
+    /// positional arguments.
+    fn bind_with<T, F>(
+        mut self,
+        vm: &VirtualMachine,
+        arity: RangeInclusive<usize>,
+        from_args: F,
+    ) -> PyResult<T>
+    where
+        F: FnOnce(&VirtualMachine, &mut PyFuncArgs) -> Result<T, ArgumentError>,
+    {
         let given_args = self.args.len();
-        let bound = match T::from_args(vm, &mut self) {
+        let bound = match from_args(vm, &mut self) {
             Ok(args) => args,
             Err(ArgumentError::TooFewArgs) => {
                 return Err(vm.new_type_error(format!(
                     "Expected at least {} arguments ({} given)",
-                    T::arity().start(),
+                    arity.start(),
                     given_args,
                 )));
             }
             Err(ArgumentError::TooManyArgs) => {
                 return Err(vm.new_type_error(format!(
                     "Expected at most {} arguments ({} given)",
-                    T::arity().end(),
+                    arity.end(),
                     given_args,
                 )));
             }
@@ -172,7 +193,7 @@ impl PyFuncArgs {
         if !self.args.is_empty() {
             Err(vm.new_type_error(format!(
                 "Expected at most {} arguments ({} given)",
-                T::arity().end(),
+                arity.end(),
                 given_args,
             )))
         } else if !self.kwargs.is_empty() {
@@ -218,6 +239,147 @@ pub trait FromArgs: Sized {
     fn from_args(vm: &VirtualMachine, args: &mut PyFuncArgs) -> Result<Self, ArgumentError>;
 }
 
+/// How a parameter of a `#[derive(FromArgs)]` struct is passed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterKind {
+    PositionalOnly,
+    PositionalOrKeyword,
+    KeywordOnly,
+}
+
+/// A named parameter of a `#[derive(FromArgs)]` struct.
+#[derive(Debug)]
+pub struct Parameter {
+    pub name: &'static str,
+    pub kind: ParameterKind,
+    pub required: bool,
+}
+
+/// Bind arguments to `parameters` the way python does, giving the value of
+/// each parameter that got one. The arguments no parameter takes are left in
+/// `args` when there are `varargs` and `varkwargs` to take them, and raise a
+/// `TypeError` otherwise, which names `function` if given.
+///
+/// This is what the `FromArgs` implementations `#[derive(FromArgs)]`
+/// generates are made of.
+pub fn bind_parameters(
+    vm: &VirtualMachine,
+    args: &mut PyFuncArgs,
+    function: Option<&str>,
+    parameters: &[Parameter],
+    varargs: bool,
+    varkwargs: bool,
+) -> Result<Vec<Option<PyObjectRef>>, ArgumentError> {
+    let type_error = |message: String| {
+        let message = match function {
+            Some(function) => format!("{}() {}", function, message),
+            None => message,
+        };
+        ArgumentError::from(vm.new_type_error(message))
+    };
+    let is_positional = |parameter: &&Parameter| parameter.kind != ParameterKind::KeywordOnly;
+    let positional_count = parameters.iter().filter(is_positional).count();
+    let given = args.args.len();
+    if given > positional_count && !varargs {
+        let required_count = parameters
+            .iter()
+            .filter(|parameter| is_positional(parameter) && parameter.required)
+            .count();
+        let takes = if required_count == positional_count {
+            format!(
+                "{} positional argument{}",
+                positional_count,
+                if positional_count == 1 { "" } else { "s" }
+            )
+        } else {
+            format!(
+                "from {} to {} positional arguments",
+                required_count, positional_count
+            )
+        };
+        let were = if given == 1 { "was" } else { "were" };
+        return Err(type_error(format!(
+            "takes {} but {} {} given",
+            takes, given, were
+        )));
+    }
+
+    let mut values: Vec<Option<PyObjectRef>> = parameters.iter().map(|_| None).collect();
+    let mut positional = args.args.drain(..cmp::min(given, positional_count));
+    for (_, value) in parameters
+        .iter()
+        .zip(values.iter_mut())
+        .filter(|(parameter, _)| is_positional(parameter))
+    {
+        *value = positional.next();
+    }
+    drop(positional);
+
+    for (parameter, value) in parameters.iter().zip(values.iter_mut()) {
+        if parameter.kind == ParameterKind::PositionalOnly {
+            continue;
+        }
+        if let Some(keyword) = args.take_keyword(parameter.name) {
+            if value.is_some() {
+                return Err(type_error(format!(
+                    "got multiple values for argument '{}'",
+                    parameter.name
+                )));
+            }
+            *value = Some(keyword);
+        }
+    }
+    if !varkwargs {
+        if let Some((name, _)) = args.kwargs.first() {
+            let positional_only = parameters.iter().any(|parameter| {
+                parameter.kind == ParameterKind::PositionalOnly && parameter.name == name
+            });
+            let message = if positional_only {
+                format!(
+                    "got some positional-only arguments passed as keyword arguments: '{}'",
+                    name
+                )
+            } else {
+                format!("got an unexpected keyword argument '{}'", name)
+            };
+            return Err(type_error(message));
+        }
+    }
+
+    for &keyword_only in &[false, true] {
+        let missing: Vec<_> = parameters
+            .iter()
+            .zip(values.iter())
+            .filter(|(parameter, value)| {
+                parameter.required
+                    && value.is_none()
+                    && (parameter.kind == ParameterKind::KeywordOnly) == keyword_only
+            })
+            .map(|(parameter, _)| format!("'{}'", parameter.name))
+            .collect();
+        if !missing.is_empty() {
+            let names = match missing.split_last() {
+                Some((last, [])) => last.clone(),
+                Some((last, [first])) => format!("{} and {}", first, last),
+                Some((last, rest)) => format!("{}, and {}", rest.join(", "), last),
+                None => unreachable!(),
+            };
+            return Err(type_error(format!(
+                "missing {} required {} argument{}: {}",
+                missing.len(),
+                if keyword_only {
+                    "keyword-only"
+                } else {
+                    "positional"
+                },
+                if missing.len() == 1 { "" } else { "s" },
+                names
+            )));
+        }
+    }
+    Ok(values)
+}
+
 /// A map of keyword arguments to their values.
 ///
 /// A built-in function with a `KwArgs` parameter is analagous to a Python
@@ -247,6 +409,18 @@ where
     }
 }
 
+impl<T> Default for KwArgs<T> {
+    fn default() -> Self {
+        KwArgs(HashMap::new())
+    }
+}
+
+impl<T> KwArgs<T> {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
 impl<T> IntoIterator for KwArgs<T> {
     type Item = (String, T);
     type IntoIter = std::collections::hash_map::IntoIter<String, T>;
@@ -356,18 +530,24 @@ impl FromArgs for () {
     }
 }
 
-// A tuple of types that each implement `FromArgs` represents a sequence of
-// arguments that can be bound and passed to a built-in function.
-//
-// Technically, a tuple can contain tuples, which can contain tuples, and so on,
-// so this actually represents a tree of values to be bound from arguments, but
-// in practice this is only used for the top-level parameters.
+/// A tuple of types that each implement `FromArgs`, which represents a
+/// sequence of parameters bound to arguments in turn.
+///
+/// This is apart from `FromArgs` so that a tuple can be a single argument too,
+/// converted with `TryFromObject`.
+pub trait FromArgsTuple: Sized {
+    fn arity() -> RangeInclusive<usize>;
+
+    fn from_args_tuple(vm: &VirtualMachine, args: &mut PyFuncArgs) -> Result<Self, ArgumentError>;
+}
+
 macro_rules! tuple_from_py_func_args {
-    ($($T:ident),+) => {
-        impl<$($T),+> FromArgs for ($($T,)+)
+    ($($T:ident),*) => {
+        impl<$($T),*> FromArgsTuple for ($($T,)*)
         where
-            $($T: FromArgs),+
+            $($T: FromArgs),*
         {
+            #[allow(unused_mut)]
             fn arity() -> RangeInclusive<usize> {
                 let mut min = 0;
                 let mut max = 0;
@@ -375,20 +555,25 @@ macro_rules! tuple_from_py_func_args {
                     let (start, end) = $T::arity().into_inner();
                     min += start;
                     max += end;
-                )+
+                )*
                 min..=max
             }
 
-            fn from_args(vm: &VirtualMachine, args: &mut PyFuncArgs) -> Result<Self, ArgumentError> {
-                Ok(($($T::from_args(vm, args)?,)+))
+            #[allow(unused_variables)]
+            fn from_args_tuple(
+                vm: &VirtualMachine,
+                args: &mut PyFuncArgs,
+            ) -> Result<Self, ArgumentError> {
+                Ok(($($T::from_args(vm, args)?,)*))
             }
         }
     };
 }
 
-// Implement `FromArgs` for up to 5-tuples, allowing built-in functions to bind
-// up to 5 top-level parameters (note that `Args`, `KwArgs`, nested tuples, etc.
-// count as 1, so this should actually be more than enough).
+// Implement `FromArgsTuple` for up to 5-tuples, allowing built-in functions to
+// bind up to 5 top-level parameters (note that `Args`, `KwArgs`, derived
+// structs, etc. count as 1, so this should actually be more than enough).
+tuple_from_py_func_args!();
 tuple_from_py_func_args!(A);
 tuple_from_py_func_args!(A, B);
 tuple_from_py_func_args!(A, B, C);
@@ -425,12 +610,6 @@ where
     }
 }
 
-impl IntoPyNativeFunc<PyFuncArgs, PyResult> for PyNativeFunc {
-    fn into_func(self) -> PyNativeFunc {
-        self
-    }
-}
-
 // This is the "magic" that allows rust functions of varying signatures to
 // generate native python functions.
 //
@@ -441,12 +620,12 @@ macro_rules! into_py_native_func_tuple {
         where
             F: Fn($($T,)* &VirtualMachine) -> R + 'static,
             $($T: FromArgs,)*
-            ($($T,)*): FromArgs,
+            ($($T,)*): FromArgsTuple,
             R: IntoPyObject,
         {
             fn into_func(self) -> PyNativeFunc {
                 Box::new(move |vm, args| {
-                    let ($($n,)*) = args.bind::<($($T,)*)>(vm)?;
+                    let ($($n,)*) = args.bind_params::<($($T,)*)>(vm)?;
 
                     (self)($($n,)* vm).into_pyobject(vm)
                 })
@@ -461,3 +640,107 @@ into_py_native_func_tuple!((a, A), (b, B));
 into_py_native_func_tuple!((a, A), (b, B), (c, C));
 into_py_native_func_tuple!((a, A), (b, B), (c, C), (d, D));
 into_py_native_func_tuple!((a, A), (b, B), (c, C), (d, D), (e, E));
+
+#[cfg(test)]
+mod tests {
+    use super::{Args, KwArgs, OptionalArg, PyFuncArgs};
+    use crate::obj::objtype;
+    use crate::pyobject::PyObjectRef;
+    use crate::vm::VirtualMachine;
+
+    #[derive(FromArgs)]
+    struct Signature {
+        #[pyarg(positional_only)]
+        a: i32,
+        #[pyarg(positional_or_keyword, optional)]
+        b: OptionalArg<i32>,
+        #[pyarg(args)]
+        rest: Args<i32>,
+        #[pyarg(keyword_only)]
+        c: i32,
+        #[pyarg(keyword_only, default = "7")]
+        d: i32,
+        #[pyarg(kwargs)]
+        extra: KwArgs<i32>,
+    }
+
+    #[derive(FromArgs)]
+    #[pyarg(name = "strict")]
+    struct Strict {
+        #[pyarg(positional_only)]
+        a: i32,
+        #[pyarg(positional_or_keyword)]
+        b: i32,
+        #[pyarg(positional_or_keyword, default)]
+        c: i32,
+        #[pyarg(keyword_only)]
+        d: i32,
+    }
+
+    fn call<T: super::FromArgs>(
+        vm: &VirtualMachine,
+        args: Vec<i32>,
+        kwargs: Vec<(&str, i32)>,
+    ) -> Result<T, String> {
+        let args = PyFuncArgs {
+            args: args.into_iter().map(|arg| vm.new_int(arg)).collect(),
+            kwargs: kwargs
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), vm.new_int(value)))
+                .collect(),
+        };
+        args.bind::<T>(vm).map_err(|exc: PyObjectRef| {
+            assert!(objtype::isinstance(&exc, &vm.ctx.exceptions.type_error));
+            vm.to_pystr(&exc).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_bind() {
+        let vm = VirtualMachine::new();
+        let bound: Signature = call(&vm, vec![1, 2, 3, 4], vec![("c", 5), ("e", 6)]).unwrap();
+        assert_eq!(bound.a, 1);
+        assert_eq!(bound.b.into_option(), Some(2));
+        assert_eq!(bound.rest.into_iter().collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!((bound.c, bound.d), (5, 7));
+        let extra: Vec<_> = bound.extra.into_iter().collect();
+        assert_eq!(extra, vec![("e".to_string(), 6)]);
+
+        let bound: Signature = call(&vm, vec![1], vec![("c", 5), ("d", 8)]).unwrap();
+        assert!(bound.b.into_option().is_none());
+        assert_eq!(bound.d, 8);
+    }
+
+    #[test]
+    fn test_bind_errors() {
+        let vm = VirtualMachine::new();
+        let bound: Strict = call(&vm, vec![1, 2], vec![("d", 4)]).unwrap();
+        assert_eq!((bound.a, bound.b, bound.c, bound.d), (1, 2, 0, 4));
+
+        let error = |args, kwargs| call::<Strict>(&vm, args, kwargs).err().unwrap();
+        assert_eq!(
+            error(vec![1, 2, 3, 4], vec![]),
+            "strict() takes from 2 to 3 positional arguments but 4 were given"
+        );
+        assert_eq!(
+            error(vec![1, 2], vec![("b", 3)]),
+            "strict() got multiple values for argument 'b'"
+        );
+        assert_eq!(
+            error(vec![], vec![("a", 1), ("b", 2)]),
+            "strict() got some positional-only arguments passed as keyword arguments: 'a'"
+        );
+        assert_eq!(
+            error(vec![1, 2], vec![("e", 3)]),
+            "strict() got an unexpected keyword argument 'e'"
+        );
+        assert_eq!(
+            error(vec![], vec![]),
+            "strict() missing 2 required positional arguments: 'a' and 'b'"
+        );
+        assert_eq!(
+            error(vec![1, 2], vec![]),
+            "strict() missing 1 required keyword-only argument: 'd'"
+        );
+    }
+}
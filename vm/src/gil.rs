@@ -0,0 +1,93 @@
+//! The global interpreter lock.
+//!
+//! Python objects are reference counted with `Rc` and mutated through
+//! `RefCell`, neither of which may be touched by two threads at once. Every
+//! OS thread running python code therefore has to hold the GIL of its
+//! `VirtualMachine`, which serializes all access to the object graph. The
+//! lock is handed out in FIFO order, so a thread that gives it up to let
+//! others run can't immediately grab it back.
+//!
+//! The lock is shared through an `Arc` by every thread, and outlives the
+//! `VirtualMachine`. A VM is only dropped by the thread holding its lock, so
+//! a thread that gets the lock back after the VM was dropped can't run
+//! python code anymore: it passes the lock on and sleeps until the process
+//! exits, like CPython's daemon threads do after finalization.
+
+use std::sync::{Condvar, Mutex};
+
+use crate::vm;
+
+#[derive(Debug)]
+struct GilState {
+    /// Ticket handed to the next thread that wants the lock.
+    next_ticket: u64,
+    /// Ticket of the thread currently holding the lock.
+    now_serving: u64,
+    /// Set once the VM was dropped.
+    finalized: bool,
+}
+
+#[derive(Debug)]
+pub struct Gil {
+    state: Mutex<GilState>,
+    turn: Condvar,
+}
+
+impl Gil {
+    /// Create a GIL which is held by the calling thread.
+    pub fn new_locked() -> Gil {
+        Gil {
+            state: Mutex::new(GilState {
+                next_ticket: 1,
+                now_serving: 0,
+                finalized: false,
+            }),
+            turn: Condvar::new(),
+        }
+    }
+
+    /// Block until the calling thread holds the lock. Never returns if the
+    /// VM was dropped in the meantime.
+    pub fn acquire(&self) {
+        let mut state = self.state.lock().unwrap();
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        let waiting = state.now_serving != ticket;
+        if waiting {
+            // Make the holder check whether it is time to let us in.
+            vm::request_eval_break();
+        }
+        while state.now_serving != ticket {
+            state = self.turn.wait(state).unwrap();
+        }
+        if waiting {
+            vm::release_eval_break();
+        }
+        if state.finalized {
+            state.now_serving += 1;
+            self.turn.notify_all();
+            loop {
+                state = self.turn.wait(state).unwrap();
+            }
+        }
+    }
+
+    /// Hand the lock over to the longest waiting thread, if any.
+    pub fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.now_serving += 1;
+        self.turn.notify_all();
+    }
+
+    /// Called when the VM is dropped, by the thread holding the lock. The
+    /// threads waiting for the lock never get to run python code again.
+    pub fn finalize(&self) {
+        self.state.lock().unwrap().finalized = true;
+    }
+
+    /// Whether any thread is waiting for the lock to be released.
+    pub fn has_waiters(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.next_ticket - state.now_serving > 1
+    }
+}
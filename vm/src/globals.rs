@@ -0,0 +1,76 @@
+//! The state this crate keeps for the whole process, rather than per VM.
+//!
+//! An extension module (see `extension`) is linked with its own copy of this
+//! crate, statics and thread locals included. So that both copies work on
+//! the same state, the state is only ever reached through `globals()`, and
+//! the copy of an extension is pointed at the interpreter's `Globals` when
+//! the extension is loaded.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::extension::{self, MakeModule};
+use crate::obj::objabc;
+use crate::pyobject::{self, PyObjectRef};
+use crate::signal;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::stdlib::{thread, threading};
+use crate::{inline_cache, vm};
+
+/// The process wide state of one copy of the crate.
+pub struct Globals {
+    pub(crate) next_version_tag: &'static AtomicUsize,
+    pub(crate) abc_invalidation_counter: &'static AtomicUsize,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) thread_counter: &'static AtomicUsize,
+    pub(crate) interrupted: &'static AtomicBool,
+    pub(crate) pending_finalizer_count: &'static AtomicUsize,
+    pub(crate) eval_breaker: &'static AtomicUsize,
+    pub(crate) repr_guards: &'static Mutex<HashSet<usize>>,
+    pub(crate) extensions: &'static Mutex<HashMap<PathBuf, (extension::Library, MakeModule)>>,
+    // The thread locals, through functions of the copy that owns them.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) get_ident: fn() -> u64,
+    pub(crate) is_interrupt_thread: fn() -> bool,
+    pub(crate) queue_finalizer: fn(PyObjectRef),
+    pub(crate) take_pending_finalizers: fn() -> Vec<PyObjectRef>,
+}
+
+lazy_static! {
+    static ref OWN: Globals = Globals {
+        next_version_tag: &inline_cache::NEXT_VERSION_TAG,
+        abc_invalidation_counter: &objabc::INVALIDATION_COUNTER,
+        #[cfg(not(target_arch = "wasm32"))]
+        thread_counter: &threading::THREAD_COUNTER,
+        interrupted: &signal::INTERRUPTED,
+        pending_finalizer_count: &pyobject::PENDING_FINALIZER_COUNT,
+        eval_breaker: &vm::EVAL_BREAKER,
+        repr_guards: &vm::REPR_GUARDS,
+        extensions: &extension::LOADED,
+        #[cfg(not(target_arch = "wasm32"))]
+        get_ident: thread::own_ident,
+        is_interrupt_thread: signal::own_is_interrupt_thread,
+        queue_finalizer: pyobject::own_queue_finalizer,
+        take_pending_finalizers: pyobject::own_take_pending_finalizers,
+    };
+}
+
+/// The `Globals` of the interpreter, if this copy of the crate isn't its.
+static SHARED: AtomicPtr<Globals> = AtomicPtr::new(ptr::null_mut());
+
+pub fn globals() -> &'static Globals {
+    let shared = SHARED.load(Ordering::Acquire);
+    if shared.is_null() {
+        &OWN
+    } else {
+        unsafe { &*shared }
+    }
+}
+
+/// Make this copy of the crate use `globals` from now on.
+pub(crate) fn share(globals: &'static Globals) {
+    SHARED.store(globals as *const Globals as *mut Globals, Ordering::Release);
+}
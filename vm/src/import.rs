@@ -6,6 +6,7 @@ use std::error::Error;
 use std::path::PathBuf;
 
 use crate::compile;
+use crate::extension;
 use crate::frame::Scope;
 use crate::obj::{objsequence, objstr};
 use crate::pyobject::{DictProtocol, PyResult};
@@ -24,6 +25,12 @@ fn import_uncached_module(vm: &VirtualMachine, current_path: PathBuf, module: &s
     // Time to search for module in any place:
     let file_path = find_source(vm, current_path, module)
         .map_err(|e| vm.new_exception(notfound_error.clone(), e))?;
+    if extension::is_extension(&file_path) {
+        let make_module =
+            extension::load(&file_path).map_err(|e| vm.new_exception(import_error.clone(), e))?;
+        // Catches the panics of the extension itself.
+        return make_module(vm);
+    }
     let source = util::read_file(file_path.as_path())
         .map_err(|e| vm.new_exception(import_error.clone(), e.description().to_string()))?;
     let code_obj = compile::compile(
@@ -64,7 +71,10 @@ fn find_source(vm: &VirtualMachine, current_path: PathBuf, name: &str) -> Result
 
     paths.insert(0, current_path);
 
-    let suffixes = [".py", "/__init__.py"];
+    let suffixes: Vec<_> = extension::SUFFIXES
+        .iter()
+        .chain(&[".py", "/__init__.py"])
+        .collect();
     let mut file_paths = vec![];
     for path in paths {
         for suffix in suffixes.iter() {
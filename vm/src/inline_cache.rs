@@ -0,0 +1,304 @@
+/*! Per instruction caches for attribute and name lookups.
+
+Dicts and types carry a version tag, which changes whenever they are mutated.
+Tags come from a single counter, so a tag identifies both the object and its
+contents: when the tag of a type or dict matches the one recorded in a cache,
+looking the name up again would give the same answer.
+
+Caches only hold weak references to what they found, so they never keep
+objects alive: a value that is still stored where it was found is alive, and
+if it is not the tag of the place it was found in has changed.
+
+*/
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::globals::globals;
+use crate::obj::objbuiltinfunc::PyBuiltinFunction;
+use crate::obj::objdict::{self, PyDictRef};
+use crate::obj::objfunction::PyFunction;
+use crate::obj::objstr::PyStringRef;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::obj::objweakref::PyWeak;
+use crate::pyobject::{DictProtocol, IdProtocol, PyObjectRef, PyResult, TypeProtocol};
+use crate::vm::VirtualMachine;
+
+pub(crate) static NEXT_VERSION_TAG: AtomicUsize = AtomicUsize::new(1);
+
+/// A version tag no dict or type had before.
+pub fn next_version_tag() -> usize {
+    globals().next_version_tag.fetch_add(1, Ordering::Relaxed)
+}
+
+/// How `object.__getattribute__` finds an attribute on instances of a type.
+#[derive(Debug, Clone)]
+enum AttributeLookup {
+    /// The type customizes attribute access, so the lookup can't be cached.
+    Generic,
+    /// None of the classes in the MRO has the attribute.
+    Instance,
+    /// A class in the MRO has the attribute.
+    Class {
+        attr: PyWeak,
+        /// The `__get__` of the attribute's type, if it is a descriptor.
+        getter: Option<PyWeak>,
+        /// Whether it is a data descriptor, which takes precedence over the
+        /// instance dict.
+        data: bool,
+        /// Whether it is a function, which `LoadMethod` can call without
+        /// binding it.
+        method: bool,
+        attr_class: PyWeak,
+        attr_class_version: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum CacheEntry {
+    Empty,
+    Attribute {
+        type_version: usize,
+        lookup: AttributeLookup,
+    },
+    /// A name found in the globals, or in the builtins when
+    /// `builtins_version` is set.
+    Global {
+        globals_version: usize,
+        builtins_version: Option<usize>,
+        value: PyWeak,
+    },
+}
+
+/// The caches of the instructions of one code object, shared by all the
+/// frames running it.
+#[derive(Debug)]
+pub struct InlineCaches {
+    entries: RefCell<Vec<CacheEntry>>,
+}
+
+impl InlineCaches {
+    pub fn new(instruction_count: usize) -> Self {
+        InlineCaches {
+            entries: RefCell::new(vec![CacheEntry::Empty; instruction_count]),
+        }
+    }
+
+    fn get(&self, index: usize) -> CacheEntry {
+        self.entries.borrow()[index].clone()
+    }
+
+    fn set(&self, index: usize, entry: CacheEntry) {
+        self.entries.borrow_mut()[index] = entry;
+    }
+
+    fn attribute_lookup(
+        &self,
+        vm: &VirtualMachine,
+        index: usize,
+        cls: &PyClassRef,
+        name: &PyStringRef,
+    ) -> AttributeLookup {
+        let type_version = cls.version_tag();
+        let cached = match self.get(index) {
+            CacheEntry::Attribute {
+                type_version: version,
+                lookup,
+            } => Some(lookup).filter(|_| version == type_version),
+            _ => None,
+        };
+        match cached {
+            Some(lookup) => lookup,
+            None => {
+                let lookup = attribute_lookup(vm, cls, &name.value);
+                self.set(
+                    index,
+                    CacheEntry::Attribute {
+                        type_version,
+                        lookup: lookup.clone(),
+                    },
+                );
+                lookup
+            }
+        }
+    }
+
+    /// `obj.<name>` for the `LoadAttr` instruction at `index`.
+    pub fn load_attr(
+        &self,
+        vm: &VirtualMachine,
+        index: usize,
+        obj: PyObjectRef,
+        name: &PyStringRef,
+    ) -> PyResult {
+        let cls = obj.class();
+        match self.attribute_lookup(vm, index, &cls, name) {
+            AttributeLookup::Generic => vm.get_attribute(obj, name.clone()),
+            AttributeLookup::Instance => match instance_attr(&obj, name) {
+                Some(value) => Ok(value),
+                // Let `__getattr__` or the error message take over.
+                None => vm.get_attribute(obj, name.clone()),
+            },
+            AttributeLookup::Class {
+                attr,
+                getter,
+                data,
+                attr_class,
+                attr_class_version,
+                ..
+            } => {
+                let attr = match class_attr(&attr, &attr_class, attr_class_version) {
+                    Some(attr) => attr,
+                    None => return vm.get_attribute(obj, name.clone()),
+                };
+                let getter = getter.and_then(|getter| getter.upgrade());
+                if !data {
+                    if let Some(value) = instance_attr(&obj, name) {
+                        return Ok(value);
+                    }
+                }
+                match getter {
+                    Some(getter) => vm.invoke(getter, vec![attr, obj, cls.into_object()]),
+                    None => Ok(attr),
+                }
+            }
+        }
+    }
+
+    /// `obj.<name>` for the `LoadMethod` instruction at `index`. A function
+    /// found on the class comes back unbound, along with `obj` to pass as its
+    /// first argument, so no bound method gets created.
+    pub fn load_method(
+        &self,
+        vm: &VirtualMachine,
+        index: usize,
+        obj: PyObjectRef,
+        name: &PyStringRef,
+    ) -> PyResult<(PyObjectRef, Option<PyObjectRef>)> {
+        if let AttributeLookup::Class {
+            attr,
+            method: true,
+            attr_class,
+            attr_class_version,
+            ..
+        } = self.attribute_lookup(vm, index, &obj.class(), name)
+        {
+            if let Some(function) = class_attr(&attr, &attr_class, attr_class_version) {
+                if instance_attr(&obj, name).is_none() {
+                    return Ok((function, Some(obj)));
+                }
+            }
+        }
+        let attr = self.load_attr(vm, index, obj, name)?;
+        Ok((attr, None))
+    }
+
+    /// A name not found in the local scopes, looked up in the globals and
+    /// then the builtins for the `LoadName` instruction at `index`.
+    pub fn load_global(
+        &self,
+        vm: &VirtualMachine,
+        index: usize,
+        globals: &PyDictRef,
+        name: &str,
+    ) -> Option<PyObjectRef> {
+        let builtins_dict = vm.builtins.dict.as_ref();
+        let globals_version = globals.version_tag();
+        let builtins_version = builtins_dict.map(|dict| dict.version_tag());
+        if let CacheEntry::Global {
+            globals_version: cached_globals,
+            builtins_version: cached_builtins,
+            value,
+        } = self.get(index)
+        {
+            let valid = cached_globals == globals_version
+                && cached_builtins.map_or(true, |version| Some(version) == builtins_version);
+            if valid {
+                if let Some(value) = value.upgrade() {
+                    return Some(value);
+                }
+            }
+        }
+
+        if let Some(value) = globals.get_item(name) {
+            self.set(
+                index,
+                CacheEntry::Global {
+                    globals_version,
+                    builtins_version: None,
+                    value: PyWeak::downgrade(&value),
+                },
+            );
+            return Some(value);
+        }
+        let value = vm.get_attribute(vm.builtins.clone(), name).ok()?;
+        // Only cache what was found in the dict of the builtins module, not
+        // attributes of the module type.
+        let in_dict = builtins_dict
+            .and_then(|dict| dict.get_item(name))
+            .map_or(false, |item| item.is(&value));
+        if in_dict {
+            self.set(
+                index,
+                CacheEntry::Global {
+                    globals_version,
+                    builtins_version,
+                    value: PyWeak::downgrade(&value),
+                },
+            );
+        }
+        Some(value)
+    }
+}
+
+/// The attribute found on a class, unless the class it is an instance of
+/// changed since.
+fn class_attr(
+    attr: &PyWeak,
+    attr_class: &PyWeak,
+    attr_class_version: usize,
+) -> Option<PyObjectRef> {
+    let attr_class = attr_class.upgrade()?.downcast::<objtype::PyClass>().ok()?;
+    if attr_class.version_tag() == attr_class_version {
+        attr.upgrade()
+    } else {
+        None
+    }
+}
+
+fn instance_attr(obj: &PyObjectRef, name: &PyStringRef) -> Option<PyObjectRef> {
+    obj.dict
+        .as_ref()
+        .and_then(|dict| objdict::content_get_key(&dict.entries(), name))
+}
+
+/// Work out how `object.__getattribute__` would find `name` on instances of
+/// `cls`, mirroring it step by step.
+fn attribute_lookup(vm: &VirtualMachine, cls: &PyClassRef, name: &str) -> AttributeLookup {
+    let object_getattribute = objtype::class_get_attr(&vm.ctx.object(), "__getattribute__");
+    let getattribute = objtype::class_get_attr(cls, "__getattribute__");
+    let generic = match (getattribute, object_getattribute) {
+        (Some(getattribute), Some(object_getattribute)) => getattribute.is(&object_getattribute),
+        _ => false,
+    };
+    if !generic {
+        return AttributeLookup::Generic;
+    }
+    match objtype::class_get_attr(cls, name) {
+        None => AttributeLookup::Instance,
+        Some(attr) => {
+            let attr_class = attr.class();
+            let getter = objtype::class_get_attr(&attr_class, "__get__");
+            let data = getter.is_some() && objtype::class_has_attr(&attr_class, "__set__");
+            let method = attr.payload_is::<PyFunction>() || attr.payload_is::<PyBuiltinFunction>();
+            AttributeLookup::Class {
+                attr: PyWeak::downgrade(&attr),
+                getter: getter.as_ref().map(PyWeak::downgrade),
+                data,
+                method,
+                attr_class_version: attr_class.version_tag(),
+                attr_class: PyWeak::downgrade(attr_class.as_object()),
+            }
+        }
+    }
+}
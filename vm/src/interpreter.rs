@@ -0,0 +1,447 @@
+/*! Embedding the interpreter.
+
+`InterpreterBuilder` sets up a `VirtualMachine` the way an application needs
+it, and `Interpreter` runs code in it, reporting the outcome as plain rust
+results:
+
+```
+use rustpython_vm::InterpreterBuilder;
+
+let interpreter = InterpreterBuilder::new()
+    .argv(vec!["embedded", "--verbose"])
+    .stdlib_modules(vec!["math"])
+    .build();
+interpreter.run_source("import sys\nargc = len(sys.argv)\n").unwrap();
+let argc: i32 = interpreter.eval_expr("argc * 10").unwrap();
+assert_eq!(argc, 20);
+assert!(interpreter.run_source("import json").is_err());
+```
+
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use crate::compile;
+use crate::error::CompileError;
+use crate::exceptions;
+use crate::frame::Scope;
+use crate::pyobject::{DictProtocol, PyContext, PyObjectRef, TryFromObject};
+use crate::stdlib::StdlibInitFunc;
+use crate::util;
+use crate::vm::VirtualMachine;
+
+/// How a `VirtualMachine` is configured.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// The directories modules are searched in, which make up `sys.path`
+    /// along with the ones from `PYTHONPATH`.
+    pub path: Vec<String>,
+    /// The command line arguments as seen by python, `sys.argv`.
+    pub argv: Vec<String>,
+    /// Ignore the environment variables that configure python, like
+    /// `PYTHONPATH`, as the `-E` option does.
+    pub ignore_environment: bool,
+    /// The native stdlib modules that can be imported, all of them when
+    /// `None`.
+    pub stdlib_modules: Option<HashSet<String>>,
+    /// The optimization level of the `-O` option. From 1 on `assert`
+    /// statements are left out and `__debug__` is false.
+    pub optimize: u8,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            path: vec![],
+            argv: vec![String::new()],
+            ignore_environment: false,
+            stdlib_modules: None,
+            optimize: 0,
+        }
+    }
+}
+
+/// Configures and creates an `Interpreter`, or just its `VirtualMachine`.
+pub struct InterpreterBuilder {
+    settings: Settings,
+    native_modules: HashMap<String, StdlibInitFunc>,
+    stdin: Option<Box<dyn BufRead>>,
+    stdout: Option<Box<dyn Write>>,
+    stderr: Option<Box<dyn Write>>,
+}
+
+impl Default for InterpreterBuilder {
+    fn default() -> Self {
+        InterpreterBuilder::new()
+    }
+}
+
+impl InterpreterBuilder {
+    pub fn new() -> Self {
+        InterpreterBuilder {
+            settings: Settings::default(),
+            native_modules: HashMap::new(),
+            stdin: None,
+            stdout: None,
+            stderr: None,
+        }
+    }
+
+    /// Replace all the settings made so far.
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn path<I, S>(mut self, path: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.settings.path = path.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn argv<I, S>(mut self, argv: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.settings.argv = argv.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn ignore_environment(mut self, ignore_environment: bool) -> Self {
+        self.settings.ignore_environment = ignore_environment;
+        self
+    }
+
+    /// Only offer the given native stdlib modules.
+    pub fn stdlib_modules<I, S>(mut self, modules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.settings.stdlib_modules = Some(modules.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn optimize(mut self, level: u8) -> Self {
+        self.settings.optimize = level;
+        self
+    }
+
+    /// Add a module implemented in rust, which takes precedence over stdlib
+    /// modules of the same name.
+    pub fn native_module<F>(mut self, name: &str, init: F) -> Self
+    where
+        F: Fn(&PyContext) -> PyObjectRef + 'static,
+    {
+        self.native_modules
+            .insert(name.to_string(), Box::new(init) as StdlibInitFunc);
+        self
+    }
+
+    /// What `sys.stdin`, and so `input`, reads from, instead of the process'
+    /// stdin.
+    pub fn stdin<R: BufRead + 'static>(mut self, stdin: R) -> Self {
+        self.stdin = Some(Box::new(stdin));
+        self
+    }
+
+    /// Where `sys.stdout`, and so `print`, writes to, instead of the process'
+    /// stdout.
+    pub fn stdout<W: Write + 'static>(mut self, stdout: W) -> Self {
+        self.stdout = Some(Box::new(stdout));
+        self
+    }
+
+    /// Where `sys.stderr`, and so tracebacks, write to, instead of the
+    /// process' stderr.
+    pub fn stderr<W: Write + 'static>(mut self, stderr: W) -> Self {
+        self.stderr = Some(Box::new(stderr));
+        self
+    }
+
+    pub fn build_vm(self) -> VirtualMachine {
+        let vm = VirtualMachine::new_with_settings(self.settings);
+        vm.stdlib_inits.borrow_mut().extend(self.native_modules);
+        if let Some(stdin) = self.stdin {
+            vm.stdin.replace(stdin);
+        }
+        if let Some(stdout) = self.stdout {
+            vm.stdout.replace(stdout);
+        }
+        if let Some(stderr) = self.stderr {
+            vm.stderr.replace(stderr);
+        }
+        vm
+    }
+
+    pub fn build(self) -> Interpreter {
+        Interpreter::from_vm(self.build_vm())
+    }
+}
+
+/// Why running code in an `Interpreter` failed.
+#[derive(Debug)]
+pub enum RunError {
+    /// The file to run couldn't be read.
+    Io(io::Error),
+    Compile(CompileError),
+    /// The code raised an exception.
+    Exception {
+        exception: PyObjectRef,
+        /// The exception with its traceback, as python would print it.
+        traceback: String,
+    },
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::Io(err) => write!(f, "{}", err),
+            RunError::Compile(err) => write!(f, "{}", err),
+            RunError::Exception { traceback, .. } => write!(f, "{}", traceback.trim_end()),
+        }
+    }
+}
+
+impl Error for RunError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RunError::Io(err) => Some(err),
+            RunError::Compile(err) => Some(err),
+            RunError::Exception { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for RunError {
+    fn from(err: io::Error) -> Self {
+        RunError::Io(err)
+    }
+}
+
+impl From<CompileError> for RunError {
+    fn from(err: CompileError) -> Self {
+        RunError::Compile(err)
+    }
+}
+
+/// A `VirtualMachine` along with the `__main__` namespace code runs in.
+pub struct Interpreter {
+    vm: VirtualMachine,
+    scope: Scope,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        InterpreterBuilder::new().build()
+    }
+}
+
+impl Interpreter {
+    pub fn from_vm(vm: VirtualMachine) -> Self {
+        let scope = vm.ctx.new_scope();
+        scope
+            .globals
+            .set_item(&vm.ctx, "__name__", vm.new_str("__main__".to_string()));
+        Interpreter { vm, scope }
+    }
+
+    pub fn vm(&self) -> &VirtualMachine {
+        &self.vm
+    }
+
+    /// The `__main__` namespace.
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    /// Run statements in the `__main__` namespace.
+    pub fn run_source(&self, source: &str) -> Result<(), RunError> {
+        self.run(source, &compile::Mode::Exec, "<string>".to_string())
+            .map(|_| ())
+    }
+
+    /// Run the statements of a file in the `__main__` namespace.
+    pub fn run_file<P: AsRef<Path>>(&self, path: P) -> Result<(), RunError> {
+        let path = path.as_ref();
+        let source = util::read_file(path)?;
+        self.run(&source, &compile::Mode::Exec, path.display().to_string())
+            .map(|_| ())
+    }
+
+    /// Evaluate an expression in the `__main__` namespace, and convert its
+    /// value like the arguments of native functions are.
+    pub fn eval_expr<T: TryFromObject>(&self, expr: &str) -> Result<T, RunError> {
+        // The parser needs the expression to end with a newline.
+        let expr = format!("{}\n", expr);
+        let value = self.run(&expr, &compile::Mode::Eval, "<string>".to_string())?;
+        T::try_from_object(&self.vm, value).map_err(|exc| self.exception_error(exc))
+    }
+
+    fn run(
+        &self,
+        source: &str,
+        mode: &compile::Mode,
+        source_path: String,
+    ) -> Result<PyObjectRef, RunError> {
+        let code = compile::compile(&self.vm, source, mode, source_path)?;
+        self.vm
+            .run_code_obj(code, self.scope.clone())
+            .map_err(|exc| self.exception_error(exc))
+    }
+
+    fn exception_error(&self, exception: PyObjectRef) -> RunError {
+        let mut traceback = vec![];
+        let _ = exceptions::write_exception(&mut traceback, &self.vm, &exception);
+        RunError::Exception {
+            exception,
+            traceback: String::from_utf8_lossy(&traceback).into_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{self, Read, Write};
+    use std::rc::Rc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{InterpreterBuilder, RunError};
+    use crate::exceptions;
+
+    /// Collects what is written to it, while the test keeps a handle.
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Output {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_captured_output() {
+        let stdout = Output::default();
+        let interpreter = InterpreterBuilder::new().stdout(stdout.clone()).build();
+        interpreter.run_source("print('hello', 42)\n").unwrap();
+        assert_eq!(stdout.text(), "hello 42\n");
+    }
+
+    #[test]
+    fn test_std_streams() {
+        let stdout = Output::default();
+        let stderr = Output::default();
+        let interpreter = InterpreterBuilder::new()
+            .stdin(io::Cursor::new("Alice\n"))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .build();
+        interpreter
+            .run_source("name = input('name: ')\nprint('hi', name)\n")
+            .unwrap();
+        assert_eq!(stdout.text(), "name: hi Alice\n");
+
+        let source = "import sys\nprint('oops', file=sys.stderr)\nraise ValueError('bad')\n";
+        let exc = match interpreter.run_source(source) {
+            Err(RunError::Exception { exception, .. }) => exception,
+            other => panic!("expected an exception, got {:?}", other),
+        };
+        exceptions::print_exception(&interpreter.vm, &exc);
+        assert!(stderr
+            .text()
+            .starts_with("oops\nTraceback (most recent call last):\n"));
+        assert!(stderr.text().ends_with("ValueError: bad\n"));
+        assert_eq!(stdout.text(), "name: hi Alice\n");
+    }
+
+    /// Input that takes a while to arrive.
+    struct SlowInput(io::Cursor<&'static str>);
+
+    impl Read for SlowInput {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_millis(50));
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_threads_take_turns_reading() {
+        let interpreter = InterpreterBuilder::new()
+            .stdin(io::BufReader::with_capacity(
+                1,
+                SlowInput(io::Cursor::new("one\ntwo\n")),
+            ))
+            .build();
+        let source = "import sys, threading\n\
+                      lines = []\n\
+                      t = threading.Thread(target=lambda: lines.append(sys.stdin.readline()))\n\
+                      t.start()\n\
+                      lines.append(sys.stdin.readline())\n\
+                      t.join()\n\
+                      assert sorted(lines) == ['one\\n', 'two\\n'], lines\n";
+        interpreter.run_source(source).unwrap();
+    }
+
+    #[test]
+    fn test_native_module() {
+        let interpreter = InterpreterBuilder::new()
+            .stdlib_modules(Vec::<String>::new())
+            .native_module("answers", |ctx| {
+                py_module!(ctx, "answers", {
+                    "everything" => ctx.new_int(42),
+                })
+            })
+            .build();
+        interpreter.run_source("import answers\n").unwrap();
+        let answer: i32 = interpreter.eval_expr("answers.everything").unwrap();
+        assert_eq!(answer, 42);
+        assert!(interpreter.run_source("import math\n").is_err());
+    }
+
+    #[test]
+    fn test_optimize() {
+        let interpreter = InterpreterBuilder::new().optimize(1).build();
+        interpreter.run_source("assert False\n").unwrap();
+        let debug: bool = interpreter.eval_expr("__debug__").unwrap();
+        assert!(!debug);
+    }
+
+    #[test]
+    fn test_errors() {
+        let interpreter = InterpreterBuilder::new().build();
+        match interpreter.run_source("1 +\n") {
+            Err(RunError::Compile(_)) => {}
+            other => panic!("expected a compile error, got {:?}", other),
+        }
+        match interpreter.run_source("raise ValueError('bad value')\n") {
+            Err(err @ RunError::Exception { .. }) => {
+                assert!(err.to_string().ends_with("ValueError: bad value"));
+            }
+            other => panic!("expected an exception, got {:?}", other),
+        }
+        match interpreter.run_file("/nonexistent/script.py") {
+            Err(RunError::Io(_)) => {}
+            other => panic!("expected an io error, got {:?}", other),
+        }
+    }
+}
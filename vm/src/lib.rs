@@ -24,6 +24,7 @@ extern crate num_bigint;
 extern crate num_complex;
 extern crate num_integer;
 extern crate num_traits;
+#[macro_use]
 extern crate serde;
 extern crate serde_json;
 extern crate statrs;
@@ -42,15 +43,25 @@ pub mod macros;
 mod builtins;
 pub mod bytecode;
 pub mod compile;
+pub mod debugger;
 pub mod error;
 pub mod eval;
 mod exceptions;
+pub mod extension;
 pub mod format;
 pub mod frame;
 pub mod function;
+mod gil;
+mod globals;
 pub mod import;
+mod inline_cache;
+pub mod interpreter;
+mod native_panic;
 pub mod obj;
+pub mod profiler;
+pub mod py_serde;
 pub mod pyobject;
+pub mod signal;
 pub mod stdlib;
 mod sysmodule;
 mod traceback;
@@ -59,4 +70,7 @@ mod vm;
 
 // pub use self::pyobject::Executor;
 pub use self::exceptions::print_exception;
-pub use self::vm::VirtualMachine;
+pub use self::interpreter::{Interpreter, InterpreterBuilder, RunError, Settings};
+pub use self::sysmodule::write_stderr;
+pub use self::vm::{VirtualMachine, THREAD_STACK_SIZE};
+pub use rustpython_derive::{pyclass, pymethods};
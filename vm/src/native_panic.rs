@@ -0,0 +1,198 @@
+//! Turning panics in native functions into python exceptions.
+//!
+//! A bug in a native function, or an input it doesn't expect, shouldn't take
+//! down the application the interpreter is embedded in. Native functions are
+//! called through `catch_panic`, which turns a panic into a `SystemError`
+//! carrying the panic message and location, with the native backtrace in its
+//! `native_backtrace` attribute.
+//!
+//! The panics that are caught are never printed, whatever `RUST_BACKTRACE`
+//! says, and their backtrace is only resolved when `native_backtrace` is
+//! read. The panics nothing catches are left to the hook std installed.
+
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe, PanicInfo};
+use std::sync::Once;
+
+use crate::obj::objtype::PyClassRef;
+use crate::pyobject::{PyObjectRef, PyResult, PyValue, TypeProtocol};
+use crate::vm::VirtualMachine;
+
+// Unlike the rest of the state of the crate this isn't in `globals`: an
+// extension module also has its own copy of std, with its own panic hook.
+thread_local! {
+    /// How many calls of `catch_panic` this thread is in.
+    static DEPTH: Cell<usize> = Cell::new(0);
+    /// The panic `catch_panic` is about to catch, as the panic hook saw it.
+    static CAUGHT: RefCell<Option<Panic>> = RefCell::new(None);
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+struct Panic {
+    /// The panic message, with its location.
+    message: String,
+    backtrace: Option<Backtrace>,
+}
+
+/// The backtrace of a caught panic, kept by the `SystemError` it became.
+#[derive(Debug)]
+struct NativeBacktrace(Option<Backtrace>);
+
+impl PyValue for NativeBacktrace {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.ctx.object()
+    }
+}
+
+/// The attribute of the `SystemError` holding its `NativeBacktrace`.
+const BACKTRACE_ATTR: &str = "__native_backtrace__";
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<Any>".to_string()
+    }
+}
+
+/// The panic hook records the panics that are going to be caught, and leaves
+/// the other ones to the hook that was there before.
+fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info: &PanicInfo| {
+            if DEPTH.with(Cell::get) == 0 {
+                previous(info);
+                return;
+            }
+            let mut message = payload_message(info.payload());
+            if let Some(location) = info.location() {
+                message = format!(
+                    "'{}', {}:{}:{}",
+                    message,
+                    location.file(),
+                    location.line(),
+                    location.column()
+                );
+            }
+            let panic = Panic {
+                message,
+                backtrace: Some(Backtrace::force_capture()),
+            };
+            CAUGHT.with(|caught| *caught.borrow_mut() = Some(panic));
+        }));
+    });
+}
+
+/// Run a native function, raising a `SystemError` if it panics. The frames
+/// the function left on the stack are popped, so the VM stays usable.
+pub fn catch_panic<F>(vm: &VirtualMachine, f: F) -> PyResult
+where
+    F: FnOnce() -> PyResult,
+{
+    install_hook();
+    let frame_count = vm.frames.borrow().len();
+    DEPTH.with(|depth| depth.set(depth.get() + 1));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    DEPTH.with(|depth| depth.set(depth.get() - 1));
+    let payload = match result {
+        Ok(result) => return result,
+        Err(payload) => payload,
+    };
+
+    vm.frames.borrow_mut().truncate(frame_count);
+    let panic = CAUGHT
+        .with(|caught| caught.borrow_mut().take())
+        .unwrap_or_else(|| Panic {
+            message: format!("'{}'", payload_message(&*payload)),
+            backtrace: None,
+        });
+    let exc = vm.new_system_error(format!("native code panicked at {}", panic.message));
+    let backtrace = NativeBacktrace(panic.backtrace).into_ref(vm);
+    vm.ctx
+        .set_attr(&exc, BACKTRACE_ATTR, backtrace.into_object());
+    Err(exc)
+}
+
+/// The `native_backtrace` property of `SystemError`, only set on the ones
+/// raised for a panic.
+pub fn native_backtrace(exc: PyObjectRef, vm: &VirtualMachine) -> PyResult<String> {
+    let backtrace = vm
+        .get_attribute(exc.clone(), BACKTRACE_ATTR)
+        .ok()
+        .and_then(|backtrace| backtrace.downcast::<NativeBacktrace>().ok());
+    match backtrace {
+        Some(backtrace) => Ok(backtrace
+            .0
+            .as_ref()
+            .map_or_else(String::new, ToString::to_string)),
+        None => Err(vm.new_attribute_error(format!(
+            "'{}' object has no attribute 'native_backtrace'",
+            exc.class().name
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+
+    use super::{install_hook, CAUGHT};
+    use crate::compile;
+    use crate::obj::objtype;
+    use crate::pyobject::{DictProtocol, PyObjectRef};
+    use crate::vm::VirtualMachine;
+
+    fn fail(value: i32, _vm: &VirtualMachine) -> i32 {
+        if value < 0 {
+            panic!("negative value {}", value);
+        }
+        value
+    }
+
+    #[test]
+    fn test_panic_becomes_system_error() {
+        let vm = VirtualMachine::new();
+        let scope = vm.ctx.new_scope();
+        scope
+            .globals
+            .set_item(&vm.ctx, "fail", vm.ctx.new_rustfunc(fail));
+        let source = "def outer():\n    return [fail(x) for x in (1, -1)]\n";
+        let code = compile::compile(&vm, source, &compile::Mode::Exec, "<test>".to_string())
+            .expect("the test code compiles");
+        vm.run_code_obj(code, scope.clone()).unwrap();
+        let outer = scope.globals.get_item("outer").unwrap();
+
+        let frame_count = vm.frames.borrow().len();
+        let exc: PyObjectRef = vm.invoke(outer, vec![]).err().unwrap();
+        assert!(objtype::isinstance(&exc, &vm.ctx.exceptions.system_error));
+        let message = vm.to_pystr(&exc).unwrap();
+        assert!(message.starts_with("native code panicked at 'negative value -1', "));
+        let backtrace = vm.get_attribute(exc, "native_backtrace").unwrap();
+        assert!(vm
+            .to_pystr(&backtrace)
+            .unwrap()
+            .contains("native_panic::tests::fail"));
+        assert_eq!(vm.frames.borrow().len(), frame_count);
+
+        let fail = scope.globals.get_item("fail").unwrap();
+        let value = vm.invoke(fail, vec![vm.ctx.new_int(3)]).unwrap();
+        assert_eq!(vm.to_pystr(&value).unwrap(), "3");
+
+        let exc = vm.new_system_error("not a panic".to_string());
+        assert!(vm.get_attribute(exc, "native_backtrace").is_err());
+    }
+
+    /// Panics outside of `catch_panic` are left alone by the hook.
+    #[test]
+    fn test_uncaught_panic_is_not_recorded() {
+        install_hook();
+        assert!(panic::catch_unwind(|| panic!("uncaught panic")).is_err());
+        assert!(CAUGHT.with(|caught| caught.borrow().is_none()));
+    }
+}
@@ -1,5 +1,6 @@
 //! This package contains the python basic/builtin types
 
+pub mod objabc;
 pub mod objbool;
 pub mod objbuiltinfunc;
 pub mod objbytearray;
@@ -28,8 +29,11 @@ pub mod objrange;
 pub mod objsequence;
 pub mod objset;
 pub mod objslice;
+pub mod objslots;
 pub mod objstaticmethod;
+pub mod objstdio;
 pub mod objstr;
+pub mod objstructseq;
 pub mod objsuper;
 pub mod objtuple;
 pub mod objtype;
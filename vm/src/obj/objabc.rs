@@ -0,0 +1,401 @@
+//! `ABCMeta`, the metaclass of abstract base classes.
+//!
+//! An abstract base class counts as a base of the classes registered with
+//! it and of those its `__subclasshook__` accepts, besides its real
+//! subclasses. The answers are cached in the class, and the negative ones
+//! are dropped whenever a class gets registered anywhere.
+//!
+//! A class whose metaclass is `ABCMeta` can't be instantiated while some of
+//! its methods are still marked with `abc.abstractmethod`.
+
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::function::{IntoPyNativeFunc, KwArgs, PyFuncArgs};
+use crate::globals::globals;
+use crate::obj::objbool;
+use crate::obj::objbuiltinfunc::PyBuiltinFunction;
+use crate::obj::objdict::PyDictRef;
+use crate::obj::objproperty::PropertyBuilder;
+use crate::obj::objstr::PyStringRef;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::obj::objweakref::PyWeak;
+use crate::pyobject::{
+    DictProtocol, IdProtocol, PyAttributes, PyContext, PyIterable, PyObject, PyObjectRef, PyRef,
+    PyResult, PyValue, TypeProtocol,
+};
+use crate::vm::VirtualMachine;
+
+/// Bumped by every registration, to invalidate the negative caches.
+pub(crate) static INVALIDATION_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The value of the invalidation counter, which changes whenever a class is
+/// registered with an abstract base class.
+pub fn cache_token() -> usize {
+    globals().abc_invalidation_counter.load(Ordering::SeqCst)
+}
+
+/// Classes held weakly, so that caching an answer about a class doesn't keep
+/// it alive.
+#[derive(Debug, Default)]
+struct WeakClassSet {
+    classes: RefCell<Vec<PyWeak>>,
+}
+
+impl WeakClassSet {
+    fn contains(&self, class: &PyClassRef) -> bool {
+        self.classes
+            .borrow()
+            .iter()
+            .any(|weak| weak.upgrade().map_or(false, |c| c.is(class)))
+    }
+
+    fn add(&self, class: &PyClassRef) {
+        if !self.contains(class) {
+            let mut classes = self.classes.borrow_mut();
+            classes.retain(|weak| weak.upgrade().is_some());
+            classes.push(PyWeak::downgrade(class.as_object()));
+        }
+    }
+
+    fn clear(&self) {
+        self.classes.borrow_mut().clear();
+    }
+
+    fn to_vec(&self) -> Vec<PyObjectRef> {
+        self.classes
+            .borrow()
+            .iter()
+            .filter_map(PyWeak::upgrade)
+            .collect()
+    }
+}
+
+/// What `ABCMeta` keeps in each of its classes, as `_abc_impl`.
+#[derive(Debug, Default)]
+pub struct PyAbcData {
+    /// The names of the abstract methods, sorted.
+    abstract_methods: RefCell<Vec<String>>,
+    registry: WeakClassSet,
+    cache: WeakClassSet,
+    negative_cache: WeakClassSet,
+    negative_cache_version: Cell<usize>,
+}
+
+pub type PyAbcDataRef = PyRef<PyAbcData>;
+
+impl PyValue for PyAbcData {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.ctx.abc_data_type()
+    }
+}
+
+impl PyAbcData {
+    fn new(mut abstract_methods: Vec<String>) -> Self {
+        abstract_methods.sort();
+        abstract_methods.dedup();
+        PyAbcData {
+            abstract_methods: RefCell::new(abstract_methods),
+            negative_cache_version: Cell::new(cache_token()),
+            ..Default::default()
+        }
+    }
+}
+
+/// The data `ABCMeta` keeps in `class`, if `class` has some of its own.
+fn own_data(class: &PyClassRef) -> Option<PyAbcDataRef> {
+    class
+        .attributes
+        .borrow()
+        .get("_abc_impl")
+        .and_then(|data| data.clone().downcast().ok())
+}
+
+/// The data `ABCMeta` keeps in `class`, created if the class was made
+/// without going through `ABCMeta.__new__`.
+fn data(class: &PyClassRef, vm: &VirtualMachine) -> PyAbcDataRef {
+    own_data(class).unwrap_or_else(|| {
+        let data = PyAbcData::new(vec![]).into_ref(vm);
+        class.set_attr("_abc_impl", data.clone().into_object());
+        data
+    })
+}
+
+/// Whether `obj` was marked with `abc.abstractmethod`, directly or through
+/// the descriptor that wraps it.
+pub fn is_abstract(vm: &VirtualMachine, obj: &PyObjectRef) -> PyResult<bool> {
+    match vm.get_attribute(obj.clone(), "__isabstractmethod__") {
+        Ok(value) => objbool::boolval(vm, value),
+        Err(exc) => {
+            if objtype::isinstance(&exc, &vm.ctx.exceptions.attribute_error) {
+                Ok(false)
+            } else {
+                Err(exc)
+            }
+        }
+    }
+}
+
+/// Create an abstract base class without running any python code, for the
+/// modules built into the VM. The methods named in `abstract_methods` should
+/// be made with `new_abstract_method`.
+pub fn new_abstract_class(
+    ctx: &PyContext,
+    name: &str,
+    bases: Vec<PyClassRef>,
+    attributes: PyAttributes,
+    abstract_methods: &[&str],
+) -> PyClassRef {
+    let class = objtype::new(ctx.abc_meta_type(), name, bases, attributes).unwrap();
+    // An inherited abstract method stays abstract unless the class, or a
+    // base before the abstract one in the MRO, defines a concrete one.
+    let abstract_methods = abstract_methods.iter().map(ToString::to_string);
+    let inherited = class.mro.iter().filter_map(own_data).flat_map(|data| {
+        let names = data.abstract_methods.borrow().clone();
+        names.into_iter().filter(|name| {
+            objtype::class_get_attr(&class, name).map_or(false, |attr| {
+                attr.dict
+                    .as_ref()
+                    .map_or(false, |dict| dict.contains_key("__isabstractmethod__"))
+            })
+        })
+    });
+    let data = PyAbcData::new(abstract_methods.chain(inherited).collect());
+    let data = PyObject::new(data, ctx.abc_data_type(), None);
+    class
+        .attributes
+        .borrow_mut()
+        .insert("_abc_impl".to_string(), data);
+    class
+}
+
+/// Register a builtin type with an abstract base class made by
+/// `new_abstract_class`, while there are no caches to invalidate yet.
+pub fn register_builtin(class: &PyClassRef, subclass: &PyClassRef) {
+    if let Some(data) = own_data(class) {
+        data.registry.add(subclass);
+    }
+}
+
+/// A method that only raises `NotImplementedError`, and that is marked as
+/// abstract like the ones decorated with `abc.abstractmethod`.
+pub fn new_abstract_method(ctx: &PyContext) -> PyObjectRef {
+    let dict = ctx.new_dict();
+    dict.set_item(ctx, "__isabstractmethod__", ctx.new_bool(true));
+    PyObject::new(
+        PyBuiltinFunction::new(abstract_method.into_func()),
+        ctx.builtin_function_or_method_type(),
+        Some(dict),
+    )
+}
+
+fn abstract_method(vm: &VirtualMachine, _args: PyFuncArgs) -> PyResult {
+    Err(vm.new_not_implemented_error("abstract method".to_string()))
+}
+
+/// Refuse to instantiate `class` if it still has abstract methods.
+pub fn check_instantiable(vm: &VirtualMachine, class: &PyClassRef) -> PyResult<()> {
+    if !objtype::issubclass(&class.class(), &vm.ctx.abc_meta_type()) {
+        return Ok(());
+    }
+    let abstract_methods = match own_data(class) {
+        Some(data) => data.abstract_methods.borrow().clone(),
+        None => return Ok(()),
+    };
+    match abstract_methods.len() {
+        0 => Ok(()),
+        count => Err(vm.new_type_error(format!(
+            "Can't instantiate abstract class {} with abstract method{} {}",
+            class.name,
+            if count == 1 { "" } else { "s" },
+            abstract_methods.join(", ")
+        ))),
+    }
+}
+
+fn abc_meta_new(
+    metaclass: PyClassRef,
+    name: PyStringRef,
+    bases: PyIterable<PyClassRef>,
+    namespace: PyDictRef,
+    kwargs: KwArgs,
+    vm: &VirtualMachine,
+) -> PyResult<PyClassRef> {
+    let class = objtype::type_new_class(vm, metaclass, name, bases, namespace, kwargs)?;
+
+    let attributes: Vec<_> = class
+        .attributes
+        .borrow()
+        .iter()
+        .map(|(name, attr)| (name.clone(), attr.clone()))
+        .collect();
+    let mut abstract_methods = vec![];
+    for (name, attr) in &attributes {
+        if is_abstract(vm, attr)? {
+            abstract_methods.push(name.clone());
+        }
+    }
+    // An inherited abstract method stays abstract unless something else
+    // was found for its name first.
+    for base in class.mro.iter() {
+        let inherited = match own_data(base) {
+            Some(data) => data.abstract_methods.borrow().clone(),
+            None => continue,
+        };
+        for name in inherited {
+            if abstract_methods.contains(&name) {
+                continue;
+            }
+            if let Some(attr) = objtype::class_get_attr(&class, &name) {
+                if is_abstract(vm, &attr)? {
+                    abstract_methods.push(name);
+                }
+            }
+        }
+    }
+    class.set_attr(
+        "_abc_impl",
+        PyAbcData::new(abstract_methods).into_ref(vm).into_object(),
+    );
+    Ok(class)
+}
+
+fn abc_meta_register(
+    class: PyClassRef,
+    subclass: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<PyClassRef> {
+    let subclass: PyClassRef = subclass
+        .downcast()
+        .map_err(|_| vm.new_type_error("Can only register classes".to_string()))?;
+    if vm.issubclass(subclass.as_object(), class.as_object())? {
+        // Already a subclass, maybe virtually.
+        return Ok(subclass);
+    }
+    if vm.issubclass(class.as_object(), subclass.as_object())? {
+        return Err(vm.new_runtime_error("Refusing to create an inheritance cycle".to_string()));
+    }
+    data(&class, vm).registry.add(&subclass);
+    globals()
+        .abc_invalidation_counter
+        .fetch_add(1, Ordering::SeqCst);
+    Ok(subclass)
+}
+
+fn abc_meta_instance_check(
+    class: PyClassRef,
+    instance: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult {
+    let subclass = instance.class();
+    if data(&class, vm).cache.contains(&subclass) {
+        return Ok(vm.new_bool(true));
+    }
+    vm.call_method(
+        class.as_object(),
+        "__subclasscheck__",
+        vec![subclass.into_object()],
+    )
+}
+
+fn abc_meta_subclass_check(
+    class: PyClassRef,
+    subclass: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<bool> {
+    let subclass: PyClassRef = subclass
+        .downcast()
+        .map_err(|_| vm.new_type_error("issubclass() arg 1 must be a class".to_string()))?;
+    let data = data(&class, vm);
+    if data.cache.contains(&subclass) {
+        return Ok(true);
+    }
+    let token = cache_token();
+    if data.negative_cache_version.get() < token {
+        data.negative_cache.clear();
+        data.negative_cache_version.set(token);
+    } else if data.negative_cache.contains(&subclass) {
+        return Ok(false);
+    }
+
+    let hook = vm.get_attribute(class.clone().into_object(), "__subclasshook__")?;
+    let answer = vm.invoke(hook, vec![subclass.clone().into_object()])?;
+    let answer = if answer.is(&vm.ctx.not_implemented()) {
+        check_bases(&class, &data, &subclass, vm)?
+    } else {
+        objbool::boolval(vm, answer)?
+    };
+    if answer {
+        data.cache.add(&subclass);
+    } else {
+        data.negative_cache.add(&subclass);
+    }
+    Ok(answer)
+}
+
+/// Whether `subclass` derives from `class`, from one of the classes
+/// registered with it, or from one of its subclasses.
+fn check_bases(
+    class: &PyClassRef,
+    data: &PyAbcData,
+    subclass: &PyClassRef,
+    vm: &VirtualMachine,
+) -> PyResult<bool> {
+    if objtype::issubclass(subclass, class) {
+        return Ok(true);
+    }
+    let subclasses: Vec<_> = class
+        .subclasses
+        .borrow()
+        .iter()
+        .filter_map(PyWeak::upgrade)
+        .collect();
+    for base in data.registry.to_vec().into_iter().chain(subclasses) {
+        if vm.issubclass(subclass.as_object(), &base)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn abc_meta_abstract_methods(class: PyClassRef, vm: &VirtualMachine) -> PyResult {
+    let names = data(&class, vm)
+        .abstract_methods
+        .borrow()
+        .iter()
+        .map(|name| vm.new_str(name.clone()))
+        .collect();
+    vm.invoke(
+        vm.ctx.frozenset_type().into_object(),
+        vec![vm.ctx.new_tuple(names)],
+    )
+}
+
+fn abc_meta_set_abstract_methods(
+    class: PyClassRef,
+    names: PyIterable<PyStringRef>,
+    vm: &VirtualMachine,
+) -> PyResult<()> {
+    let mut names = names
+        .iter(vm)?
+        .map(|name| name.map(|name| name.value.clone()))
+        .collect::<PyResult<Vec<_>>>()?;
+    names.sort();
+    names.dedup();
+    *data(&class, vm).abstract_methods.borrow_mut() = names;
+    Ok(())
+}
+
+pub fn init(context: &PyContext) {
+    extend_class!(context, &context.abc_meta_type, {
+        "__new__" => context.new_rustfunc(abc_meta_new),
+        "register" => context.new_rustfunc(abc_meta_register),
+        "__instancecheck__" => context.new_rustfunc(abc_meta_instance_check),
+        "__subclasscheck__" => context.new_rustfunc(abc_meta_subclass_check),
+        "__abstractmethods__" =>
+            PropertyBuilder::new(context)
+                .add_getter(abc_meta_abstract_methods)
+                .add_setter(abc_meta_set_abstract_methods)
+                .create(),
+    });
+}
@@ -1,6 +1,7 @@
 use std::fmt;
 
 use crate::function::PyNativeFunc;
+use crate::native_panic;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::PyValue;
 use crate::vm::VirtualMachine;
@@ -23,7 +24,12 @@ impl fmt::Debug for PyBuiltinFunction {
 }
 
 impl PyBuiltinFunction {
+    /// Calling the function raises a `SystemError` if it panics. The panic is
+    /// caught here, by whichever copy of the crate makes the function: std
+    /// can't catch the panics of the copy of std an extension module has.
     pub fn new(value: PyNativeFunc) -> Self {
-        Self { value }
+        Self {
+            value: Box::new(move |vm, args| native_panic::catch_panic(vm, || value(vm, args))),
+        }
     }
 }
@@ -65,6 +65,7 @@ pub fn init(context: &PyContext) {
         "__doc__" => context.new_str(bytearray_doc.to_string()),
         "__eq__" => context.new_rustfunc(bytearray_eq),
         "__len__" => context.new_rustfunc(bytesarray_len),
+        "__hash__" => context.none(),
         "__new__" => context.new_rustfunc(bytearray_new),
         "__repr__" => context.new_rustfunc(bytearray_repr),
         "clear" => context.new_rustfunc(bytearray_clear),
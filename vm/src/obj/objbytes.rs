@@ -6,10 +6,12 @@ use num_traits::ToPrimitive;
 
 use crate::function::{OptionalArg, PyFuncArgs};
 use crate::pyobject::{
-    PyContext, PyIteratorValue, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
+    IntoPyObject, PyContext, PyIteratorValue, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject,
+    TypeProtocol,
 };
 use crate::vm::VirtualMachine;
 
+use super::objbytearray::{self, PyByteArray};
 use super::objint;
 use super::objtype::{self, PyClassRef};
 
@@ -17,7 +19,7 @@ use super::objtype::{self, PyClassRef};
 pub struct PyBytes {
     value: Vec<u8>,
 }
-type PyBytesRef = PyRef<PyBytes>;
+pub type PyBytesRef = PyRef<PyBytes>;
 
 impl PyBytes {
     pub fn new(data: Vec<u8>) -> Self {
@@ -39,14 +41,53 @@ impl PyValue for PyBytes {
     }
 }
 
+/// The data of a `bytes` or a `bytearray` for a native function to take, or
+/// of a `bytes` to return, where a `Vec<u8>` would be a list of ints.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Bytes(pub Vec<u8>);
+
+impl Deref for Bytes {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(data: Vec<u8>) -> Self {
+        Bytes(data)
+    }
+}
+
+impl TryFromObject for Bytes {
+    fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
+        if obj.payload_is::<PyBytes>() {
+            Ok(Bytes(get_value(&obj).clone()))
+        } else if obj.payload_is::<PyByteArray>() {
+            Ok(Bytes(objbytearray::get_value(&obj).clone()))
+        } else {
+            Err(vm.new_type_error(format!(
+                "a bytes-like object is required, not '{}'",
+                obj.class().name
+            )))
+        }
+    }
+}
+
+impl IntoPyObject for Bytes {
+    fn into_pyobject(self, vm: &VirtualMachine) -> PyResult {
+        Ok(vm.ctx.new_bytes(self.0))
+    }
+}
+
 // Binary data support
 
 // Fill bytes class methods:
 pub fn init(context: &PyContext) {
     let bytes_type = context.bytes_type.as_object();
 
-    let bytes_doc =
-        "bytes(iterable_of_ints) -> bytes\n\
+    let bytes_doc = "bytes(iterable_of_ints) -> bytes\n\
          bytes(string, encoding[, errors]) -> bytes\n\
          bytes(bytes_or_buffer) -> immutable copy of bytes_or_buffer\n\
          bytes(int) -> bytes object of size given by the parameter initialized with null bytes\n\
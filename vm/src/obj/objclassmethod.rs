@@ -1,3 +1,4 @@
+use super::objabc;
 use super::objtype::PyClassRef;
 use crate::pyobject::{PyContext, PyObjectRef, PyRef, PyResult, PyValue};
 use crate::vm::VirtualMachine;
@@ -26,6 +27,10 @@ impl PyClassMethodRef {
         .into_ref_with_type(vm, cls)
     }
 
+    fn is_abstract(self, vm: &VirtualMachine) -> PyResult<bool> {
+        objabc::is_abstract(vm, &self.callable)
+    }
+
     fn get(self, _inst: PyObjectRef, owner: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         Ok(vm
             .ctx
@@ -37,6 +42,7 @@ pub fn init(context: &PyContext) {
     let classmethod_type = &context.classmethod_type;
     extend_class!(context, classmethod_type, {
         "__get__" => context.new_rustfunc(PyClassMethodRef::get),
-        "__new__" => context.new_rustfunc(PyClassMethodRef::new)
+        "__new__" => context.new_rustfunc(PyClassMethodRef::new),
+        "__isabstractmethod__" => context.new_property(PyClassMethodRef::is_abstract)
     });
 }
@@ -3,8 +3,12 @@
 */
 
 use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
 
 use crate::bytecode;
+use crate::inline_cache::InlineCaches;
+use crate::obj::objstr::PyStringRef;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue};
 use crate::vm::VirtualMachine;
@@ -13,11 +17,33 @@ pub type PyCodeRef = PyRef<PyCode>;
 
 pub struct PyCode {
     pub code: bytecode::CodeObject,
+    pub caches: Rc<InlineCaches>,
+    /// The interned name each instruction refers to, see
+    /// `bytecode::Instruction::name`.
+    pub names: Rc<Vec<Option<PyStringRef>>>,
 }
 
 impl PyCode {
-    pub fn new(code: bytecode::CodeObject) -> PyCode {
-        PyCode { code }
+    pub fn new(ctx: &PyContext, code: bytecode::CodeObject) -> PyCode {
+        let caches = Rc::new(InlineCaches::new(code.instructions.len()));
+        let names = code
+            .instructions
+            .iter()
+            .map(|instruction| instruction.name().map(|name| ctx.intern_str(name)))
+            .collect();
+        PyCode {
+            code,
+            caches,
+            names: Rc::new(names),
+        }
+    }
+}
+
+impl Deref for PyCode {
+    type Target = bytecode::CodeObject;
+
+    fn deref(&self) -> &bytecode::CodeObject {
+        &self.code
     }
 }
 
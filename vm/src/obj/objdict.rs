@@ -1,28 +1,130 @@
-use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 use std::ops::{Deref, DerefMut};
 
+use indexmap::{Equivalent, IndexMap};
+
 use crate::function::{KwArgs, OptionalArg};
+use crate::inline_cache;
 use crate::pyobject::{
-    DictProtocol, PyAttributes, PyContext, PyIteratorValue, PyObjectRef, PyRef, PyResult, PyValue,
+    DictProtocol, IdProtocol, IntoPyObject, PyAttributes, PyContext, PyIteratorValue, PyObjectRef,
+    PyRef, PyResult, PyValue, TryFromObject, TypeProtocol,
 };
 use crate::vm::{ReprGuard, VirtualMachine};
 
 use super::objiter;
-use super::objstr::{self, PyStringRef};
+use super::objstr::{self, PyString, PyStringRef};
 use super::objtype;
 use crate::obj::objtype::PyClassRef;
 
-pub type DictContentType = HashMap<String, (PyObjectRef, PyObjectRef)>;
+/// The key of a dict entry, which can only be a str for now.
+///
+/// Keys compare by identity before comparing their contents, so looking up an
+/// interned string, like the names in code objects, usually finds the entry
+/// without comparing any characters. They hash with the hash the str caches,
+/// so a name isn't rehashed on every lookup either.
+#[derive(Debug, Clone)]
+pub struct DictKey(PyStringRef);
+
+impl DictKey {
+    pub fn new(key: PyStringRef) -> Self {
+        DictKey(key)
+    }
+
+    pub fn string(&self) -> &PyStringRef {
+        &self.0
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0.value
+    }
+
+    pub fn as_object(&self) -> &PyObjectRef {
+        self.0.as_object()
+    }
+}
+
+impl Hash for DictKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.0.hash_value())
+    }
+}
+
+impl PartialEq for DictKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.is(&other.0) || self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for DictKey {}
+
+/// A `&str` to look up in dicts without creating a str object, which hashes
+/// it the same way as `DictKey`.
+pub struct StrKey<'a> {
+    value: &'a str,
+    hash: u64,
+}
+
+impl<'a> StrKey<'a> {
+    pub fn new(value: &'a str) -> Self {
+        StrKey {
+            value,
+            hash: objstr::hash_str(value),
+        }
+    }
+}
+
+impl Hash for StrKey<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash)
+    }
+}
+
+impl Equivalent<DictKey> for StrKey<'_> {
+    fn equivalent(&self, key: &DictKey) -> bool {
+        self.value == key.as_str()
+    }
+}
+
+/// The entries of a dict, in the order they were inserted. Removing one
+/// shifts the entries after it down, like deleting from a list.
+pub type DictContentType = IndexMap<DictKey, PyObjectRef>;
 
-#[derive(Default)]
 pub struct PyDict {
-    // TODO: should be private
-    pub entries: RefCell<DictContentType>,
+    /// Mutated only through `entries_mut`, so the version tag changes.
+    entries: RefCell<DictContentType>,
+    version_tag: Cell<usize>,
 }
 pub type PyDictRef = PyRef<PyDict>;
 
+impl Default for PyDict {
+    fn default() -> Self {
+        PyDict {
+            entries: RefCell::default(),
+            version_tag: Cell::new(inline_cache::next_version_tag()),
+        }
+    }
+}
+
+impl PyDict {
+    /// Changes whenever the contents of the dict change.
+    pub fn version_tag(&self) -> usize {
+        self.version_tag.get()
+    }
+
+    pub fn entries(&self) -> Ref<'_, DictContentType> {
+        self.entries.borrow()
+    }
+
+    pub fn entries_mut(&self) -> RefMut<DictContentType> {
+        self.version_tag.set(inline_cache::next_version_tag());
+        self.entries.borrow_mut()
+    }
+}
+
 impl fmt::Debug for PyDict {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // TODO: implement more detailed, non-recursive Debug formatter
@@ -36,12 +138,77 @@ impl PyValue for PyDict {
     }
 }
 
+fn try_from_dict<K, V, C>(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<C>
+where
+    K: TryFromObject,
+    V: TryFromObject,
+    C: FromIterator<(K, V)>,
+{
+    let dict = PyDictRef::try_from_object(vm, obj)?;
+    dict.get_key_value_pairs()
+        .into_iter()
+        .map(|(key, value)| Ok((K::try_from_object(vm, key)?, V::try_from_object(vm, value)?)))
+        .collect()
+}
+
+fn into_dict<K, V, I>(vm: &VirtualMachine, items: I) -> PyResult
+where
+    K: IntoPyObject,
+    V: IntoPyObject,
+    I: IntoIterator<Item = (K, V)>,
+{
+    let dict = vm.ctx.new_dict();
+    for (key, value) in items {
+        let key = key.into_pyobject(vm)?;
+        if !objtype::isinstance(&key, &vm.ctx.str_type()) {
+            return Err(vm.new_type_error(format!(
+                "dict keys can only be str, not {}",
+                key.class().name
+            )));
+        }
+        set_item(dict.as_object(), vm, &key, &value.into_pyobject(vm)?);
+    }
+    Ok(dict.into_object())
+}
+
+impl<K, V> TryFromObject for HashMap<K, V>
+where
+    K: TryFromObject + Eq + Hash,
+    V: TryFromObject,
+{
+    fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
+        try_from_dict(vm, obj)
+    }
+}
+
+impl<K: IntoPyObject, V: IntoPyObject> IntoPyObject for HashMap<K, V> {
+    fn into_pyobject(self, vm: &VirtualMachine) -> PyResult {
+        into_dict(vm, self)
+    }
+}
+
+impl<K, V> TryFromObject for BTreeMap<K, V>
+where
+    K: TryFromObject + Ord,
+    V: TryFromObject,
+{
+    fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
+        try_from_dict(vm, obj)
+    }
+}
+
+impl<K: IntoPyObject, V: IntoPyObject> IntoPyObject for BTreeMap<K, V> {
+    fn into_pyobject(self, vm: &VirtualMachine) -> PyResult {
+        into_dict(vm, self)
+    }
+}
+
 pub fn get_elements<'a>(obj: &'a PyObjectRef) -> impl Deref<Target = DictContentType> + 'a {
     obj.payload::<PyDict>().unwrap().entries.borrow()
 }
 
 pub fn get_mut_elements<'a>(obj: &'a PyObjectRef) -> impl DerefMut<Target = DictContentType> + 'a {
-    obj.payload::<PyDict>().unwrap().entries.borrow_mut()
+    obj.payload::<PyDict>().unwrap().entries_mut()
 }
 
 pub fn set_item(
@@ -64,8 +231,8 @@ pub fn set_item_in_content(
     // PyObject (and ensure it is a String).
 
     // TODO: invoke __hash__ function here!
-    let needle_str = objstr::get_value(needle);
-    elements.insert(needle_str, (needle.clone(), value.clone()));
+    let key = needle.clone().downcast::<PyString>().unwrap();
+    elements.insert(DictKey(key), value.clone());
 }
 
 pub fn get_key_value_pairs(dict: &PyObjectRef) -> Vec<(PyObjectRef, PyObjectRef)> {
@@ -77,9 +244,8 @@ pub fn get_key_value_pairs_from_content(
     dict_content: &DictContentType,
 ) -> Vec<(PyObjectRef, PyObjectRef)> {
     let mut pairs: Vec<(PyObjectRef, PyObjectRef)> = Vec::new();
-    for (_str_key, pair) in dict_content.iter() {
-        let (key, obj) = pair;
-        pairs.push((key.clone(), obj.clone()));
+    for (key, obj) in dict_content.iter() {
+        pairs.push((key.as_object().clone(), obj.clone()));
     }
     pairs
 }
@@ -97,11 +263,13 @@ pub fn get_key_str(dict: &PyObjectRef, key: &str) -> Option<PyObjectRef> {
 
 /// Retrieve a key from dict contents:
 pub fn content_get_key_str(elements: &DictContentType, key: &str) -> Option<PyObjectRef> {
-    // TODO: let hash: usize = key;
-    match elements.get(key) {
-        Some(v) => Some(v.1.clone()),
-        None => None,
-    }
+    elements.get(&StrKey::new(key)).cloned()
+}
+
+/// Retrieve a str key, which is found by identity if it is the same object
+/// as the key of the entry.
+pub fn content_get_key(elements: &DictContentType, key: &PyStringRef) -> Option<PyObjectRef> {
+    elements.get(&DictKey(key.clone())).cloned()
 }
 
 pub fn contains_key_str(dict: &PyObjectRef, key: &str) -> bool {
@@ -110,8 +278,7 @@ pub fn contains_key_str(dict: &PyObjectRef, key: &str) -> bool {
 }
 
 pub fn content_contains_key_str(elements: &DictContentType, key: &str) -> bool {
-    // TODO: let hash: usize = key;
-    elements.get(key).is_some()
+    elements.contains_key(&StrKey::new(key))
 }
 
 /// Take a python dictionary and convert it to attributes.
@@ -192,21 +359,20 @@ impl PyDictRef {
     }
 
     fn contains(self, key: PyStringRef, _vm: &VirtualMachine) -> bool {
-        self.entries.borrow().contains_key(&key.value)
+        self.entries.borrow().contains_key(&DictKey(key))
     }
 
     fn delitem(self, key: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
-        let key = &key.value;
         // Delete the item:
-        let mut elements = self.entries.borrow_mut();
-        match elements.remove(key) {
+        let mut elements = self.entries_mut();
+        match elements.shift_remove(&DictKey(key.clone())) {
             Some(_) => Ok(()),
-            None => Err(vm.new_value_error(format!("Key not found: {}", key))),
+            None => Err(vm.new_key_error(key.into_object())),
         }
     }
 
     fn clear(self, _vm: &VirtualMachine) {
-        self.entries.borrow_mut().clear()
+        self.entries_mut().clear()
     }
 
     /// When iterating over a dictionary, we iterate over the keys of it.
@@ -214,8 +380,8 @@ impl PyDictRef {
         let keys = self
             .entries
             .borrow()
-            .values()
-            .map(|(k, _v)| k.clone())
+            .keys()
+            .map(|k| k.as_object().clone())
             .collect();
         let key_list = vm.ctx.new_list(keys);
 
@@ -226,12 +392,7 @@ impl PyDictRef {
     }
 
     fn values(self, vm: &VirtualMachine) -> PyIteratorValue {
-        let values = self
-            .entries
-            .borrow()
-            .values()
-            .map(|(_k, v)| v.clone())
-            .collect();
+        let values = self.entries.borrow().values().cloned().collect();
         let values_list = vm.ctx.new_list(values);
 
         PyIteratorValue {
@@ -244,8 +405,8 @@ impl PyDictRef {
         let items = self
             .entries
             .borrow()
-            .values()
-            .map(|(k, v)| vm.ctx.new_tuple(vec![k.clone(), v.clone()]))
+            .iter()
+            .map(|(k, v)| vm.ctx.new_tuple(vec![k.as_object().clone(), v.clone()]))
             .collect();
         let items_list = vm.ctx.new_list(items);
 
@@ -256,19 +417,15 @@ impl PyDictRef {
     }
 
     fn setitem(self, needle: PyObjectRef, value: PyObjectRef, _vm: &VirtualMachine) {
-        let mut elements = self.entries.borrow_mut();
+        let mut elements = self.entries_mut();
         set_item_in_content(&mut elements, &needle, &value)
     }
 
     fn getitem(self, key: PyStringRef, vm: &VirtualMachine) -> PyResult {
-        let key = &key.value;
-
         // What we are looking for:
-        let elements = self.entries.borrow();
-        if elements.contains_key(key) {
-            Ok(elements[key].1.clone())
-        } else {
-            Err(vm.new_value_error(format!("Key not found: {}", key)))
+        match content_get_key(&self.entries.borrow(), &key) {
+            Some(value) => Ok(value),
+            None => Err(vm.new_key_error(key.into_object())),
         }
     }
 
@@ -279,16 +436,12 @@ impl PyDictRef {
         vm: &VirtualMachine,
     ) -> PyObjectRef {
         // What we are looking for:
-        let key = &key.value;
-
-        let elements = self.entries.borrow();
-        if elements.contains_key(key) {
-            elements[key].1.clone()
-        } else {
-            match default {
+        match content_get_key(&self.entries.borrow(), &key) {
+            Some(value) => value,
+            None => match default {
                 OptionalArg::Present(value) => value,
                 OptionalArg::Missing => vm.ctx.none(),
-            }
+            },
         }
     }
 }
@@ -308,13 +461,13 @@ impl DictProtocol for PyDictRef {
 
     // Item set/get:
     fn set_item(&self, ctx: &PyContext, key: &str, v: PyObjectRef) {
-        let key = ctx.new_str(key.to_string());
-        set_item_in_content(&mut self.entries.borrow_mut(), &key, &v);
+        let key = ctx.intern_str(key);
+        self.entries_mut().insert(DictKey(key), v);
     }
 
     fn del_item(&self, key: &str) {
         let mut elements = get_mut_elements(self.as_object());
-        elements.remove(key).unwrap();
+        elements.shift_remove(&StrKey::new(key)).unwrap();
     }
 }
 
@@ -322,6 +475,7 @@ pub fn init(context: &PyContext) {
     extend_class!(context, &context.dict_type, {
         "__bool__" => context.new_rustfunc(PyDictRef::bool),
         "__len__" => context.new_rustfunc(PyDictRef::len),
+        "__hash__" => context.none(),
         "__contains__" => context.new_rustfunc(PyDictRef::contains),
         "__delitem__" => context.new_rustfunc(PyDictRef::delitem),
         "__getitem__" => context.new_rustfunc(PyDictRef::getitem),
@@ -14,6 +14,11 @@ pub fn init(context: &PyContext) {
         "__repr__" => context.new_rustfunc(FrameRef::repr),
         "f_locals" => context.new_property(FrameRef::flocals),
         "f_code" => context.new_property(FrameRef::fcode),
+        "f_back" => context.new_property(FrameRef::fback),
+        "f_lineno" => context.new_property(FrameRef::flineno),
+        "f_lasti" => context.new_property(FrameRef::flasti),
+        "f_globals" => context.new_property(FrameRef::fglobals),
+        "f_builtins" => context.new_property(FrameRef::fbuiltins),
     });
 }
 
@@ -30,7 +35,28 @@ impl FrameRef {
         self.scope.get_locals()
     }
 
-    fn fcode(self, vm: &VirtualMachine) -> PyCodeRef {
-        vm.ctx.new_code_object(self.code.clone())
+    fn fcode(self, _vm: &VirtualMachine) -> PyCodeRef {
+        self.code.clone()
+    }
+
+    fn fback(self, _vm: &VirtualMachine) -> Option<FrameRef> {
+        self.back.borrow().clone()
+    }
+
+    fn flineno(self, _vm: &VirtualMachine) -> usize {
+        self.current_location().get_row()
+    }
+
+    fn flasti(self, _vm: &VirtualMachine) -> isize {
+        // -1 until the first instruction has run, like CPython.
+        *self.lasti.borrow() as isize - 1
+    }
+
+    fn fglobals(self, _vm: &VirtualMachine) -> PyDictRef {
+        self.scope.globals.clone()
+    }
+
+    fn fbuiltins(self, vm: &VirtualMachine) -> PyResult {
+        vm.get_attribute(vm.builtins.clone(), "__dict__")
     }
 }
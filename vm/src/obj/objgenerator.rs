@@ -2,16 +2,34 @@
  * The mythical generator.
  */
 
+use std::cell::Cell;
+
 use crate::frame::{ExecutionResult, FrameRef};
-use crate::obj::objtype::PyClassRef;
-use crate::pyobject::{PyContext, PyObjectRef, PyRef, PyResult, PyValue};
+use crate::function::OptionalArg;
+use crate::obj::objcode::PyCodeRef;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol};
 use crate::vm::VirtualMachine;
 
 pub type PyGeneratorRef = PyRef<PyGenerator>;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GeneratorState {
+    /// Not started yet.
+    Created,
+    /// Waiting at a `yield`.
+    Suspended,
+    Running,
+    /// Returned, raised or was closed.
+    Finished,
+}
+
 #[derive(Debug)]
 pub struct PyGenerator {
     frame: FrameRef,
+    state: Cell<GeneratorState>,
+    /// Set once `close()` has been scheduled for an abandoned generator.
+    finalized: Cell<bool>,
 }
 
 impl PyValue for PyGenerator {
@@ -20,9 +38,74 @@ impl PyValue for PyGenerator {
     }
 }
 
+impl PyGenerator {
+    /// Whether dropping this generator should `close()` it, so that its
+    /// `finally` blocks run.
+    pub fn needs_finalizer(&self) -> bool {
+        !self.finalized.get() && self.state.get() == GeneratorState::Suspended
+    }
+
+    /// A stand-in for a dropped generator, which `close()` is called on.
+    pub fn zombie(&self) -> PyGenerator {
+        PyGenerator {
+            frame: self.frame.clone(),
+            state: Cell::new(self.state.get()),
+            finalized: Cell::new(true),
+        }
+    }
+
+    /// Run the frame, keeping track of the state of the generator.
+    fn resume<F>(&self, vm: &VirtualMachine, run: F) -> PyResult
+    where
+        F: FnOnce() -> PyResult<ExecutionResult>,
+    {
+        match self.state.get() {
+            GeneratorState::Running => {
+                return Err(vm.new_value_error("generator already executing".to_string()));
+            }
+            GeneratorState::Finished => return Err(vm.new_empty_exception(stop_iteration(vm))?),
+            GeneratorState::Created | GeneratorState::Suspended => {}
+        }
+        self.state.set(GeneratorState::Running);
+        let result = run();
+        match result {
+            Ok(ExecutionResult::Yield(value)) => {
+                self.state.set(GeneratorState::Suspended);
+                Ok(value)
+            }
+            Ok(ExecutionResult::Return(value)) => {
+                self.state.set(GeneratorState::Finished);
+                let args = if value.is(&vm.get_none()) {
+                    vec![]
+                } else {
+                    vec![value]
+                };
+                Err(vm.invoke(stop_iteration(vm).into_object(), args)?)
+            }
+            Err(exc) => {
+                self.state.set(GeneratorState::Finished);
+                if objtype::isinstance(&exc, &stop_iteration(vm)) {
+                    Err(vm.new_runtime_error("generator raised StopIteration".to_string()))
+                } else {
+                    Err(exc)
+                }
+            }
+        }
+    }
+}
+
+fn stop_iteration(vm: &VirtualMachine) -> PyClassRef {
+    vm.ctx.exceptions.stop_iteration.clone()
+}
+
 impl PyGeneratorRef {
     pub fn new(frame: FrameRef, vm: &VirtualMachine) -> PyGeneratorRef {
-        PyGenerator { frame }.into_ref(vm)
+        PyGenerator {
+            frame,
+            state: Cell::new(GeneratorState::Created),
+            finalized: Cell::new(false),
+        }
+        .into_ref(vm)
     }
 
     fn iter(self, _vm: &VirtualMachine) -> PyGeneratorRef {
@@ -34,17 +117,108 @@ impl PyGeneratorRef {
     }
 
     fn send(self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        self.frame.push_value(value.clone());
+        if self.state.get() == GeneratorState::Created {
+            if !value.is(&vm.get_none()) {
+                return Err(vm.new_type_error(
+                    "can't send non-None value to a just-started generator".to_string(),
+                ));
+            }
+        } else if self.state.get() == GeneratorState::Suspended {
+            // The value of the `yield` expression:
+            self.frame.push_value(value.clone());
+        }
 
-        match vm.run_frame(self.frame.clone())? {
-            ExecutionResult::Yield(value) => Ok(value),
-            ExecutionResult::Return(_value) => {
-                // Stop iteration!
-                let stop_iteration = vm.ctx.exceptions.stop_iteration.clone();
-                Err(vm.new_exception(stop_iteration, "End of generator".to_string()))
+        self.resume(vm, || vm.run_frame(self.frame.clone()))
+    }
+
+    fn throw(
+        self,
+        exc_type: PyObjectRef,
+        exc_val: OptionalArg<PyObjectRef>,
+        _exc_tb: OptionalArg<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        let exc = normalize_exception(exc_type, exc_val.into_option(), vm)?;
+        match self.state.get() {
+            GeneratorState::Created | GeneratorState::Finished => {
+                self.state.set(GeneratorState::Finished);
+                Err(exc)
+            }
+            GeneratorState::Suspended | GeneratorState::Running => {
+                self.resume(vm, || vm.throw_into_frame(self.frame.clone(), exc))
             }
         }
     }
+
+    fn close(self, vm: &VirtualMachine) -> PyResult<()> {
+        match self.state.get() {
+            GeneratorState::Created | GeneratorState::Finished => {
+                self.state.set(GeneratorState::Finished);
+                return Ok(());
+            }
+            GeneratorState::Suspended | GeneratorState::Running => {}
+        }
+        let generator_exit = vm.new_empty_exception(vm.ctx.exceptions.generator_exit.clone())?;
+        match self.resume(vm, || {
+            vm.throw_into_frame(self.frame.clone(), generator_exit)
+        }) {
+            Ok(_) => Err(vm.new_runtime_error("generator ignored GeneratorExit".to_string())),
+            Err(exc) => {
+                if objtype::isinstance(&exc, &vm.ctx.exceptions.generator_exit)
+                    || objtype::isinstance(&exc, &stop_iteration(vm))
+                {
+                    Ok(())
+                } else {
+                    Err(exc)
+                }
+            }
+        }
+    }
+
+    fn gi_frame(self, vm: &VirtualMachine) -> PyObjectRef {
+        if self.state.get() == GeneratorState::Finished {
+            vm.get_none()
+        } else {
+            self.frame.clone().into_object()
+        }
+    }
+
+    fn gi_running(self, _vm: &VirtualMachine) -> bool {
+        self.state.get() == GeneratorState::Running
+    }
+
+    fn gi_code(self, _vm: &VirtualMachine) -> PyCodeRef {
+        self.frame.code.clone()
+    }
+}
+
+/// Turn the arguments of `throw()` into an exception instance, like `raise`.
+pub fn normalize_exception(
+    exc_type: PyObjectRef,
+    exc_val: Option<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult {
+    let base_exception = &vm.ctx.exceptions.base_exception_type;
+    let exc_val = exc_val.filter(|val| !val.is(&vm.get_none()));
+    if objtype::isinstance(&exc_type, base_exception) {
+        if exc_val.is_some() {
+            return Err(
+                vm.new_type_error("instance exception may not have a separate value".to_string())
+            );
+        }
+        return Ok(exc_type);
+    }
+    match exc_type.clone().downcast::<objtype::PyClass>() {
+        Ok(ref cls) if objtype::issubclass(cls, base_exception) => match exc_val {
+            Some(ref val) if objtype::isinstance(val, cls) => Ok(val.clone()),
+            Some(val) => vm.invoke(cls.clone().into_object(), vec![val]),
+            None => vm.new_empty_exception(cls.clone()),
+        },
+        _ => Err(vm.new_type_error(format!(
+            "exceptions must be classes or instances deriving from BaseException, not {}",
+            exc_type.class().name
+        ))),
+    }
 }
 
 pub fn init(context: &PyContext) {
@@ -52,6 +226,11 @@ pub fn init(context: &PyContext) {
     extend_class!(context, generator_type, {
         "__iter__" => context.new_rustfunc(PyGeneratorRef::iter),
         "__next__" => context.new_rustfunc(PyGeneratorRef::next),
-        "send" => context.new_rustfunc(PyGeneratorRef::send)
+        "send" => context.new_rustfunc(PyGeneratorRef::send),
+        "throw" => context.new_rustfunc(PyGeneratorRef::throw),
+        "close" => context.new_rustfunc(PyGeneratorRef::close),
+        "gi_frame" => context.new_property(PyGeneratorRef::gi_frame),
+        "gi_running" => context.new_property(PyGeneratorRef::gi_running),
+        "gi_code" => context.new_property(PyGeneratorRef::gi_code)
     });
 }
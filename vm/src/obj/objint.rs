@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
@@ -8,7 +10,8 @@ use num_traits::{Pow, Signed, ToPrimitive, Zero};
 use crate::format::FormatSpec;
 use crate::function::{OptionalArg, PyFuncArgs};
 use crate::pyobject::{
-    IntoPyObject, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject, TypeProtocol,
+    IdProtocol, IntoPyObject, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject,
+    TypeProtocol,
 };
 use crate::vm::VirtualMachine;
 
@@ -17,26 +20,92 @@ use super::objstr;
 use super::objtype;
 use crate::obj::objtype::PyClassRef;
 
+/// Ints that fit in a machine word are stored inline, so the common small
+/// ones don't need an allocation and arbitrary precision arithmetic.
+#[derive(Debug, Clone)]
+enum IntValue {
+    Small(i64),
+    /// Only used for values that don't fit in an `i64`.
+    Big(BigInt),
+}
+
 #[derive(Debug)]
 pub struct PyInt {
-    value: BigInt,
+    value: IntValue,
 }
 
 impl fmt::Display for PyInt {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        BigInt::fmt(&self.value, f)
+        match self.value {
+            IntValue::Small(value) => fmt::Display::fmt(&value, f),
+            IntValue::Big(ref value) => BigInt::fmt(value, f),
+        }
     }
 }
 
 pub type PyIntRef = PyRef<PyInt>;
 
 impl PyInt {
-    pub fn new<T: Into<BigInt>>(i: T) -> Self {
-        PyInt { value: i.into() }
+    pub fn new<T: Into<BigInt> + ToPrimitive>(i: T) -> Self {
+        match i.to_i64() {
+            Some(value) => PyInt::from_i64(value),
+            None => PyInt {
+                value: IntValue::Big(i.into()),
+            },
+        }
+    }
+
+    pub fn from_i64(value: i64) -> Self {
+        PyInt {
+            value: IntValue::Small(value),
+        }
+    }
+
+    /// The value, if it fits in a machine word.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.value {
+            IntValue::Small(value) => Some(value),
+            IntValue::Big(_) => None,
+        }
+    }
+
+    pub fn as_bigint(&self) -> Cow<BigInt> {
+        match self.value {
+            IntValue::Small(value) => Cow::Owned(BigInt::from(value)),
+            IntValue::Big(ref value) => Cow::Borrowed(value),
+        }
     }
+}
 
-    pub fn as_bigint(&self) -> &BigInt {
-        &self.value
+impl PyInt {
+    pub fn is_zero(&self) -> bool {
+        match self.value {
+            IntValue::Small(value) => value == 0,
+            IntValue::Big(ref value) => value.is_zero(),
+        }
+    }
+}
+
+impl ToPrimitive for PyInt {
+    fn to_i64(&self) -> Option<i64> {
+        match self.value {
+            IntValue::Small(value) => Some(value),
+            IntValue::Big(ref value) => value.to_i64(),
+        }
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        match self.value {
+            IntValue::Small(value) => value.to_u64(),
+            IntValue::Big(ref value) => value.to_u64(),
+        }
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        match self.value {
+            IntValue::Small(value) => value.to_f64(),
+            IntValue::Big(ref value) => value.to_f64(),
+        }
     }
 }
 
@@ -46,6 +115,12 @@ impl IntoPyObject for BigInt {
     }
 }
 
+impl TryFromObject for BigInt {
+    fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
+        Ok(PyIntRef::try_from_object(vm, obj)?.as_bigint().into_owned())
+    }
+}
+
 impl PyValue for PyInt {
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.int_type()
@@ -62,13 +137,13 @@ macro_rules! impl_into_pyobject_int {
     )*};
 }
 
-impl_into_pyobject_int!(isize i8 i16 i32 i64 usize u8 u16 u32 u64) ;
+impl_into_pyobject_int!(isize i8 i16 i32 i64 usize u8 u16 u32 u64);
 
 macro_rules! impl_try_from_object_int {
     ($(($t:ty, $to_prim:ident),)*) => {$(
         impl TryFromObject for $t {
             fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
-                match PyRef::<PyInt>::try_from_object(vm, obj)?.value.$to_prim() {
+                match PyRef::<PyInt>::try_from_object(vm, obj)?.$to_prim() {
                     Some(value) => Ok(value),
                     None => Err(
                         vm.new_overflow_error(concat!(
@@ -95,62 +170,104 @@ impl_try_from_object_int!(
     (u64, to_u64),
 );
 
+/// `a <op> b`, computed inline by `small` when both operands fit in a machine
+/// word, unless it returns `None` because the result doesn't.
+fn int_binop<S, B>(vm: &VirtualMachine, a: &PyInt, b: &PyInt, small: S, big: B) -> PyObjectRef
+where
+    S: Fn(i64, i64) -> Option<i64>,
+    B: Fn(&BigInt, &BigInt) -> BigInt,
+{
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        if let Some(result) = small(a, b) {
+            return vm.ctx.new_int(result);
+        }
+    }
+    vm.ctx.new_int(big(&a.as_bigint(), &b.as_bigint()))
+}
+
+fn compare(a: &PyInt, b: &PyInt) -> Ordering {
+    match (&a.value, &b.value) {
+        (IntValue::Small(a), IntValue::Small(b)) => a.cmp(b),
+        _ => a.as_bigint().cmp(&b.as_bigint()),
+    }
+}
+
+/// Division rounding towards negative infinity, like python's `//`.
+pub fn floor_div(a: i64, b: i64) -> Option<i64> {
+    let quotient = a.checked_div(b)?;
+    if a % b != 0 && (a < 0) != (b < 0) {
+        Some(quotient - 1)
+    } else {
+        Some(quotient)
+    }
+}
+
+/// The remainder of `floor_div`, which has the sign of the divisor.
+pub fn floor_mod(a: i64, b: i64) -> Option<i64> {
+    let remainder = a.checked_rem(b)?;
+    if remainder != 0 && (remainder < 0) != (b < 0) {
+        Some(remainder + b)
+    } else {
+        Some(remainder)
+    }
+}
+
+/// `a << b` for a non negative shift, unless the result doesn't fit.
+pub fn small_lshift(a: i64, b: i64) -> Option<i64> {
+    if b < 0 || b >= 63 {
+        return if a == 0 && b >= 0 { Some(0) } else { None };
+    }
+    let result = a << b;
+    if result >> b == a {
+        Some(result)
+    } else {
+        None
+    }
+}
+
 impl PyIntRef {
     fn pass_value(self, _vm: &VirtualMachine) -> Self {
         self
     }
 
-    fn eq(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+    fn compare_with<F>(self, other: PyObjectRef, vm: &VirtualMachine, test: F) -> PyObjectRef
+    where
+        F: Fn(Ordering) -> bool,
+    {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value == *get_value(&other))
+            vm.ctx.new_bool(test(compare(&self, get_int(&other))))
         } else {
             vm.ctx.not_implemented()
         }
     }
 
+    fn eq(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+        self.compare_with(other, vm, |ordering| ordering == Ordering::Equal)
+    }
+
     fn ne(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value != *get_value(&other))
-        } else {
-            vm.ctx.not_implemented()
-        }
+        self.compare_with(other, vm, |ordering| ordering != Ordering::Equal)
     }
 
     fn lt(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value < *get_value(&other))
-        } else {
-            vm.ctx.not_implemented()
-        }
+        self.compare_with(other, vm, |ordering| ordering == Ordering::Less)
     }
 
     fn le(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value <= *get_value(&other))
-        } else {
-            vm.ctx.not_implemented()
-        }
+        self.compare_with(other, vm, |ordering| ordering != Ordering::Greater)
     }
 
     fn gt(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value > *get_value(&other))
-        } else {
-            vm.ctx.not_implemented()
-        }
+        self.compare_with(other, vm, |ordering| ordering == Ordering::Greater)
     }
 
     fn ge(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value >= *get_value(&other))
-        } else {
-            vm.ctx.not_implemented()
-        }
+        self.compare_with(other, vm, |ordering| ordering != Ordering::Less)
     }
 
     fn add(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int((&self.value) + get_value(&other))
+            int_binop(vm, &self, get_int(&other), i64::checked_add, |a, b| a + b)
         } else {
             vm.ctx.not_implemented()
         }
@@ -158,7 +275,7 @@ impl PyIntRef {
 
     fn sub(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int((&self.value) - get_value(&other))
+            int_binop(vm, &self, get_int(&other), i64::checked_sub, |a, b| a - b)
         } else {
             vm.ctx.not_implemented()
         }
@@ -166,7 +283,7 @@ impl PyIntRef {
 
     fn rsub(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int(get_value(&other) - (&self.value))
+            int_binop(vm, get_int(&other), &self, i64::checked_sub, |a, b| a - b)
         } else {
             vm.ctx.not_implemented()
         }
@@ -174,7 +291,7 @@ impl PyIntRef {
 
     fn mul(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int((&self.value) * get_value(&other))
+            int_binop(vm, &self, get_int(&other), i64::checked_mul, |a, b| a * b)
         } else {
             vm.ctx.not_implemented()
         }
@@ -182,7 +299,7 @@ impl PyIntRef {
 
     fn truediv(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            div_ints(vm, &self.value, &get_value(&other))
+            div_ints(vm, &self.as_bigint(), &get_value(&other))
         } else {
             Ok(vm.ctx.not_implemented())
         }
@@ -190,7 +307,7 @@ impl PyIntRef {
 
     fn rtruediv(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            div_ints(vm, &get_value(&other), &self.value)
+            div_ints(vm, &get_value(&other), &self.as_bigint())
         } else {
             Ok(vm.ctx.not_implemented())
         }
@@ -198,9 +315,11 @@ impl PyIntRef {
 
     fn floordiv(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other);
-            if *v2 != BigInt::zero() {
-                Ok(vm.ctx.new_int((&self.value) / v2))
+            let other = get_int(&other);
+            if !other.is_zero() {
+                Ok(int_binop(vm, &self, other, floor_div, |a, b| {
+                    a.div_floor(b)
+                }))
             } else {
                 Err(vm.new_zero_division_error("integer floordiv by zero".to_string()))
             }
@@ -214,18 +333,18 @@ impl PyIntRef {
             return Ok(vm.ctx.not_implemented());
         }
 
-        if let Some(n_bits) = get_value(&other).to_usize() {
-            return Ok(vm.ctx.new_int((&self.value) << n_bits));
+        let other = get_int(&other);
+        if let (Some(a), Some(b)) = (self.as_i64(), other.as_i64()) {
+            if let Some(result) = small_lshift(a, b) {
+                return Ok(vm.ctx.new_int(result));
+            }
+        }
+        if let Some(n_bits) = other.to_usize() {
+            return Ok(vm.ctx.new_int(&*self.as_bigint() << n_bits));
         }
 
         // i2 failed `to_usize()` conversion
-        match get_value(&other) {
-            v if *v < BigInt::zero() => Err(vm.new_value_error("negative shift count".to_string())),
-            v if *v > BigInt::from(usize::max_value()) => {
-                Err(vm.new_overflow_error("the number is too large to convert to int".to_string()))
-            }
-            _ => panic!("Failed converting {} to rust usize", get_value(&other)),
-        }
+        shift_error(vm, other)
     }
 
     fn rshift(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
@@ -233,23 +352,23 @@ impl PyIntRef {
             return Ok(vm.ctx.not_implemented());
         }
 
-        if let Some(n_bits) = get_value(&other).to_usize() {
-            return Ok(vm.ctx.new_int((&self.value) >> n_bits));
+        let other = get_int(&other);
+        if let (Some(a), Some(b)) = (self.as_i64(), other.as_i64()) {
+            if b >= 0 {
+                return Ok(vm.ctx.new_int(a >> b.min(63)));
+            }
+        }
+        if let Some(n_bits) = other.to_usize() {
+            return Ok(vm.ctx.new_int(&*self.as_bigint() >> n_bits));
         }
 
         // i2 failed `to_usize()` conversion
-        match get_value(&other) {
-            v if *v < BigInt::zero() => Err(vm.new_value_error("negative shift count".to_string())),
-            v if *v > BigInt::from(usize::max_value()) => {
-                Err(vm.new_overflow_error("the number is too large to convert to int".to_string()))
-            }
-            _ => panic!("Failed converting {} to rust usize", get_value(&other)),
-        }
+        shift_error(vm, other)
     }
 
     fn xor(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int((&self.value) ^ get_value(&other))
+            int_binop(vm, &self, get_int(&other), |a, b| Some(a ^ b), |a, b| a ^ b)
         } else {
             vm.ctx.not_implemented()
         }
@@ -257,7 +376,7 @@ impl PyIntRef {
 
     fn rxor(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int(get_value(&other) ^ (&self.value))
+            int_binop(vm, get_int(&other), &self, |a, b| Some(a ^ b), |a, b| a ^ b)
         } else {
             vm.ctx.not_implemented()
         }
@@ -265,7 +384,7 @@ impl PyIntRef {
 
     fn or(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int((&self.value) | get_value(&other))
+            int_binop(vm, &self, get_int(&other), |a, b| Some(a | b), |a, b| a | b)
         } else {
             vm.ctx.not_implemented()
         }
@@ -273,8 +392,7 @@ impl PyIntRef {
 
     fn and(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other);
-            vm.ctx.new_int((&self.value) & v2)
+            int_binop(vm, &self, get_int(&other), |a, b| Some(a & b), |a, b| a & b)
         } else {
             vm.ctx.not_implemented()
         }
@@ -282,11 +400,14 @@ impl PyIntRef {
 
     fn pow(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other).to_u32().unwrap();
-            vm.ctx.new_int(self.value.pow(v2))
+            let v2 = get_int(&other).to_u32().unwrap();
+            if let Some(result) = self.as_i64().and_then(|v1| v1.checked_pow(v2)) {
+                return vm.ctx.new_int(result);
+            }
+            vm.ctx.new_int(self.as_bigint().pow(v2))
         } else if objtype::isinstance(&other, &vm.ctx.float_type()) {
             let v2 = objfloat::get_value(&other);
-            vm.ctx.new_float((self.value.to_f64().unwrap()).powf(v2))
+            vm.ctx.new_float((self.to_f64().unwrap()).powf(v2))
         } else {
             vm.ctx.not_implemented()
         }
@@ -294,9 +415,11 @@ impl PyIntRef {
 
     fn mod_(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other);
-            if *v2 != BigInt::zero() {
-                Ok(vm.ctx.new_int((&self.value) % v2))
+            let other = get_int(&other);
+            if !other.is_zero() {
+                Ok(int_binop(vm, &self, other, floor_mod, |a, b| {
+                    a.mod_floor(b)
+                }))
             } else {
                 Err(vm.new_zero_division_error("integer modulo by zero".to_string()))
             }
@@ -307,12 +430,12 @@ impl PyIntRef {
 
     fn divmod(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other);
-            if *v2 != BigInt::zero() {
-                let (r1, r2) = self.value.div_rem(v2);
-                Ok(vm
-                    .ctx
-                    .new_tuple(vec![vm.ctx.new_int(r1), vm.ctx.new_int(r2)]))
+            let other = get_int(&other);
+            if !other.is_zero() {
+                Ok(vm.ctx.new_tuple(vec![
+                    int_binop(vm, &self, other, floor_div, |a, b| a.div_floor(b)),
+                    int_binop(vm, &self, other, floor_mod, |a, b| a.mod_floor(b)),
+                ]))
             } else {
                 Err(vm.new_zero_division_error("integer divmod by zero".to_string()))
             }
@@ -321,18 +444,29 @@ impl PyIntRef {
         }
     }
 
-    fn neg(self, _vm: &VirtualMachine) -> BigInt {
-        -(&self.value)
+    fn neg(self, vm: &VirtualMachine) -> PyObjectRef {
+        match self.as_i64().and_then(i64::checked_neg) {
+            Some(result) => vm.ctx.new_int(result),
+            None => vm.ctx.new_int(-&*self.as_bigint()),
+        }
     }
 
     fn hash(self, _vm: &VirtualMachine) -> u64 {
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        self.value.hash(&mut hasher);
+        // Hashed as an i64 whenever the value fits in one, which spares small
+        // ints a BigInt, and keeps the hash the same however it is stored.
+        match self.to_i64() {
+            Some(value) => value.hash(&mut hasher),
+            None => self.as_bigint().hash(&mut hasher),
+        }
         hasher.finish()
     }
 
-    fn abs(self, _vm: &VirtualMachine) -> BigInt {
-        self.value.abs()
+    fn abs(self, vm: &VirtualMachine) -> PyObjectRef {
+        match self.as_i64().and_then(i64::checked_abs) {
+            Some(result) => vm.ctx.new_int(result),
+            None => vm.ctx.new_int(self.as_bigint().abs()),
+        }
     }
 
     fn round(self, _precision: OptionalArg<PyObjectRef>, _vm: &VirtualMachine) -> Self {
@@ -340,31 +474,34 @@ impl PyIntRef {
     }
 
     fn float(self, _vm: &VirtualMachine) -> f64 {
-        self.value.to_f64().unwrap()
+        self.to_f64().unwrap()
     }
 
-    fn invert(self, _vm: &VirtualMachine) -> BigInt {
-        !(&self.value)
+    fn invert(self, vm: &VirtualMachine) -> PyObjectRef {
+        match self.as_i64() {
+            Some(value) => vm.ctx.new_int(!value),
+            None => vm.ctx.new_int(!&*self.as_bigint()),
+        }
     }
 
     fn repr(self, _vm: &VirtualMachine) -> String {
-        self.value.to_string()
+        self.to_string()
     }
 
     fn format(self, spec: PyRef<objstr::PyString>, vm: &VirtualMachine) -> PyResult<String> {
         let format_spec = FormatSpec::parse(&spec.value);
-        match format_spec.format_int(&self.value) {
+        match format_spec.format_int(&self.as_bigint()) {
             Ok(string) => Ok(string),
             Err(err) => Err(vm.new_value_error(err.to_string())),
         }
     }
 
     fn bool(self, _vm: &VirtualMachine) -> bool {
-        !self.value.is_zero()
+        !self.is_zero()
     }
 
     fn bit_length(self, _vm: &VirtualMachine) -> usize {
-        self.value.bits()
+        self.as_bigint().bits()
     }
 
     fn imag(self, _vm: &VirtualMachine) -> usize {
@@ -372,6 +509,14 @@ impl PyIntRef {
     }
 }
 
+fn shift_error(vm: &VirtualMachine, shift: &PyInt) -> PyResult {
+    if shift.as_bigint().is_negative() {
+        Err(vm.new_value_error("negative shift count".to_string()))
+    } else {
+        Err(vm.new_overflow_error("the number is too large to convert to int".to_string()))
+    }
+}
+
 fn int_new(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(
         vm,
@@ -381,13 +526,16 @@ fn int_new(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     );
 
     let base = match args.get_optional_kwarg("base") {
-        Some(argument) => get_value(&argument).to_u32().unwrap(),
+        Some(argument) => get_int(&argument).to_u32().unwrap(),
         None => 10,
     };
     let val = match val_option {
         Some(val) => to_int(vm, val, base)?,
         None => Zero::zero(),
     };
+    if cls.is(&vm.ctx.int_type) {
+        return Ok(vm.ctx.new_int(val));
+    }
     Ok(PyInt::new(val)
         .into_ref_with_type(vm, cls.clone().downcast().unwrap())?
         .into_object())
@@ -396,7 +544,7 @@ fn int_new(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
 // Casting function:
 pub fn to_int(vm: &VirtualMachine, obj: &PyObjectRef, base: u32) -> PyResult<BigInt> {
     let val = if objtype::isinstance(obj, &vm.ctx.int_type()) {
-        get_value(obj).clone()
+        get_value(obj).into_owned()
     } else if objtype::isinstance(obj, &vm.ctx.float_type()) {
         objfloat::get_value(obj).to_bigint().unwrap()
     } else if objtype::isinstance(obj, &vm.ctx.str_type()) {
@@ -421,8 +569,12 @@ pub fn to_int(vm: &VirtualMachine, obj: &PyObjectRef, base: u32) -> PyResult<Big
 }
 
 // Retrieve inner int value:
-pub fn get_value(obj: &PyObjectRef) -> &BigInt {
-    &obj.payload::<PyInt>().unwrap().value
+pub fn get_value(obj: &PyObjectRef) -> Cow<BigInt> {
+    get_int(obj).as_bigint()
+}
+
+pub fn get_int(obj: &PyObjectRef) -> &PyInt {
+    obj.payload::<PyInt>().unwrap()
 }
 
 #[inline]
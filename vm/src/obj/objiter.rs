@@ -9,8 +9,10 @@ use crate::vm::VirtualMachine;
 use super::objbool;
 use super::objbytearray::PyByteArray;
 use super::objbytes::PyBytes;
+use super::objlist::PyList;
 use super::objrange::PyRange;
 use super::objsequence;
+use super::objtuple::PyTuple;
 use super::objtype;
 use crate::obj::objtype::PyClassRef;
 
@@ -67,7 +69,12 @@ pub fn get_all(vm: &VirtualMachine, iter_obj: &PyObjectRef) -> PyResult<Vec<PyOb
 
 pub fn new_stop_iteration(vm: &VirtualMachine) -> PyObjectRef {
     let stop_iteration_type = vm.ctx.exceptions.stop_iteration.clone();
-    vm.new_exception(stop_iteration_type, "End of iterator".to_string())
+    vm.new_empty_exception(stop_iteration_type).unwrap()
+}
+
+/// The value a generator returned, as carried by the StopIteration it raised.
+pub fn stop_iteration_value(vm: &VirtualMachine, exc: &PyObjectRef) -> PyResult {
+    vm.get_attribute(exc.clone(), "value")
 }
 
 fn contains(vm: &VirtualMachine, args: PyFuncArgs, iter_type: PyClassRef) -> PyResult {
@@ -154,7 +161,9 @@ fn iter_next(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
             } else {
                 Err(new_stop_iteration(vm))
             }
-        } else {
+        } else if iterated_obj_ref.payload_is::<PyList>()
+            || iterated_obj_ref.payload_is::<PyTuple>()
+        {
             let elements = objsequence::get_elements(iterated_obj_ref);
             if position.get() < elements.len() {
                 let obj_ref = elements[position.get()].clone();
@@ -163,6 +172,22 @@ fn iter_next(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
             } else {
                 Err(new_stop_iteration(vm))
             }
+        } else {
+            // Any other object is indexed until it raises IndexError.
+            let index = vm.ctx.new_int(position.get());
+            match vm.call_method(iterated_obj_ref, "__getitem__", vec![index]) {
+                Ok(obj_ref) => {
+                    position.set(position.get() + 1);
+                    Ok(obj_ref)
+                }
+                Err(exc) => {
+                    if objtype::isinstance(&exc, &vm.ctx.exceptions.index_error) {
+                        Err(new_stop_iteration(vm))
+                    } else {
+                        Err(exc)
+                    }
+                }
+            }
         }
     } else {
         panic!("NOT IMPL");
@@ -5,7 +5,8 @@ use num_traits::ToPrimitive;
 
 use crate::function::{OptionalArg, PyFuncArgs};
 use crate::pyobject::{
-    IdProtocol, PyContext, PyIteratorValue, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
+    IdProtocol, IntoPyObject, PyContext, PyIterable, PyIteratorValue, PyObjectRef, PyRef, PyResult,
+    PyValue, TryFromObject, TypeProtocol,
 };
 use crate::vm::{ReprGuard, VirtualMachine};
 
@@ -15,6 +16,7 @@ use super::objsequence::{
     get_elements, get_elements_cell, get_item, seq_equal, seq_ge, seq_gt, seq_le, seq_lt, seq_mul,
     PySliceableSequence,
 };
+use super::objtuple::PyTuple;
 use super::objtype;
 use crate::obj::objtype::PyClassRef;
 
@@ -47,6 +49,38 @@ impl PyValue for PyList {
 
 pub type PyListRef = PyRef<PyList>;
 
+/// Takes any iterable, like `list()` does, so a `Vec<u8>` can be made of a
+/// bytes object too.
+impl<T: TryFromObject> TryFromObject for Vec<T> {
+    fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
+        if obj.payload_is::<PyList>() || obj.payload_is::<PyTuple>() {
+            // The elements are copied, as converting them may run code that
+            // changes the list.
+            let elements = get_elements(&obj).clone();
+            elements
+                .into_iter()
+                .map(|element| T::try_from_object(vm, element))
+                .collect()
+        } else {
+            PyIterable::<T>::try_from_object(vm, obj)?
+                .iter(vm)?
+                .collect()
+        }
+    }
+}
+
+/// Makes a list, a `Vec<u8>` too: return an `objbytes::Bytes` to make a bytes
+/// object.
+impl<T: IntoPyObject> IntoPyObject for Vec<T> {
+    fn into_pyobject(self, vm: &VirtualMachine) -> PyResult {
+        let elements = self
+            .into_iter()
+            .map(|element| element.into_pyobject(vm))
+            .collect::<PyResult<_>>()?;
+        Ok(vm.ctx.new_list(elements))
+    }
+}
+
 impl PyListRef {
     pub fn append(self, x: PyObjectRef, _vm: &VirtualMachine) {
         self.elements.borrow_mut().push(x);
@@ -435,6 +469,7 @@ pub fn init(context: &PyContext) {
         "__setitem__" => context.new_rustfunc(PyListRef::setitem),
         "__mul__" => context.new_rustfunc(PyListRef::mul),
         "__len__" => context.new_rustfunc(PyListRef::len),
+        "__hash__" => context.none(),
         "__new__" => context.new_rustfunc(list_new),
         "__repr__" => context.new_rustfunc(PyListRef::repr),
         "__doc__" => context.new_str(list_doc.to_string()),
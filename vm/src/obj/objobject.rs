@@ -1,8 +1,12 @@
+use std::cell::{Cell, RefCell};
+use std::mem;
+
+use super::objabc;
 use super::objdict::{self, PyDictRef};
 use super::objlist::PyList;
 use super::objstr::{self, PyStringRef};
 use super::objtype;
-use crate::function::PyFuncArgs;
+use crate::function::{KwArgs, PyFuncArgs};
 use crate::obj::objproperty::PropertyBuilder;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::{
@@ -11,8 +15,51 @@ use crate::pyobject::{
 };
 use crate::vm::VirtualMachine;
 
-#[derive(Debug)]
-pub struct PyInstance;
+#[derive(Debug, Default)]
+pub struct PyInstance {
+    /// Set once `__del__` has been scheduled for this instance, so that an
+    /// object resurrected by its finalizer is not finalized a second time.
+    finalized: Cell<bool>,
+    /// The values of the `__slots__` of the class, `None` while unset.
+    slots: RefCell<Vec<Option<PyObjectRef>>>,
+}
+
+impl PyInstance {
+    pub fn new(slot_count: usize) -> Self {
+        PyInstance {
+            finalized: Cell::new(false),
+            slots: RefCell::new(vec![None; slot_count]),
+        }
+    }
+
+    /// The finalized instance that takes over the slots of this one, which
+    /// is being dropped, to have its `__del__` called.
+    pub fn zombie(&self) -> Self {
+        PyInstance {
+            finalized: Cell::new(true),
+            slots: RefCell::new(mem::replace(&mut *self.slots.borrow_mut(), vec![])),
+        }
+    }
+
+    pub fn is_finalized(&self) -> bool {
+        self.finalized.get()
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.slots.borrow().len()
+    }
+
+    pub fn get_slot(&self, index: usize) -> Option<PyObjectRef> {
+        self.slots.borrow()[index].clone()
+    }
+
+    /// Set or clear a slot, returning its previous value.
+    pub fn set_slot(&self, index: usize, value: Option<PyObjectRef>) -> Option<PyObjectRef> {
+        // The previous value is dropped by the caller, once the slots are no
+        // longer borrowed.
+        mem::replace(&mut self.slots.borrow_mut()[index], value)
+    }
+}
 
 impl PyValue for PyInstance {
     fn class(vm: &VirtualMachine) -> PyClassRef {
@@ -23,12 +70,14 @@ impl PyValue for PyInstance {
 pub fn new_instance(vm: &VirtualMachine, mut args: PyFuncArgs) -> PyResult {
     // more or less __new__ operator
     let cls = PyClassRef::try_from_object(vm, args.shift())?;
-    let dict = if cls.is(&vm.ctx.object) {
-        None
-    } else {
+    objabc::check_instantiable(vm, &cls)?;
+    let dict = if cls.layout.has_dict {
         Some(vm.ctx.new_dict())
+    } else {
+        None
     };
-    Ok(PyObject::new(PyInstance, cls, dict))
+    let instance = PyInstance::new(cls.layout.slot_count);
+    Ok(PyObject::new(instance, cls, dict))
 }
 
 fn object_eq(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
@@ -115,7 +164,8 @@ fn object_setattr(
     }
 
     if let Some(ref dict) = obj.clone().dict {
-        dict.set_item(&vm.ctx, &attr_name.value, value);
+        let attr_name = vm.ctx.intern_string(attr_name);
+        objdict::set_item(dict.as_object(), vm, attr_name.as_object(), &value);
         Ok(())
     } else {
         Err(vm.new_attribute_error(format!(
@@ -186,6 +236,8 @@ pub fn init(context: &PyContext) {
     extend_class!(context, object, {
         "__new__" => context.new_rustfunc(new_instance),
         "__init__" => context.new_rustfunc(object_init),
+        "__init_subclass__" => context.new_classmethod(object_init_subclass),
+        "__subclasshook__" => context.new_classmethod(object_subclasshook),
         "__class__" =>
         PropertyBuilder::new(context)
             .add_getter(object_class)
@@ -214,6 +266,29 @@ fn object_init(vm: &VirtualMachine, _args: PyFuncArgs) -> PyResult {
     Ok(vm.ctx.none())
 }
 
+/// What `__init_subclass__` does unless a base class overrides it: nothing,
+/// and no class keyword arguments are expected.
+fn object_init_subclass(cls: PyClassRef, kwargs: KwArgs, vm: &VirtualMachine) -> PyResult<()> {
+    if kwargs.is_empty() {
+        Ok(())
+    } else {
+        Err(vm.new_type_error(format!(
+            "{}.__init_subclass__() takes no keyword arguments",
+            cls.name
+        )))
+    }
+}
+
+/// What `ABCMeta.__subclasscheck__` asks a class before it looks at the
+/// bases: no opinion.
+fn object_subclasshook(
+    _cls: PyClassRef,
+    _subclass: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyObjectRef {
+    vm.ctx.not_implemented()
+}
+
 fn object_class(obj: PyObjectRef, _vm: &VirtualMachine) -> PyObjectRef {
     obj.class().into_object()
 }
@@ -231,7 +306,10 @@ fn object_dict(object: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyDictRef>
     if let Some(ref dict) = object.dict {
         Ok(dict.clone())
     } else {
-        Err(vm.new_type_error("TypeError: no dictionary.".to_string()))
+        Err(vm.new_attribute_error(format!(
+            "'{}' object has no attribute '__dict__'",
+            object.class().name
+        )))
     }
 }
 
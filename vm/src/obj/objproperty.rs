@@ -4,6 +4,7 @@
 
 use crate::function::IntoPyNativeFunc;
 use crate::function::OptionalArg;
+use crate::obj::objabc;
 use crate::obj::objstr::PyStringRef;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::{
@@ -131,6 +132,15 @@ impl PyPropertyRef {
         self.deleter.clone()
     }
 
+    fn is_abstract(self, vm: &VirtualMachine) -> PyResult<bool> {
+        for accessor in self.getter.iter().chain(&self.setter).chain(&self.deleter) {
+            if objabc::is_abstract(vm, accessor)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     // Python builder functions
 
     fn getter(self, getter: Option<PyObjectRef>, vm: &VirtualMachine) -> PyResult<Self> {
@@ -185,7 +195,7 @@ impl<'a> PropertyBuilder<'a> {
         }
     }
 
-    pub fn add_setter<I, V, F: IntoPyNativeFunc<(I, V), PyResult>>(self, func: F) -> Self {
+    pub fn add_setter<I, V, R, F: IntoPyNativeFunc<(I, V), R>>(self, func: F) -> Self {
         let func = self.ctx.new_rustfunc(func);
         Self {
             ctx: self.ctx,
@@ -259,6 +269,7 @@ pub fn init(context: &PyContext) {
         "fget" => context.new_property(PyPropertyRef::fget),
         "fset" => context.new_property(PyPropertyRef::fset),
         "fdel" => context.new_property(PyPropertyRef::fdel),
+        "__isabstractmethod__" => context.new_property(PyPropertyRef::is_abstract),
 
         "getter" => context.new_rustfunc(PyPropertyRef::getter),
         "setter" => context.new_rustfunc(PyPropertyRef::setter),
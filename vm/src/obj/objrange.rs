@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::cell::Cell;
 use std::ops::Mul;
 
@@ -27,21 +28,29 @@ impl PyValue for PyRange {
 }
 
 impl PyRange {
+    #[inline]
+    fn bounds(&self) -> (Cow<BigInt>, Cow<BigInt>, Cow<BigInt>) {
+        (
+            self.start.as_bigint(),
+            self.stop.as_bigint(),
+            self.step.as_bigint(),
+        )
+    }
+
     #[inline]
     fn offset(&self, value: &BigInt) -> Option<BigInt> {
-        let start = self.start.as_bigint();
-        let stop = self.stop.as_bigint();
-        let step = self.step.as_bigint();
+        let (start, stop, step) = self.bounds();
+        let (start, stop, step) = (&*start, &*stop, &*step);
         match step.sign() {
             Sign::Plus if value >= start && value < stop => Some(value - start),
-            Sign::Minus if value <= self.start.as_bigint() && value > stop => Some(start - value),
+            Sign::Minus if value <= start && value > stop => Some(start - value),
             _ => None,
         }
     }
 
     #[inline]
     pub fn index_of(&self, value: &BigInt) -> Option<BigInt> {
-        let step = self.step.as_bigint();
+        let step = &*self.step.as_bigint();
         match self.offset(value) {
             Some(ref offset) if offset.is_multiple_of(step) => Some((offset / step).abs()),
             Some(_) | None => None,
@@ -50,9 +59,8 @@ impl PyRange {
 
     #[inline]
     pub fn is_empty(&self) -> bool {
-        let start = self.start.as_bigint();
-        let stop = self.stop.as_bigint();
-        let step = self.step.as_bigint();
+        let (start, stop, step) = self.bounds();
+        let (start, stop, step) = (&*start, &*stop, &*step);
         (start <= stop && step.is_negative()) || (start >= stop && step.is_positive())
     }
 
@@ -62,13 +70,12 @@ impl PyRange {
     }
 
     #[inline]
-    pub fn get<'a, T>(&'a self, index: T) -> Option<BigInt>
+    pub fn get<T>(&self, index: T) -> Option<BigInt>
     where
-        &'a BigInt: Mul<T, Output = BigInt>,
+        for<'a> &'a BigInt: Mul<T, Output = BigInt>,
     {
-        let start = self.start.as_bigint();
-        let stop = self.stop.as_bigint();
-        let step = self.step.as_bigint();
+        let (start, stop, step) = self.bounds();
+        let (start, stop, step) = (&*start, &*stop, &*step);
 
         let result = start + step * index;
 
@@ -164,9 +171,8 @@ impl PyRangeRef {
     }
 
     fn reversed(self: PyRangeRef, vm: &VirtualMachine) -> PyIteratorValue {
-        let start = self.start.as_bigint();
-        let stop = self.stop.as_bigint();
-        let step = self.step.as_bigint();
+        let (start, stop, step) = self.bounds();
+        let (start, stop, step) = (&*start, &*stop, &*step);
 
         // compute the last element that is actually contained within the range
         // this is the new start
@@ -196,9 +202,8 @@ impl PyRangeRef {
     }
 
     fn len(self, _vm: &VirtualMachine) -> PyInt {
-        let start = self.start.as_bigint();
-        let stop = self.stop.as_bigint();
-        let step = self.step.as_bigint();
+        let (start, stop, step) = self.bounds();
+        let (start, stop, step) = (&*start, &*stop, &*step);
 
         match step.sign() {
             Sign::Plus if start < stop => PyInt::new((stop - start - 1usize) / step + 1),
@@ -222,8 +227,8 @@ impl PyRangeRef {
 
     fn contains(self, needle: PyObjectRef, _vm: &VirtualMachine) -> bool {
         if let Ok(int) = needle.downcast::<PyInt>() {
-            match self.offset(int.as_bigint()) {
-                Some(ref offset) => offset.is_multiple_of(self.step.as_bigint()),
+            match self.offset(&int.as_bigint()) {
+                Some(ref offset) => offset.is_multiple_of(&self.step.as_bigint()),
                 None => false,
             }
         } else {
@@ -233,7 +238,7 @@ impl PyRangeRef {
 
     fn index(self, needle: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyInt> {
         if let Ok(int) = needle.downcast::<PyInt>() {
-            match self.index_of(int.as_bigint()) {
+            match self.index_of(&int.as_bigint()) {
                 Some(idx) => Ok(PyInt::new(idx)),
                 None => Err(vm.new_value_error(format!("{} is not in range", int))),
             }
@@ -244,7 +249,7 @@ impl PyRangeRef {
 
     fn count(self, item: PyObjectRef, _vm: &VirtualMachine) -> PyInt {
         if let Ok(int) = item.downcast::<PyInt>() {
-            if self.index_of(int.as_bigint()).is_some() {
+            if self.index_of(&int.as_bigint()).is_some() {
                 PyInt::new(1)
             } else {
                 PyInt::new(0)
@@ -257,7 +262,7 @@ impl PyRangeRef {
     fn getitem(self, subscript: Either<PyIntRef, PySliceRef>, vm: &VirtualMachine) -> PyResult {
         match subscript {
             Either::A(index) => {
-                if let Some(value) = self.get(index.as_bigint()) {
+                if let Some(value) = self.get(&*index.as_bigint()) {
                     Ok(PyInt::new(value).into_ref(vm).into_object())
                 } else {
                     Err(vm.new_index_error("range object index out of range".to_string()))
@@ -285,7 +290,7 @@ impl PyRangeRef {
                 };
 
                 let new_step = if let Some(int) = slice.step.as_ref() {
-                    PyInt::new(int * self.step.as_bigint()).into_ref(vm)
+                    PyInt::new(int * &*self.step.as_bigint()).into_ref(vm)
                 } else {
                     self.step.clone()
                 };
@@ -304,10 +309,10 @@ impl PyRangeRef {
 
 fn range_new(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let range = if args.args.len() <= 2 {
-        let (cls, stop) = args.bind(vm)?;
+        let (cls, stop) = args.bind_params(vm)?;
         PyRangeRef::new(cls, stop, vm)
     } else {
-        let (cls, start, stop, step) = args.bind(vm)?;
+        let (cls, start, stop, step) = args.bind_params(vm)?;
         PyRangeRef::new_from(cls, start, stop, step, vm)
     }?;
 
@@ -5,7 +5,7 @@ use std::ops::{Deref, DerefMut, Range};
 use num_bigint::BigInt;
 use num_traits::{One, Signed, ToPrimitive, Zero};
 
-use crate::pyobject::{IdProtocol, PyObject, PyObjectRef, PyResult, TypeProtocol};
+use crate::pyobject::{IdProtocol, PyObject, PyObjectRef, PyResult};
 use crate::vm::VirtualMachine;
 
 use super::objbool;
@@ -163,13 +163,13 @@ pub fn get_item(
         if sequence.payload::<PyList>().is_some() {
             Ok(PyObject::new(
                 PyList::from(elements.to_vec().get_slice_items(vm, &subscript)?),
-                sequence.class(),
+                vm.ctx.list_type(),
                 None,
             ))
         } else if sequence.payload::<PyTuple>().is_some() {
             Ok(PyObject::new(
                 PyTuple::from(elements.to_vec().get_slice_items(vm, &subscript)?),
-                sequence.class(),
+                vm.ctx.tuple_type(),
                 None,
             ))
         } else {
@@ -3,13 +3,14 @@
  */
 
 use std::cell::{Cell, RefCell};
-use std::collections::{hash_map::DefaultHasher, HashMap};
+use std::collections::{hash_map::DefaultHasher, HashMap, HashSet};
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
 use crate::function::{OptionalArg, PyFuncArgs};
 use crate::pyobject::{
-    PyContext, PyIteratorValue, PyObject, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
+    IntoPyObject, PyContext, PyIteratorValue, PyObject, PyObjectRef, PyRef, PyResult, PyValue,
+    TryFromObject, TypeProtocol,
 };
 use crate::vm::{ReprGuard, VirtualMachine};
 
@@ -57,6 +58,23 @@ impl PyValue for PyFrozenSet {
     }
 }
 
+/// Takes any iterable, like `set()` does.
+impl<T: TryFromObject + Eq + Hash> TryFromObject for HashSet<T> {
+    fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
+        Ok(Vec::<T>::try_from_object(vm, obj)?.into_iter().collect())
+    }
+}
+
+impl<T: IntoPyObject> IntoPyObject for HashSet<T> {
+    fn into_pyobject(self, vm: &VirtualMachine) -> PyResult {
+        let mut elements = HashMap::new();
+        for item in self {
+            insert_into_set(vm, &mut elements, &item.into_pyobject(vm)?)?;
+        }
+        create_set(vm, elements, vm.ctx.set_type())
+    }
+}
+
 pub fn get_elements(obj: &PyObjectRef) -> HashMap<u64, PyObjectRef> {
     if let Some(set) = obj.payload::<PySet>() {
         return set.elements.borrow().clone();
@@ -105,7 +123,7 @@ fn perform_action_with_hash(
     item: &PyObjectRef,
     f: &Fn(&VirtualMachine, &mut HashMap<u64, PyObjectRef>, u64, &PyObjectRef) -> PyResult,
 ) -> PyResult {
-    let hash: PyObjectRef = vm.call_method(item, "__hash__", vec![])?;
+    let hash: PyObjectRef = vm._hash(item)?;
 
     let hash_value = objint::get_value(&hash);
     let mut hasher = DefaultHasher::new();
@@ -160,10 +178,7 @@ fn set_remove(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
                 value: &PyObjectRef,
             ) -> PyResult {
                 match elements.remove(&key) {
-                    None => {
-                        let item_str = format!("{:?}", value);
-                        Err(vm.new_key_error(item_str))
-                    }
+                    None => Err(vm.new_key_error(value.clone())),
                     Some(_) => Ok(vm.get_none()),
                 }
             }
@@ -450,7 +465,7 @@ fn set_pop(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
             let mut elements = set.elements.borrow_mut();
             match elements.clone().keys().next() {
                 Some(key) => Ok(elements.remove(key).unwrap()),
-                None => Err(vm.new_key_error("pop from an empty set".to_string())),
+                None => Err(vm.new_key_error(vm.new_str("pop from an empty set".to_string()))),
             }
         }
         _ => Err(vm.new_type_error("".to_string())),
@@ -598,6 +613,7 @@ pub fn init(context: &PyContext) {
     extend_class!(context, set_type, {
         "__contains__" => context.new_rustfunc(set_contains),
         "__len__" => context.new_rustfunc(set_len),
+        "__hash__" => context.none(),
         "__new__" => context.new_rustfunc(set_new),
         "__repr__" => context.new_rustfunc(set_repr),
         "__eq__" => context.new_rustfunc(set_eq),
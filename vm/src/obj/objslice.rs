@@ -58,9 +58,9 @@ fn slice_new(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         }
     }?;
     PySlice {
-        start: start.map(|x| objint::get_value(x).clone()),
-        stop: stop.map(|x| objint::get_value(x).clone()),
-        step: step.map(|x| objint::get_value(x).clone()),
+        start: start.map(|x| objint::get_value(x).into_owned()),
+        stop: stop.map(|x| objint::get_value(x).into_owned()),
+        step: step.map(|x| objint::get_value(x).into_owned()),
     }
     .into_ref_with_type(vm, cls.clone().downcast().unwrap())
     .map(|x| x.into_object())
@@ -0,0 +1,301 @@
+//! `__slots__`, which give the instances of a class a fixed set of
+//! attributes in place of a `__dict__`.
+//!
+//! The values live in the `PyInstance` payload, one slot per name, and are
+//! read and written through the `member_descriptor` each name gets in the
+//! class. Subclasses append their slots after those of their base, so the
+//! bases of a class must agree on which slots come first.
+
+use crate::function::OptionalArg;
+use crate::obj::objbuiltinfunc::PyBuiltinFunction;
+use crate::obj::objobject::PyInstance;
+use crate::obj::objstr;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::obj::objweakref::PyWeak;
+use crate::pyobject::{
+    IdProtocol, PyAttributes, PyClassImpl, PyContext, PyObjectRef, PyRef, PyResult, PyValue,
+    TypeProtocol,
+};
+use crate::vm::VirtualMachine;
+
+/// What the instances of a class hold besides their payload.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstanceLayout {
+    /// The number of slots, counting those of the base classes.
+    pub slot_count: usize,
+    /// Whether the instances have a `__dict__`.
+    pub has_dict: bool,
+    /// Whether the instances can be weakly referenced.
+    pub has_weakref: bool,
+}
+
+impl InstanceLayout {
+    /// The layout of `object` instances, which have no attributes at all.
+    pub fn object() -> Self {
+        InstanceLayout {
+            slot_count: 0,
+            has_dict: false,
+            has_weakref: false,
+        }
+    }
+
+    /// The layout of a class without `__slots__`: the slots of its bases,
+    /// and a `__dict__`.
+    pub fn with_dict(bases: &[PyClassRef]) -> Self {
+        InstanceLayout {
+            slot_count: bases
+                .iter()
+                .map(|base| base.layout.slot_count)
+                .max()
+                .unwrap_or(0),
+            has_dict: true,
+            has_weakref: true,
+        }
+    }
+}
+
+/// The class that added the last slot of `class`, or `object` when it has
+/// no slots. The instances of its subclasses start with the same slots.
+fn solid_base(class: &PyClassRef) -> &PyClassRef {
+    let slot_count = class.layout.slot_count;
+    std::iter::once(class)
+        .chain(class.mro.iter())
+        .filter(|base| base.layout.slot_count == slot_count)
+        .last()
+        .unwrap_or(class)
+}
+
+/// The base whose slots the instances of a class deriving from `bases`
+/// start with.
+fn layout_base<'a>(vm: &VirtualMachine, bases: &'a [PyClassRef]) -> PyResult<&'a PyClassRef> {
+    let best = bases
+        .iter()
+        .max_by_key(|base| base.layout.slot_count)
+        .expect("a class has at least one base");
+    for base in bases {
+        if !objtype::issubclass(best, solid_base(base)) {
+            return Err(
+                vm.new_type_error("multiple bases have instance lay-out conflict".to_string())
+            );
+        }
+    }
+    Ok(best)
+}
+
+/// The native class, like `int`, the instances of a class deriving from
+/// `bases` get their payload from. Only `object` and the exceptions make a
+/// `PyInstance`, which is where the slots live.
+fn native_base<'a>(vm: &VirtualMachine, bases: &'a [PyClassRef]) -> Option<&'a PyClassRef> {
+    let object = vm.ctx.object();
+    let base_exception = &vm.ctx.exceptions.base_exception_type;
+    bases
+        .iter()
+        .flat_map(|base| std::iter::once(base).chain(base.mro.iter()))
+        .filter(|class| !class.is(&object) && !objtype::issubclass(class, base_exception))
+        .find(|class| match class.attributes.borrow().get("__new__") {
+            Some(new) => new.payload_is::<PyBuiltinFunction>(),
+            None => false,
+        })
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first == '_' || first.is_alphabetic() => {
+            chars.all(|c| c == '_' || c.is_alphanumeric())
+        }
+        _ => false,
+    }
+}
+
+/// The names in the `__slots__` of a class, which may be a single string.
+fn slot_names(vm: &VirtualMachine, slots: &PyObjectRef) -> PyResult<Vec<String>> {
+    let items = if objtype::isinstance(slots, &vm.ctx.str_type()) {
+        vec![slots.clone()]
+    } else {
+        vm.extract_elements(slots)?
+    };
+    items
+        .iter()
+        .map(|item| {
+            if !objtype::isinstance(item, &vm.ctx.str_type()) {
+                return Err(vm.new_type_error(format!(
+                    "__slots__ items must be strings, not '{}'",
+                    item.class().name
+                )));
+            }
+            let name = objstr::get_value(item);
+            if is_identifier(&name) {
+                Ok(name)
+            } else {
+                Err(vm.new_type_error("__slots__ must be identifiers".to_string()))
+            }
+        })
+        .collect()
+}
+
+/// Lay out the instances of a new class, from its bases and the
+/// `__slots__` in its `attributes`. Also returns the names of the slots the
+/// class adds, with their index, for `add_members`.
+pub fn new_layout(
+    vm: &VirtualMachine,
+    bases: &[PyClassRef],
+    attributes: &PyAttributes,
+) -> PyResult<(InstanceLayout, Vec<(String, usize)>)> {
+    let base = layout_base(vm, bases)?;
+    let slots = match attributes.get("__slots__") {
+        Some(slots) => slot_names(vm, slots)?,
+        None => return Ok((InstanceLayout::with_dict(bases), vec![])),
+    };
+
+    let mut layout = InstanceLayout {
+        slot_count: base.layout.slot_count,
+        has_dict: bases.iter().any(|base| base.layout.has_dict),
+        has_weakref: bases.iter().any(|base| base.layout.has_weakref),
+    };
+    let mut members: Vec<(String, usize)> = vec![];
+    for name in slots {
+        if name == "__dict__" {
+            if layout.has_dict {
+                return Err(
+                    vm.new_type_error("__dict__ slot disallowed: we already got one".to_string())
+                );
+            }
+            layout.has_dict = true;
+        } else if name == "__weakref__" {
+            if layout.has_weakref {
+                return Err(vm.new_type_error(
+                    "__weakref__ slot disallowed: we already got one".to_string(),
+                ));
+            }
+            layout.has_weakref = true;
+        } else if attributes.contains_key(&name) {
+            return Err(vm.new_value_error(format!(
+                "'{}' in __slots__ conflicts with class variable",
+                name
+            )));
+        } else if !members.iter().any(|(member, _)| *member == name) {
+            members.push((name, layout.slot_count));
+            layout.slot_count += 1;
+        }
+    }
+    if !members.is_empty() {
+        if let Some(native) = native_base(vm, bases) {
+            return Err(vm.new_type_error(format!(
+                "nonempty __slots__ not supported for subtype of '{}'",
+                native.name
+            )));
+        }
+    }
+    Ok((layout, members))
+}
+
+/// Give `class` a member descriptor for each of the slots it added.
+pub fn add_members(vm: &VirtualMachine, class: &PyClassRef, members: Vec<(String, usize)>) {
+    for (name, index) in members {
+        let member = PyMemberDescriptor {
+            name: name.clone(),
+            index,
+            class: PyWeak::downgrade(class.as_object()),
+            class_name: class.name.clone(),
+        };
+        class.set_attr(&name, member.into_ref(vm).into_object());
+    }
+}
+
+/// The attribute of the instances that a slot holds.
+#[pyclass(name = "member_descriptor")]
+#[derive(Debug)]
+pub struct PyMemberDescriptor {
+    name: String,
+    index: usize,
+    /// The class that declared the slot, which in turn holds the descriptor.
+    class: PyWeak,
+    class_name: String,
+}
+
+pub type PyMemberDescriptorRef = PyRef<PyMemberDescriptor>;
+
+impl PyValue for PyMemberDescriptor {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.ctx.member_descriptor_type()
+    }
+}
+
+impl PyMemberDescriptor {
+    /// The payload holding the slots of `obj`, if the slot is one of its.
+    fn instance<'a>(&self, obj: &'a PyObjectRef, vm: &VirtualMachine) -> PyResult<&'a PyInstance> {
+        let applies = match self.class.upgrade() {
+            Some(class) => objtype::issubclass(&obj.class(), &class.downcast().unwrap()),
+            None => true,
+        };
+        match obj.payload::<PyInstance>() {
+            Some(instance) if applies && self.index < instance.slot_count() => Ok(instance),
+            _ => Err(vm.new_type_error(format!(
+                "descriptor '{}' for '{}' objects doesn't apply to a '{}' object",
+                self.name,
+                self.class_name,
+                obj.class().name
+            ))),
+        }
+    }
+
+    fn missing(&self, obj: &PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+        vm.new_attribute_error(format!(
+            "'{}' object has no attribute '{}'",
+            obj.class().name,
+            self.name
+        ))
+    }
+}
+
+#[pymethods]
+impl PyMemberDescriptorRef {
+    #[pymethod(magic)]
+    fn get(
+        self,
+        obj: PyObjectRef,
+        _owner: OptionalArg<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        if obj.is(&vm.get_none()) {
+            return Ok(self.into_object());
+        }
+        self.instance(&obj, vm)?
+            .get_slot(self.index)
+            .ok_or_else(|| self.missing(&obj, vm))
+    }
+
+    #[pymethod(magic)]
+    fn set(self, obj: PyObjectRef, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        self.instance(&obj, vm)?.set_slot(self.index, Some(value));
+        Ok(())
+    }
+
+    #[pymethod(magic)]
+    fn delete(self, obj: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        match self.instance(&obj, vm)?.set_slot(self.index, None) {
+            Some(_) => Ok(()),
+            None => Err(self.missing(&obj, vm)),
+        }
+    }
+
+    #[pyproperty(name = "__name__")]
+    fn name(self, _vm: &VirtualMachine) -> String {
+        self.name.clone()
+    }
+
+    #[pyproperty(name = "__objclass__")]
+    fn objclass(self, vm: &VirtualMachine) -> PyObjectRef {
+        self.class.upgrade().unwrap_or_else(|| vm.get_none())
+    }
+
+    #[pymethod(magic)]
+    fn repr(self, _vm: &VirtualMachine) -> String {
+        format!("<member '{}' of '{}' objects>", self.name, self.class_name)
+    }
+}
+
+pub fn init(context: &PyContext) {
+    PyMemberDescriptorRef::extend_class(context, &context.member_descriptor_type);
+}
@@ -1,3 +1,4 @@
+use super::objabc;
 use super::objtype::PyClassRef;
 use crate::pyobject::{PyContext, PyObjectRef, PyRef, PyResult, PyValue};
 use crate::vm::VirtualMachine;
@@ -26,6 +27,10 @@ impl PyStaticMethodRef {
         .into_ref_with_type(vm, cls)
     }
 
+    fn is_abstract(self, vm: &VirtualMachine) -> PyResult<bool> {
+        objabc::is_abstract(vm, &self.callable)
+    }
+
     fn get(self, _inst: PyObjectRef, _owner: PyObjectRef, _vm: &VirtualMachine) -> PyResult {
         Ok(self.callable.clone())
     }
@@ -36,5 +41,6 @@ pub fn init(context: &PyContext) {
     extend_class!(context, staticmethod_type, {
         "__get__" => context.new_rustfunc(PyStaticMethodRef::get),
         "__new__" => context.new_rustfunc(PyStaticMethodRef::new),
+        "__isabstractmethod__" => context.new_property(PyStaticMethodRef::is_abstract),
     });
 }
@@ -0,0 +1,343 @@
+//! The text files `sys.stdin`, `sys.stdout` and `sys.stderr` start out as.
+//!
+//! They read from and write to the streams of the `VirtualMachine`, which an
+//! embedding application can replace with `InterpreterBuilder`.
+
+use std::cell::Cell;
+use std::io::{self, BufRead, Read, Write};
+use std::str;
+use std::sync::{Condvar, Mutex};
+use std::thread::{self, ThreadId};
+
+use crate::function::OptionalArg;
+use crate::obj::objiter;
+use crate::obj::objstr::PyStringRef;
+use crate::obj::objtype::PyClassRef;
+use crate::pyobject::{PyClassImpl, PyContext, PyRef, PyResult, PyValue};
+use crate::signal;
+use crate::vm::VirtualMachine;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StdStream {
+    Stdin,
+    Stdout,
+    Stderr,
+}
+
+/// Which thread is reading from the stdin of a VM. A read lets go of the GIL
+/// while it waits for input, so other threads wanting to read meanwhile
+/// have to wait for their turn.
+#[derive(Debug, Default)]
+pub struct StdinReader {
+    reader: Mutex<Option<ThreadId>>,
+    done: Condvar,
+}
+
+/// The turn of the calling thread to read from stdin, until dropped.
+pub(crate) struct ReadTurn<'a>(&'a StdinReader);
+
+impl Drop for ReadTurn<'_> {
+    fn drop(&mut self) {
+        *self.0.reader.lock().unwrap() = None;
+        self.0.done.notify_all();
+    }
+}
+
+impl StdinReader {
+    /// Take the turn to read, unless some thread is reading already.
+    pub(crate) fn try_take_turn(&self) -> Result<ReadTurn<'_>, ThreadId> {
+        let mut reader = self.reader.lock().unwrap();
+        match *reader {
+            Some(id) => Err(id),
+            None => {
+                *reader = Some(thread::current().id());
+                Ok(ReadTurn(self))
+            }
+        }
+    }
+
+    /// Wait, with the GIL released, until no other thread is reading. A
+    /// thread reading again before its read is done, say from a signal
+    /// handler, gets an error instead.
+    fn take_turn(&self, vm: &VirtualMachine) -> PyResult<ReadTurn<'_>> {
+        loop {
+            match self.try_take_turn() {
+                Ok(turn) => return Ok(turn),
+                Err(id) if id == thread::current().id() => {
+                    return Err(vm.new_runtime_error("reentrant call inside <stdin>".to_string()));
+                }
+                Err(_) => {}
+            }
+            vm.allow_threads(|| {
+                // Condition variables aren't woken up by signals.
+                let poll = signal::receives_interrupts();
+                let mut reader = self.reader.lock().unwrap();
+                while reader.is_some() && !(poll && signal::interrupt_pending()) {
+                    reader = self
+                        .done
+                        .wait_timeout(reader, signal::POLL_INTERVAL)
+                        .unwrap()
+                        .0;
+                }
+            });
+            signal::check_signals(vm)?;
+        }
+    }
+}
+
+/// Character and line based access to a standard stream of the interpreter.
+#[pyclass(name = "TextIOWrapper")]
+#[derive(Debug)]
+pub struct PyStdStream {
+    stream: StdStream,
+    closed: Cell<bool>,
+}
+
+pub type PyStdStreamRef = PyRef<PyStdStream>;
+
+impl PyValue for PyStdStream {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.ctx.std_stream_type()
+    }
+}
+
+impl PyStdStream {
+    pub fn new(stream: StdStream) -> Self {
+        PyStdStream {
+            stream,
+            closed: Cell::new(false),
+        }
+    }
+
+    fn check_open(&self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.closed.get() {
+            Err(vm.new_value_error("I/O operation on closed file.".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_readable(&self, vm: &VirtualMachine) -> PyResult<()> {
+        self.check_open(vm)?;
+        if self.stream == StdStream::Stdin {
+            Ok(())
+        } else {
+            Err(vm.new_os_error("not readable".to_string()))
+        }
+    }
+
+    /// Run `read` on the stdin of the VM, letting other threads run while it
+    /// waits for input.
+    fn read_with<T, F>(&self, vm: &VirtualMachine, read: F) -> PyResult<T>
+    where
+        F: FnOnce(&mut dyn BufRead) -> io::Result<T>,
+    {
+        self.check_readable(vm)?;
+        let _turn = vm.stdin_reader.take_turn(vm)?;
+        let mut stdin = vm.stdin.borrow_mut();
+        vm.allow_threads(|| read(&mut **stdin)).or_else(|err| {
+            // The read may have given up to let an interrupt be raised.
+            signal::check_signals(vm)?;
+            Err(vm.new_io_error(&err))
+        })
+    }
+
+    /// Read a line, including its newline, or an empty string at the end of
+    /// the stream.
+    fn read_line(&self, vm: &VirtualMachine) -> PyResult<String> {
+        self.read_with(vm, |stdin| {
+            let mut line = String::new();
+            stdin.read_line(&mut line)?;
+            Ok(line)
+        })
+    }
+}
+
+/// Read `size` characters, fewer only at the end of the stream.
+fn read_chars(input: &mut dyn BufRead, size: usize) -> io::Result<String> {
+    let mut bytes = vec![];
+    loop {
+        let missing = match str::from_utf8(&bytes) {
+            Ok(text) => size - text.chars().count(),
+            // The last character isn't complete yet.
+            Err(err) if err.error_len().is_none() => 1,
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        };
+        if missing == 0 {
+            break;
+        }
+        if input.take(missing as u64).read_to_end(&mut bytes)? == 0 {
+            break;
+        }
+    }
+    String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[pymethods]
+impl PyStdStreamRef {
+    #[pymethod]
+    fn write(self, text: PyStringRef, vm: &VirtualMachine) -> PyResult<usize> {
+        self.check_open(vm)?;
+        let mut output = match self.stream {
+            StdStream::Stdin => return Err(vm.new_os_error("not writable".to_string())),
+            StdStream::Stdout => vm.stdout(),
+            StdStream::Stderr => vm.stderr(),
+        };
+        output
+            .write_all(text.value.as_bytes())
+            .map_err(|err| vm.new_io_error(&err))?;
+        // Like CPython's stderr, which is line buffered even when it isn't a
+        // terminal.
+        if self.stream == StdStream::Stderr {
+            output.flush().map_err(|err| vm.new_io_error(&err))?;
+        }
+        Ok(text.value.chars().count())
+    }
+
+    #[pymethod]
+    fn flush(self, vm: &VirtualMachine) -> PyResult<()> {
+        self.check_open(vm)?;
+        let result = match self.stream {
+            StdStream::Stdin => Ok(()),
+            StdStream::Stdout => vm.stdout().flush(),
+            StdStream::Stderr => vm.stderr().flush(),
+        };
+        result.map_err(|err| vm.new_io_error(&err))
+    }
+
+    #[pymethod]
+    fn readline(self, vm: &VirtualMachine) -> PyResult<String> {
+        self.read_line(vm)
+    }
+
+    /// Read `size` characters, or everything up to the end of the stream if
+    /// `size` is missing or negative.
+    #[pymethod]
+    fn read(self, size: OptionalArg<Option<isize>>, vm: &VirtualMachine) -> PyResult<String> {
+        match size.into_option() {
+            Some(Some(size)) if size >= 0 => {
+                self.read_with(vm, |stdin| read_chars(stdin, size as usize))
+            }
+            _ => self.read_with(vm, |stdin| {
+                let mut text = String::new();
+                stdin.read_to_string(&mut text)?;
+                Ok(text)
+            }),
+        }
+    }
+
+    #[pymethod(magic)]
+    fn iter(self, vm: &VirtualMachine) -> PyResult<Self> {
+        self.check_open(vm)?;
+        Ok(self)
+    }
+
+    #[pymethod(magic)]
+    fn next(self, vm: &VirtualMachine) -> PyResult<String> {
+        let line = self.read_line(vm)?;
+        if line.is_empty() {
+            Err(objiter::new_stop_iteration(vm))
+        } else {
+            Ok(line)
+        }
+    }
+
+    #[pymethod]
+    fn fileno(self, _vm: &VirtualMachine) -> i32 {
+        match self.stream {
+            StdStream::Stdin => 0,
+            StdStream::Stdout => 1,
+            StdStream::Stderr => 2,
+        }
+    }
+
+    #[pymethod]
+    fn isatty(self, vm: &VirtualMachine) -> PyResult<bool> {
+        self.check_open(vm)?;
+        Ok(is_terminal(self.clone().fileno(vm)))
+    }
+
+    #[pymethod]
+    fn readable(self, vm: &VirtualMachine) -> PyResult<bool> {
+        self.check_open(vm)?;
+        Ok(self.stream == StdStream::Stdin)
+    }
+
+    #[pymethod]
+    fn writable(self, vm: &VirtualMachine) -> PyResult<bool> {
+        self.check_open(vm)?;
+        Ok(self.stream != StdStream::Stdin)
+    }
+
+    #[pymethod]
+    fn seekable(self, vm: &VirtualMachine) -> PyResult<bool> {
+        self.check_open(vm)?;
+        Ok(false)
+    }
+
+    #[pymethod]
+    fn close(self, _vm: &VirtualMachine) {
+        self.closed.set(true);
+    }
+
+    #[pyproperty]
+    fn closed(self, _vm: &VirtualMachine) -> bool {
+        self.closed.get()
+    }
+
+    #[pyproperty]
+    fn name(self, _vm: &VirtualMachine) -> String {
+        match self.stream {
+            StdStream::Stdin => "<stdin>",
+            StdStream::Stdout => "<stdout>",
+            StdStream::Stderr => "<stderr>",
+        }
+        .to_string()
+    }
+
+    #[pyproperty]
+    fn mode(self, _vm: &VirtualMachine) -> String {
+        match self.stream {
+            StdStream::Stdin => "r",
+            StdStream::Stdout | StdStream::Stderr => "w",
+        }
+        .to_string()
+    }
+
+    #[pyproperty]
+    fn encoding(self, _vm: &VirtualMachine) -> String {
+        "utf-8".to_string()
+    }
+
+    #[pyproperty]
+    fn errors(self, _vm: &VirtualMachine) -> String {
+        match self.stream {
+            StdStream::Stdin | StdStream::Stdout => "strict",
+            StdStream::Stderr => "backslashreplace",
+        }
+        .to_string()
+    }
+
+    #[pymethod(magic)]
+    fn repr(self, vm: &VirtualMachine) -> String {
+        format!(
+            "<_io.TextIOWrapper name='{}' mode='{}' encoding='utf-8'>",
+            self.clone().name(vm),
+            self.clone().mode(vm)
+        )
+    }
+}
+
+#[cfg(unix)]
+fn is_terminal(fd: i32) -> bool {
+    unsafe { libc::isatty(fd) != 0 }
+}
+
+#[cfg(not(unix))]
+fn is_terminal(_fd: i32) -> bool {
+    false
+}
+
+pub fn init(context: &PyContext) {
+    PyStdStreamRef::extend_class(context, &context.std_stream_type);
+}
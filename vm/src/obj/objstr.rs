@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::ops::Range;
@@ -24,9 +25,40 @@ use super::objtype::{self, PyClassRef};
 pub struct PyString {
     // TODO: shouldn't be public
     pub value: String,
+    /// The hash of `value`, computed the first time it is needed.
+    hash: Cell<Option<u64>>,
 }
 pub type PyStringRef = PyRef<PyString>;
 
+impl PyString {
+    pub fn new(value: String) -> Self {
+        PyString {
+            value,
+            hash: Cell::new(None),
+        }
+    }
+
+    /// The hash of the string, see `hash_str`. Interned strings, like the
+    /// names in code objects, only ever compute it once.
+    pub fn hash_value(&self) -> u64 {
+        match self.hash.get() {
+            Some(hash) => hash,
+            None => {
+                let hash = hash_str(&self.value);
+                self.hash.set(Some(hash));
+                hash
+            }
+        }
+    }
+}
+
+/// The hash of a str, as python sees it and as dicts use it.
+pub fn hash_str(value: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
 impl fmt::Display for PyString {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Display::fmt(&self.value, f)
@@ -35,16 +67,13 @@ impl fmt::Display for PyString {
 
 impl TryIntoRef<PyString> for String {
     fn try_into_ref(self, vm: &VirtualMachine) -> PyResult<PyRef<PyString>> {
-        Ok(PyString { value: self }.into_ref(vm))
+        Ok(PyString::new(self).into_ref(vm))
     }
 }
 
 impl TryIntoRef<PyString> for &str {
     fn try_into_ref(self, vm: &VirtualMachine) -> PyResult<PyRef<PyString>> {
-        Ok(PyString {
-            value: self.to_string(),
-        }
-        .into_ref(vm))
+        Ok(PyString::new(self.to_string()).into_ref(vm))
     }
 }
 
@@ -110,9 +139,7 @@ impl PyStringRef {
     }
 
     fn hash(self, _vm: &VirtualMachine) -> usize {
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        self.value.hash(&mut hasher);
-        hasher.finish() as usize
+        self.hash_value() as usize
     }
 
     fn len(self, _vm: &VirtualMachine) -> usize {
@@ -620,6 +647,12 @@ impl IntoPyObject for String {
     }
 }
 
+impl TryFromObject for String {
+    fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
+        Ok(PyStringRef::try_from_object(vm, obj)?.value.clone())
+    }
+}
+
 #[rustfmt::skip] // to avoid line splitting
 pub fn init(context: &PyContext) {
     let str_type = &context.str_type;
@@ -785,7 +818,7 @@ fn perform_format(
                 let result = match arguments.get_optional_kwarg(&keyword) {
                     Some(argument) => call_object_format(vm, argument.clone(), &format_spec)?,
                     None => {
-                        return Err(vm.new_key_error(format!("'{}'", keyword)));
+                        return Err(vm.new_key_error(vm.new_str(keyword.to_string())));
                     }
                 };
                 get_value(&result)
@@ -0,0 +1,41 @@
+//! Struct sequences, the tuples with named items like `sys.version_info`.
+
+use crate::obj::objtuple::{PyTuple, PyTupleRef};
+use crate::obj::objtype::PyClassRef;
+use crate::pyobject::{PyContext, PyObject, PyObjectRef, PyResult};
+use crate::vm::VirtualMachine;
+
+/// Make a subclass of tuple named `name`, whose items can also be read as
+/// the attributes `fields`.
+pub fn new_struct_sequence_type(
+    ctx: &PyContext,
+    name: &str,
+    fields: &'static [&'static str],
+) -> PyClassRef {
+    let class = ctx.new_class(name, ctx.tuple_type());
+    for (index, field) in fields.iter().enumerate() {
+        let getter = move |zelf: PyTupleRef, _vm: &VirtualMachine| -> PyObjectRef {
+            zelf.elements.borrow()[index].clone()
+        };
+        ctx.set_attr(&class, field, ctx.new_property(getter));
+    }
+
+    let class_name = name.to_string();
+    let repr = move |zelf: PyTupleRef, vm: &VirtualMachine| -> PyResult<String> {
+        let mut items = vec![];
+        for (field, value) in fields.iter().zip(zelf.elements.borrow().iter()) {
+            items.push(format!("{}={}", field, vm.to_repr(value)?.value));
+        }
+        Ok(format!("{}({})", class_name, items.join(", ")))
+    };
+    ctx.set_attr(&class, "__repr__", ctx.new_rustfunc(repr));
+    ctx.set_attr(&class, "n_fields", ctx.new_int(fields.len()));
+    ctx.set_attr(&class, "n_sequence_fields", ctx.new_int(fields.len()));
+    ctx.set_attr(&class, "n_unnamed_fields", ctx.new_int(0));
+    class
+}
+
+/// Make an instance of a struct sequence type, with a value for each field.
+pub fn new_struct_sequence(class: &PyClassRef, values: Vec<PyObjectRef>) -> PyObjectRef {
+    PyObject::new(PyTuple::from(values), class.clone(), None)
+}
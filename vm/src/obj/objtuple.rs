@@ -4,7 +4,8 @@ use std::hash::{Hash, Hasher};
 
 use crate::function::OptionalArg;
 use crate::pyobject::{
-    IdProtocol, PyContext, PyIteratorValue, PyObjectRef, PyRef, PyResult, PyValue,
+    IdProtocol, IntoPyObject, PyContext, PyIteratorValue, PyObjectRef, PyRef, PyResult, PyValue,
+    TryFromObject,
 };
 use crate::vm::{ReprGuard, VirtualMachine};
 
@@ -44,6 +45,102 @@ impl PyValue for PyTuple {
 
 pub type PyTupleRef = PyRef<PyTuple>;
 
+// Tuples convert to python tuples, and are made of any iterable of as many
+// items, the way `a, b = iterable` unpacks it.
+macro_rules! tuple_conversions {
+    ($(($n:tt, $T:ident)),+) => {
+        impl<$($T: TryFromObject),+> TryFromObject for ($($T,)+) {
+            fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
+                let elements = Vec::<PyObjectRef>::try_from_object(vm, obj)?;
+                let expected = [$(stringify!($T)),+].len();
+                if elements.len() != expected {
+                    return Err(vm.new_type_error(format!(
+                        "expected a sequence of {} items, got {}",
+                        expected,
+                        elements.len()
+                    )));
+                }
+                let mut elements = elements.into_iter();
+                Ok(($($T::try_from_object(vm, elements.next().unwrap())?,)+))
+            }
+        }
+
+        impl<$($T: IntoPyObject),+> IntoPyObject for ($($T,)+) {
+            fn into_pyobject(self, vm: &VirtualMachine) -> PyResult {
+                Ok(vm.ctx.new_tuple(vec![$(self.$n.into_pyobject(vm)?),+]))
+            }
+        }
+    };
+}
+
+tuple_conversions!((0, A));
+tuple_conversions!((0, A), (1, B));
+tuple_conversions!((0, A), (1, B), (2, C));
+tuple_conversions!((0, A), (1, B), (2, C), (3, D));
+tuple_conversions!((0, A), (1, B), (2, C), (3, D), (4, E));
+tuple_conversions!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F));
+tuple_conversions!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F), (6, G));
+tuple_conversions!(
+    (0, A),
+    (1, B),
+    (2, C),
+    (3, D),
+    (4, E),
+    (5, F),
+    (6, G),
+    (7, H)
+);
+tuple_conversions!(
+    (0, A),
+    (1, B),
+    (2, C),
+    (3, D),
+    (4, E),
+    (5, F),
+    (6, G),
+    (7, H),
+    (8, I)
+);
+tuple_conversions!(
+    (0, A),
+    (1, B),
+    (2, C),
+    (3, D),
+    (4, E),
+    (5, F),
+    (6, G),
+    (7, H),
+    (8, I),
+    (9, J)
+);
+tuple_conversions!(
+    (0, A),
+    (1, B),
+    (2, C),
+    (3, D),
+    (4, E),
+    (5, F),
+    (6, G),
+    (7, H),
+    (8, I),
+    (9, J),
+    (10, K)
+);
+tuple_conversions!(
+    (0, A),
+    (1, B),
+    (2, C),
+    (3, D),
+    (4, E),
+    (5, F),
+    (6, G),
+    (7, H),
+    (8, I),
+    (9, J),
+    (10, K),
+    (11, L)
+);
+
 impl PyTupleRef {
     fn lt(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.tuple_type()) {
@@ -133,7 +230,7 @@ impl PyTupleRef {
     fn hash(self, vm: &VirtualMachine) -> PyResult<u64> {
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
         for element in self.elements.borrow().iter() {
-            let hash_result = vm.call_method(element, "__hash__", vec![])?;
+            let hash_result = vm._hash(element)?;
             let element_hash = objint::get_value(&hash_result);
             element_hash.hash(&mut hasher);
         }
@@ -1,18 +1,22 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::fmt;
 
 use crate::function::{Args, KwArgs, PyFuncArgs};
+use crate::inline_cache;
 use crate::pyobject::{
     IdProtocol, PyAttributes, PyContext, PyIterable, PyObject, PyObjectRef, PyRef, PyResult,
     PyValue, TypeProtocol,
 };
 use crate::vm::VirtualMachine;
 
+use super::objclassmethod::PyClassMethod;
 use super::objdict;
 use super::objdict::PyDictRef;
+use super::objfunction::PyFunction;
 use super::objlist::PyList;
 use super::objproperty::PropertyBuilder;
+use super::objslots::{self, InstanceLayout};
 use super::objstr::PyStringRef;
 use super::objtuple::PyTuple;
 use super::objweakref::PyWeak;
@@ -23,6 +27,44 @@ pub struct PyClass {
     pub mro: Vec<PyClassRef>,
     pub subclasses: RefCell<Vec<PyWeak>>,
     pub attributes: RefCell<PyAttributes>,
+    pub version_tag: Cell<usize>,
+    /// What the instances hold besides their payload, see `objslots`.
+    pub layout: InstanceLayout,
+}
+
+impl PyClass {
+    /// Changes whenever the attributes of the class, or of one of its base
+    /// classes, change.
+    pub fn version_tag(&self) -> usize {
+        self.version_tag.get()
+    }
+
+    /// Set an attribute of the class, invalidating what was cached about it.
+    pub fn set_attr(&self, attr_name: &str, value: PyObjectRef) {
+        self.attributes
+            .borrow_mut()
+            .insert(attr_name.to_string(), value);
+        self.modified();
+    }
+
+    /// Remove an attribute of the class, returning it if it was there.
+    pub fn del_attr(&self, attr_name: &str) -> Option<PyObjectRef> {
+        let removed = self.attributes.borrow_mut().remove(attr_name);
+        self.modified();
+        removed
+    }
+
+    /// Give the class and all its subclasses new version tags.
+    fn modified(&self) {
+        self.version_tag.set(inline_cache::next_version_tag());
+        for subclass in self.subclasses.borrow().iter() {
+            if let Some(subclass) = subclass.upgrade() {
+                if let Some(subclass) = subclass.payload::<PyClass>() {
+                    subclass.modified();
+                }
+            }
+        }
+    }
 }
 
 impl fmt::Display for PyClass {
@@ -104,7 +146,12 @@ impl PyClassRef {
         format!("<class '{}'>", self.name)
     }
 
-    fn prepare(_name: PyStringRef, _bases: PyObjectRef, vm: &VirtualMachine) -> PyDictRef {
+    fn prepare(
+        _name: PyStringRef,
+        _bases: PyObjectRef,
+        _kwargs: KwArgs,
+        vm: &VirtualMachine,
+    ) -> PyDictRef {
         vm.ctx.new_dict()
     }
 
@@ -157,12 +204,27 @@ impl PyClassRef {
             }
         }
 
-        self.attributes
-            .borrow_mut()
-            .insert(attr_name.to_string(), value);
+        PyClass::set_attr(&self, &attr_name.value, value);
         Ok(())
     }
 
+    fn del_attr(self, attr_name: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
+        if let Some(attr) = class_get_attr(&self.class(), &attr_name.value) {
+            if let Some(descriptor) = class_get_attr(&attr.class(), "__delete__") {
+                vm.invoke(descriptor, vec![attr, self.into_object()])?;
+                return Ok(());
+            }
+        }
+
+        match PyClass::del_attr(&self, &attr_name.value) {
+            Some(_) => Ok(()),
+            None => Err(vm.new_attribute_error(format!(
+                "type object '{}' has no attribute '{}'",
+                self.name, attr_name.value
+            ))),
+        }
+    }
+
     fn subclasses(self, _vm: &VirtualMachine) -> PyList {
         let mut subclasses = self.subclasses.borrow_mut();
         subclasses.retain(|x| x.upgrade().is_some());
@@ -196,6 +258,7 @@ pub fn init(ctx: &PyContext) {
         "__prepare__" => ctx.new_rustfunc(PyClassRef::prepare),
         "__getattribute__" => ctx.new_rustfunc(PyClassRef::getattribute),
         "__setattr__" => ctx.new_rustfunc(PyClassRef::set_attr),
+        "__delattr__" => ctx.new_rustfunc(PyClassRef::del_attr),
         "__subclasses__" => ctx.new_rustfunc(PyClassRef::subclasses),
         "__getattribute__" => ctx.new_rustfunc(PyClassRef::getattribute),
         "__instancecheck__" => ctx.new_rustfunc(PyClassRef::instance_check),
@@ -228,28 +291,91 @@ pub fn type_new(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     if args.args.len() == 2 {
         Ok(args.args[1].class().into_object())
     } else if args.args.len() == 4 {
-        let (typ, name, bases, dict) = args.bind(vm)?;
-        type_new_class(vm, typ, name, bases, dict).map(|x| x.into_object())
+        let (typ, name, bases, dict, kwargs) = args.bind_params(vm)?;
+        type_new_class(vm, typ, name, bases, dict, kwargs).map(|x| x.into_object())
     } else {
         Err(vm.new_type_error(format!(": type_new: {:?}", args)))
     }
 }
 
+/// Create a class, then run the hooks of PEP 487: `__set_name__` on its
+/// attributes, and the `__init_subclass__` of its parent, which gets the
+/// keyword arguments of the class statement.
 pub fn type_new_class(
     vm: &VirtualMachine,
     typ: PyClassRef,
     name: PyStringRef,
     bases: PyIterable<PyClassRef>,
     dict: PyDictRef,
+    kwargs: KwArgs,
 ) -> PyResult<PyClassRef> {
     let mut bases: Vec<PyClassRef> = bases.iter(vm)?.collect::<Result<Vec<_>, _>>()?;
     bases.push(vm.ctx.object());
-    new(
-        typ.clone(),
-        &name.value,
-        bases,
-        objdict::py_dict_to_attributes(dict.as_object()),
-    )
+    let mut attributes = objdict::py_dict_to_attributes(dict.as_object());
+    // These two are class methods without being decorated as such.
+    for name in &["__init_subclass__", "__class_getitem__"] {
+        if let Some(attr) = attributes.get_mut(*name) {
+            if attr.payload_is::<PyFunction>() {
+                let callable = attr.clone();
+                *attr = PyClassMethod { callable }.into_ref(vm).into_object();
+            }
+        }
+    }
+    let (layout, members) = objslots::new_layout(vm, &bases, &attributes)?;
+    let class = new_with_layout(typ.clone(), &name.value, bases, attributes, layout)?;
+    objslots::add_members(vm, &class, members);
+
+    set_names(vm, &class, &dict)?;
+    init_subclass(vm, &class, kwargs)?;
+    Ok(class)
+}
+
+/// Tell the attributes of a new class that define `__set_name__` which
+/// class and name they were given, in the order `namespace`, the dict the
+/// class body ran in, got them.
+fn set_names(vm: &VirtualMachine, class: &PyClassRef, namespace: &PyDictRef) -> PyResult<()> {
+    let attributes: Vec<_> = {
+        let class_attributes = class.attributes.borrow();
+        objdict::get_elements(namespace.as_object())
+            .keys()
+            .filter_map(|name| {
+                let attr = class_attributes.get(name.as_str())?;
+                Some((name.as_str().to_string(), attr.clone()))
+            })
+            .collect()
+    };
+    for (name, attr) in attributes {
+        if class_has_attr(&attr.class(), "__set_name__") {
+            vm.call_method(
+                &attr,
+                "__set_name__",
+                vec![class.clone().into_object(), vm.new_str(name)],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Call `super(class, class).__init_subclass__(**kwargs)`.
+fn init_subclass(vm: &VirtualMachine, class: &PyClassRef, kwargs: KwArgs) -> PyResult<()> {
+    let init = class
+        .mro
+        .iter()
+        .find_map(|base| base.attributes.borrow().get("__init_subclass__").cloned());
+    let init = match init {
+        Some(init) => init,
+        None => return Ok(()),
+    };
+    let init = match class_get_attr(&init.class(), "__get__") {
+        Some(get) => vm.invoke(get, vec![init, vm.get_none(), class.clone().into_object()])?,
+        None => init,
+    };
+    let args = PyFuncArgs {
+        args: vec![],
+        kwargs: kwargs.into_iter().collect(),
+    };
+    vm.invoke(init, args)?;
+    Ok(())
 }
 
 pub fn type_call(class: PyClassRef, args: Args, kwargs: KwArgs, vm: &VirtualMachine) -> PyResult {
@@ -289,6 +415,18 @@ pub fn class_has_attr(class: &PyClassRef, attr_name: &str) -> bool {
             .any(|c| c.attributes.borrow().contains_key(attr_name))
 }
 
+/// Determines if instances of `class` may need their `__del__` method called.
+///
+/// This runs while objects are being dropped, possibly while one of the class
+/// dictionaries is mutably borrowed, so it answers `true` whenever it cannot
+/// look and leaves the final check to `VirtualMachine::run_finalizers`.
+pub fn class_may_have_finalizer(class: &PyClassRef) -> bool {
+    class.iter_mro().any(|c| match c.attributes.try_borrow() {
+        Ok(attributes) => attributes.contains_key("__del__"),
+        Err(_) => true,
+    })
+}
+
 pub fn get_attributes(cls: PyClassRef) -> PyAttributes {
     // Gather all members here:
     let mut attributes = PyAttributes::new();
@@ -352,6 +490,17 @@ pub fn new(
     name: &str,
     bases: Vec<PyClassRef>,
     dict: HashMap<String, PyObjectRef>,
+) -> PyResult<PyClassRef> {
+    let layout = InstanceLayout::with_dict(&bases);
+    new_with_layout(typ, name, bases, dict, layout)
+}
+
+fn new_with_layout(
+    typ: PyClassRef,
+    name: &str,
+    bases: Vec<PyClassRef>,
+    dict: HashMap<String, PyObjectRef>,
+    layout: InstanceLayout,
 ) -> PyResult<PyClassRef> {
     let mros = bases.iter().map(|x| _mro(&x)).collect();
     let mro = linearise_mro(mros).unwrap();
@@ -361,6 +510,8 @@ pub fn new(
             mro,
             subclasses: RefCell::new(vec![]),
             attributes: RefCell::new(dict),
+            version_tag: Cell::new(inline_cache::next_version_tag()),
+            layout,
         },
         dict: None,
         typ,
@@ -1,11 +1,13 @@
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::PyValue;
-use crate::pyobject::{PyContext, PyObject, PyObjectPayload, PyObjectRef, PyRef, PyResult};
+use crate::pyobject::{
+    PyContext, PyObject, PyObjectPayload, PyObjectRef, PyRef, PyResult, TypeProtocol,
+};
 use crate::vm::VirtualMachine;
 
 use std::rc::{Rc, Weak};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PyWeak {
     referent: Weak<PyObject<dyn PyObjectPayload>>,
 }
@@ -33,6 +35,12 @@ pub type PyWeakRef = PyRef<PyWeak>;
 impl PyWeakRef {
     // TODO callbacks
     fn create(cls: PyClassRef, referent: PyObjectRef, vm: &VirtualMachine) -> PyResult<Self> {
+        if !referent.class().layout.has_weakref {
+            return Err(vm.new_type_error(format!(
+                "cannot create weak reference to '{}' object",
+                referent.class().name
+            )));
+        }
         PyWeak::downgrade(&referent).into_ref_with_type(vm, cls)
     }
 
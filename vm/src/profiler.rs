@@ -0,0 +1,227 @@
+/*! A deterministic profiler, fed by the frame dispatch loop.
+
+While a profiler is installed in the virtual machine, every python frame that
+starts or resumes running and every line it starts executing is reported to
+it. It keeps per function and per line call counts and timings, and the time
+spent in each distinct call stack, which flamegraph tools can render.
+
+Only python code is profiled; time spent in builtin functions counts towards
+the python function calling them.
+
+*/
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use crate::frame::Frame;
+
+/// Identifies a function like pstats does: file name, first line and name.
+pub type FunctionKey = (String, usize, String);
+
+#[derive(Debug, Default, Clone)]
+pub struct FunctionStats {
+    /// How often the function was called, recursive calls included.
+    pub calls: usize,
+    /// How often the function was called while it was not already running.
+    pub primitive_calls: usize,
+    /// Time spent in the function itself, without the functions it called.
+    pub total_time: Duration,
+    /// Time spent in the function and everything it called.
+    pub cumulative_time: Duration,
+    /// How often each of the calling functions called it.
+    pub callers: HashMap<FunctionKey, usize>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct LineStats {
+    pub hits: usize,
+    pub time: Duration,
+}
+
+/// A function call that is still running.
+#[derive(Debug)]
+struct Call {
+    frame_id: usize,
+    key: FunctionKey,
+    start: Instant,
+    /// Time spent in the functions it called so far.
+    children: Duration,
+    line: usize,
+    line_start: Instant,
+}
+
+#[derive(Debug, Default)]
+pub struct Profiler {
+    functions: RefCell<HashMap<FunctionKey, FunctionStats>>,
+    lines: RefCell<HashMap<(String, usize), LineStats>>,
+    /// Time spent in the innermost function of each call stack.
+    stacks: RefCell<HashMap<Vec<FunctionKey>, Duration>>,
+    /// The running calls of each thread, innermost last.
+    calls: RefCell<HashMap<ThreadId, Vec<Call>>>,
+}
+
+/// A duration as a number of seconds.
+pub fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
+}
+
+fn frame_id(frame: &Frame) -> usize {
+    frame as *const Frame as usize
+}
+
+fn function_key(frame: &Frame) -> FunctionKey {
+    let code = &frame.code;
+    (
+        code.source_path.clone(),
+        code.first_line_number,
+        code.obj_name.clone(),
+    )
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler::default()
+    }
+
+    /// A frame starts or resumes running.
+    pub fn call(&self, frame: &Frame) {
+        let now = Instant::now();
+        let call = Call {
+            frame_id: frame_id(frame),
+            key: function_key(frame),
+            start: now,
+            children: Duration::default(),
+            line: 0,
+            line_start: now,
+        };
+        let mut calls = self.calls.borrow_mut();
+        calls
+            .entry(thread::current().id())
+            .or_insert_with(Vec::new)
+            .push(call);
+    }
+
+    /// The running frame starts executing a new line.
+    pub fn line(&self, frame: &Frame, line: usize) {
+        let now = Instant::now();
+        let mut calls = self.calls.borrow_mut();
+        let call = match calls
+            .get_mut(&thread::current().id())
+            .and_then(|calls| calls.last_mut())
+        {
+            Some(call) => call,
+            None => return,
+        };
+        if call.frame_id != frame_id(frame) {
+            // The frame was already running when profiling started.
+            return;
+        }
+        self.finish_line(call, now);
+        call.line = line;
+        call.line_start = now;
+        let mut lines = self.lines.borrow_mut();
+        let stats = lines.entry((call.key.0.clone(), line)).or_default();
+        stats.hits += 1;
+    }
+
+    /// A frame returns, raises or yields.
+    pub fn ret(&self, frame: &Frame) {
+        let now = Instant::now();
+        let mut calls = self.calls.borrow_mut();
+        let calls = match calls.get_mut(&thread::current().id()) {
+            Some(calls) => calls,
+            None => return,
+        };
+        match calls.last() {
+            Some(call) if call.frame_id == frame_id(frame) => {}
+            _ => return,
+        }
+        self.finish_call(calls, now);
+    }
+
+    /// Account for all calls that are still running, as if they returned
+    /// now. Used when profiling stops.
+    pub fn flush(&self) {
+        let now = Instant::now();
+        for calls in self.calls.borrow_mut().values_mut() {
+            while !calls.is_empty() {
+                self.finish_call(calls, now);
+            }
+        }
+    }
+
+    fn finish_line(&self, call: &Call, now: Instant) {
+        if call.line == 0 {
+            return;
+        }
+        let mut lines = self.lines.borrow_mut();
+        let stats = lines.entry((call.key.0.clone(), call.line)).or_default();
+        stats.time += now - call.line_start;
+    }
+
+    fn finish_call(&self, calls: &mut Vec<Call>, now: Instant) {
+        let call = calls.pop().unwrap();
+        self.finish_line(&call, now);
+        let elapsed = now - call.start;
+        let own_time = elapsed.checked_sub(call.children).unwrap_or_default();
+        let recursive = calls.iter().any(|outer| outer.key == call.key);
+
+        let mut stack: Vec<FunctionKey> = calls.iter().map(|outer| outer.key.clone()).collect();
+        stack.push(call.key.clone());
+        *self.stacks.borrow_mut().entry(stack).or_default() += own_time;
+
+        let mut functions = self.functions.borrow_mut();
+        let stats = functions.entry(call.key).or_default();
+        stats.calls += 1;
+        stats.total_time += own_time;
+        if !recursive {
+            stats.primitive_calls += 1;
+            stats.cumulative_time += elapsed;
+        }
+        if let Some(caller) = calls.last_mut() {
+            caller.children += elapsed;
+            *stats.callers.entry(caller.key.clone()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn clear(&self) {
+        self.functions.borrow_mut().clear();
+        self.lines.borrow_mut().clear();
+        self.stacks.borrow_mut().clear();
+        self.calls.borrow_mut().clear();
+    }
+
+    pub fn function_stats(&self) -> HashMap<FunctionKey, FunctionStats> {
+        self.functions.borrow().clone()
+    }
+
+    pub fn line_stats(&self) -> HashMap<(String, usize), LineStats> {
+        self.lines.borrow().clone()
+    }
+
+    /// Write the time spent in each call stack in the "collapsed" format of
+    /// flamegraph tools: the functions from outermost to innermost separated
+    /// by semicolons, then the number of microseconds.
+    pub fn write_collapsed<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let stacks = self.stacks.borrow();
+        let mut lines: Vec<(String, u128)> = stacks
+            .iter()
+            .map(|(stack, time)| {
+                let names: Vec<String> = stack
+                    .iter()
+                    .map(|(file, line, name)| format!("{} ({}:{})", name, file, line))
+                    .collect();
+                (names.join(";"), time.as_micros())
+            })
+            .filter(|(_, micros)| *micros > 0)
+            .collect();
+        lines.sort();
+        for (stack, micros) in lines {
+            writeln!(output, "{} {}", stack, micros)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1196 @@
+/*! Converting between python objects and anything serde supports.
+
+`PyObjectSerializer` and `PyObjectDeserializer` let serde formats read and
+write python objects, which is how the `json` module works. The other way
+around, `to_pyobject` and `from_pyobject` turn any `Serialize` rust value into
+a python object and back:
+
+```
+use rustpython_vm::{py_serde, VirtualMachine};
+use std::collections::HashMap;
+
+let vm = VirtualMachine::new();
+let mut scores = HashMap::new();
+scores.insert("alice".to_string(), vec![3, 5]);
+let obj = py_serde::to_pyobject(&vm, &scores).unwrap();
+let back: HashMap<String, Vec<i32>> = py_serde::from_pyobject(&vm, &obj).unwrap();
+assert_eq!(back, scores);
+```
+
+Python values map onto the serde data model as follows:
+
+| python                  | serde                                    |
+|-------------------------|------------------------------------------|
+| `None`                  | unit, `None` options and unit structs    |
+| `bool`, `int`, `float`  | bool, the integers up to 128 bits, f64   |
+| larger `int`            | str of its decimal digits                |
+| `str`                   | str, char and unit enum variants         |
+| `bytes`, `bytearray`    | bytes                                    |
+| `list`, `tuple`         | sequences; tuples and tuple structs become `tuple` |
+| `dict`                  | maps and structs                         |
+
+Other enum variants become a dict with the variant name as its only key.
+
+A `BigInt` field marked `#[serde(with = "rustpython_vm::py_serde::big_int")]`
+becomes an `int` of any size, and can be read from one.
+
+*/
+
+use std::error;
+use std::fmt;
+use std::vec;
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq};
+
+use crate::obj::objdict::PyDictRef;
+use crate::obj::objstr::{self, PyString};
+use crate::obj::objtype::{self, PyClassRef};
+use crate::obj::{objbool, objbytearray, objbytes, objdict, objfloat, objint, objsequence};
+use crate::pyobject::{DictProtocol, IdProtocol, PyObjectRef, PyResult, TypeProtocol};
+use crate::vm::VirtualMachine;
+
+/// Why a value couldn't be converted, raised as a `TypeError` by
+/// `to_pyobject` and `from_pyobject`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+/// The newtype struct `big_int` wraps the digits of an int in, so that
+/// `PySerializer` can tell them from a str.
+const BIG_INT: &str = "$rustpython::BigInt";
+
+/// Serialize a `BigInt` field as a python int, or as the str of its decimal
+/// digits in other formats, with
+/// `#[serde(with = "rustpython_vm::py_serde::big_int")]`.
+pub mod big_int {
+    use std::fmt;
+
+    use num_bigint::BigInt;
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::ser::Serializer;
+
+    use super::BIG_INT;
+
+    pub fn serialize<S: Serializer>(value: &BigInt, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(BIG_INT, &value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigInt, D::Error> {
+        deserializer.deserialize_newtype_struct(BIG_INT, BigIntVisitor)
+    }
+
+    struct BigIntVisitor;
+
+    impl<'de> Visitor<'de> for BigIntVisitor {
+        type Value = BigInt;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an integer or a str of its decimal digits")
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<BigInt, E> {
+            Ok(value.into())
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<BigInt, E> {
+            Ok(value.into())
+        }
+
+        fn visit_i128<E: de::Error>(self, value: i128) -> Result<BigInt, E> {
+            Ok(value.into())
+        }
+
+        fn visit_u128<E: de::Error>(self, value: u128) -> Result<BigInt, E> {
+            Ok(value.into())
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<BigInt, E> {
+            value
+                .parse()
+                .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+        }
+
+        fn visit_newtype_struct<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<BigInt, D::Error> {
+            // Formats that can't tell what comes next only have the digits.
+            if deserializer.is_human_readable() {
+                deserializer.deserialize_any(self)
+            } else {
+                deserializer.deserialize_str(self)
+            }
+        }
+    }
+}
+
+/// Convert a rust value into a python object.
+pub fn to_pyobject<T: Serialize + ?Sized>(vm: &VirtualMachine, value: &T) -> PyResult {
+    value
+        .serialize(PySerializer { vm })
+        .map_err(|err| vm.new_type_error(err.to_string()))
+}
+
+/// Convert a python object into a rust value.
+pub fn from_pyobject<T: DeserializeOwned>(vm: &VirtualMachine, obj: &PyObjectRef) -> PyResult<T> {
+    T::deserialize(PyDeserializer::new(vm, obj.clone()))
+        .map_err(|err| vm.new_type_error(err.to_string()))
+}
+
+// We need to have a VM available to serialise a PyObject based on its subclass, so we implement
+// PyObject serialisation via a proxy object which holds a reference to a VM
+pub struct PyObjectSerializer<'s> {
+    pyobject: &'s PyObjectRef,
+    vm: &'s VirtualMachine,
+}
+
+impl<'s> PyObjectSerializer<'s> {
+    pub fn new(vm: &'s VirtualMachine, pyobject: &'s PyObjectRef) -> Self {
+        PyObjectSerializer { pyobject, vm }
+    }
+
+    fn clone_with_object(&self, pyobject: &'s PyObjectRef) -> PyObjectSerializer {
+        PyObjectSerializer {
+            pyobject,
+            vm: self.vm,
+        }
+    }
+}
+
+impl<'s> Serialize for PyObjectSerializer<'s> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let serialize_seq_elements =
+            |serializer: S, elements: &Vec<PyObjectRef>| -> Result<S::Ok, S::Error> {
+                let mut seq = serializer.serialize_seq(Some(elements.len()))?;
+                for e in elements.iter() {
+                    seq.serialize_element(&self.clone_with_object(e))?;
+                }
+                seq.end()
+            };
+        let is_bytes = objtype::isinstance(self.pyobject, &self.vm.ctx.bytes_type())
+            || objtype::isinstance(self.pyobject, &self.vm.ctx.bytearray_type());
+        if objtype::isinstance(self.pyobject, &self.vm.ctx.str_type()) {
+            serializer.serialize_str(&objstr::get_value(&self.pyobject))
+        } else if objtype::isinstance(self.pyobject, &self.vm.ctx.float_type()) {
+            serializer.serialize_f64(objfloat::get_value(self.pyobject))
+        } else if objtype::isinstance(self.pyobject, &self.vm.ctx.bool_type()) {
+            serializer.serialize_bool(objbool::get_value(self.pyobject))
+        } else if objtype::isinstance(self.pyobject, &self.vm.ctx.int_type()) {
+            let v = objint::get_value(self.pyobject);
+            if let Some(v) = v.to_i64() {
+                serializer.serialize_i64(v)
+            } else if let Some(v) = v.to_u64() {
+                serializer.serialize_u64(v)
+            } else if let Some(v) = v.to_i128() {
+                serializer.serialize_i128(v)
+            } else if let Some(v) = v.to_u128() {
+                serializer.serialize_u128(v)
+            } else {
+                serializer.serialize_newtype_struct(BIG_INT, &v.to_string())
+            }
+        // Text formats like JSON have no bytes, and would write them as a
+        // list of ints instead.
+        } else if is_bytes && !serializer.is_human_readable() {
+            if objtype::isinstance(self.pyobject, &self.vm.ctx.bytes_type()) {
+                serializer.serialize_bytes(&objbytes::get_value(self.pyobject))
+            } else {
+                serializer.serialize_bytes(&objbytearray::get_value(self.pyobject))
+            }
+        } else if objtype::isinstance(self.pyobject, &self.vm.ctx.list_type())
+            || objtype::isinstance(self.pyobject, &self.vm.ctx.tuple_type())
+        {
+            let elements = objsequence::get_elements(self.pyobject);
+            serialize_seq_elements(serializer, &elements)
+        } else if objtype::isinstance(self.pyobject, &self.vm.ctx.dict_type()) {
+            let pairs = objdict::get_elements(self.pyobject);
+            let mut map = serializer.serialize_map(Some(pairs.len()))?;
+            for (key, e) in pairs.iter() {
+                map.serialize_entry(key.as_str(), &self.clone_with_object(e))?;
+            }
+            map.end()
+        } else if self.pyobject.is(&self.vm.get_none()) {
+            serializer.serialize_none()
+        } else {
+            Err(ser::Error::custom(format!(
+                "Object of type '{}' is not serializable",
+                self.pyobject.class().name
+            )))
+        }
+    }
+}
+
+// This object is used as the seed for deserialization so we have access to the PyContext for type
+// creation
+#[derive(Clone)]
+pub struct PyObjectDeserializer<'c> {
+    vm: &'c VirtualMachine,
+}
+
+impl<'c> PyObjectDeserializer<'c> {
+    pub fn new(vm: &'c VirtualMachine) -> Self {
+        PyObjectDeserializer { vm }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for PyObjectDeserializer<'de> {
+    type Value = PyObjectRef;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self.clone())
+    }
+}
+
+impl<'de> Visitor<'de> for PyObjectDeserializer<'de> {
+    type Value = PyObjectRef;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a type that can deserialise in Python")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(self.vm.ctx.new_str(value.to_string()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(self.vm.ctx.new_str(value))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(self.vm.ctx.new_bytes(value.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(self.vm.ctx.new_bytes(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        // The smaller integers are widened to i64/u64 by serde.
+        Ok(self.vm.ctx.new_int(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(self.vm.ctx.new_int(value))
+    }
+
+    fn visit_i128<E>(self, value: i128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(self.vm.ctx.new_int(value))
+    }
+
+    fn visit_u128<E>(self, value: u128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(self.vm.ctx.new_int(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(self.vm.ctx.new_float(value))
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(self.vm.ctx.new_bool(value))
+    }
+
+    fn visit_seq<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut seq = Vec::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some(value) = access.next_element_seed(self.clone())? {
+            seq.push(value);
+        }
+        Ok(self.vm.ctx.new_list(seq))
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: de::MapAccess<'de>,
+    {
+        let dict = self.vm.ctx.new_dict();
+        while let Some((key, value)) = access.next_entry_seed(self.clone(), self.clone())? {
+            // The key is stored as it is, rather than interned.
+            if key.payload::<PyString>().is_none() {
+                return Err(de::Error::custom("map keys must be strings"));
+            }
+            objdict::set_item(dict.as_object(), self.vm, &key, &value);
+        }
+        Ok(dict.into_object())
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(self.vm.get_none())
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(self.vm.get_none())
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+/// A serde `Serializer` which creates python objects.
+#[derive(Clone, Copy)]
+pub struct PySerializer<'a> {
+    vm: &'a VirtualMachine,
+}
+
+impl<'a> PySerializer<'a> {
+    pub fn new(vm: &'a VirtualMachine) -> Self {
+        PySerializer { vm }
+    }
+
+    fn sequence(self, len: Option<usize>, tuple: bool) -> PySequenceSerializer<'a> {
+        PySequenceSerializer {
+            vm: self.vm,
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+            tuple,
+            variant: None,
+        }
+    }
+
+    fn dict(self) -> PyDictSerializer<'a> {
+        PyDictSerializer {
+            vm: self.vm,
+            dict: self.vm.ctx.new_dict(),
+            key: None,
+            variant: None,
+        }
+    }
+}
+
+/// The `{variant: value}` dict an enum variant with data turns into.
+fn variant_dict(vm: &VirtualMachine, variant: &str, value: PyObjectRef) -> PyObjectRef {
+    let dict = vm.ctx.new_dict();
+    dict.set_item(&vm.ctx, variant, value);
+    dict.into_object()
+}
+
+impl<'a> ser::Serializer for PySerializer<'a> {
+    type Ok = PyObjectRef;
+    type Error = Error;
+    type SerializeSeq = PySequenceSerializer<'a>;
+    type SerializeTuple = PySequenceSerializer<'a>;
+    type SerializeTupleStruct = PySequenceSerializer<'a>;
+    type SerializeTupleVariant = PySequenceSerializer<'a>;
+    type SerializeMap = PyDictSerializer<'a>;
+    type SerializeStruct = PyDictSerializer<'a>;
+    type SerializeStructVariant = PyDictSerializer<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_int(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_int(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_int(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_int(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_int(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_int(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_int(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_int(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_int(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_int(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_float(f64::from(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_str(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_str(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.get_none())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<PyObjectRef, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.get_none())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.get_none())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<PyObjectRef, Error> {
+        Ok(self.vm.ctx.new_str(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<PyObjectRef, Error> {
+        let obj = value.serialize(self)?;
+        if name == BIG_INT {
+            let digits = obj.payload::<PyString>().map(|digits| &digits.value);
+            if let Some(value) = digits.and_then(|digits| digits.parse::<BigInt>().ok()) {
+                return Ok(self.vm.ctx.new_int(value));
+            }
+        }
+        Ok(obj)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<PyObjectRef, Error> {
+        let value = value.serialize(self)?;
+        Ok(variant_dict(self.vm, variant, value))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<PySequenceSerializer<'a>, Error> {
+        Ok(self.sequence(len, false))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<PySequenceSerializer<'a>, Error> {
+        Ok(self.sequence(Some(len), true))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<PySequenceSerializer<'a>, Error> {
+        Ok(self.sequence(Some(len), true))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<PySequenceSerializer<'a>, Error> {
+        let mut sequence = self.sequence(Some(len), true);
+        sequence.variant = Some(variant);
+        Ok(sequence)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<PyDictSerializer<'a>, Error> {
+        Ok(self.dict())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<PyDictSerializer<'a>, Error> {
+        Ok(self.dict())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<PyDictSerializer<'a>, Error> {
+        let mut dict = self.dict();
+        dict.variant = Some(variant);
+        Ok(dict)
+    }
+}
+
+/// Collects the elements of a `list` or `tuple`.
+pub struct PySequenceSerializer<'a> {
+    vm: &'a VirtualMachine,
+    elements: Vec<PyObjectRef>,
+    tuple: bool,
+    variant: Option<&'static str>,
+}
+
+impl<'a> PySequenceSerializer<'a> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let element = value.serialize(PySerializer::new(self.vm))?;
+        self.elements.push(element);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<PyObjectRef, Error> {
+        let sequence = if self.tuple {
+            self.vm.ctx.new_tuple(self.elements)
+        } else {
+            self.vm.ctx.new_list(self.elements)
+        };
+        Ok(match self.variant {
+            Some(variant) => variant_dict(self.vm, variant, sequence),
+            None => sequence,
+        })
+    }
+}
+
+impl<'a> ser::SerializeSeq for PySequenceSerializer<'a> {
+    type Ok = PyObjectRef;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<PyObjectRef, Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTuple for PySequenceSerializer<'a> {
+    type Ok = PyObjectRef;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<PyObjectRef, Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for PySequenceSerializer<'a> {
+    type Ok = PyObjectRef;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<PyObjectRef, Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for PySequenceSerializer<'a> {
+    type Ok = PyObjectRef;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<PyObjectRef, Error> {
+        self.finish()
+    }
+}
+
+/// Fills a `dict`, whose keys have to be strings.
+pub struct PyDictSerializer<'a> {
+    vm: &'a VirtualMachine,
+    dict: PyDictRef,
+    key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl<'a> PyDictSerializer<'a> {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        let value = value.serialize(PySerializer::new(self.vm))?;
+        self.dict.set_item(&self.vm.ctx, key, value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<PyObjectRef, Error> {
+        let dict = self.dict.into_object();
+        Ok(match self.variant {
+            Some(variant) => variant_dict(self.vm, variant, dict),
+            None => dict,
+        })
+    }
+}
+
+impl<'a> ser::SerializeMap for PyDictSerializer<'a> {
+    type Ok = PyObjectRef;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        let key = key.serialize(PySerializer::new(self.vm))?;
+        match key.payload::<PyString>() {
+            Some(key) => {
+                self.key = Some(key.value.clone());
+                Ok(())
+            }
+            None => Err(Error(format!(
+                "dict keys must be str, not {}",
+                key.class().name
+            ))),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("dict value serialized before its key".to_string()))?;
+        self.insert(&key, value)
+    }
+
+    fn end(self) -> Result<PyObjectRef, Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStruct for PyDictSerializer<'a> {
+    type Ok = PyObjectRef;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<PyObjectRef, Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for PyDictSerializer<'a> {
+    type Ok = PyObjectRef;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<PyObjectRef, Error> {
+        self.finish()
+    }
+}
+
+/// A serde `Deserializer` which reads a python object.
+pub struct PyDeserializer<'a> {
+    vm: &'a VirtualMachine,
+    obj: PyObjectRef,
+}
+
+impl<'a> PyDeserializer<'a> {
+    pub fn new(vm: &'a VirtualMachine, obj: PyObjectRef) -> Self {
+        PyDeserializer { vm, obj }
+    }
+
+    fn with_object(&self, obj: PyObjectRef) -> Self {
+        PyDeserializer { vm: self.vm, obj }
+    }
+
+    fn isinstance(&self, cls: &PyClassRef) -> bool {
+        objtype::isinstance(&self.obj, cls)
+    }
+
+    fn is_bytes(&self) -> bool {
+        self.isinstance(&self.vm.ctx.bytes_type()) || self.isinstance(&self.vm.ctx.bytearray_type())
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        if self.isinstance(&self.vm.ctx.bytes_type()) {
+            objbytes::get_value(&self.obj).clone()
+        } else {
+            objbytearray::get_value(&self.obj).clone()
+        }
+    }
+
+    fn unsupported(&self) -> Error {
+        Error(format!(
+            "Object of type '{}' can't be deserialized",
+            self.obj.class().name
+        ))
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for PyDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let ctx = &self.vm.ctx;
+        if self.obj.is(&self.vm.get_none()) {
+            visitor.visit_unit()
+        } else if self.isinstance(&ctx.bool_type()) {
+            visitor.visit_bool(objbool::get_value(&self.obj))
+        } else if self.isinstance(&ctx.int_type()) {
+            let v = objint::get_value(&self.obj);
+            if let Some(v) = v.to_i64() {
+                visitor.visit_i64(v)
+            } else if let Some(v) = v.to_u64() {
+                visitor.visit_u64(v)
+            } else if let Some(v) = v.to_i128() {
+                visitor.visit_i128(v)
+            } else if let Some(v) = v.to_u128() {
+                visitor.visit_u128(v)
+            } else {
+                visitor.visit_str(&v.to_string())
+            }
+        } else if self.isinstance(&ctx.float_type()) {
+            visitor.visit_f64(objfloat::get_value(&self.obj))
+        } else if self.isinstance(&ctx.str_type()) {
+            visitor.visit_str(&objstr::get_value(&self.obj))
+        } else if self.is_bytes() {
+            visitor.visit_byte_buf(self.bytes())
+        } else if self.isinstance(&ctx.list_type()) || self.isinstance(&ctx.tuple_type()) {
+            let elements = objsequence::get_elements(&self.obj).clone();
+            visitor.visit_seq(PySequenceDeserializer {
+                vm: self.vm,
+                elements: elements.into_iter(),
+            })
+        } else if self.isinstance(&ctx.dict_type()) {
+            let entries: Vec<_> = objdict::get_elements(&self.obj)
+                .iter()
+                .map(|(key, value)| (key.as_object().clone(), value.clone()))
+                .collect();
+            visitor.visit_map(PyDictDeserializer {
+                vm: self.vm,
+                entries: entries.into_iter(),
+                value: None,
+            })
+        } else {
+            Err(self.unsupported())
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.obj.is(&self.vm.get_none()) {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // Let `Vec<u8>` and the like read bytes as well.
+        if self.is_bytes() {
+            visitor.visit_seq(de::value::SeqDeserializer::new(self.bytes().into_iter()))
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if name == BIG_INT && self.isinstance(&self.vm.ctx.int_type()) {
+            return visitor.visit_str(&objint::get_value(&self.obj).to_string());
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if self.isinstance(&self.vm.ctx.str_type()) {
+            return visitor.visit_enum(objstr::get_value(&self.obj).into_deserializer());
+        }
+        if self.isinstance(&self.vm.ctx.dict_type()) {
+            let entry = {
+                let entries = objdict::get_elements(&self.obj);
+                if entries.len() == 1 {
+                    entries
+                        .iter()
+                        .next()
+                        .map(|(key, value)| (key.as_str().to_string(), value.clone()))
+                } else {
+                    None
+                }
+            };
+            if let Some((variant, value)) = entry {
+                return visitor.visit_enum(PyEnumDeserializer {
+                    deserializer: self.with_object(value),
+                    variant,
+                });
+            }
+        }
+        Err(Error(format!(
+            "expected a str or a dict with a single key for an enum, not {}",
+            self.obj.class().name
+        )))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+struct PySequenceDeserializer<'a> {
+    vm: &'a VirtualMachine,
+    elements: vec::IntoIter<PyObjectRef>,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for PySequenceDeserializer<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.elements.next() {
+            Some(element) => seed
+                .deserialize(PyDeserializer::new(self.vm, element))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+struct PyDictDeserializer<'a> {
+    vm: &'a VirtualMachine,
+    entries: vec::IntoIter<(PyObjectRef, PyObjectRef)>,
+    value: Option<PyObjectRef>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for PyDictDeserializer<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(PyDeserializer::new(self.vm, key))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error("dict value deserialized before its key".to_string()))?;
+        seed.deserialize(PyDeserializer::new(self.vm, value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// An enum variant with data, from a `{variant: value}` dict.
+struct PyEnumDeserializer<'a> {
+    deserializer: PyDeserializer<'a>,
+    variant: String,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for PyEnumDeserializer<'a> {
+    type Error = Error;
+    type Variant = PyDeserializer<'a>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, PyDeserializer<'a>), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self.deserializer))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for PyDeserializer<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use num_bigint::BigInt;
+    use num_traits::Pow;
+    use serde_derive::{Deserialize, Serialize};
+
+    use super::{from_pyobject, to_pyobject, PyObjectSerializer};
+    use crate::compile;
+    use crate::obj::{objbool, objtype};
+    use crate::pyobject::DictProtocol;
+    use crate::vm::VirtualMachine;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rectangle { width: u32, height: u32 },
+        Segment(i64, i64),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Drawing {
+        name: String,
+        shapes: Vec<Shape>,
+        origin: (i32, i32),
+        tags: HashMap<String, Option<bool>>,
+        huge: u128,
+        #[serde(with = "bytes")]
+        data: Vec<u8>,
+    }
+
+    /// Writes a `Vec<u8>` as bytes, which serde does for `serde_bytes`.
+    mod bytes {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(data)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<u8>, D::Error> {
+            Vec::deserialize(deserializer)
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let vm = VirtualMachine::new();
+        let mut tags = HashMap::new();
+        tags.insert("draft".to_string(), Some(true));
+        tags.insert("shared".to_string(), None);
+        let drawing = Drawing {
+            name: "sketch".to_string(),
+            shapes: vec![
+                Shape::Empty,
+                Shape::Circle(1.5),
+                Shape::Rectangle {
+                    width: 3,
+                    height: 4,
+                },
+                Shape::Segment(-1, 1),
+            ],
+            origin: (0, -2),
+            tags,
+            huge: u128::max_value(),
+            data: b"\x00raw".to_vec(),
+        };
+        let obj = to_pyobject(&vm, &drawing).unwrap();
+        assert_eq!(from_pyobject::<Drawing>(&vm, &obj).unwrap(), drawing);
+
+        let scope = vm.ctx.new_scope();
+        scope.globals.set_item(&vm.ctx, "drawing", obj);
+        let check = "drawing['shapes'][0] == 'Empty' \
+                     and drawing['shapes'][2]['Rectangle']['height'] == 4 \
+                     and drawing['origin'] == (0, -2) \
+                     and drawing['huge'] == 2 ** 128 - 1 \
+                     and list(drawing['data']) == [0, 114, 97, 119]\n";
+        let code =
+            compile::compile(&vm, check, &compile::Mode::Eval, "<test>".to_string()).unwrap();
+        let result = vm.run_code_obj(code, scope).unwrap();
+        assert!(objbool::get_value(&result));
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Counter {
+        #[serde(with = "super::big_int")]
+        count: BigInt,
+    }
+
+    #[test]
+    fn test_big_int() {
+        let vm = VirtualMachine::new();
+        let scope = vm.ctx.new_scope();
+        let run = |source: &str| {
+            let code =
+                compile::compile(&vm, source, &compile::Mode::Eval, "<test>".to_string()).unwrap();
+            vm.run_code_obj(code, scope.clone()).unwrap()
+        };
+
+        let counter = Counter {
+            count: BigInt::from(2).pow(200u32),
+        };
+        let obj = to_pyobject(&vm, &counter).unwrap();
+        assert_eq!(from_pyobject::<Counter>(&vm, &obj).unwrap(), counter);
+        scope.globals.set_item(&vm.ctx, "counter", obj);
+        assert!(objbool::get_value(&run("counter['count'] == 2 ** 200\n")));
+
+        let obj = run("{'count': -3 ** 100}\n");
+        let counter = from_pyobject::<Counter>(&vm, &obj).unwrap();
+        assert_eq!(counter.count, -BigInt::from(3).pow(100u32));
+        let counter = from_pyobject::<Counter>(&vm, &run("{'count': 7}\n")).unwrap();
+        assert_eq!(counter.count, BigInt::from(7));
+
+        // Without the field to say so, other formats get the digits.
+        let obj = run("2 ** 200\n");
+        let json = serde_json::to_string(&PyObjectSerializer::new(&vm, &obj)).unwrap();
+        assert_eq!(json, format!("\"{}\"", BigInt::from(2).pow(200u32)));
+        let digits: String = from_pyobject(&vm, &obj).unwrap();
+        assert_eq!(digits, BigInt::from(2).pow(200u32).to_string());
+    }
+
+    #[test]
+    fn test_errors() {
+        let vm = VirtualMachine::new();
+        let mut by_number = HashMap::new();
+        by_number.insert(1, "one");
+        let err = to_pyobject(&vm, &by_number).unwrap_err();
+        assert!(objtype::isinstance(&err, &vm.ctx.exceptions.type_error));
+
+        let obj = vm.ctx.new_str("not a number".to_string());
+        let err = from_pyobject::<i32>(&vm, &obj).unwrap_err();
+        assert!(objtype::isinstance(&err, &vm.ctx.exceptions.type_error));
+
+        let obj = vm.ctx.new_int(300);
+        assert!(from_pyobject::<u8>(&vm, &obj).is_err());
+    }
+}
@@ -6,21 +6,25 @@ use std::marker::PhantomData;
 use std::mem;
 use std::ops::Deref;
 use std::ptr;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use num_bigint::BigInt;
 use num_complex::Complex64;
-use num_traits::{One, Zero};
+use num_traits::{One, ToPrimitive, Zero};
 
 use crate::bytecode;
 use crate::exceptions;
 use crate::frame::Scope;
 use crate::function::{IntoPyNativeFunc, PyFuncArgs};
+use crate::globals::globals;
+use crate::inline_cache;
+use crate::obj::objabc;
 use crate::obj::objbool;
 use crate::obj::objbuiltinfunc::PyBuiltinFunction;
 use crate::obj::objbytearray;
 use crate::obj::objbytes;
-use crate::obj::objclassmethod;
+use crate::obj::objclassmethod::{self, PyClassMethod};
 use crate::obj::objcode;
 use crate::obj::objcode::PyCodeRef;
 use crate::obj::objcomplex::{self, PyComplex};
@@ -45,14 +49,16 @@ use crate::obj::objproperty::PropertyBuilder;
 use crate::obj::objrange;
 use crate::obj::objset::{self, PySet};
 use crate::obj::objslice;
+use crate::obj::objslots::{self, InstanceLayout};
 use crate::obj::objstaticmethod;
-use crate::obj::objstr;
+use crate::obj::objstdio;
+use crate::obj::objstr::{self, PyStringRef};
 use crate::obj::objsuper;
 use crate::obj::objtuple::{self, PyTuple};
 use crate::obj::objtype::{self, PyClass, PyClassRef};
 use crate::obj::objweakref;
 use crate::obj::objzip;
-use crate::vm::VirtualMachine;
+use crate::vm::{self, VirtualMachine};
 
 /* Python objects and references.
 
@@ -105,6 +111,36 @@ impl fmt::Display for PyObject<dyn PyObjectPayload> {
     }
 }
 
+/// The strs from `PyContext::intern_str` and `intern_string`. Being interned
+/// doesn't keep a str alive: the table only refers to them weakly, and drops
+/// the entries of the dead ones whenever it has doubled in size.
+#[derive(Debug, Default)]
+struct InternedStrs {
+    strs: HashMap<String, Weak<PyObject<dyn PyObjectPayload>>>,
+    /// The number of entries at which the dead ones are dropped next.
+    prune_at: usize,
+}
+
+impl InternedStrs {
+    fn get(&self, s: &str) -> Option<PyStringRef> {
+        let string = self.strs.get(s)?.upgrade()?;
+        Some(string.downcast().unwrap())
+    }
+
+    fn insert(&mut self, string: &PyStringRef) {
+        self.strs
+            .insert(string.value.clone(), Rc::downgrade(string.as_object()));
+        if self.strs.len() > self.prune_at {
+            self.strs.retain(|_, string| string.upgrade().is_some());
+            self.prune_at = (self.strs.len() * 2).max(64);
+        }
+    }
+}
+
+/// The range of ints common enough to be preallocated, like in CPython.
+const SMALL_INT_MIN: i64 = -5;
+const SMALL_INT_MAX: i64 = 256;
+
 #[derive(Debug)]
 pub struct PyContext {
     pub bytes_type: PyClassRef,
@@ -125,6 +161,10 @@ pub struct PyContext {
     pub complex_type: PyClassRef,
     pub true_value: PyIntRef,
     pub false_value: PyIntRef,
+    interned_strs: RefCell<InternedStrs>,
+    /// The ints from `SMALL_INT_MIN` to `SMALL_INT_MAX`, created once and
+    /// shared.
+    small_ints: Vec<PyIntRef>,
     pub list_type: PyClassRef,
     pub map_type: PyClassRef,
     pub memoryview_type: PyClassRef,
@@ -147,6 +187,10 @@ pub struct PyContext {
     pub module_type: PyClassRef,
     pub bound_method_type: PyClassRef,
     pub weakref_type: PyClassRef,
+    pub std_stream_type: PyClassRef,
+    pub member_descriptor_type: PyClassRef,
+    pub abc_meta_type: PyClassRef,
+    pub abc_data_type: PyClassRef,
     pub object: PyClassRef,
     pub exceptions: exceptions::ExceptionZoo,
 }
@@ -178,6 +222,7 @@ impl PyValue for PyEllipsis {
     }
 }
 
+#[allow(clippy::uninit_assumed_init)]
 fn init_type_hierarchy() -> (PyClassRef, PyClassRef) {
     // `type` inherits from `object`
     // and both `type` and `object are instances of `type`.
@@ -185,25 +230,29 @@ fn init_type_hierarchy() -> (PyClassRef, PyClassRef) {
     // (and yes, this will never get dropped. TODO?)
     let (type_type, object_type) = unsafe {
         let object_type = PyObject {
-            typ: mem::uninitialized(), // !
+            typ: mem::MaybeUninit::uninit().assume_init(), // !
             dict: None,
             payload: PyClass {
                 name: String::from("object"),
                 mro: vec![],
                 subclasses: RefCell::new(vec![]),
                 attributes: RefCell::new(PyAttributes::new()),
+                version_tag: Cell::new(inline_cache::next_version_tag()),
+                layout: InstanceLayout::object(),
             },
         }
         .into_ref();
 
         let type_type = PyObject {
-            typ: mem::uninitialized(), // !
+            typ: mem::MaybeUninit::uninit().assume_init(), // !
             dict: None,
             payload: PyClass {
                 name: String::from("type"),
                 mro: vec![object_type.clone().downcast().unwrap()],
                 subclasses: RefCell::new(vec![]),
                 attributes: RefCell::new(PyAttributes::new()),
+                version_tag: Cell::new(inline_cache::next_version_tag()),
+                layout: InstanceLayout::with_dict(&[]),
             },
         }
         .into_ref();
@@ -244,6 +293,10 @@ impl PyContext {
         let readonly_property_type = create_type("readonly_property", &type_type, &object_type);
         let super_type = create_type("super", &type_type, &object_type);
         let weakref_type = create_type("ref", &type_type, &object_type);
+        let std_stream_type = create_type("TextIOWrapper", &type_type, &object_type);
+        let member_descriptor_type = create_type("member_descriptor", &type_type, &object_type);
+        let abc_meta_type = create_type("ABCMeta", &type_type, &type_type);
+        let abc_data_type = create_type("_abc_data", &type_type, &object_type);
         let generator_type = create_type("generator", &type_type, &object_type);
         let bound_method_type = create_type("method", &type_type, &object_type);
         let str_type = create_type("str", &type_type, &object_type);
@@ -287,6 +340,9 @@ impl PyContext {
 
         let true_value = create_object(PyInt::new(BigInt::one()), &bool_type);
         let false_value = create_object(PyInt::new(BigInt::zero()), &bool_type);
+        let small_ints = (SMALL_INT_MIN..=SMALL_INT_MAX)
+            .map(|value| create_object(PyInt::from_i64(value), &int_type))
+            .collect();
         let context = PyContext {
             bool_type,
             memoryview_type,
@@ -304,6 +360,8 @@ impl PyContext {
             frozenset_type,
             true_value,
             false_value,
+            small_ints,
+            interned_strs: RefCell::default(),
             tuple_type,
             iter_type,
             ellipsis_type,
@@ -328,6 +386,10 @@ impl PyContext {
             module_type,
             bound_method_type,
             weakref_type,
+            std_stream_type,
+            member_descriptor_type,
+            abc_meta_type,
+            abc_data_type,
             type_type,
             exceptions,
         };
@@ -363,6 +425,9 @@ impl PyContext {
         objcode::init(&context);
         objframe::init(&context);
         objweakref::init(&context);
+        objstdio::init(&context);
+        objslots::init(&context);
+        objabc::init(&context);
         objnone::init(&context);
         objmodule::init(&context);
         exceptions::init(&context);
@@ -501,6 +566,22 @@ impl PyContext {
         self.weakref_type.clone()
     }
 
+    pub fn std_stream_type(&self) -> PyClassRef {
+        self.std_stream_type.clone()
+    }
+
+    pub fn member_descriptor_type(&self) -> PyClassRef {
+        self.member_descriptor_type.clone()
+    }
+
+    pub fn abc_meta_type(&self) -> PyClassRef {
+        self.abc_meta_type.clone()
+    }
+
+    pub fn abc_data_type(&self) -> PyClassRef {
+        self.abc_data_type.clone()
+    }
+
     pub fn type_type(&self) -> PyClassRef {
         self.type_type.clone()
     }
@@ -521,7 +602,14 @@ impl PyContext {
         self.object.clone()
     }
 
-    pub fn new_int<T: Into<BigInt>>(&self, i: T) -> PyObjectRef {
+    pub fn new_int<T: Into<BigInt> + ToPrimitive>(&self, i: T) -> PyObjectRef {
+        if let Some(value) = i.to_i64() {
+            if SMALL_INT_MIN <= value && value <= SMALL_INT_MAX {
+                return self.small_ints[(value - SMALL_INT_MIN) as usize]
+                    .clone()
+                    .into_object();
+            }
+        }
         PyObject::new(PyInt::new(i), self.int_type(), None)
     }
 
@@ -533,8 +621,36 @@ impl PyContext {
         PyObject::new(PyComplex::from(value), self.complex_type(), None)
     }
 
+    /// The str with the given contents that is shared with all the other
+    /// users of `intern_str` while it lives, like the names in code objects
+    /// and the keys set by `DictProtocol::set_item`.
+    pub fn intern_str(&self, s: &str) -> PyStringRef {
+        if let Some(string) = self.interned_strs.borrow().get(s) {
+            return string;
+        }
+        let string = PyObject::new(objstr::PyString::new(s.to_string()), self.str_type(), None)
+            .downcast()
+            .unwrap();
+        self.intern_string(string)
+    }
+
+    /// The interned str equal to `string`, which becomes the interned one if
+    /// there was none yet.
+    pub fn intern_string(&self, string: PyStringRef) -> PyStringRef {
+        if !string.typ().is(&self.str_type) {
+            // Instances of subclasses can't be shared.
+            return self.intern_str(&string.value);
+        }
+        let mut interned = self.interned_strs.borrow_mut();
+        if let Some(interned) = interned.get(&string.value) {
+            return interned;
+        }
+        interned.insert(&string);
+        string
+    }
+
     pub fn new_str(&self, s: String) -> PyObjectRef {
-        PyObject::new(objstr::PyString { value: s }, self.str_type(), None)
+        PyObject::new(objstr::PyString::new(s), self.str_type(), None)
     }
 
     pub fn new_bytes(&self, data: Vec<u8>) -> PyObjectRef {
@@ -606,6 +722,21 @@ impl PyContext {
         )
     }
 
+    /// A class method implemented by `f`, which gets the class as its first
+    /// argument.
+    pub fn new_classmethod<F, T, R>(&self, f: F) -> PyObjectRef
+    where
+        F: IntoPyNativeFunc<T, R>,
+    {
+        PyObject::new(
+            PyClassMethod {
+                callable: self.new_rustfunc(f),
+            },
+            self.classmethod_type(),
+            None,
+        )
+    }
+
     pub fn new_property<F, I, V>(&self, f: F) -> PyObjectRef
     where
         F: IntoPyNativeFunc<I, V>,
@@ -614,7 +745,7 @@ impl PyContext {
     }
 
     pub fn new_code_object(&self, code: bytecode::CodeObject) -> PyCodeRef {
-        PyObject::new(objcode::PyCode::new(code), self.code_type(), None)
+        PyObject::new(objcode::PyCode::new(self, code), self.code_type(), None)
             .downcast()
             .unwrap()
     }
@@ -641,10 +772,11 @@ impl PyContext {
     }
 
     pub fn new_instance(&self, class: PyClassRef, dict: Option<PyDictRef>) -> PyObjectRef {
+        let instance = objobject::PyInstance::new(class.layout.slot_count);
         PyObject {
             typ: class,
             dict: dict,
-            payload: objobject::PyInstance,
+            payload: instance,
         }
         .into_ref()
     }
@@ -652,8 +784,8 @@ impl PyContext {
     // Item set/get:
     pub fn set_item(&self, obj: &PyObjectRef, key: &str, v: PyObjectRef) {
         if let Some(dict) = obj.payload::<PyDict>() {
-            let key = self.new_str(key.to_string());
-            objdict::set_item_in_content(&mut dict.entries.borrow_mut(), &key, &v);
+            let key = self.intern_str(key).into_object();
+            objdict::set_item_in_content(&mut dict.entries_mut(), &key, &v);
         } else {
             unimplemented!()
         };
@@ -666,10 +798,8 @@ impl PyContext {
         value: V,
     ) {
         let obj = obj.into();
-        if let Some(PyClass { ref attributes, .. }) = obj.payload::<PyClass>() {
-            attributes
-                .borrow_mut()
-                .insert(attr_name.to_string(), value.into());
+        if let Some(class) = obj.payload::<PyClass>() {
+            class.set_attr(attr_name, value.into());
         } else if let Some(ref dict) = obj.dict {
             dict.set_item(self, attr_name, value.into());
         } else {
@@ -719,6 +849,105 @@ where
     pub payload: T,
 }
 
+thread_local! {
+    /// Instances whose last reference went away while their class defines
+    /// `__del__`. Dropping can happen anywhere (even with a dict borrowed), so
+    /// the finalizers are queued here and run by the VM once it is safe to
+    /// execute python code again, see `VirtualMachine::run_finalizers`.
+    static PENDING_FINALIZERS: FinalizerQueue = FinalizerQueue(RefCell::new(vec![]));
+}
+
+struct FinalizerQueue(RefCell<Vec<PyObjectRef>>);
+
+impl Drop for FinalizerQueue {
+    fn drop(&mut self) {
+        // A thread running python code finalizes its queue before it exits,
+        // see `thread::start_thread`. Whatever is left can't be dropped now,
+        // as the thread no longer holds the GIL, so it is leaked.
+        let pending = mem::replace(self.0.get_mut(), vec![]);
+        PENDING_FINALIZER_COUNT.fetch_sub(pending.len(), Ordering::Relaxed);
+        vm::EVAL_BREAKER.fetch_sub(pending.len(), Ordering::SeqCst);
+        mem::forget(pending);
+    }
+}
+
+/// How many objects the `PENDING_FINALIZERS` of all threads hold, so that
+/// the VM can tell there is nothing to finalize, which is almost always the
+/// case, without looking.
+pub(crate) static PENDING_FINALIZER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Take all objects waiting for their `__del__` method to be called.
+pub fn take_pending_finalizers() -> Vec<PyObjectRef> {
+    let globals = globals();
+    if globals.pending_finalizer_count.load(Ordering::Relaxed) == 0 {
+        return vec![];
+    }
+    (globals.take_pending_finalizers)()
+}
+
+pub(crate) fn own_take_pending_finalizers() -> Vec<PyObjectRef> {
+    let pending = PENDING_FINALIZERS
+        .try_with(|pending| mem::replace(&mut *pending.0.borrow_mut(), vec![]))
+        .unwrap_or_default();
+    PENDING_FINALIZER_COUNT.fetch_sub(pending.len(), Ordering::Relaxed);
+    vm::EVAL_BREAKER.fetch_sub(pending.len(), Ordering::SeqCst);
+    pending
+}
+
+pub(crate) fn own_queue_finalizer(zombie: PyObjectRef) {
+    // If the thread is shutting down there is no VM left to finalize it.
+    let _ = PENDING_FINALIZERS.try_with(move |pending| {
+        if let Ok(mut pending) = pending.0.try_borrow_mut() {
+            pending.push(zombie);
+            PENDING_FINALIZER_COUNT.fetch_add(1, Ordering::Relaxed);
+            vm::EVAL_BREAKER.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+}
+
+impl<T> Drop for PyObject<T>
+where
+    T: ?Sized + PyObjectPayload,
+{
+    fn drop(&mut self) {
+        let payload = self.payload.as_any();
+        // The memory of this object is about to be released, so move whatever
+        // its finalizer needs over to a stand-in object, which may even be
+        // resurrected by it. Unlike in CPython, that is a new object: it has
+        // another `id()`, and weak references to this one are dead already.
+        let zombie = if let Some(instance) = payload.downcast_ref::<objobject::PyInstance>() {
+            // Only instances of classes deriving from `object` or an exception
+            // get `__del__` called, their state lives entirely in the class,
+            // the instance dict and the slots. Subclasses of other builtin
+            // types, like `list`, keep theirs in a payload that can't be moved
+            // out here, so their `__del__` is never called.
+            if instance.is_finalized() || !objtype::class_may_have_finalizer(&self.typ) {
+                return;
+            }
+            PyObject {
+                typ: self.typ.clone(),
+                dict: self.dict.take(),
+                payload: instance.zombie(),
+            }
+            .into_ref()
+        } else if let Some(generator) = payload.downcast_ref::<objgenerator::PyGenerator>() {
+            // Abandoned generators are closed, to run their `finally` blocks.
+            if !generator.needs_finalizer() {
+                return;
+            }
+            PyObject {
+                typ: self.typ.clone(),
+                dict: None,
+                payload: generator.zombie(),
+            }
+            .into_ref()
+        } else {
+            return;
+        };
+        (globals().queue_finalizer)(zombie);
+    }
+}
+
 impl PyObject<dyn PyObjectPayload> {
     /// Attempt to downcast this reference to a subclass.
     ///
@@ -916,7 +1145,7 @@ pub trait DictProtocol {
 impl DictProtocol for PyObjectRef {
     fn contains_key(&self, k: &str) -> bool {
         if let Some(dict) = self.payload::<PyDict>() {
-            objdict::content_contains_key_str(&dict.entries.borrow(), k)
+            objdict::content_contains_key_str(&dict.entries(), k)
         } else {
             unimplemented!()
         }
@@ -924,7 +1153,7 @@ impl DictProtocol for PyObjectRef {
 
     fn get_item(&self, k: &str) -> Option<PyObjectRef> {
         if let Some(dict) = self.payload::<PyDict>() {
-            objdict::content_get_key_str(&dict.entries.borrow(), k)
+            objdict::content_get_key_str(&dict.entries(), k)
         } else {
             panic!("TODO {:?}", k)
         }
@@ -941,8 +1170,8 @@ impl DictProtocol for PyObjectRef {
     // Item set/get:
     fn set_item(&self, ctx: &PyContext, key: &str, v: PyObjectRef) {
         if let Some(dict) = self.payload::<PyDict>() {
-            let key = ctx.new_str(key.to_string());
-            objdict::set_item_in_content(&mut dict.entries.borrow_mut(), &key, &v);
+            let key = ctx.intern_str(key).into_object();
+            objdict::set_item_in_content(&mut dict.entries_mut(), &key, &v);
         } else {
             panic!("TODO {:?}", self);
         }
@@ -950,7 +1179,7 @@ impl DictProtocol for PyObjectRef {
 
     fn del_item(&self, key: &str) {
         let mut elements = objdict::get_mut_elements(self);
-        elements.remove(key).unwrap();
+        elements.shift_remove(&objdict::StrKey::new(key)).unwrap();
     }
 }
 
@@ -1185,11 +1414,18 @@ pub trait PyValue: fmt::Debug + Sized + 'static {
     fn into_ref_with_type(self, vm: &VirtualMachine, cls: PyClassRef) -> PyResult<PyRef<Self>> {
         let class = Self::class(vm);
         if objtype::issubclass(&cls, &class) {
-            let dict = if cls.is(&class) {
+            let dict = if cls.is(&class) || !cls.layout.has_dict {
                 None
             } else {
                 Some(vm.ctx.new_dict())
             };
+            // Only the instances made by `object.__new__` have room for slots.
+            if cls.layout.slot_count > 0 {
+                return Err(vm.new_type_error(format!(
+                    "nonempty __slots__ not supported for subtype of '{}'",
+                    class.name
+                )));
+            }
             Ok(PyRef {
                 obj: PyObject::new(self, cls, dict),
                 _payload: PhantomData,
@@ -1202,6 +1438,41 @@ pub trait PyValue: fmt::Debug + Sized + 'static {
     }
 }
 
+/// The name and docstring of a class implemented in rust, see `#[pyclass]`.
+pub trait PyClassDef {
+    const NAME: &'static str;
+    const DOC: Option<&'static str> = None;
+}
+
+// Lets `#[pymethods]` go on `impl PyFooRef` blocks as well.
+impl<T: PyClassDef> PyClassDef for PyRef<T> {
+    const NAME: &'static str = T::NAME;
+    const DOC: Option<&'static str> = T::DOC;
+}
+
+/// The attributes of a class implemented in rust, which `#[pymethods]`
+/// collects from an `impl` block.
+pub trait PyClassImpl: PyClassDef {
+    fn impl_extend_class(ctx: &PyContext, class: &PyClassRef);
+
+    fn extend_class(ctx: &PyContext, class: &PyClassRef) {
+        Self::impl_extend_class(ctx, class);
+        if let Some(doc) = Self::DOC {
+            ctx.set_attr(class, "__doc__", ctx.new_str(doc.to_string()));
+        }
+    }
+
+    fn make_class(ctx: &PyContext) -> PyClassRef {
+        Self::make_class_with_base(ctx, ctx.object())
+    }
+
+    fn make_class_with_base(ctx: &PyContext, base: PyClassRef) -> PyClassRef {
+        let class = ctx.new_class(Self::NAME, base);
+        Self::extend_class(ctx, &class);
+        class
+    }
+}
+
 pub trait PyObjectPayload: Any + fmt::Debug + 'static {
     fn as_any(&self) -> &dyn Any;
 }
@@ -1261,11 +1532,197 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use super::*;
+    use crate::compile;
+    use crate::function::OptionalArg;
+    use crate::obj::objbytes::Bytes;
 
     #[test]
     fn test_type_type() {
         // TODO: Write this test
         PyContext::new();
     }
+
+    #[test]
+    fn test_interned_strs_are_not_kept_alive() {
+        let ctx = PyContext::new();
+        let interned = {
+            let interned = ctx.intern_str("interned");
+            assert!(ctx.intern_str("interned").is(&interned));
+            Rc::downgrade(interned.as_object())
+        };
+        assert!(interned.upgrade().is_none());
+
+        let dict = ctx.new_dict();
+        dict.set_item(&ctx, "a key nothing else uses", ctx.none());
+        let key = Rc::downgrade(&dict.get_key_value_pairs().pop().unwrap().0);
+        drop(dict);
+        assert!(key.upgrade().is_none());
+
+        for i in 0..1000 {
+            ctx.intern_str(&i.to_string());
+        }
+        assert!(ctx.interned_strs.borrow().strs.len() < 1000);
+    }
+
+    #[test]
+    fn test_str_keys_hash_like_strs() {
+        let ctx = PyContext::new();
+        let dict = ctx.new_dict();
+        dict.set_item(&ctx, "spam", ctx.new_int(1));
+        let other = ctx.new_str("spam".to_string()).downcast().unwrap();
+        assert!(objdict::content_get_key(&dict.entries(), &other).is_some());
+        assert!(dict.get_item("spam").is_some());
+        assert!(dict.get_item("eggs").is_none());
+        dict.del_item("spam");
+        assert!(dict.get_item("spam").is_none());
+    }
+
+    /// A counter for testing `#[pyclass]`.
+    #[pyclass(name = "Counter")]
+    #[derive(Debug)]
+    struct PyCounter {
+        count: Cell<i32>,
+    }
+
+    impl PyValue for PyCounter {
+        fn class(vm: &VirtualMachine) -> PyClassRef {
+            vm.class("counter", "Counter")
+        }
+    }
+
+    #[pymethods]
+    impl PyRef<PyCounter> {
+        #[pymethod(magic)]
+        fn new(cls: PyClassRef, start: OptionalArg<i32>, vm: &VirtualMachine) -> PyResult<Self> {
+            PyCounter {
+                count: Cell::new(start.into_option().unwrap_or(0)),
+            }
+            .into_ref_with_type(vm, cls)
+        }
+
+        #[pymethod(name = "increment")]
+        fn incr(self, _vm: &VirtualMachine) -> i32 {
+            self.count.set(self.count.get() + 1);
+            self.count.get()
+        }
+
+        #[pymethod(magic)]
+        fn len(self, _vm: &VirtualMachine) -> usize {
+            self.count.get() as usize
+        }
+
+        #[pyproperty]
+        fn count(self, _vm: &VirtualMachine) -> i32 {
+            self.count.get()
+        }
+
+        #[pyproperty(setter)]
+        fn set_count(self, count: i32, _vm: &VirtualMachine) {
+            self.count.set(count)
+        }
+    }
+
+    #[test]
+    fn test_pyclass() {
+        let vm = VirtualMachine::new();
+        vm.stdlib_inits.borrow_mut().insert(
+            "counter".to_string(),
+            Box::new(|ctx| {
+                py_module!(ctx, "counter", {
+                    "Counter" => PyRef::<PyCounter>::make_class(ctx),
+                })
+            }),
+        );
+        let source = "from counter import Counter\n\
+                      counter = Counter(2)\n\
+                      assert counter.increment() == 3\n\
+                      assert len(counter) == 3\n\
+                      counter.count = 10\n\
+                      assert counter.count == 10\n\
+                      assert repr(Counter) == \"<class 'Counter'>\"\n\
+                      assert Counter.__doc__ == 'A counter for testing `#[pyclass]`.'\n";
+        let code = compile::compile(&vm, source, &compile::Mode::Exec, "<test>".to_string())
+            .expect("the test code compiles");
+        if let Err(exc) = vm.run_code_obj(code, vm.ctx.new_scope()) {
+            panic!("{}", vm.to_pystr(&exc).unwrap());
+        }
+    }
+
+    /// Rust values of collection types round trip through python.
+    fn round_trip<T>(vm: &VirtualMachine, value: T) -> T
+    where
+        T: IntoPyObject + TryFromObject,
+    {
+        let tuple_type = vm.get_attribute(vm.builtins.clone(), "tuple").unwrap();
+        let obj = value.into_pyobject(vm).unwrap();
+        let obj = vm
+            .invoke(tuple_type, vec![vm.ctx.new_tuple(vec![obj])])
+            .unwrap();
+        let (value,): (T,) = TryFromObject::try_from_object(vm, obj).unwrap();
+        value
+    }
+
+    #[test]
+    fn test_collection_conversions() {
+        use std::collections::BTreeMap;
+
+        let vm = VirtualMachine::new();
+        assert_eq!(round_trip(&vm, vec![1, 2, 3]), vec![1, 2, 3]);
+        let mut map = HashMap::new();
+        map.insert("a".to_string(), vec![Some(1), None]);
+        assert_eq!(round_trip(&vm, map.clone()), map);
+        let mut tree: BTreeMap<String, BigInt> = BTreeMap::new();
+        tree.insert("big".to_string(), BigInt::from(1) << 100);
+        assert_eq!(round_trip(&vm, tree.clone()), tree);
+        let set: HashSet<i64> = vec![1, 5, 7].into_iter().collect();
+        assert_eq!(round_trip(&vm, set.clone()), set);
+        let tuple = (1, "two".to_string(), true, (3u8, 4i16));
+        assert_eq!(round_trip(&vm, tuple.clone()), tuple);
+        let twelve = (1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12);
+        assert_eq!(round_trip(&vm, twelve), twelve);
+
+        // Native functions take and return them too.
+        let describe = vm.ctx.new_rustfunc(
+            |numbers: Vec<i32>,
+             names: HashMap<String, bool>,
+             data: Vec<u8>,
+             _vm: &VirtualMachine| {
+                let mut names: Vec<_> = names.into_iter().filter(|(_, v)| *v).collect();
+                names.sort();
+                (numbers.iter().sum::<i32>(), names, data.len())
+            },
+        );
+        let source = "describe([1, 2], {'x': True, 'y': False}, b'abc')\n";
+        let scope = vm.ctx.new_scope();
+        scope.globals.set_item(&vm.ctx, "describe", describe);
+        let code = compile::compile(&vm, source, &compile::Mode::Eval, "<test>".to_string())
+            .expect("the test code compiles");
+        let result = vm.run_code_obj(code, scope).unwrap();
+        let (sum, names, len): (i32, Vec<(String, bool)>, usize) =
+            TryFromObject::try_from_object(&vm, result).unwrap();
+        assert_eq!((sum, names, len), (3, vec![("x".to_string(), true)], 3));
+
+        // Where a `Vec<u8>` is a list, `Bytes` is a bytes object.
+        let reverse = vm.ctx.new_rustfunc(|data: Bytes, _vm: &VirtualMachine| {
+            Bytes(data.iter().rev().cloned().collect())
+        });
+        let data = vm.ctx.new_bytes(b"abc".to_vec());
+        let reversed: Bytes = vm.invoke_as(reverse.clone(), vec![data]).unwrap();
+        assert_eq!(reversed, Bytes(b"cba".to_vec()));
+        let data = vm.ctx.new_list(vec![vm.ctx.new_int(97)]);
+        let error = vm.invoke_as::<Bytes, _>(reverse, vec![data]).err().unwrap();
+        assert!(objtype::isinstance(&error, &vm.ctx.exceptions.type_error));
+
+        let error = Vec::<i32>::try_from_object(&vm, vm.ctx.new_int(1))
+            .err()
+            .unwrap();
+        assert!(objtype::isinstance(&error, &vm.ctx.exceptions.type_error));
+        let error = <(i32, i32)>::try_from_object(&vm, vm.ctx.new_list(vec![]))
+            .err()
+            .unwrap();
+        assert!(objtype::isinstance(&error, &vm.ctx.exceptions.type_error));
+    }
 }
@@ -0,0 +1,248 @@
+//! Turning Ctrl-C into a `KeyboardInterrupt`.
+//!
+//! An application that wants Ctrl-C to interrupt the python code it runs,
+//! like the `rustpython` binary, calls `install_interrupt_handler` from its
+//! main thread. The interrupt is raised in that thread, before the next
+//! instruction it executes, or right away when it is blocked reading stdin,
+//! sleeping or waiting for a lock, a thread or an event.
+
+use std::cell::Cell;
+use std::cmp;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::globals::globals;
+use crate::pyobject::PyResult;
+use crate::vm::{self, VirtualMachine};
+
+/// How often the thread that gets interrupts looks for one while it waits.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Set when an interrupt is waiting to be raised, which holds a request for
+/// `vm::EVAL_BREAKER`.
+pub(crate) static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// Whether interrupts are raised in this thread.
+    static IS_MAIN: Cell<bool> = Cell::new(false);
+}
+
+/// Raise `KeyboardInterrupt` in the calling thread when the process gets a
+/// SIGINT.
+pub fn install_interrupt_handler() {
+    IS_MAIN.with(|is_main| is_main.set(true));
+    install_sigint_handler();
+}
+
+pub(crate) fn own_is_interrupt_thread() -> bool {
+    IS_MAIN.with(Cell::get)
+}
+
+#[cfg(unix)]
+fn install_sigint_handler() {
+    extern "C" fn handle_sigint(_signum: libc::c_int) {
+        // Updating atomics is all a signal handler can safely do, so it
+        // can't go through `globals`: the copy of the crate that installs
+        // the handler owns them anyway.
+        if !INTERRUPTED.swap(true, Ordering::SeqCst) {
+            vm::EVAL_BREAKER.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handle_sigint as libc::sighandler_t;
+        // Without SA_RESTART, a blocking read of the main thread fails with
+        // EINTR, so it can give up to raise the interrupt.
+        action.sa_flags = 0;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+    }
+}
+
+#[cfg(not(unix))]
+fn install_sigint_handler() {}
+
+/// Keep the process' SIGINTs from being delivered to the calling thread. The
+/// threads that don't raise interrupts call this, so that SIGINTs go to the
+/// one that does and interrupt the blocking call it may be in.
+pub fn block_interrupt_signals() {
+    #[cfg(unix)]
+    unsafe {
+        let mut signals: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut signals);
+        libc::sigaddset(&mut signals, libc::SIGINT);
+        libc::pthread_sigmask(libc::SIG_BLOCK, &signals, std::ptr::null_mut());
+    }
+}
+
+/// Interrupt the thread that installed the handler as if Ctrl-C was pressed.
+pub fn interrupt() {
+    if !globals().interrupted.swap(true, Ordering::SeqCst) {
+        vm::request_eval_break();
+    }
+}
+
+/// Whether the calling thread is the one interrupts are raised in.
+pub fn receives_interrupts() -> bool {
+    (globals().is_interrupt_thread)()
+}
+
+/// Whether the calling thread has an interrupt waiting to be raised. A
+/// thread blocked with the GIL released gives up when this turns true, so
+/// that `check_signals` raises the interrupt.
+pub fn interrupt_pending() -> bool {
+    globals().interrupted.load(Ordering::Relaxed) && receives_interrupts()
+}
+
+/// Raise the pending interrupt, if this is the thread that gets them.
+pub fn check_signals(vm: &VirtualMachine) -> PyResult<()> {
+    if !interrupt_pending() {
+        return Ok(());
+    }
+    if globals().interrupted.swap(false, Ordering::SeqCst) {
+        vm::release_eval_break();
+        let keyboard_interrupt = vm.ctx.exceptions.keyboard_interrupt.clone();
+        Err(vm.new_empty_exception(keyboard_interrupt)?)
+    } else {
+        Ok(())
+    }
+}
+
+/// Sleep for `duration`, returning early if an interrupt becomes pending.
+/// Must be called with the GIL released.
+pub fn sleep(duration: Duration) {
+    if !receives_interrupts() {
+        thread::sleep(duration);
+        return;
+    }
+    let deadline = Instant::now() + duration;
+    loop {
+        let now = Instant::now();
+        if now >= deadline || interrupt_pending() {
+            return;
+        }
+        thread::sleep(cmp::min(deadline - now, POLL_INTERVAL));
+    }
+}
+
+/// A reader whose reads fail, rather than being retried, when a signal
+/// interrupts them while an interrupt is pending.
+pub struct Interruptible<R>(pub R);
+
+impl<R: Read> Read for Interruptible<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.0.read(buf) {
+                // Std retries interrupted reads, so this has to be another
+                // kind of error.
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {
+                    if interrupt_pending() {
+                        return Err(io::Error::new(io::ErrorKind::Other, "interrupted"));
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::{interrupt, Interruptible, IS_MAIN};
+    use crate::compile;
+    use crate::obj::objtype;
+    use crate::pyobject::PyResult;
+    use crate::vm::VirtualMachine;
+
+    lazy_static! {
+        /// The interrupt flag is the process', so the tests take turns.
+        static ref SERIAL: Mutex<()> = Mutex::new(());
+    }
+
+    /// Have interrupts raised in this thread, without installing a handler
+    /// for the signals of the whole test process.
+    fn receive_interrupts() {
+        IS_MAIN.with(|is_main| is_main.set(true));
+    }
+
+    fn run(vm: &VirtualMachine, source: &str) -> PyResult {
+        let code = compile::compile(vm, source, &compile::Mode::Exec, "<test>".to_string())
+            .expect("the test code compiles");
+        vm.run_code_obj(code, vm.ctx.new_scope())
+    }
+
+    #[test]
+    fn test_interrupt_raises_keyboard_interrupt() {
+        let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+        let vm = VirtualMachine::new();
+        receive_interrupts();
+        interrupt();
+        let exc = run(&vm, "while True:\n    pass\n").err().unwrap();
+        assert!(objtype::isinstance(
+            &exc,
+            &vm.ctx.exceptions.keyboard_interrupt
+        ));
+    }
+
+    #[test]
+    fn test_interrupt_wakes_blocked_thread() {
+        let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+        let vm = VirtualMachine::new();
+        receive_interrupts();
+        let sources = [
+            "import time\ntime.sleep(600.0)\n",
+            "import _thread\nlock = _thread.allocate_lock()\nlock.acquire()\nlock.acquire()\n",
+            "import threading\nthreading.Event().wait()\n",
+        ];
+        for source in sources.iter() {
+            let interrupter = thread::spawn(|| {
+                thread::sleep(Duration::from_millis(100));
+                interrupt();
+            });
+            let start = Instant::now();
+            let exc = run(&vm, source).err().unwrap();
+            interrupter.join().unwrap();
+            assert!(objtype::isinstance(
+                &exc,
+                &vm.ctx.exceptions.keyboard_interrupt
+            ));
+            assert!(start.elapsed() < Duration::from_secs(60));
+        }
+    }
+
+    /// Reads as if a signal arrived during the first one.
+    struct Signalled(bool);
+
+    impl Read for Signalled {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if !self.0 {
+                self.0 = true;
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "EINTR"));
+            }
+            buf[0] = b'x';
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn test_interruptible_read() {
+        let _serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+        receive_interrupts();
+        let mut buf = [0; 1];
+        // Some other signal: the read is retried.
+        assert_eq!(Interruptible(Signalled(false)).read(&mut buf).unwrap(), 1);
+
+        interrupt();
+        let err = Interruptible(Signalled(false)).read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        let vm = VirtualMachine::new();
+        assert!(super::check_signals(&vm).is_err());
+    }
+}
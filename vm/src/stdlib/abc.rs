@@ -0,0 +1,35 @@
+//! The `abc` module, for defining abstract base classes.
+//!
+//! `ABCMeta` itself is built into the VM, see `obj::objabc`, so that the
+//! abstract base classes of `collections.abc` share it.
+
+use crate::obj::objabc;
+use crate::pyobject::{PyAttributes, PyContext, PyObjectRef, PyResult};
+use crate::vm::VirtualMachine;
+
+/// Mark `func` as abstract: the classes made by `ABCMeta` that don't
+/// override it can't be instantiated.
+fn abc_abstractmethod(func: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    vm.set_attr(
+        &func,
+        vm.new_str("__isabstractmethod__".to_string()),
+        vm.new_bool(true),
+    )?;
+    Ok(func)
+}
+
+fn abc_get_cache_token(_vm: &VirtualMachine) -> usize {
+    objabc::cache_token()
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    // A base to derive from instead of passing `metaclass=ABCMeta`.
+    let abc = objabc::new_abstract_class(ctx, "ABC", vec![ctx.object()], PyAttributes::new(), &[]);
+
+    py_module!(ctx, "abc", {
+        "ABCMeta" => ctx.abc_meta_type(),
+        "ABC" => abc,
+        "abstractmethod" => ctx.new_rustfunc(abc_abstractmethod),
+        "get_cache_token" => ctx.new_rustfunc(abc_get_cache_token),
+    })
+}
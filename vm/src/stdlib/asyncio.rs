@@ -0,0 +1,1446 @@
+//! A minimal `asyncio`: an event loop which drives generator based coroutines.
+//!
+//! Coroutines are plain generators, which wait for a future by delegating to
+//! it with `yield from`. The future yields itself up to the task running the
+//! coroutine, which then sleeps until the future is done. Sockets are waited
+//! for with the OS poller, so many connections can be served by one thread.
+
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::io;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use super::socket::{self, SocketRef};
+use super::thread;
+use crate::function::{Args, ArgumentError, FromArgs, OptionalArg, PyFuncArgs};
+use crate::obj::objbytes::PyBytesRef;
+use crate::obj::objfloat;
+use crate::obj::objgenerator::{self, PyGenerator};
+use crate::obj::objiter;
+use crate::obj::objlist::PyListRef;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::pyobject::{
+    IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject, TypeProtocol,
+};
+use crate::vm::VirtualMachine;
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) * 1e-9
+}
+
+fn duration(seconds: f64) -> Duration {
+    if seconds > 0.0 {
+        Duration::from_micros((seconds * 1e6) as u64)
+    } else {
+        Duration::from_secs(0)
+    }
+}
+
+fn new_error(vm: &VirtualMachine, name: &str) -> PyResult {
+    vm.new_empty_exception(vm.class("asyncio", name))
+}
+
+/// What to do when a handle is run by the event loop.
+#[derive(Debug)]
+enum Callback {
+    /// Call a python callable with the given arguments.
+    Call(PyObjectRef, Vec<PyObjectRef>),
+    /// Advance a task, throwing the exception into its coroutine if given.
+    Step(PyFutureRef, Option<PyObjectRef>),
+    /// Tell a done callback that the future is done.
+    Done(DoneCallback, PyFutureRef),
+    /// Finish a future with a result, unless it got cancelled meanwhile.
+    SetResult(PyFutureRef, PyObjectRef),
+    /// Give up on the inner future of `wait_for()`.
+    Timeout(PyFutureRef, PyFutureRef),
+    /// Retry a socket operation now that the socket is ready.
+    Socket(PyFutureRef, SocketRef, SocketOp),
+}
+
+/// Something to be done once a future is done.
+#[derive(Debug)]
+enum DoneCallback {
+    /// A python callable, called with the future.
+    Call(PyObjectRef),
+    /// Resume the task waiting for the future.
+    Wakeup(PyFutureRef),
+    /// Collect the results of `gather()` once all children are done.
+    Gather {
+        outer: PyFutureRef,
+        children: Rc<Vec<PyFutureRef>>,
+        return_exceptions: bool,
+    },
+    /// Pass the outcome on to the future returned by `wait_for()`.
+    Chain {
+        outer: PyFutureRef,
+        timer: PyHandleRef,
+    },
+    /// Stop the loop of the future, as used by `run_until_complete()`.
+    StopLoop,
+}
+
+#[derive(Debug)]
+enum SocketOp {
+    Recv(usize),
+    SendAll(RefCell<Vec<u8>>),
+    Accept,
+}
+
+impl SocketOp {
+    fn writable(&self) -> bool {
+        match self {
+            SocketOp::SendAll(_) => true,
+            SocketOp::Recv(_) | SocketOp::Accept => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PyHandle {
+    callback: Callback,
+    cancelled: Cell<bool>,
+    when: Option<f64>,
+}
+
+pub type PyHandleRef = PyRef<PyHandle>;
+
+impl PyValue for PyHandle {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("asyncio", "Handle")
+    }
+}
+
+impl PyHandle {
+    fn new(callback: Callback) -> PyHandle {
+        PyHandle {
+            callback,
+            cancelled: Cell::new(false),
+            when: None,
+        }
+    }
+}
+
+impl PyHandleRef {
+    fn cancel(self, _vm: &VirtualMachine) {
+        self.cancelled.set(true);
+    }
+
+    fn cancelled(self, _vm: &VirtualMachine) -> bool {
+        self.cancelled.get()
+    }
+
+    fn when(self, _vm: &VirtualMachine) -> Option<f64> {
+        self.when
+    }
+}
+
+/// A handle waiting in the timer heap, the earliest deadline first.
+#[derive(Debug)]
+struct Timer {
+    deadline: Duration,
+    sequence: u64,
+    handle: PyHandleRef,
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Timer) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Timer) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timer {
+    fn cmp(&self, other: &Timer) -> Ordering {
+        (other.deadline, other.sequence).cmp(&(self.deadline, self.sequence))
+    }
+}
+
+#[derive(Debug)]
+pub struct PyEventLoop {
+    start: Instant,
+    ready: RefCell<VecDeque<PyHandleRef>>,
+    timers: RefCell<BinaryHeap<Timer>>,
+    timer_count: Cell<u64>,
+    readers: RefCell<HashMap<i64, PyHandleRef>>,
+    writers: RefCell<HashMap<i64, PyHandleRef>>,
+    current_task: RefCell<Option<PyFutureRef>>,
+    /// The thread running the loop, if it is running.
+    thread: Cell<Option<u64>>,
+    stopping: Cell<bool>,
+    closed: Cell<bool>,
+}
+
+pub type PyEventLoopRef = PyRef<PyEventLoop>;
+
+impl PyValue for PyEventLoop {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("asyncio", "AbstractEventLoop")
+    }
+}
+
+impl PyEventLoop {
+    fn new() -> PyEventLoop {
+        PyEventLoop {
+            start: Instant::now(),
+            ready: RefCell::new(VecDeque::new()),
+            timers: RefCell::new(BinaryHeap::new()),
+            timer_count: Cell::new(0),
+            readers: RefCell::new(HashMap::new()),
+            writers: RefCell::new(HashMap::new()),
+            current_task: RefCell::new(None),
+            thread: Cell::new(None),
+            stopping: Cell::new(false),
+            closed: Cell::new(false),
+        }
+    }
+
+    fn check_closed(&self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.closed.get() {
+            Err(vm.new_runtime_error("Event loop is closed".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn watchers(&self, writable: bool) -> &RefCell<HashMap<i64, PyHandleRef>> {
+        if writable {
+            &self.writers
+        } else {
+            &self.readers
+        }
+    }
+}
+
+/// The loops running right now, as kept in `asyncio._running_loops`.
+fn running_loops(vm: &VirtualMachine) -> PyResult<PyListRef> {
+    let module = vm.import("asyncio")?;
+    PyListRef::try_from_object(vm, vm.get_attribute(module, "_running_loops")?)
+}
+
+fn running_loop(vm: &VirtualMachine) -> PyResult<Option<PyEventLoopRef>> {
+    let ident = thread::get_ident();
+    let running = running_loops(vm)?;
+    let found = running.elements.borrow().iter().find_map(|event_loop| {
+        event_loop
+            .clone()
+            .downcast::<PyEventLoop>()
+            .ok()
+            .filter(|event_loop| event_loop.thread.get() == Some(ident))
+    });
+    Ok(found)
+}
+
+/// The running loop, or else the current loop, which is created on demand.
+fn get_event_loop(vm: &VirtualMachine) -> PyResult<PyEventLoopRef> {
+    if let Some(event_loop) = running_loop(vm)? {
+        return Ok(event_loop);
+    }
+    let module = vm.import("asyncio")?;
+    let current = vm.get_attribute(module.clone(), "_event_loop")?;
+    if current.is(&vm.get_none()) {
+        let event_loop = PyEventLoop::new().into_ref(vm);
+        vm.set_attr(
+            &module,
+            vm.new_str("_event_loop".to_string()),
+            event_loop.as_object().clone(),
+        )?;
+        Ok(event_loop)
+    } else {
+        PyEventLoopRef::try_from_object(vm, current)
+    }
+}
+
+/// Wrap a coroutine in a task, or pass a future on as is.
+fn ensure_future(
+    vm: &VirtualMachine,
+    obj: PyObjectRef,
+    event_loop: Option<PyEventLoopRef>,
+) -> PyResult<PyFutureRef> {
+    match obj.downcast::<PyFuture>() {
+        Ok(future) => Ok(future),
+        Err(obj) => {
+            if obj.payload_is::<PyGenerator>() {
+                let event_loop = match event_loop {
+                    Some(event_loop) => event_loop,
+                    None => get_event_loop(vm)?,
+                };
+                event_loop.spawn(obj, vm)
+            } else {
+                Err(vm.new_type_error(
+                    "An asyncio.Future, a coroutine or an awaitable is required".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/// Wait until one of the file descriptors is ready or the timeout runs out,
+/// returning the readable and writable ones. Must be called with the GIL
+/// released.
+#[cfg(unix)]
+fn poll(
+    read: &[i64],
+    write: &[i64],
+    timeout: Option<Duration>,
+) -> io::Result<(Vec<i64>, Vec<i64>)> {
+    let watched = |fd: &i64, events| libc::pollfd {
+        fd: *fd as libc::c_int,
+        events,
+        revents: 0,
+    };
+    let mut fds: Vec<libc::pollfd> = read
+        .iter()
+        .map(|fd| watched(fd, libc::POLLIN))
+        .chain(write.iter().map(|fd| watched(fd, libc::POLLOUT)))
+        .collect();
+    let timeout = match timeout {
+        // Round up, so that timers are not woken up too early.
+        Some(timeout) => {
+            let millis = timeout.as_secs() * 1000
+                + (u64::from(timeout.subsec_nanos()) + 999_999) / 1_000_000;
+            millis.min(libc::c_int::max_value() as u64) as libc::c_int
+        }
+        None => -1,
+    };
+    let result = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+    let mut readable = vec![];
+    let mut writable = vec![];
+    if result < 0 {
+        let err = io::Error::last_os_error();
+        if err.kind() == io::ErrorKind::Interrupted {
+            return Ok((readable, writable));
+        }
+        return Err(err);
+    }
+    for (index, fd) in fds.iter().enumerate() {
+        if fd.revents != 0 {
+            if index < read.len() {
+                readable.push(i64::from(fd.fd));
+            } else {
+                writable.push(i64::from(fd.fd));
+            }
+        }
+    }
+    Ok((readable, writable))
+}
+
+#[cfg(not(unix))]
+fn poll(
+    read: &[i64],
+    write: &[i64],
+    timeout: Option<Duration>,
+) -> io::Result<(Vec<i64>, Vec<i64>)> {
+    if !read.is_empty() || !write.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "waiting for sockets is not supported on this platform",
+        ));
+    }
+    if let Some(timeout) = timeout {
+        std::thread::sleep(timeout);
+    }
+    Ok((vec![], vec![]))
+}
+
+impl PyEventLoopRef {
+    fn new(cls: PyClassRef, vm: &VirtualMachine) -> PyResult<Self> {
+        PyEventLoop::new().into_ref_with_type(vm, cls)
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn schedule(&self, callback: Callback, vm: &VirtualMachine) -> PyHandleRef {
+        let handle = PyHandle::new(callback).into_ref(vm);
+        self.ready.borrow_mut().push_back(handle.clone());
+        handle
+    }
+
+    fn schedule_at(
+        &self,
+        deadline: Duration,
+        callback: Callback,
+        vm: &VirtualMachine,
+    ) -> PyHandleRef {
+        let handle = PyHandle {
+            when: Some(seconds(deadline)),
+            ..PyHandle::new(callback)
+        };
+        let handle = handle
+            .into_ref_with_type(vm, vm.class("asyncio", "TimerHandle"))
+            .unwrap();
+        let sequence = self.timer_count.get();
+        self.timer_count.set(sequence + 1);
+        self.timers.borrow_mut().push(Timer {
+            deadline,
+            sequence,
+            handle: handle.clone(),
+        });
+        handle
+    }
+
+    fn new_future(&self, vm: &VirtualMachine) -> PyFutureRef {
+        PyFuture::new(self.clone(), None).into_ref(vm)
+    }
+
+    fn spawn(&self, coro: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyFutureRef> {
+        let task = PyFuture::new(self.clone(), Some(coro))
+            .into_ref_with_type(vm, vm.class("asyncio", "Task"))?;
+        self.schedule(Callback::Step(task.clone(), None), vm);
+        Ok(task)
+    }
+
+    fn time(self, _vm: &VirtualMachine) -> f64 {
+        seconds(self.elapsed())
+    }
+
+    fn call_soon(
+        self,
+        callback: PyObjectRef,
+        args: Args,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyHandleRef> {
+        self.check_closed(vm)?;
+        Ok(self.schedule(Callback::Call(callback, args.into_iter().collect()), vm))
+    }
+
+    fn call_later(
+        self,
+        delay: PyObjectRef,
+        callback: PyObjectRef,
+        args: Args,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyHandleRef> {
+        let when = seconds(self.elapsed()) + objfloat::make_float(vm, &delay)?;
+        self.call_at(vm.ctx.new_float(when), callback, args, vm)
+    }
+
+    fn call_at(
+        self,
+        when: PyObjectRef,
+        callback: PyObjectRef,
+        args: Args,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyHandleRef> {
+        self.check_closed(vm)?;
+        let deadline = duration(objfloat::make_float(vm, &when)?);
+        let callback = Callback::Call(callback, args.into_iter().collect());
+        Ok(self.schedule_at(deadline, callback, vm))
+    }
+
+    fn create_future(self, vm: &VirtualMachine) -> PyFutureRef {
+        self.new_future(vm)
+    }
+
+    fn create_task(self, coro: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyFutureRef> {
+        self.check_closed(vm)?;
+        if !coro.payload_is::<PyGenerator>() {
+            return Err(vm.new_type_error(format!(
+                "a coroutine was expected, got {}",
+                vm.to_repr(&coro)?
+            )));
+        }
+        self.spawn(coro, vm)
+    }
+
+    fn run_until_complete(self, future: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        self.check_closed(vm)?;
+        let future = ensure_future(vm, future, Some(self.clone()))?;
+        future.add_callback(DoneCallback::StopLoop, vm);
+        self.clone().run_forever(vm)?;
+        if !future.is_done() {
+            return Err(
+                vm.new_runtime_error("Event loop stopped before Future completed.".to_string())
+            );
+        }
+        future.outcome(vm)
+    }
+
+    /// Run until `stop()` is called, or until nothing is left that could
+    /// ever wake the loop up again.
+    fn run_forever(self, vm: &VirtualMachine) -> PyResult<()> {
+        self.check_closed(vm)?;
+        if self.thread.get().is_some() {
+            return Err(vm.new_runtime_error("This event loop is already running".to_string()));
+        }
+        if running_loop(vm)?.is_some() {
+            return Err(vm.new_runtime_error(
+                "Cannot run the event loop while another loop is running".to_string(),
+            ));
+        }
+        let running = running_loops(vm)?;
+        running.clone().append(self.as_object().clone(), vm);
+        self.thread.set(Some(thread::get_ident()));
+
+        let result = self.run_until_stopped(vm);
+
+        self.thread.set(None);
+        self.stopping.set(false);
+        running
+            .elements
+            .borrow_mut()
+            .retain(|other| !other.is(self.as_object()));
+        result
+    }
+
+    fn run_until_stopped(&self, vm: &VirtualMachine) -> PyResult<()> {
+        while self.run_once(vm)? {
+            if self.stopping.get() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for I/O or timers, then run the callbacks that are ready.
+    /// Returns false if there was nothing left to wait for.
+    fn run_once(&self, vm: &VirtualMachine) -> PyResult<bool> {
+        let timeout = if !self.ready.borrow().is_empty() || self.stopping.get() {
+            Some(Duration::from_secs(0))
+        } else if let Some(timer) = self.timers.borrow().peek() {
+            Some(
+                timer
+                    .deadline
+                    .checked_sub(self.elapsed())
+                    .unwrap_or_default(),
+            )
+        } else if self.readers.borrow().is_empty() && self.writers.borrow().is_empty() {
+            return Ok(false);
+        } else {
+            None
+        };
+
+        let read: Vec<i64> = self.readers.borrow().keys().cloned().collect();
+        let write: Vec<i64> = self.writers.borrow().keys().cloned().collect();
+        if !read.is_empty() || !write.is_empty() || timeout != Some(Duration::from_secs(0)) {
+            let (readable, writable) = vm
+                .allow_threads(|| poll(&read, &write, timeout))
+                .map_err(|err| vm.new_io_error(&err))?;
+            let mut ready = self.ready.borrow_mut();
+            for (fds, writable) in vec![(readable, false), (writable, true)] {
+                let watchers = self.watchers(writable).borrow();
+                ready.extend(fds.iter().filter_map(|fd| watchers.get(fd).cloned()));
+            }
+        }
+
+        let now = self.elapsed();
+        loop {
+            let timer = {
+                let mut timers = self.timers.borrow_mut();
+                match timers.peek() {
+                    Some(timer) if timer.deadline <= now => timers.pop(),
+                    _ => None,
+                }
+            };
+            match timer {
+                Some(timer) => self.ready.borrow_mut().push_back(timer.handle),
+                None => break,
+            }
+        }
+
+        // Callbacks scheduled from now on wait for the next iteration.
+        let count = self.ready.borrow().len();
+        for _ in 0..count {
+            let handle = self.ready.borrow_mut().pop_front();
+            match handle {
+                Some(ref handle) if !handle.cancelled.get() => self.run_callback(handle, vm),
+                Some(_) => {}
+                None => break,
+            }
+        }
+        Ok(true)
+    }
+
+    fn run_callback(&self, handle: &PyHandleRef, vm: &VirtualMachine) {
+        let result = match handle.callback {
+            Callback::Call(ref callable, ref args) => {
+                vm.invoke(callable.clone(), args.clone()).map(|_| ())
+            }
+            Callback::Step(ref task, ref exc) => step_task(task, exc.clone(), vm),
+            Callback::Done(ref callback, ref future) => future_done(callback, future, vm),
+            Callback::SetResult(ref future, ref result) => {
+                if !future.is_done() {
+                    future.finish(FutureState::Finished(result.clone()), vm);
+                }
+                Ok(())
+            }
+            Callback::Timeout(ref outer, ref inner) => {
+                if outer.is_done() {
+                    Ok(())
+                } else {
+                    outer.waiting.borrow_mut().clear();
+                    inner.request_cancel(vm).and_then(|_| {
+                        let timeout = new_error(vm, "TimeoutError")?;
+                        outer.finish(FutureState::Failed(timeout), vm);
+                        Ok(())
+                    })
+                }
+            }
+            Callback::Socket(ref future, ref sock, ref op) => {
+                let finished = if future.is_done() {
+                    Ok(true)
+                } else {
+                    try_socket_op(future, sock, op, vm)
+                };
+                if let Ok(true) = finished {
+                    if let Some(fd) = sock.fileno() {
+                        self.watchers(op.writable()).borrow_mut().remove(&fd);
+                    }
+                }
+                finished.map(|_| ())
+            }
+        };
+        if let Err(exc) = result {
+            thread::print_thread_exception(vm, "Exception in callback:", &exc);
+        }
+    }
+
+    fn stop(self, _vm: &VirtualMachine) {
+        self.stopping.set(true);
+    }
+
+    fn is_running(self, _vm: &VirtualMachine) -> bool {
+        self.thread.get().is_some()
+    }
+
+    fn is_closed(self, _vm: &VirtualMachine) -> bool {
+        self.closed.get()
+    }
+
+    fn close(self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.thread.get().is_some() {
+            return Err(vm.new_runtime_error("Cannot close a running event loop".to_string()));
+        }
+        self.closed.set(true);
+        // Let go of everything still pending, which may refer back to us.
+        self.ready.borrow_mut().clear();
+        self.timers.borrow_mut().clear();
+        self.readers.borrow_mut().clear();
+        self.writers.borrow_mut().clear();
+        Ok(())
+    }
+
+    fn watch(&self, fd: i64, writable: bool, callback: Callback, vm: &VirtualMachine) {
+        let handle = PyHandle::new(callback).into_ref(vm);
+        self.watchers(writable).borrow_mut().insert(fd, handle);
+    }
+
+    fn add_reader(
+        self,
+        fd: i64,
+        callback: PyObjectRef,
+        args: Args,
+        vm: &VirtualMachine,
+    ) -> PyResult<()> {
+        self.check_closed(vm)?;
+        let callback = Callback::Call(callback, args.into_iter().collect());
+        self.watch(fd, false, callback, vm);
+        Ok(())
+    }
+
+    fn remove_reader(self, fd: i64, _vm: &VirtualMachine) -> bool {
+        self.readers.borrow_mut().remove(&fd).is_some()
+    }
+
+    fn add_writer(
+        self,
+        fd: i64,
+        callback: PyObjectRef,
+        args: Args,
+        vm: &VirtualMachine,
+    ) -> PyResult<()> {
+        self.check_closed(vm)?;
+        let callback = Callback::Call(callback, args.into_iter().collect());
+        self.watch(fd, true, callback, vm);
+        Ok(())
+    }
+
+    fn remove_writer(self, fd: i64, _vm: &VirtualMachine) -> bool {
+        self.writers.borrow_mut().remove(&fd).is_some()
+    }
+
+    fn socket_op(
+        &self,
+        sock: SocketRef,
+        op: SocketOp,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyFutureRef> {
+        self.check_closed(vm)?;
+        let future = self.new_future(vm);
+        if !try_socket_op(&future, &sock, &op, vm)? {
+            if let Some(fd) = sock.fileno() {
+                self.watch(
+                    fd,
+                    op.writable(),
+                    Callback::Socket(future.clone(), sock, op),
+                    vm,
+                );
+            }
+        }
+        Ok(future)
+    }
+
+    fn sock_recv(
+        self,
+        sock: SocketRef,
+        nbytes: usize,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyFutureRef> {
+        self.socket_op(sock, SocketOp::Recv(nbytes), vm)
+    }
+
+    fn sock_sendall(
+        self,
+        sock: SocketRef,
+        data: PyBytesRef,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyFutureRef> {
+        let data = RefCell::new(data.to_vec());
+        self.socket_op(sock, SocketOp::SendAll(data), vm)
+    }
+
+    fn sock_accept(self, sock: SocketRef, vm: &VirtualMachine) -> PyResult<PyFutureRef> {
+        self.socket_op(sock, SocketOp::Accept, vm)
+    }
+
+    /// Connecting itself blocks, only the resulting socket is non-blocking.
+    fn sock_connect(
+        self,
+        sock: SocketRef,
+        address: PyObjectRef,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyFutureRef> {
+        self.check_closed(vm)?;
+        let future = self.new_future(vm);
+        let state = match vm.call_method(sock.as_object(), "connect", vec![address]) {
+            Ok(_) => FutureState::Finished(vm.get_none()),
+            Err(exc) => FutureState::Failed(exc),
+        };
+        future.finish(state, vm);
+        Ok(future)
+    }
+}
+
+/// Try a socket operation, which finishes `future` unless it would block.
+fn try_socket_op(
+    future: &PyFutureRef,
+    sock: &SocketRef,
+    op: &SocketOp,
+    vm: &VirtualMachine,
+) -> PyResult<bool> {
+    let result = match op {
+        SocketOp::Recv(size) => sock.recv_some(*size).map(|data| vm.ctx.new_bytes(data)),
+        SocketOp::SendAll(data) => loop {
+            let mut data = data.borrow_mut();
+            if data.is_empty() {
+                break Ok(vm.get_none());
+            }
+            match sock.send_some(&data) {
+                Ok(size) => {
+                    data.drain(..size);
+                }
+                Err(err) => break Err(err),
+            }
+        },
+        SocketOp::Accept => match sock.accept_socket() {
+            Ok((conn, address)) => {
+                let address = socket::get_addr_tuple(vm, address)?;
+                Ok(vm
+                    .ctx
+                    .new_tuple(vec![conn.into_ref(vm).into_object(), address]))
+            }
+            Err(err) => Err(err),
+        },
+    };
+    let state = match result {
+        Ok(value) => FutureState::Finished(value),
+        Err(ref err)
+            if err.kind() == io::ErrorKind::WouldBlock
+                || err.kind() == io::ErrorKind::Interrupted =>
+        {
+            return Ok(false);
+        }
+        Err(err) => FutureState::Failed(vm.new_io_error(&err)),
+    };
+    future.finish(state, vm);
+    Ok(true)
+}
+
+#[derive(Debug, Clone)]
+enum FutureState {
+    Pending,
+    Finished(PyObjectRef),
+    Failed(PyObjectRef),
+    Cancelled,
+}
+
+/// A future, or a task when it drives a coroutine.
+#[derive(Debug)]
+pub struct PyFuture {
+    event_loop: PyEventLoopRef,
+    state: RefCell<FutureState>,
+    callbacks: RefCell<Vec<DoneCallback>>,
+    /// The futures this one is waiting for, which get cancelled with it.
+    waiting: RefCell<Vec<PyFutureRef>>,
+    coro: Option<PyObjectRef>,
+    /// Throw `CancelledError` into the coroutine when it next runs.
+    must_cancel: Cell<bool>,
+}
+
+pub type PyFutureRef = PyRef<PyFuture>;
+
+impl PyValue for PyFuture {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("asyncio", "Future")
+    }
+}
+
+impl PyFuture {
+    fn new(event_loop: PyEventLoopRef, coro: Option<PyObjectRef>) -> PyFuture {
+        PyFuture {
+            event_loop,
+            state: RefCell::new(FutureState::Pending),
+            callbacks: RefCell::new(vec![]),
+            waiting: RefCell::new(vec![]),
+            coro,
+            must_cancel: Cell::new(false),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        match *self.state.borrow() {
+            FutureState::Pending => false,
+            _ => true,
+        }
+    }
+
+    fn check_pending(&self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.is_done() {
+            Err(vm.new_exception(
+                vm.class("asyncio", "InvalidStateError"),
+                "invalid state".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The result, raising the exception if there is one.
+    fn outcome(&self, vm: &VirtualMachine) -> PyResult {
+        match *self.state.borrow() {
+            FutureState::Pending => Err(vm.new_exception(
+                vm.class("asyncio", "InvalidStateError"),
+                "Result is not set.".to_string(),
+            )),
+            FutureState::Finished(ref result) => Ok(result.clone()),
+            FutureState::Failed(ref exc) => Err(exc.clone()),
+            FutureState::Cancelled => Err(new_error(vm, "CancelledError")?),
+        }
+    }
+
+    /// The exception a done future raises, if any.
+    fn error(&self, vm: &VirtualMachine) -> PyResult<Option<PyObjectRef>> {
+        match *self.state.borrow() {
+            FutureState::Failed(ref exc) => Ok(Some(exc.clone())),
+            FutureState::Cancelled => Ok(Some(new_error(vm, "CancelledError")?)),
+            FutureState::Pending | FutureState::Finished(_) => Ok(None),
+        }
+    }
+}
+
+/// Advance the coroutine of a task to the next future it waits for.
+fn step_task(task: &PyFutureRef, exc: Option<PyObjectRef>, vm: &VirtualMachine) -> PyResult<()> {
+    let coro = match task.coro {
+        Some(ref coro) if !task.is_done() => coro.clone(),
+        _ => return Ok(()),
+    };
+    let exc = if task.must_cancel.replace(false) {
+        Some(new_error(vm, "CancelledError")?)
+    } else {
+        exc
+    };
+    task.waiting.borrow_mut().clear();
+
+    let event_loop = &task.event_loop;
+    let previous = event_loop.current_task.replace(Some(task.clone()));
+    let result = match exc {
+        Some(exc) => vm.call_method(&coro, "throw", vec![exc]),
+        None => vm.call_method(&coro, "send", vec![vm.get_none()]),
+    };
+    event_loop.current_task.replace(previous);
+
+    match result {
+        Ok(yielded) => match yielded.downcast::<PyFuture>() {
+            Ok(ref future) if future.is(task) => {
+                let exc = vm.new_runtime_error("Task cannot await on itself".to_string());
+                event_loop.schedule(Callback::Step(task.clone(), Some(exc)), vm);
+            }
+            Ok(future) => {
+                future.add_callback(DoneCallback::Wakeup(task.clone()), vm);
+                task.waiting.borrow_mut().push(future.clone());
+                if task.must_cancel.get() && future.request_cancel(vm)? {
+                    task.must_cancel.set(false);
+                }
+            }
+            Err(ref yielded) if yielded.is(&vm.get_none()) => {
+                // A bare `yield` just lets the other tasks run first.
+                event_loop.schedule(Callback::Step(task.clone(), None), vm);
+            }
+            Err(yielded) => {
+                let exc =
+                    vm.new_runtime_error(format!("Task got bad yield: {}", vm.to_repr(&yielded)?));
+                event_loop.schedule(Callback::Step(task.clone(), Some(exc)), vm);
+            }
+        },
+        Err(exc) => {
+            let state = if objtype::isinstance(&exc, &vm.ctx.exceptions.stop_iteration) {
+                if task.must_cancel.replace(false) {
+                    FutureState::Cancelled
+                } else {
+                    FutureState::Finished(objiter::stop_iteration_value(vm, &exc)?)
+                }
+            } else if objtype::isinstance(&exc, &vm.class("asyncio", "CancelledError")) {
+                FutureState::Cancelled
+            } else {
+                FutureState::Failed(exc)
+            };
+            task.finish(state, vm);
+        }
+    }
+    Ok(())
+}
+
+fn future_done(callback: &DoneCallback, future: &PyFutureRef, vm: &VirtualMachine) -> PyResult<()> {
+    match callback {
+        DoneCallback::Call(callable) => {
+            vm.invoke(callable.clone(), vec![future.as_object().clone()])?;
+        }
+        DoneCallback::Wakeup(task) => step_task(task, future.error(vm)?, vm)?,
+        DoneCallback::Gather {
+            outer,
+            children,
+            return_exceptions,
+        } => {
+            if outer.is_done() {
+                return Ok(());
+            }
+            if !return_exceptions {
+                if let Some(exc) = future.error(vm)? {
+                    outer.finish(FutureState::Failed(exc), vm);
+                    return Ok(());
+                }
+            }
+            if children.iter().all(|child| child.is_done()) {
+                let mut results = vec![];
+                for child in children.iter() {
+                    results.push(match child.error(vm)? {
+                        Some(exc) => exc,
+                        None => child.outcome(vm)?,
+                    });
+                }
+                outer.finish(FutureState::Finished(vm.ctx.new_list(results)), vm);
+            }
+        }
+        DoneCallback::Chain { outer, timer } => {
+            timer.cancelled.set(true);
+            if !outer.is_done() {
+                let state = future.state.borrow().clone();
+                outer.finish(state, vm);
+            }
+        }
+        DoneCallback::StopLoop => future.event_loop.stopping.set(true),
+    }
+    Ok(())
+}
+
+/// The keyword only `loop` parameter.
+struct LoopArgs {
+    event_loop: Option<PyEventLoopRef>,
+}
+
+impl FromArgs for LoopArgs {
+    fn from_args(vm: &VirtualMachine, args: &mut PyFuncArgs) -> Result<Self, ArgumentError> {
+        let event_loop = match args.take_keyword("loop") {
+            Some(event_loop) => Option::<PyEventLoopRef>::try_from_object(vm, event_loop)?,
+            None => None,
+        };
+        Ok(LoopArgs { event_loop })
+    }
+}
+
+impl LoopArgs {
+    fn event_loop(self, vm: &VirtualMachine) -> PyResult<PyEventLoopRef> {
+        match self.event_loop {
+            Some(event_loop) => Ok(event_loop),
+            None => get_event_loop(vm),
+        }
+    }
+}
+
+impl PyFutureRef {
+    fn new(cls: PyClassRef, args: LoopArgs, vm: &VirtualMachine) -> PyResult<Self> {
+        PyFuture::new(args.event_loop(vm)?, None).into_ref_with_type(vm, cls)
+    }
+
+    fn new_task(
+        cls: PyClassRef,
+        coro: PyObjectRef,
+        args: LoopArgs,
+        vm: &VirtualMachine,
+    ) -> PyResult<Self> {
+        if !coro.payload_is::<PyGenerator>() {
+            return Err(vm.new_type_error(format!(
+                "a coroutine was expected, got {}",
+                vm.to_repr(&coro)?
+            )));
+        }
+        let event_loop = args.event_loop(vm)?;
+        let task = PyFuture::new(event_loop.clone(), Some(coro)).into_ref_with_type(vm, cls)?;
+        event_loop.schedule(Callback::Step(task.clone(), None), vm);
+        Ok(task)
+    }
+
+    fn finish(&self, state: FutureState, vm: &VirtualMachine) {
+        *self.state.borrow_mut() = state;
+        let callbacks = self.callbacks.replace(vec![]);
+        for callback in callbacks {
+            self.event_loop
+                .schedule(Callback::Done(callback, self.clone()), vm);
+        }
+    }
+
+    fn add_callback(&self, callback: DoneCallback, vm: &VirtualMachine) {
+        if self.is_done() {
+            self.event_loop
+                .schedule(Callback::Done(callback, self.clone()), vm);
+        } else {
+            self.callbacks.borrow_mut().push(callback);
+        }
+    }
+
+    fn request_cancel(&self, vm: &VirtualMachine) -> PyResult<bool> {
+        if self.is_done() {
+            return Ok(false);
+        }
+        let waiting = self.waiting.replace(vec![]);
+        let mut cancelled = false;
+        for future in waiting {
+            cancelled |= future.request_cancel(vm)?;
+        }
+        if self.coro.is_some() {
+            // The task finishes once its coroutine handles the cancellation.
+            if !cancelled {
+                self.must_cancel.set(true);
+            }
+        } else {
+            self.finish(FutureState::Cancelled, vm);
+        }
+        Ok(true)
+    }
+
+    fn result(self, vm: &VirtualMachine) -> PyResult {
+        self.outcome(vm)
+    }
+
+    fn exception(self, vm: &VirtualMachine) -> PyResult {
+        match *self.state.borrow() {
+            FutureState::Pending => Err(vm.new_exception(
+                vm.class("asyncio", "InvalidStateError"),
+                "Exception is not set.".to_string(),
+            )),
+            FutureState::Finished(_) => Ok(vm.get_none()),
+            FutureState::Failed(ref exc) => Ok(exc.clone()),
+            FutureState::Cancelled => Err(new_error(vm, "CancelledError")?),
+        }
+    }
+
+    fn done(self, _vm: &VirtualMachine) -> bool {
+        self.is_done()
+    }
+
+    fn cancelled(self, _vm: &VirtualMachine) -> bool {
+        match *self.state.borrow() {
+            FutureState::Cancelled => true,
+            _ => false,
+        }
+    }
+
+    fn cancel(self, vm: &VirtualMachine) -> PyResult<bool> {
+        self.request_cancel(vm)
+    }
+
+    fn set_result(self, result: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        self.check_pending(vm)?;
+        self.finish(FutureState::Finished(result), vm);
+        Ok(())
+    }
+
+    fn set_exception(self, exception: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        self.check_pending(vm)?;
+        let exception = objgenerator::normalize_exception(exception, None, vm)?;
+        if objtype::isinstance(&exception, &vm.ctx.exceptions.stop_iteration) {
+            return Err(vm.new_type_error(
+                "StopIteration interacts badly with generators and cannot be raised into a Future"
+                    .to_string(),
+            ));
+        }
+        self.finish(FutureState::Failed(exception), vm);
+        Ok(())
+    }
+
+    fn add_done_callback(self, callback: PyObjectRef, vm: &VirtualMachine) {
+        self.add_callback(DoneCallback::Call(callback), vm);
+    }
+
+    fn remove_done_callback(self, callback: PyObjectRef, _vm: &VirtualMachine) -> usize {
+        let mut callbacks = self.callbacks.borrow_mut();
+        let before = callbacks.len();
+        callbacks.retain(|other| match other {
+            DoneCallback::Call(other) => !other.is(&callback),
+            _ => true,
+        });
+        before - callbacks.len()
+    }
+
+    fn get_loop(self, _vm: &VirtualMachine) -> PyEventLoopRef {
+        self.event_loop.clone()
+    }
+
+    fn get_coro(self, vm: &VirtualMachine) -> PyObjectRef {
+        self.coro.clone().unwrap_or_else(|| vm.get_none())
+    }
+
+    fn iter(self, vm: &VirtualMachine) -> PyFutureIterRef {
+        PyFutureIter {
+            future: self,
+            yielded: Cell::new(false),
+        }
+        .into_ref(vm)
+    }
+
+    fn repr(self, vm: &VirtualMachine) -> PyResult<String> {
+        let state = match *self.state.borrow() {
+            FutureState::Pending => "pending".to_string(),
+            FutureState::Finished(ref result) => format!("finished result={}", vm.to_repr(result)?),
+            FutureState::Failed(ref exc) => format!("finished exception={}", vm.to_repr(exc)?),
+            FutureState::Cancelled => "cancelled".to_string(),
+        };
+        Ok(format!("<{} {}>", self.as_object().class().name, state))
+    }
+}
+
+/// What `yield from future` iterates over: it yields the future once, and
+/// returns its result when the task resumes it after the future is done.
+#[derive(Debug)]
+pub struct PyFutureIter {
+    future: PyFutureRef,
+    yielded: Cell<bool>,
+}
+
+pub type PyFutureIterRef = PyRef<PyFutureIter>;
+
+impl PyValue for PyFutureIter {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("asyncio", "_FutureIter")
+    }
+}
+
+impl PyFutureIterRef {
+    fn iter(self, _vm: &VirtualMachine) -> Self {
+        self
+    }
+
+    fn next(self, vm: &VirtualMachine) -> PyResult {
+        if !self.future.is_done() {
+            if self.yielded.replace(true) {
+                return Err(vm.new_runtime_error("await wasn't used with future".to_string()));
+            }
+            return Ok(self.future.as_object().clone());
+        }
+        let result = self.future.outcome(vm)?;
+        let stop_iteration = vm.ctx.exceptions.stop_iteration.clone().into_object();
+        Err(vm.invoke(stop_iteration, vec![result])?)
+    }
+
+    fn send(self, _value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        self.next(vm)
+    }
+
+    fn throw(
+        self,
+        exc_type: PyObjectRef,
+        exc_val: OptionalArg<PyObjectRef>,
+        _exc_tb: OptionalArg<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        Err(objgenerator::normalize_exception(
+            exc_type,
+            exc_val.into_option(),
+            vm,
+        )?)
+    }
+
+    fn close(self, _vm: &VirtualMachine) {}
+}
+
+fn asyncio_get_event_loop(vm: &VirtualMachine) -> PyResult<PyEventLoopRef> {
+    get_event_loop(vm)
+}
+
+fn asyncio_get_running_loop(vm: &VirtualMachine) -> PyResult<PyEventLoopRef> {
+    running_loop(vm)?.ok_or_else(|| vm.new_runtime_error("no running event loop".to_string()))
+}
+
+fn asyncio_new_event_loop(vm: &VirtualMachine) -> PyEventLoopRef {
+    PyEventLoop::new().into_ref(vm)
+}
+
+fn asyncio_set_event_loop(event_loop: Option<PyEventLoopRef>, vm: &VirtualMachine) -> PyResult<()> {
+    let module = vm.import("asyncio")?;
+    let event_loop = match event_loop {
+        Some(event_loop) => event_loop.into_object(),
+        None => vm.get_none(),
+    };
+    vm.set_attr(&module, vm.new_str("_event_loop".to_string()), event_loop)?;
+    Ok(())
+}
+
+/// Run a coroutine to completion on a new event loop.
+fn asyncio_run(main: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    if running_loop(vm)?.is_some() {
+        return Err(vm.new_runtime_error(
+            "asyncio.run() cannot be called from a running event loop".to_string(),
+        ));
+    }
+    if !main.payload_is::<PyGenerator>() {
+        return Err(vm.new_value_error(format!(
+            "a coroutine was expected, got {}",
+            vm.to_repr(&main)?
+        )));
+    }
+    let event_loop = PyEventLoop::new().into_ref(vm);
+    asyncio_set_event_loop(Some(event_loop.clone()), vm)?;
+    let result = event_loop.clone().run_until_complete(main, vm);
+    event_loop.close(vm)?;
+    asyncio_set_event_loop(None, vm)?;
+    result
+}
+
+fn asyncio_ensure_future(
+    obj: PyObjectRef,
+    args: LoopArgs,
+    vm: &VirtualMachine,
+) -> PyResult<PyFutureRef> {
+    ensure_future(vm, obj, args.event_loop)
+}
+
+fn asyncio_sleep(
+    delay: PyObjectRef,
+    result: OptionalArg<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult<PyFutureRef> {
+    let delay = objfloat::make_float(vm, &delay)?;
+    let event_loop = get_event_loop(vm)?;
+    let future = event_loop.new_future(vm);
+    let result = result.into_option().unwrap_or_else(|| vm.get_none());
+    let callback = Callback::SetResult(future.clone(), result);
+    if delay <= 0.0 {
+        event_loop.schedule(callback, vm);
+    } else {
+        let deadline = event_loop.elapsed() + duration(delay);
+        event_loop.schedule_at(deadline, callback, vm);
+    }
+    Ok(future)
+}
+
+/// The keyword only `return_exceptions` parameter of `gather()`.
+struct GatherArgs {
+    return_exceptions: bool,
+}
+
+impl FromArgs for GatherArgs {
+    fn from_args(vm: &VirtualMachine, args: &mut PyFuncArgs) -> Result<Self, ArgumentError> {
+        let return_exceptions = match args.take_keyword("return_exceptions") {
+            Some(value) => bool::try_from_object(vm, value)?,
+            None => false,
+        };
+        Ok(GatherArgs { return_exceptions })
+    }
+}
+
+/// A future for the list of results of all the given awaitables.
+fn asyncio_gather(aws: Args, args: GatherArgs, vm: &VirtualMachine) -> PyResult<PyFutureRef> {
+    let event_loop = get_event_loop(vm)?;
+    let mut children = vec![];
+    for aw in aws {
+        children.push(ensure_future(vm, aw, Some(event_loop.clone()))?);
+    }
+    let outer = event_loop.new_future(vm);
+    if children.is_empty() {
+        outer.finish(FutureState::Finished(vm.ctx.new_list(vec![])), vm);
+        return Ok(outer);
+    }
+    let children = Rc::new(children);
+    for child in children.iter() {
+        child.add_callback(
+            DoneCallback::Gather {
+                outer: outer.clone(),
+                children: children.clone(),
+                return_exceptions: args.return_exceptions,
+            },
+            vm,
+        );
+    }
+    *outer.waiting.borrow_mut() = children.to_vec();
+    Ok(outer)
+}
+
+/// A future for the outcome of `aw`, which is cancelled if it takes longer
+/// than `timeout` seconds.
+fn asyncio_wait_for(
+    aw: PyObjectRef,
+    timeout: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<PyFutureRef> {
+    let event_loop = get_event_loop(vm)?;
+    let inner = ensure_future(vm, aw, Some(event_loop.clone()))?;
+    if timeout.is(&vm.get_none()) {
+        return Ok(inner);
+    }
+    let deadline = event_loop.elapsed() + duration(objfloat::make_float(vm, &timeout)?);
+    let outer = event_loop.new_future(vm);
+    let timer = event_loop.schedule_at(
+        deadline,
+        Callback::Timeout(outer.clone(), inner.clone()),
+        vm,
+    );
+    inner.add_callback(
+        DoneCallback::Chain {
+            outer: outer.clone(),
+            timer,
+        },
+        vm,
+    );
+    outer.waiting.borrow_mut().push(inner);
+    Ok(outer)
+}
+
+fn asyncio_current_task(
+    event_loop: OptionalArg<PyEventLoopRef>,
+    vm: &VirtualMachine,
+) -> PyResult<Option<PyFutureRef>> {
+    let event_loop = match event_loop {
+        OptionalArg::Present(event_loop) => event_loop,
+        OptionalArg::Missing => asyncio_get_running_loop(vm)?,
+    };
+    let task = event_loop.current_task.borrow().clone();
+    Ok(task)
+}
+
+fn asyncio_iscoroutine(obj: PyObjectRef, _vm: &VirtualMachine) -> bool {
+    obj.payload_is::<PyGenerator>()
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    let handle = py_class!(ctx, "Handle", ctx.object(), {
+        "cancel" => ctx.new_rustfunc(PyHandleRef::cancel),
+        "cancelled" => ctx.new_rustfunc(PyHandleRef::cancelled),
+    });
+
+    let timer_handle = py_class!(ctx, "TimerHandle", handle.clone(), {
+        "when" => ctx.new_rustfunc(PyHandleRef::when),
+    });
+
+    let event_loop = py_class!(ctx, "AbstractEventLoop", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyEventLoopRef::new),
+        "time" => ctx.new_rustfunc(PyEventLoopRef::time),
+        "call_soon" => ctx.new_rustfunc(PyEventLoopRef::call_soon),
+        "call_later" => ctx.new_rustfunc(PyEventLoopRef::call_later),
+        "call_at" => ctx.new_rustfunc(PyEventLoopRef::call_at),
+        "create_future" => ctx.new_rustfunc(PyEventLoopRef::create_future),
+        "create_task" => ctx.new_rustfunc(PyEventLoopRef::create_task),
+        "run_until_complete" => ctx.new_rustfunc(PyEventLoopRef::run_until_complete),
+        "run_forever" => ctx.new_rustfunc(PyEventLoopRef::run_forever),
+        "stop" => ctx.new_rustfunc(PyEventLoopRef::stop),
+        "is_running" => ctx.new_rustfunc(PyEventLoopRef::is_running),
+        "is_closed" => ctx.new_rustfunc(PyEventLoopRef::is_closed),
+        "close" => ctx.new_rustfunc(PyEventLoopRef::close),
+        "add_reader" => ctx.new_rustfunc(PyEventLoopRef::add_reader),
+        "remove_reader" => ctx.new_rustfunc(PyEventLoopRef::remove_reader),
+        "add_writer" => ctx.new_rustfunc(PyEventLoopRef::add_writer),
+        "remove_writer" => ctx.new_rustfunc(PyEventLoopRef::remove_writer),
+        "sock_recv" => ctx.new_rustfunc(PyEventLoopRef::sock_recv),
+        "sock_sendall" => ctx.new_rustfunc(PyEventLoopRef::sock_sendall),
+        "sock_accept" => ctx.new_rustfunc(PyEventLoopRef::sock_accept),
+        "sock_connect" => ctx.new_rustfunc(PyEventLoopRef::sock_connect),
+    });
+
+    let future = py_class!(ctx, "Future", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyFutureRef::new),
+        "__iter__" => ctx.new_rustfunc(PyFutureRef::iter),
+        "__repr__" => ctx.new_rustfunc(PyFutureRef::repr),
+        "result" => ctx.new_rustfunc(PyFutureRef::result),
+        "exception" => ctx.new_rustfunc(PyFutureRef::exception),
+        "done" => ctx.new_rustfunc(PyFutureRef::done),
+        "cancelled" => ctx.new_rustfunc(PyFutureRef::cancelled),
+        "cancel" => ctx.new_rustfunc(PyFutureRef::cancel),
+        "set_result" => ctx.new_rustfunc(PyFutureRef::set_result),
+        "set_exception" => ctx.new_rustfunc(PyFutureRef::set_exception),
+        "add_done_callback" => ctx.new_rustfunc(PyFutureRef::add_done_callback),
+        "remove_done_callback" => ctx.new_rustfunc(PyFutureRef::remove_done_callback),
+        "get_loop" => ctx.new_rustfunc(PyFutureRef::get_loop),
+    });
+
+    let task = py_class!(ctx, "Task", future.clone(), {
+        "__new__" => ctx.new_rustfunc(PyFutureRef::new_task),
+        "get_coro" => ctx.new_rustfunc(PyFutureRef::get_coro),
+    });
+
+    let future_iter = py_class!(ctx, "_FutureIter", ctx.object(), {
+        "__iter__" => ctx.new_rustfunc(PyFutureIterRef::iter),
+        "__next__" => ctx.new_rustfunc(PyFutureIterRef::next),
+        "send" => ctx.new_rustfunc(PyFutureIterRef::send),
+        "throw" => ctx.new_rustfunc(PyFutureIterRef::throw),
+        "close" => ctx.new_rustfunc(PyFutureIterRef::close),
+    });
+
+    let exceptions = &ctx.exceptions;
+    let cancelled_error = ctx.new_class("CancelledError", exceptions.base_exception_type.clone());
+    let timeout_error = exceptions.timeout_error.clone();
+    let invalid_state_error = ctx.new_class("InvalidStateError", exceptions.exception_type.clone());
+
+    py_module!(ctx, "asyncio", {
+        "AbstractEventLoop" => event_loop.into_object(),
+        "Handle" => handle.into_object(),
+        "TimerHandle" => timer_handle.into_object(),
+        "Future" => future.into_object(),
+        "Task" => task.into_object(),
+        "_FutureIter" => future_iter.into_object(),
+        "CancelledError" => cancelled_error.into_object(),
+        "TimeoutError" => timeout_error.into_object(),
+        "InvalidStateError" => invalid_state_error.into_object(),
+        "get_event_loop" => ctx.new_rustfunc(asyncio_get_event_loop),
+        "get_running_loop" => ctx.new_rustfunc(asyncio_get_running_loop),
+        "new_event_loop" => ctx.new_rustfunc(asyncio_new_event_loop),
+        "set_event_loop" => ctx.new_rustfunc(asyncio_set_event_loop),
+        "run" => ctx.new_rustfunc(asyncio_run),
+        "ensure_future" => ctx.new_rustfunc(asyncio_ensure_future),
+        "sleep" => ctx.new_rustfunc(asyncio_sleep),
+        "gather" => ctx.new_rustfunc(asyncio_gather),
+        "wait_for" => ctx.new_rustfunc(asyncio_wait_for),
+        "current_task" => ctx.new_rustfunc(asyncio_current_task),
+        "iscoroutine" => ctx.new_rustfunc(asyncio_iscoroutine),
+        "_event_loop" => ctx.none(),
+        "_running_loops" => ctx.new_list(vec![]),
+    })
+}
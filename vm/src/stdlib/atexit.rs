@@ -0,0 +1,90 @@
+//! The `atexit` module, whose callbacks run when the interpreter shuts down.
+//!
+//! The callbacks are kept by the `VirtualMachine`, and run by
+//! `VirtualMachine::finalize` in the reverse order of their registration.
+
+use crate::exceptions;
+use crate::function::PyFuncArgs;
+use crate::obj::objbool;
+use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyResult};
+use crate::sysmodule;
+use crate::vm::VirtualMachine;
+
+fn atexit_register(vm: &VirtualMachine, mut args: PyFuncArgs) -> PyResult {
+    if args.args.is_empty() {
+        return Err(vm.new_type_error("register() takes at least 1 argument (0 given)".to_string()));
+    }
+    let func = args.shift();
+    if !vm.is_callable(&func) {
+        return Err(vm.new_type_error("the first argument must be callable".to_string()));
+    }
+    vm.atexit_callbacks.borrow_mut().push((func.clone(), args));
+    Ok(func)
+}
+
+/// Remove every registration of `func`.
+fn atexit_unregister(func: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+    // Comparing may run python code, which mustn't find the callbacks
+    // borrowed.
+    let callbacks: Vec<_> = vm
+        .atexit_callbacks
+        .borrow()
+        .iter()
+        .map(|(callback, _)| callback.clone())
+        .collect();
+    let mut removed = vec![];
+    for callback in callbacks {
+        let equal = vm._eq(callback.clone(), func.clone())?;
+        if objbool::boolval(vm, equal)? {
+            removed.push(callback);
+        }
+    }
+    vm.atexit_callbacks
+        .borrow_mut()
+        .retain(|(callback, _)| !removed.iter().any(|removed| removed.is(callback)));
+    Ok(())
+}
+
+/// Run the callbacks, the last registered first, and forget them. Their
+/// exceptions are reported to `sys.stderr`.
+pub fn run_exitfuncs(vm: &VirtualMachine) {
+    loop {
+        let callback = vm.atexit_callbacks.borrow_mut().pop();
+        let (func, args) = match callback {
+            Some(callback) => callback,
+            None => break,
+        };
+        if let Err(exc) = vm.invoke(func.clone(), args) {
+            let origin = vm
+                .to_repr(&func)
+                .map(|repr| repr.value.clone())
+                .unwrap_or_else(|_| "<unknown>".to_string());
+            let mut report =
+                format!("Exception ignored in atexit callback: {}\n", origin).into_bytes();
+            let _ = exceptions::write_exception(&mut report, vm, &exc);
+            sysmodule::write_stderr(vm, &String::from_utf8_lossy(&report));
+        }
+    }
+}
+
+fn atexit_run_exitfuncs(vm: &VirtualMachine) {
+    run_exitfuncs(vm);
+}
+
+fn atexit_clear(vm: &VirtualMachine) {
+    vm.atexit_callbacks.borrow_mut().clear();
+}
+
+fn atexit_ncallbacks(vm: &VirtualMachine) -> usize {
+    vm.atexit_callbacks.borrow().len()
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    py_module!(ctx, "atexit", {
+        "register" => ctx.new_rustfunc(atexit_register),
+        "unregister" => ctx.new_rustfunc(atexit_unregister),
+        "_run_exitfuncs" => ctx.new_rustfunc(atexit_run_exitfuncs),
+        "_clear" => ctx.new_rustfunc(atexit_clear),
+        "_ncallbacks" => ctx.new_rustfunc(atexit_ncallbacks),
+    })
+}
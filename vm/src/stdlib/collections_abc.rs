@@ -0,0 +1,945 @@
+//! The `collections.abc` module: abstract base classes for containers.
+//!
+//! The one-method protocols, like `Iterable` or `Sized`, accept any class
+//! that defines the methods. `Sequence`, `Set`, `Mapping` and the other ABCs
+//! with mixin methods only accept their subclasses and the builtin types
+//! registered with them. Their mixins are written in terms of the abstract
+//! methods, as in CPython, so a subclass only has to define those.
+
+use std::cell::Cell;
+
+use crate::function::{KwArgs, OptionalArg};
+use crate::obj::objabc;
+use crate::obj::objbool;
+use crate::obj::objiter;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::pyobject::{
+    IdProtocol, PyAttributes, PyContext, PyIterable, PyIteratorValue, PyObject, PyObjectRef,
+    PyResult, TryFromObject, TypeProtocol,
+};
+use crate::vm::VirtualMachine;
+
+/// The `__subclasshook__` of an ABC that accepts the classes defining all of
+/// `methods`. It has no opinion about the subclasses of the ABC that don't
+/// define a hook of their own.
+fn subclass_hook(ctx: &PyContext, methods: &'static [&'static str]) -> PyObjectRef {
+    ctx.new_classmethod(
+        move |cls: PyClassRef, subclass: PyClassRef, vm: &VirtualMachine| {
+            if cls.attributes.borrow().contains_key("__subclasshook__")
+                && defines_all(&subclass, methods, vm)
+            {
+                vm.new_bool(true)
+            } else {
+                vm.ctx.not_implemented()
+            }
+        },
+    )
+}
+
+/// Whether `class` defines each of `methods`, without setting it to `None`.
+fn defines_all(class: &PyClassRef, methods: &[&str], vm: &VirtualMachine) -> bool {
+    methods.iter().all(|method| {
+        let attr = std::iter::once(class)
+            .chain(class.mro.iter())
+            .find_map(|base| base.attributes.borrow().get(*method).cloned());
+        attr.map_or(false, |attr| !attr.is(&vm.get_none()))
+    })
+}
+
+fn len(obj: &PyObjectRef, vm: &VirtualMachine) -> PyResult<usize> {
+    let len = vm.call_method(obj, "__len__", vec![])?;
+    usize::try_from_object(vm, len)
+}
+
+/// Whether `a` is `b` or equals it, which is how containers compare items.
+fn same_item(a: &PyObjectRef, b: &PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+    Ok(a.is(b) || objbool::boolval(vm, vm._eq(a.clone(), b.clone())?)?)
+}
+
+fn contains(container: &PyObjectRef, value: &PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+    let found = vm.call_method(container, "__contains__", vec![value.clone()])?;
+    objbool::boolval(vm, found)
+}
+
+fn is_instance(obj: &PyObjectRef, abc: &str, vm: &VirtualMachine) -> PyResult<bool> {
+    vm.isinstance(obj, &vm.class("collections.abc", abc))
+}
+
+fn getitem(obj: &PyObjectRef, key: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    vm.call_method(obj, "__getitem__", vec![key])
+}
+
+/// Whether `result` failed with an exception of class `error`.
+fn raised(result: &PyResult, error: &PyClassRef) -> bool {
+    match result {
+        Err(exc) => objtype::isinstance(exc, error),
+        Ok(_) => false,
+    }
+}
+
+fn iterator_iter(zelf: PyObjectRef, _vm: &VirtualMachine) -> PyObjectRef {
+    zelf
+}
+
+fn sequence_iter(zelf: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+    let iterator = PyIteratorValue {
+        position: Cell::new(0),
+        iterated_obj: zelf,
+    };
+    PyObject::new(iterator, vm.ctx.iter_type(), None)
+}
+
+fn sequence_reversed(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    let mut items = vec![];
+    for index in (0..len(&zelf, vm)?).rev() {
+        items.push(vm.call_method(&zelf, "__getitem__", vec![vm.new_int(index)])?);
+    }
+    objiter::get_iter(vm, &vm.ctx.new_list(items))
+}
+
+fn sequence_contains(zelf: PyObjectRef, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+    let iterator = objiter::get_iter(vm, &zelf)?;
+    while let Some(item) = objiter::get_next_object(vm, &iterator)? {
+        if same_item(&item, &value, vm)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn sequence_index(
+    zelf: PyObjectRef,
+    value: PyObjectRef,
+    start: OptionalArg<isize>,
+    stop: OptionalArg<isize>,
+    vm: &VirtualMachine,
+) -> PyResult<isize> {
+    let start = match start {
+        OptionalArg::Present(start) if start < 0 => (len(&zelf, vm)? as isize + start).max(0),
+        OptionalArg::Present(start) => start,
+        OptionalArg::Missing => 0,
+    };
+    let stop = match stop {
+        OptionalArg::Present(stop) if stop < 0 => Some(len(&zelf, vm)? as isize + stop),
+        OptionalArg::Present(stop) => Some(stop),
+        OptionalArg::Missing => None,
+    };
+    let mut index = start;
+    while stop.map_or(true, |stop| index < stop) {
+        let item = getitem(&zelf, vm.new_int(index), vm);
+        if raised(&item, &vm.ctx.exceptions.index_error) {
+            break;
+        }
+        if same_item(&item?, &value, vm)? {
+            return Ok(index);
+        }
+        index += 1;
+    }
+    Err(vm.new_value_error("sequence.index(x): x not in sequence".to_string()))
+}
+
+fn sequence_count(zelf: PyObjectRef, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<usize> {
+    let iterator = objiter::get_iter(vm, &zelf)?;
+    let mut count = 0;
+    while let Some(item) = objiter::get_next_object(vm, &iterator)? {
+        if same_item(&item, &value, vm)? {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn mutable_sequence_append(zelf: PyObjectRef, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    let end = vm.new_int(len(&zelf, vm)?);
+    vm.call_method(&zelf, "insert", vec![end, value])
+}
+
+fn mutable_sequence_extend(
+    zelf: PyObjectRef,
+    values: PyIterable,
+    vm: &VirtualMachine,
+) -> PyResult<()> {
+    // Collected first, in case `values` is `zelf`.
+    let values = values.iter(vm)?.collect::<PyResult<Vec<_>>>()?;
+    for value in values {
+        vm.call_method(&zelf, "append", vec![value])?;
+    }
+    Ok(())
+}
+
+fn mutable_sequence_pop(
+    zelf: PyObjectRef,
+    index: OptionalArg<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult {
+    let index = index.into_option().unwrap_or_else(|| vm.new_int(-1));
+    let value = vm.call_method(&zelf, "__getitem__", vec![index.clone()])?;
+    vm.call_method(&zelf, "__delitem__", vec![index])?;
+    Ok(value)
+}
+
+fn mutable_sequence_remove(zelf: PyObjectRef, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    let index = vm.call_method(&zelf, "index", vec![value])?;
+    vm.call_method(&zelf, "__delitem__", vec![index])
+}
+
+fn mutable_sequence_clear(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+    loop {
+        let popped = vm.call_method(&zelf, "pop", vec![]);
+        if raised(&popped, &vm.ctx.exceptions.index_error) {
+            return Ok(());
+        }
+        popped?;
+    }
+}
+
+fn mutable_sequence_reverse(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+    let len = len(&zelf, vm)?;
+    for low in 0..len / 2 {
+        let (low, high) = (vm.new_int(low), vm.new_int(len - low - 1));
+        let low_item = getitem(&zelf, low.clone(), vm)?;
+        let high_item = getitem(&zelf, high.clone(), vm)?;
+        vm.call_method(&zelf, "__setitem__", vec![low, high_item])?;
+        vm.call_method(&zelf, "__setitem__", vec![high, low_item])?;
+    }
+    Ok(())
+}
+
+fn mutable_sequence_iadd(zelf: PyObjectRef, values: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    vm.call_method(&zelf, "extend", vec![values])?;
+    Ok(zelf)
+}
+
+/// The items of `iterable`, collected so that `iterable` can be changed
+/// while they are used.
+fn items(iterable: &PyObjectRef, vm: &VirtualMachine) -> PyResult<Vec<PyObjectRef>> {
+    let iterator = objiter::get_iter(vm, iterable)?;
+    objiter::get_all(vm, &iterator)
+}
+
+/// The items of `values` that `container` contains, or doesn't.
+fn filter_contained(
+    values: Vec<PyObjectRef>,
+    container: &PyObjectRef,
+    keep_contained: bool,
+    vm: &VirtualMachine,
+) -> PyResult<Vec<PyObjectRef>> {
+    let mut kept = vec![];
+    for value in values {
+        if contains(container, &value, vm)? == keep_contained {
+            kept.push(value);
+        }
+    }
+    Ok(kept)
+}
+
+fn is_subset(small: &PyObjectRef, big: &PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+    for value in items(small, vm)? {
+        if !contains(big, &value, vm)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// A new set of the kind of `zelf`, from `values`.
+fn from_iterable(zelf: &PyObjectRef, values: Vec<PyObjectRef>, vm: &VirtualMachine) -> PyResult {
+    let from_iterable = vm.get_attribute(zelf.clone(), "_from_iterable")?;
+    vm.invoke(from_iterable, vec![vm.ctx.new_list(values)])
+}
+
+/// `other` as a set, if it is iterable.
+fn as_set(
+    zelf: &PyObjectRef,
+    other: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<Option<PyObjectRef>> {
+    if is_instance(&other, "Set", vm)? {
+        Ok(Some(other))
+    } else if is_instance(&other, "Iterable", vm)? {
+        Ok(Some(from_iterable(zelf, items(&other, vm)?, vm)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Compare the sizes of two sets with `sizes`, then check that the first is
+/// a subset of the second, or the other way around for `superset`.
+fn compare_sets(
+    zelf: &PyObjectRef,
+    other: &PyObjectRef,
+    sizes: fn(usize, usize) -> bool,
+    superset: bool,
+    vm: &VirtualMachine,
+) -> PyResult {
+    if !is_instance(other, "Set", vm)? {
+        return Ok(vm.ctx.not_implemented());
+    }
+    let (small, big) = if superset {
+        (other, zelf)
+    } else {
+        (zelf, other)
+    };
+    let result = sizes(len(zelf, vm)?, len(other, vm)?) && is_subset(small, big, vm)?;
+    Ok(vm.new_bool(result))
+}
+
+fn set_le(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    compare_sets(&zelf, &other, |a, b| a <= b, false, vm)
+}
+
+fn set_lt(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    compare_sets(&zelf, &other, |a, b| a < b, false, vm)
+}
+
+fn set_ge(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    compare_sets(&zelf, &other, |a, b| a >= b, true, vm)
+}
+
+fn set_gt(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    compare_sets(&zelf, &other, |a, b| a > b, true, vm)
+}
+
+fn set_eq(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    compare_sets(&zelf, &other, |a, b| a == b, false, vm)
+}
+
+fn set_from_iterable(cls: PyClassRef, iterable: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    vm.invoke(cls.into_object(), vec![iterable])
+}
+
+fn set_and(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    if !is_instance(&other, "Iterable", vm)? {
+        return Ok(vm.ctx.not_implemented());
+    }
+    let common = filter_contained(items(&other, vm)?, &zelf, true, vm)?;
+    from_iterable(&zelf, common, vm)
+}
+
+fn set_or(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    if !is_instance(&other, "Iterable", vm)? {
+        return Ok(vm.ctx.not_implemented());
+    }
+    let mut all = items(&zelf, vm)?;
+    all.extend(items(&other, vm)?);
+    from_iterable(&zelf, all, vm)
+}
+
+fn set_sub(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    match as_set(&zelf, other, vm)? {
+        Some(other) => {
+            let difference = filter_contained(items(&zelf, vm)?, &other, false, vm)?;
+            from_iterable(&zelf, difference, vm)
+        }
+        None => Ok(vm.ctx.not_implemented()),
+    }
+}
+
+fn set_rsub(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    match as_set(&zelf, other, vm)? {
+        Some(other) => {
+            let difference = filter_contained(items(&other, vm)?, &zelf, false, vm)?;
+            from_iterable(&zelf, difference, vm)
+        }
+        None => Ok(vm.ctx.not_implemented()),
+    }
+}
+
+fn set_xor(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    match as_set(&zelf, other, vm)? {
+        Some(other) => {
+            let mut difference = filter_contained(items(&zelf, vm)?, &other, false, vm)?;
+            difference.extend(filter_contained(items(&other, vm)?, &zelf, false, vm)?);
+            from_iterable(&zelf, difference, vm)
+        }
+        None => Ok(vm.ctx.not_implemented()),
+    }
+}
+
+fn set_isdisjoint(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+    Ok(filter_contained(items(&other, vm)?, &zelf, true, vm)?.is_empty())
+}
+
+fn mutable_set_remove(zelf: PyObjectRef, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    let contains = vm.call_method(&zelf, "__contains__", vec![value.clone()])?;
+    if !objbool::boolval(vm, contains)? {
+        return Err(vm.new_key_error(value));
+    }
+    vm.call_method(&zelf, "discard", vec![value])
+}
+
+fn mutable_set_pop(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    let iterator = objiter::get_iter(vm, &zelf)?;
+    match objiter::get_next_object(vm, &iterator)? {
+        Some(value) => {
+            vm.call_method(&zelf, "discard", vec![value.clone()])?;
+            Ok(value)
+        }
+        None => Err(vm.new_key_error(vm.new_str("pop from an empty set".to_string()))),
+    }
+}
+
+fn mutable_set_clear(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+    for value in items(&zelf, vm)? {
+        vm.call_method(&zelf, "discard", vec![value])?;
+    }
+    Ok(())
+}
+
+fn mutable_set_ior(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    for value in items(&other, vm)? {
+        vm.call_method(&zelf, "add", vec![value])?;
+    }
+    Ok(zelf)
+}
+
+fn mutable_set_iand(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    let difference = vm._sub(zelf.clone(), other)?;
+    for value in items(&difference, vm)? {
+        vm.call_method(&zelf, "discard", vec![value])?;
+    }
+    Ok(zelf)
+}
+
+fn mutable_set_ixor(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    if other.is(&zelf) {
+        mutable_set_clear(zelf.clone(), vm)?;
+        return Ok(zelf);
+    }
+    for value in items(&other, vm)? {
+        if contains(&zelf, &value, vm)? {
+            vm.call_method(&zelf, "discard", vec![value])?;
+        } else {
+            vm.call_method(&zelf, "add", vec![value])?;
+        }
+    }
+    Ok(zelf)
+}
+
+fn mutable_set_isub(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    if other.is(&zelf) {
+        mutable_set_clear(zelf.clone(), vm)?;
+        return Ok(zelf);
+    }
+    for value in items(&other, vm)? {
+        vm.call_method(&zelf, "discard", vec![value])?;
+    }
+    Ok(zelf)
+}
+
+fn mapping_get(
+    zelf: PyObjectRef,
+    key: PyObjectRef,
+    default: OptionalArg<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult {
+    match vm.call_method(&zelf, "__getitem__", vec![key]) {
+        Err(ref exc) if objtype::isinstance(exc, &vm.ctx.exceptions.key_error) => {
+            Ok(default.into_option().unwrap_or_else(|| vm.get_none()))
+        }
+        result => result,
+    }
+}
+
+fn mapping_contains(zelf: PyObjectRef, key: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+    match vm.call_method(&zelf, "__getitem__", vec![key]) {
+        Ok(_) => Ok(true),
+        Err(ref exc) if objtype::isinstance(exc, &vm.ctx.exceptions.key_error) => Ok(false),
+        Err(exc) => Err(exc),
+    }
+}
+
+fn mapping_view(zelf: PyObjectRef, view: &str, vm: &VirtualMachine) -> PyResult {
+    let view = vm.class("collections.abc", view);
+    vm.invoke(view.into_object(), vec![zelf])
+}
+
+fn mapping_keys(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    mapping_view(zelf, "KeysView", vm)
+}
+
+fn mapping_items(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    mapping_view(zelf, "ItemsView", vm)
+}
+
+fn mapping_values(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    mapping_view(zelf, "ValuesView", vm)
+}
+
+/// Two mappings are equal when they have the same keys, with equal values.
+fn mapping_eq(zelf: PyObjectRef, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    if !is_instance(&other, "Mapping", vm)? {
+        return Ok(vm.ctx.not_implemented());
+    }
+    if len(&zelf, vm)? != len(&other, vm)? {
+        return Ok(vm.new_bool(false));
+    }
+    for key in items(&zelf, vm)? {
+        let other_value = getitem(&other, key.clone(), vm);
+        if raised(&other_value, &vm.ctx.exceptions.key_error) {
+            return Ok(vm.new_bool(false));
+        }
+        if !same_item(&getitem(&zelf, key, vm)?, &other_value?, vm)? {
+            return Ok(vm.new_bool(false));
+        }
+    }
+    Ok(vm.new_bool(true))
+}
+
+fn mapping_view_init(zelf: PyObjectRef, mapping: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+    vm.set_attr(&zelf, vm.new_str("_mapping".to_string()), mapping)?;
+    Ok(())
+}
+
+fn viewed_mapping(view: &PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    vm.get_attribute(view.clone(), "_mapping")
+}
+
+fn mapping_view_len(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult<usize> {
+    len(&viewed_mapping(&zelf, vm)?, vm)
+}
+
+fn mapping_view_repr(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult<String> {
+    let mapping = vm.to_repr(&viewed_mapping(&zelf, vm)?)?;
+    Ok(format!("{}({})", zelf.class().name, mapping.value))
+}
+
+/// What the set operations of a view return: a builtin set.
+fn view_from_iterable(_cls: PyClassRef, iterable: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    vm.invoke(vm.ctx.set_type().into_object(), vec![iterable])
+}
+
+fn keys_view_contains(zelf: PyObjectRef, key: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+    contains(&viewed_mapping(&zelf, vm)?, &key, vm)
+}
+
+fn keys_view_iter(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    objiter::get_iter(vm, &viewed_mapping(&zelf, vm)?)
+}
+
+/// The `(key, value)` pairs of the mapping behind `view`.
+fn viewed_items(
+    view: &PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<Vec<(PyObjectRef, PyObjectRef)>> {
+    let mapping = viewed_mapping(view, vm)?;
+    let mut pairs = vec![];
+    for key in items(&mapping, vm)? {
+        let value = getitem(&mapping, key.clone(), vm)?;
+        pairs.push((key, value));
+    }
+    Ok(pairs)
+}
+
+fn items_view_contains(
+    zelf: PyObjectRef,
+    item: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<bool> {
+    let (key, value) = match <(PyObjectRef, PyObjectRef)>::try_from_object(vm, item) {
+        Ok(pair) => pair,
+        Err(_) => return Ok(false),
+    };
+    let found = getitem(&viewed_mapping(&zelf, vm)?, key, vm);
+    if raised(&found, &vm.ctx.exceptions.key_error) {
+        return Ok(false);
+    }
+    same_item(&found?, &value, vm)
+}
+
+fn items_view_iter(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    let pairs = viewed_items(&zelf, vm)?
+        .into_iter()
+        .map(|(key, value)| vm.ctx.new_tuple(vec![key, value]))
+        .collect();
+    objiter::get_iter(vm, &vm.ctx.new_list(pairs))
+}
+
+fn values_view_contains(
+    zelf: PyObjectRef,
+    value: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<bool> {
+    for (_, item) in viewed_items(&zelf, vm)? {
+        if same_item(&item, &value, vm)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn values_view_iter(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    let values = viewed_items(&zelf, vm)?
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    objiter::get_iter(vm, &vm.ctx.new_list(values))
+}
+
+fn mutable_mapping_pop(
+    zelf: PyObjectRef,
+    key: PyObjectRef,
+    default: OptionalArg<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult {
+    match vm.call_method(&zelf, "__getitem__", vec![key.clone()]) {
+        Ok(value) => {
+            vm.call_method(&zelf, "__delitem__", vec![key])?;
+            Ok(value)
+        }
+        Err(exc) => match default {
+            OptionalArg::Present(ref default)
+                if objtype::isinstance(&exc, &vm.ctx.exceptions.key_error) =>
+            {
+                Ok(default.clone())
+            }
+            _ => Err(exc),
+        },
+    }
+}
+
+fn mutable_mapping_setdefault(
+    zelf: PyObjectRef,
+    key: PyObjectRef,
+    default: OptionalArg<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult {
+    match vm.call_method(&zelf, "__getitem__", vec![key.clone()]) {
+        Err(ref exc) if objtype::isinstance(exc, &vm.ctx.exceptions.key_error) => {
+            let default = default.into_option().unwrap_or_else(|| vm.get_none());
+            vm.call_method(&zelf, "__setitem__", vec![key, default.clone()])?;
+            Ok(default)
+        }
+        result => result,
+    }
+}
+
+fn mutable_mapping_popitem(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    let iterator = objiter::get_iter(vm, &zelf)?;
+    let key = match objiter::get_next_object(vm, &iterator)? {
+        Some(key) => key,
+        None => return Err(vm.new_key_error(vm.new_str("popitem(): mapping is empty".to_string()))),
+    };
+    let value = getitem(&zelf, key.clone(), vm)?;
+    vm.call_method(&zelf, "__delitem__", vec![key.clone()])?;
+    Ok(vm.ctx.new_tuple(vec![key, value]))
+}
+
+fn mutable_mapping_clear(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+    for key in items(&zelf, vm)? {
+        vm.call_method(&zelf, "__delitem__", vec![key])?;
+    }
+    Ok(())
+}
+
+/// Set the items of a mapping, an object with `keys()`, or an iterable of
+/// pairs, then the keyword arguments.
+fn mutable_mapping_update(
+    zelf: PyObjectRef,
+    other: OptionalArg<PyObjectRef>,
+    kwargs: KwArgs,
+    vm: &VirtualMachine,
+) -> PyResult<()> {
+    let mut pairs = vec![];
+    if let OptionalArg::Present(other) = other {
+        let keys = if is_instance(&other, "Mapping", vm)? {
+            Some(items(&other, vm)?)
+        } else if objtype::class_has_attr(&other.class(), "keys") {
+            Some(items(&vm.call_method(&other, "keys", vec![])?, vm)?)
+        } else {
+            None
+        };
+        match keys {
+            Some(keys) => {
+                for key in keys {
+                    pairs.push((key.clone(), getitem(&other, key, vm)?));
+                }
+            }
+            None => {
+                for pair in items(&other, vm)? {
+                    pairs.push(<(PyObjectRef, PyObjectRef)>::try_from_object(vm, pair)?);
+                }
+            }
+        }
+    }
+    for (key, value) in kwargs {
+        pairs.push((vm.new_str(key), value));
+    }
+    for (key, value) in pairs {
+        vm.call_method(&zelf, "__setitem__", vec![key, value])?;
+    }
+    Ok(())
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    let abstract_class = |name, bases, methods: Vec<(&str, PyObjectRef)>, abstract_methods| {
+        let attributes: PyAttributes = methods
+            .into_iter()
+            .map(|(name, method)| (name.to_string(), method))
+            .collect();
+        objabc::new_abstract_class(ctx, name, bases, attributes, abstract_methods)
+    };
+    let abstract_method = || objabc::new_abstract_method(ctx);
+    let object = ctx.object();
+
+    let hashable = abstract_class(
+        "Hashable",
+        vec![object.clone()],
+        vec![
+            ("__hash__", abstract_method()),
+            ("__subclasshook__", subclass_hook(ctx, &["__hash__"])),
+        ],
+        &["__hash__"],
+    );
+    let iterable = abstract_class(
+        "Iterable",
+        vec![object.clone()],
+        vec![
+            ("__iter__", abstract_method()),
+            ("__subclasshook__", subclass_hook(ctx, &["__iter__"])),
+        ],
+        &["__iter__"],
+    );
+    let iterator = abstract_class(
+        "Iterator",
+        vec![iterable.clone()],
+        vec![
+            ("__next__", abstract_method()),
+            ("__iter__", ctx.new_rustfunc(iterator_iter)),
+            (
+                "__subclasshook__",
+                subclass_hook(ctx, &["__iter__", "__next__"]),
+            ),
+        ],
+        &["__next__"],
+    );
+    let reversible = abstract_class(
+        "Reversible",
+        vec![iterable.clone()],
+        vec![
+            ("__reversed__", abstract_method()),
+            (
+                "__subclasshook__",
+                subclass_hook(ctx, &["__reversed__", "__iter__"]),
+            ),
+        ],
+        &["__reversed__"],
+    );
+    let sized = abstract_class(
+        "Sized",
+        vec![object.clone()],
+        vec![
+            ("__len__", abstract_method()),
+            ("__subclasshook__", subclass_hook(ctx, &["__len__"])),
+        ],
+        &["__len__"],
+    );
+    let container = abstract_class(
+        "Container",
+        vec![object.clone()],
+        vec![
+            ("__contains__", abstract_method()),
+            ("__subclasshook__", subclass_hook(ctx, &["__contains__"])),
+        ],
+        &["__contains__"],
+    );
+    let callable = abstract_class(
+        "Callable",
+        vec![object.clone()],
+        vec![
+            ("__call__", abstract_method()),
+            ("__subclasshook__", subclass_hook(ctx, &["__call__"])),
+        ],
+        &["__call__"],
+    );
+    let collection = abstract_class(
+        "Collection",
+        vec![sized.clone(), iterable.clone(), container.clone()],
+        vec![(
+            "__subclasshook__",
+            subclass_hook(ctx, &["__len__", "__iter__", "__contains__"]),
+        )],
+        &[],
+    );
+    let sequence = abstract_class(
+        "Sequence",
+        vec![reversible.clone(), collection.clone()],
+        vec![
+            ("__getitem__", abstract_method()),
+            ("__iter__", ctx.new_rustfunc(sequence_iter)),
+            ("__reversed__", ctx.new_rustfunc(sequence_reversed)),
+            ("__contains__", ctx.new_rustfunc(sequence_contains)),
+            ("index", ctx.new_rustfunc(sequence_index)),
+            ("count", ctx.new_rustfunc(sequence_count)),
+        ],
+        &["__getitem__"],
+    );
+    let mutable_sequence = abstract_class(
+        "MutableSequence",
+        vec![sequence.clone()],
+        vec![
+            ("__setitem__", abstract_method()),
+            ("__delitem__", abstract_method()),
+            ("insert", abstract_method()),
+            ("append", ctx.new_rustfunc(mutable_sequence_append)),
+            ("extend", ctx.new_rustfunc(mutable_sequence_extend)),
+            ("pop", ctx.new_rustfunc(mutable_sequence_pop)),
+            ("remove", ctx.new_rustfunc(mutable_sequence_remove)),
+            ("clear", ctx.new_rustfunc(mutable_sequence_clear)),
+            ("reverse", ctx.new_rustfunc(mutable_sequence_reverse)),
+            ("__iadd__", ctx.new_rustfunc(mutable_sequence_iadd)),
+        ],
+        &["__setitem__", "__delitem__", "insert"],
+    );
+    let set = abstract_class(
+        "Set",
+        vec![collection.clone()],
+        vec![
+            ("__le__", ctx.new_rustfunc(set_le)),
+            ("__lt__", ctx.new_rustfunc(set_lt)),
+            ("__ge__", ctx.new_rustfunc(set_ge)),
+            ("__gt__", ctx.new_rustfunc(set_gt)),
+            ("__eq__", ctx.new_rustfunc(set_eq)),
+            ("__and__", ctx.new_rustfunc(set_and)),
+            ("__rand__", ctx.new_rustfunc(set_and)),
+            ("__or__", ctx.new_rustfunc(set_or)),
+            ("__ror__", ctx.new_rustfunc(set_or)),
+            ("__sub__", ctx.new_rustfunc(set_sub)),
+            ("__rsub__", ctx.new_rustfunc(set_rsub)),
+            ("__xor__", ctx.new_rustfunc(set_xor)),
+            ("__rxor__", ctx.new_rustfunc(set_xor)),
+            ("isdisjoint", ctx.new_rustfunc(set_isdisjoint)),
+            ("_from_iterable", ctx.new_classmethod(set_from_iterable)),
+            ("__hash__", ctx.none()),
+        ],
+        &[],
+    );
+    let mutable_set = abstract_class(
+        "MutableSet",
+        vec![set.clone()],
+        vec![
+            ("add", abstract_method()),
+            ("discard", abstract_method()),
+            ("remove", ctx.new_rustfunc(mutable_set_remove)),
+            ("pop", ctx.new_rustfunc(mutable_set_pop)),
+            ("clear", ctx.new_rustfunc(mutable_set_clear)),
+            ("__ior__", ctx.new_rustfunc(mutable_set_ior)),
+            ("__iand__", ctx.new_rustfunc(mutable_set_iand)),
+            ("__ixor__", ctx.new_rustfunc(mutable_set_ixor)),
+            ("__isub__", ctx.new_rustfunc(mutable_set_isub)),
+        ],
+        &["add", "discard"],
+    );
+    let mapping = abstract_class(
+        "Mapping",
+        vec![collection.clone()],
+        vec![
+            ("__getitem__", abstract_method()),
+            ("__contains__", ctx.new_rustfunc(mapping_contains)),
+            ("get", ctx.new_rustfunc(mapping_get)),
+            ("keys", ctx.new_rustfunc(mapping_keys)),
+            ("items", ctx.new_rustfunc(mapping_items)),
+            ("values", ctx.new_rustfunc(mapping_values)),
+            ("__eq__", ctx.new_rustfunc(mapping_eq)),
+            ("__reversed__", ctx.none()),
+            ("__hash__", ctx.none()),
+        ],
+        &["__getitem__"],
+    );
+    let mutable_mapping = abstract_class(
+        "MutableMapping",
+        vec![mapping.clone()],
+        vec![
+            ("__setitem__", abstract_method()),
+            ("__delitem__", abstract_method()),
+            ("pop", ctx.new_rustfunc(mutable_mapping_pop)),
+            ("setdefault", ctx.new_rustfunc(mutable_mapping_setdefault)),
+            ("popitem", ctx.new_rustfunc(mutable_mapping_popitem)),
+            ("clear", ctx.new_rustfunc(mutable_mapping_clear)),
+            ("update", ctx.new_rustfunc(mutable_mapping_update)),
+        ],
+        &["__setitem__", "__delitem__"],
+    );
+    let mapping_view = abstract_class(
+        "MappingView",
+        vec![sized.clone()],
+        vec![
+            ("__init__", ctx.new_rustfunc(mapping_view_init)),
+            ("__len__", ctx.new_rustfunc(mapping_view_len)),
+            ("__repr__", ctx.new_rustfunc(mapping_view_repr)),
+        ],
+        &[],
+    );
+    let keys_view = abstract_class(
+        "KeysView",
+        vec![mapping_view.clone(), set.clone()],
+        vec![
+            ("__contains__", ctx.new_rustfunc(keys_view_contains)),
+            ("__iter__", ctx.new_rustfunc(keys_view_iter)),
+            ("_from_iterable", ctx.new_classmethod(view_from_iterable)),
+        ],
+        &[],
+    );
+    let items_view = abstract_class(
+        "ItemsView",
+        vec![mapping_view.clone(), set.clone()],
+        vec![
+            ("__contains__", ctx.new_rustfunc(items_view_contains)),
+            ("__iter__", ctx.new_rustfunc(items_view_iter)),
+            ("_from_iterable", ctx.new_classmethod(view_from_iterable)),
+        ],
+        &[],
+    );
+    let values_view = abstract_class(
+        "ValuesView",
+        vec![mapping_view.clone(), collection.clone()],
+        vec![
+            ("__contains__", ctx.new_rustfunc(values_view_contains)),
+            ("__iter__", ctx.new_rustfunc(values_view_iter)),
+        ],
+        &[],
+    );
+
+    for builtin in &[
+        ctx.tuple_type(),
+        ctx.str_type(),
+        ctx.range_type(),
+        ctx.bytes_type(),
+    ] {
+        objabc::register_builtin(&sequence, builtin);
+    }
+    objabc::register_builtin(&mutable_sequence, &ctx.list_type());
+    objabc::register_builtin(&mutable_sequence, &ctx.bytearray_type());
+    objabc::register_builtin(&set, &ctx.frozenset_type());
+    objabc::register_builtin(&mutable_set, &ctx.set_type());
+    objabc::register_builtin(&mutable_mapping, &ctx.dict_type());
+    // These are called without looking up `__call__`, which they lack.
+    for builtin in &[
+        ctx.function_type(),
+        ctx.builtin_function_or_method_type(),
+        ctx.bound_method_type(),
+    ] {
+        objabc::register_builtin(&callable, builtin);
+    }
+
+    py_module!(ctx, "collections.abc", {
+        "Hashable" => hashable,
+        "Iterable" => iterable,
+        "Iterator" => iterator,
+        "Reversible" => reversible,
+        "Sized" => sized,
+        "Container" => container,
+        "Callable" => callable,
+        "Collection" => collection,
+        "Sequence" => sequence,
+        "MutableSequence" => mutable_sequence,
+        "Set" => set,
+        "MutableSet" => mutable_set,
+        "Mapping" => mapping,
+        "MutableMapping" => mutable_mapping,
+        "MappingView" => mapping_view,
+        "KeysView" => keys_view,
+        "ItemsView" => items_view,
+        "ValuesView" => values_view,
+    })
+}
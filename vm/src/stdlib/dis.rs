@@ -1,5 +1,6 @@
 use crate::obj::objcode::PyCodeRef;
 use crate::pyobject::{PyContext, PyObjectRef, PyResult, TryFromObject};
+use crate::sysmodule;
 use crate::vm::VirtualMachine;
 
 fn dis_dis(obj: PyObjectRef, vm: &VirtualMachine) -> PyResult {
@@ -13,7 +14,7 @@ fn dis_dis(obj: PyObjectRef, vm: &VirtualMachine) -> PyResult {
 
 fn dis_disassemble(co: PyObjectRef, vm: &VirtualMachine) -> PyResult {
     let code = &PyCodeRef::try_from_object(vm, co)?.code;
-    print!("{}", code);
+    sysmodule::write_stdout(vm, &code.to_string())?;
     Ok(vm.get_none())
 }
 
@@ -2,7 +2,7 @@
  * I/O core tools.
  */
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::prelude::*;
@@ -13,7 +13,8 @@ use num_bigint::ToBigInt;
 use num_traits::ToPrimitive;
 
 use super::os;
-use crate::function::PyFuncArgs;
+use crate::exceptions;
+use crate::function::{OptionalArg, PyFuncArgs};
 use crate::import;
 use crate::obj::objbytearray::PyByteArray;
 use crate::obj::objbytes;
@@ -21,7 +22,7 @@ use crate::obj::objint;
 use crate::obj::objstr;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::{
-    BufferProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
+    BufferProtocol, PyClassImpl, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
 };
 use crate::vm::VirtualMachine;
 
@@ -35,9 +36,13 @@ fn compute_c_flag(mode: &str) -> u16 {
     }
 }
 
+/// Text I/O implementation using an in-memory buffer.
+#[pyclass(name = "StringIO")]
 #[derive(Debug)]
 struct PyStringIO {
     data: RefCell<String>,
+    /// Where the next read or write starts, as an index into `data`.
+    position: Cell<usize>,
 }
 
 type PyStringIORef = PyRef<PyStringIO>;
@@ -48,22 +53,77 @@ impl PyValue for PyStringIO {
     }
 }
 
+#[pymethods]
 impl PyStringIORef {
-    fn write(self, data: objstr::PyStringRef, _vm: &VirtualMachine) {
-        let data = data.value.clone();
-        self.data.borrow_mut().push_str(&data);
+    #[pymethod(magic)]
+    fn new(
+        cls: PyClassRef,
+        initial_value: OptionalArg<Option<objstr::PyStringRef>>,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyStringIORef> {
+        let data = match initial_value {
+            OptionalArg::Present(Some(value)) => value.value.clone(),
+            _ => String::new(),
+        };
+        PyStringIO {
+            data: RefCell::new(data),
+            position: Cell::new(0),
+        }
+        .into_ref_with_type(vm, cls)
     }
 
-    fn getvalue(self, _vm: &VirtualMachine) -> String {
-        self.data.borrow().clone()
+    /// Write `data` over what follows the current position, returning the
+    /// number of characters written.
+    #[pymethod]
+    fn write(self, data: objstr::PyStringRef, _vm: &VirtualMachine) -> usize {
+        let mut value = self.data.borrow_mut();
+        let start = self.position.get();
+        let count = data.value.chars().count();
+        let end = value[start..]
+            .char_indices()
+            .nth(count)
+            .map_or(value.len(), |(offset, _)| start + offset);
+        value.replace_range(start..end, &data.value);
+        self.position.set(start + data.value.len());
+        count
+    }
+
+    /// Read up to and including the next newline.
+    #[pymethod]
+    fn readline(self, _vm: &VirtualMachine) -> String {
+        let value = self.data.borrow();
+        let start = self.position.get();
+        let end = value[start..]
+            .find('\n')
+            .map_or(value.len(), |offset| start + offset + 1);
+        self.position.set(end);
+        value[start..end].to_string()
+    }
+
+    /// Read `size` characters, or everything that is left if `size` is
+    /// missing or negative.
+    #[pymethod]
+    fn read(self, size: OptionalArg<Option<isize>>, _vm: &VirtualMachine) -> String {
+        let value = self.data.borrow();
+        let start = self.position.get();
+        let end = match size.into_option() {
+            Some(Some(size)) if size >= 0 => value[start..]
+                .char_indices()
+                .nth(size as usize)
+                .map_or(value.len(), |(offset, _)| start + offset),
+            _ => value.len(),
+        };
+        self.position.set(end);
+        value[start..end].to_string()
     }
-}
 
-fn string_io_new(cls: PyClassRef, vm: &VirtualMachine) -> PyResult<PyStringIORef> {
-    PyStringIO {
-        data: RefCell::new(String::default()),
+    #[pymethod]
+    fn flush(self, _vm: &VirtualMachine) {}
+
+    #[pymethod]
+    fn getvalue(self, _vm: &VirtualMachine) -> String {
+        self.data.borrow().clone()
     }
-    .into_ref_with_type(vm, cls)
 }
 
 fn bytes_io_init(vm: &VirtualMachine, _args: PyFuncArgs) -> PyResult {
@@ -286,8 +346,17 @@ fn text_io_base_read(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         let value = objbytes::get_value(&bytes).to_vec();
 
         //format bytes into string
-        let rust_string = String::from_utf8(value).unwrap();
-        Ok(vm.ctx.new_str(rust_string))
+        match String::from_utf8(value) {
+            Ok(rust_string) => Ok(vm.ctx.new_str(rust_string)),
+            Err(err) => {
+                let utf8_error = err.utf8_error();
+                Err(exceptions::utf8_decode_error(
+                    vm,
+                    err.into_bytes(),
+                    &utf8_error,
+                ))
+            }
+        }
     } else {
         Err(vm.new_value_error("Error unpacking Bytes".to_string()))
     }
@@ -409,11 +478,7 @@ pub fn make_module(ctx: &PyContext) -> PyObjectRef {
     });
 
     //StringIO: in-memory text
-    let string_io = py_class!(ctx, "StringIO", text_io_base.clone(), {
-        "__new__" => ctx.new_rustfunc(string_io_new),
-        "write" => ctx.new_rustfunc(PyStringIORef::write),
-        "getvalue" => ctx.new_rustfunc(PyStringIORef::getvalue)
-    });
+    let string_io = PyStringIORef::make_class_with_base(ctx, text_io_base.clone());
 
     //BytesIO: in-memory bytes
     let bytes_io = py_class!(ctx, "BytesIO", buffered_io_base.clone(), {
@@ -1,4 +1,9 @@
+mod abc;
 mod ast;
+#[cfg(not(target_arch = "wasm32"))]
+mod asyncio;
+pub(crate) mod atexit;
+mod collections_abc;
 mod dis;
 pub(crate) mod json;
 mod keyword;
@@ -19,6 +24,14 @@ use std::collections::HashMap;
 pub mod io;
 #[cfg(not(target_arch = "wasm32"))]
 mod os;
+#[cfg(not(target_arch = "wasm32"))]
+mod profile;
+#[cfg(not(target_arch = "wasm32"))]
+mod pstats;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod thread;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod threading;
 
 use crate::pyobject::{PyContext, PyObjectRef};
 
@@ -30,6 +43,12 @@ pub fn get_module_inits() -> HashMap<String, StdlibInitFunc> {
         "ast".to_string(),
         Box::new(ast::make_module) as StdlibInitFunc,
     );
+    modules.insert("abc".to_string(), Box::new(abc::make_module));
+    modules.insert("atexit".to_string(), Box::new(atexit::make_module));
+    modules.insert(
+        "collections.abc".to_string(),
+        Box::new(collections_abc::make_module),
+    );
     modules.insert("dis".to_string(), Box::new(dis::make_module));
     modules.insert("json".to_string(), Box::new(json::make_module));
     modules.insert("keyword".to_string(), Box::new(keyword::make_module));
@@ -47,9 +66,18 @@ pub fn get_module_inits() -> HashMap<String, StdlibInitFunc> {
     // disable some modules on WASM
     #[cfg(not(target_arch = "wasm32"))]
     {
+        modules.insert("asyncio".to_string(), Box::new(asyncio::make_module));
         modules.insert("io".to_string(), Box::new(io::make_module));
         modules.insert("os".to_string(), Box::new(os::make_module));
+        modules.insert("cProfile".to_string(), Box::new(profile::make_module));
+        modules.insert(
+            "profile".to_string(),
+            Box::new(profile::make_profile_module),
+        );
+        modules.insert("pstats".to_string(), Box::new(pstats::make_module));
         modules.insert("socket".to_string(), Box::new(socket::make_module));
+        modules.insert("_thread".to_string(), Box::new(thread::make_module));
+        modules.insert("threading".to_string(), Box::new(threading::make_module));
     }
 
     modules
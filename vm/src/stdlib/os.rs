@@ -1,6 +1,5 @@
 use std::fs::File;
 use std::fs::OpenOptions;
-use std::io::ErrorKind;
 
 use num_traits::cast::ToPrimitive;
 
@@ -81,16 +80,10 @@ pub fn os_open(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         512 => OpenOptions::new().write(true).create(true).open(&fname),
         _ => OpenOptions::new().read(true).open(&fname),
     }
-    .map_err(|err| match err.kind() {
-        ErrorKind::NotFound => {
-            let exc_type = vm.ctx.exceptions.file_not_found_error.clone();
-            vm.new_exception(exc_type, format!("No such file or directory: {}", &fname))
-        }
-        ErrorKind::PermissionDenied => {
-            let exc_type = vm.ctx.exceptions.permission_error.clone();
-            vm.new_exception(exc_type, format!("Permission denied: {}", &fname))
-        }
-        _ => vm.new_value_error("Unhandled file IO error".to_string()),
+    .map_err(|err| {
+        let exc = vm.new_io_error(&err);
+        vm.ctx.set_attr(&exc, "filename", name.clone());
+        exc
     })?;
 
     Ok(vm.ctx.new_int(raw_file_number(handle)))
@@ -1,5 +1,6 @@
 use crate::function::PyFuncArgs;
 use crate::pyobject::{PyContext, PyObjectRef, PyResult};
+use crate::sysmodule;
 use crate::vm::VirtualMachine;
 
 pub fn make_module(ctx: &PyContext) -> PyObjectRef {
@@ -17,8 +18,7 @@ fn platform_python_implementation(vm: &VirtualMachine, args: PyFuncArgs) -> PyRe
 
 fn platform_python_version(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(vm, args);
-    // TODO: fetch version from somewhere.
-    Ok(vm.new_str("4.0.0".to_string()))
+    Ok(vm.new_str(sysmodule::get_version_number()))
 }
 
 fn platform_python_compiler(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
@@ -0,0 +1,264 @@
+//! The python `cProfile` module, and the `profile` module offering the same
+//! interface.
+//!
+//! `Profile` objects install a `profiler::Profiler` in the virtual machine
+//! while they are enabled; `pstats` turns what it collected into reports.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::pstats::{PyStats, PyStatsRef};
+use crate::function::{Args, KwArgs, OptionalArg, PyFuncArgs};
+use crate::obj::objtype::PyClassRef;
+use crate::profiler::{self, Profiler};
+use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue};
+use crate::vm::VirtualMachine;
+
+#[derive(Debug)]
+pub struct PyProfile {
+    pub profiler: Rc<Profiler>,
+    /// The profiler that was installed when this one got enabled, like the
+    /// one of `--flamegraph`, restored when it gets disabled.
+    previous: RefCell<Option<Rc<Profiler>>>,
+}
+
+impl PyProfile {
+    fn new() -> Self {
+        PyProfile {
+            profiler: Rc::new(Profiler::new()),
+            previous: RefCell::new(None),
+        }
+    }
+}
+
+pub type PyProfileRef = PyRef<PyProfile>;
+
+impl PyValue for PyProfile {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("cProfile", "Profile")
+    }
+}
+
+/// The `filename=None, sort=-1` parameters of `run` and `runctx`.
+#[derive(FromArgs)]
+struct OutputArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    filename: Option<PyObjectRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    sort: Option<PyObjectRef>,
+}
+
+fn exec(
+    vm: &VirtualMachine,
+    statement: PyObjectRef,
+    globals: Option<PyObjectRef>,
+    locals: Option<PyObjectRef>,
+) -> PyResult {
+    let exec = vm.get_attribute(vm.builtins.clone(), "exec")?;
+    let mut args = vec![statement];
+    args.extend(globals);
+    args.extend(locals);
+    vm.invoke(exec, args)
+}
+
+impl PyProfileRef {
+    fn new(cls: PyClassRef, _args: Args, _kwargs: KwArgs, vm: &VirtualMachine) -> PyResult<Self> {
+        // The timer and what to measure cannot be configured.
+        PyProfile::new().into_ref_with_type(vm, cls)
+    }
+
+    fn is_enabled(&self, vm: &VirtualMachine) -> bool {
+        match vm.profiler.borrow().as_ref() {
+            Some(profiler) => Rc::ptr_eq(profiler, &self.profiler),
+            None => false,
+        }
+    }
+
+    fn enable(self, vm: &VirtualMachine) {
+        if self.is_enabled(vm) {
+            return;
+        }
+        // The previous profiler is suspended, the frames it saw running keep
+        // running meanwhile.
+        let previous = vm.set_profiler(Some(self.profiler.clone()));
+        self.previous.replace(previous);
+    }
+
+    fn disable(self, vm: &VirtualMachine) {
+        if self.is_enabled(vm) {
+            vm.set_profiler(self.previous.replace(None));
+        }
+        self.profiler.flush();
+    }
+
+    fn clear(self, _vm: &VirtualMachine) {
+        self.profiler.clear();
+    }
+
+    fn runcall(
+        self,
+        func: PyObjectRef,
+        args: Args,
+        kwargs: KwArgs,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        self.clone().enable(vm);
+        let result = vm.invoke(func, PyFuncArgs::from((&args, &kwargs)));
+        self.disable(vm);
+        result
+    }
+
+    fn run(self, statement: PyObjectRef, vm: &VirtualMachine) -> PyResult<Self> {
+        self.profile_statement(statement, None, None, vm)
+    }
+
+    fn runctx(
+        self,
+        statement: PyObjectRef,
+        globals: PyObjectRef,
+        locals: PyObjectRef,
+        vm: &VirtualMachine,
+    ) -> PyResult<Self> {
+        self.profile_statement(statement, Some(globals), Some(locals), vm)
+    }
+
+    fn profile_statement(
+        self,
+        statement: PyObjectRef,
+        globals: Option<PyObjectRef>,
+        locals: Option<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult<Self> {
+        self.clone().enable(vm);
+        let result = exec(vm, statement, globals, locals);
+        self.clone().disable(vm);
+        result.map(|_| self)
+    }
+
+    fn create_stats(self, vm: &VirtualMachine) {
+        self.disable(vm);
+    }
+
+    fn print_stats(self, sort: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult<()> {
+        self.clone().create_stats(vm);
+        let stats = PyStats::from_profile(&self).into_ref(vm);
+        stats.strip_dirs_();
+        print_sorted(stats, sort.into_option(), vm)
+    }
+
+    /// Per line hit counts and timings, as `(filename, lineno, hits, seconds)`
+    /// tuples.
+    fn get_line_stats(self, vm: &VirtualMachine) -> PyObjectRef {
+        let mut lines: Vec<_> = self.profiler.line_stats().into_iter().collect();
+        lines.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let lines = lines
+            .into_iter()
+            .map(|((file, line), stats)| {
+                vm.ctx.new_tuple(vec![
+                    vm.new_str(file),
+                    vm.ctx.new_int(line),
+                    vm.ctx.new_int(stats.hits),
+                    vm.ctx.new_float(profiler::seconds(stats.time)),
+                ])
+            })
+            .collect();
+        vm.ctx.new_list(lines)
+    }
+
+    fn enter(self, vm: &VirtualMachine) -> Self {
+        self.clone().enable(vm);
+        self
+    }
+
+    fn exit(self, _args: Args, vm: &VirtualMachine) {
+        self.disable(vm);
+    }
+}
+
+fn print_sorted(stats: PyStatsRef, sort: Option<PyObjectRef>, vm: &VirtualMachine) -> PyResult<()> {
+    let sort = sort.unwrap_or_else(|| vm.ctx.new_int(-1));
+    stats.sort_stats_(vm, vec![sort])?;
+    stats.print_stats_(vm, vec![])
+}
+
+fn run_profile(
+    statement: PyObjectRef,
+    globals: Option<PyObjectRef>,
+    locals: Option<PyObjectRef>,
+    output: OutputArgs,
+    vm: &VirtualMachine,
+) -> PyResult<()> {
+    if output
+        .filename
+        .map_or(false, |filename| !filename.is(&vm.get_none()))
+    {
+        return Err(vm.new_not_implemented_error(
+            "saving profile data to a file is not supported".to_string(),
+        ));
+    }
+    let profile = PyProfile::new().into_ref(vm);
+    profile
+        .clone()
+        .profile_statement(statement, globals, locals, vm)?;
+    let stats = PyStats::from_profile(&profile).into_ref(vm);
+    print_sorted(stats, output.sort, vm)
+}
+
+fn profile_run(statement: PyObjectRef, output: OutputArgs, vm: &VirtualMachine) -> PyResult<()> {
+    run_profile(statement, None, None, output, vm)
+}
+
+fn profile_runctx(
+    statement: PyObjectRef,
+    globals: PyObjectRef,
+    locals: PyObjectRef,
+    output: OutputArgs,
+    vm: &VirtualMachine,
+) -> PyResult<()> {
+    run_profile(statement, Some(globals), Some(locals), output, vm)
+}
+
+/// `profile.Profile()` makes a `cProfile.Profile`, as both measure the same.
+fn profile_profile_new(
+    _cls: PyClassRef,
+    args: Args,
+    kwargs: KwArgs,
+    vm: &VirtualMachine,
+) -> PyResult<PyProfileRef> {
+    PyProfileRef::new(PyProfile::class(vm), args, kwargs, vm)
+}
+
+pub fn make_profile_module(ctx: &PyContext) -> PyObjectRef {
+    let profile = py_class!(ctx, "Profile", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(profile_profile_new),
+    });
+
+    py_module!(ctx, "profile", {
+        "Profile" => profile,
+        "run" => ctx.new_rustfunc(profile_run),
+        "runctx" => ctx.new_rustfunc(profile_runctx),
+    })
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    let profile = py_class!(ctx, "Profile", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyProfileRef::new),
+        "__enter__" => ctx.new_rustfunc(PyProfileRef::enter),
+        "__exit__" => ctx.new_rustfunc(PyProfileRef::exit),
+        "enable" => ctx.new_rustfunc(PyProfileRef::enable),
+        "disable" => ctx.new_rustfunc(PyProfileRef::disable),
+        "clear" => ctx.new_rustfunc(PyProfileRef::clear),
+        "runcall" => ctx.new_rustfunc(PyProfileRef::runcall),
+        "run" => ctx.new_rustfunc(PyProfileRef::run),
+        "runctx" => ctx.new_rustfunc(PyProfileRef::runctx),
+        "create_stats" => ctx.new_rustfunc(PyProfileRef::create_stats),
+        "print_stats" => ctx.new_rustfunc(PyProfileRef::print_stats),
+        "get_line_stats" => ctx.new_rustfunc(PyProfileRef::get_line_stats),
+    });
+
+    py_module!(ctx, "cProfile", {
+        "Profile" => profile,
+        "run" => ctx.new_rustfunc(profile_run),
+        "runctx" => ctx.new_rustfunc(profile_runctx),
+    })
+}
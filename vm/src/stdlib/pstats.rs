@@ -0,0 +1,497 @@
+//! The python `pstats` module: reports of what a `cProfile.Profile` measured.
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::Path;
+
+use num_traits::ToPrimitive;
+use regex::Regex;
+
+use super::profile::PyProfile;
+use crate::function::{Args, KwArgs};
+use crate::obj::objfloat;
+use crate::obj::objint;
+use crate::obj::objstr;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::profiler::{self, FunctionKey, FunctionStats};
+use crate::pyobject::{
+    DictProtocol, IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
+};
+use crate::vm::VirtualMachine;
+
+#[derive(Debug, Default)]
+pub struct PyStats {
+    entries: RefCell<HashMap<FunctionKey, FunctionStats>>,
+    /// The current sort order, as `(key, description)` pairs.
+    order: RefCell<Vec<(&'static str, &'static str)>>,
+    stream: RefCell<Option<PyObjectRef>>,
+}
+
+pub type PyStatsRef = PyRef<PyStats>;
+
+/// Functions with their statistics, in the order they get printed.
+type Entries = Vec<(FunctionKey, FunctionStats)>;
+
+impl PyValue for PyStats {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("pstats", "Stats")
+    }
+}
+
+/// The sort keys `sort_stats` accepts, with what they are called in reports.
+const SORT_KEYS: &[(&str, &str, &str)] = &[
+    ("calls", "calls", "call count"),
+    ("ncalls", "calls", "call count"),
+    ("cumtime", "cumulative", "cumulative time"),
+    ("cumulative", "cumulative", "cumulative time"),
+    ("file", "filename", "file name"),
+    ("filename", "filename", "file name"),
+    ("module", "filename", "file name"),
+    ("line", "line", "line number"),
+    ("name", "name", "function name"),
+    ("nfl", "nfl", "name/file/line"),
+    ("pcalls", "pcalls", "primitive call count"),
+    ("stdname", "stdname", "standard name"),
+    ("time", "time", "internal time"),
+    ("tottime", "time", "internal time"),
+];
+
+/// What the legacy numeric sort arguments stand for.
+fn numeric_sort_key(number: i32) -> Option<&'static str> {
+    match number {
+        -1 => Some("stdname"),
+        0 => Some("calls"),
+        1 => Some("time"),
+        2 => Some("cumulative"),
+        _ => None,
+    }
+}
+
+/// `file:line(name)`, the way reports name functions.
+fn std_string(key: &FunctionKey) -> String {
+    format!("{}:{}({})", key.0, key.1, key.2)
+}
+
+fn compare(
+    key: &str,
+    a: (&FunctionKey, &FunctionStats),
+    b: (&FunctionKey, &FunctionStats),
+) -> Ordering {
+    let (a_key, a_stats) = a;
+    let (b_key, b_stats) = b;
+    match key {
+        // Counts and timings put the largest first.
+        "calls" => b_stats.calls.cmp(&a_stats.calls),
+        "pcalls" => b_stats.primitive_calls.cmp(&a_stats.primitive_calls),
+        "cumulative" => b_stats.cumulative_time.cmp(&a_stats.cumulative_time),
+        "time" => b_stats.total_time.cmp(&a_stats.total_time),
+        "filename" => a_key.0.cmp(&b_key.0),
+        "line" => a_key.1.cmp(&b_key.1),
+        "name" => a_key.2.cmp(&b_key.2),
+        "nfl" => (&a_key.2, &a_key.0, a_key.1).cmp(&(&b_key.2, &b_key.0, b_key.1)),
+        "stdname" => std_string(a_key).cmp(&std_string(b_key)),
+        _ => Ordering::Equal,
+    }
+}
+
+fn merge(
+    entries: &mut HashMap<FunctionKey, FunctionStats>,
+    key: FunctionKey,
+    stats: &FunctionStats,
+) {
+    let entry = entries.entry(key).or_default();
+    entry.calls += stats.calls;
+    entry.primitive_calls += stats.primitive_calls;
+    entry.total_time += stats.total_time;
+    entry.cumulative_time += stats.cumulative_time;
+    for (caller, count) in &stats.callers {
+        *entry.callers.entry(caller.clone()).or_insert(0) += count;
+    }
+}
+
+fn format_time(duration: std::time::Duration) -> String {
+    format!("{:8.3}", profiler::seconds(duration))
+}
+
+fn format_per_call(duration: std::time::Duration, calls: usize) -> String {
+    if calls == 0 {
+        " ".repeat(8)
+    } else {
+        format!("{:8.3}", profiler::seconds(duration) / calls as f64)
+    }
+}
+
+fn format_calls(stats: &FunctionStats) -> String {
+    if stats.calls == stats.primitive_calls {
+        stats.calls.to_string()
+    } else {
+        format!("{}/{}", stats.calls, stats.primitive_calls)
+    }
+}
+
+impl PyStats {
+    pub fn from_profile(profile: &PyProfile) -> PyStats {
+        PyStats {
+            entries: RefCell::new(profile.profiler.function_stats()),
+            ..PyStats::default()
+        }
+    }
+
+    fn add_source(&self, vm: &VirtualMachine, source: &PyObjectRef) -> PyResult<()> {
+        let added = if let Some(profile) = source.payload::<PyProfile>() {
+            profile.profiler.flush();
+            profile.profiler.function_stats()
+        } else if let Some(stats) = source.payload::<PyStats>() {
+            stats.entries.borrow().clone()
+        } else {
+            return Err(vm.new_type_error(format!(
+                "Cannot create or construct a {} object from {}",
+                "Stats",
+                source.class().name
+            )));
+        };
+        let mut entries = self.entries.borrow_mut();
+        for (key, stats) in &added {
+            merge(&mut entries, key.clone(), stats);
+        }
+        Ok(())
+    }
+
+    fn sorted_entries(&self) -> Entries {
+        let mut entries: Vec<_> = self
+            .entries
+            .borrow()
+            .iter()
+            .map(|(key, stats)| (key.clone(), stats.clone()))
+            .collect();
+        let order = self.order.borrow();
+        entries.sort_by(|(a_key, a_stats), (b_key, b_stats)| {
+            order
+                .iter()
+                .map(|(key, _)| compare(key, (a_key, a_stats), (b_key, b_stats)))
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        entries
+    }
+
+    fn write(&self, vm: &VirtualMachine, text: String) -> PyResult<()> {
+        match self.stream.borrow().as_ref() {
+            Some(stream) => {
+                vm.call_method(stream, "write", vec![vm.new_str(text + "\n")])?;
+            }
+            None => {
+                let print = vm.get_attribute(vm.builtins.clone(), "print")?;
+                vm.invoke(print, vec![vm.new_str(text)])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PyStatsRef {
+    fn new(cls: PyClassRef, args: Args, kwargs: KwArgs, vm: &VirtualMachine) -> PyResult<Self> {
+        let zelf = PyStats::default().into_ref_with_type(vm, cls)?;
+        for source in args {
+            zelf.add_source(vm, &source)?;
+        }
+        for (name, value) in kwargs {
+            if name != "stream" {
+                return Err(vm.new_type_error(format!(
+                    "Stats() got an unexpected keyword argument '{}'",
+                    name
+                )));
+            }
+            if !value.is(&vm.get_none()) {
+                zelf.stream.replace(Some(value));
+            }
+        }
+        Ok(zelf)
+    }
+
+    fn add(self, sources: Args, vm: &VirtualMachine) -> PyResult<Self> {
+        for source in sources {
+            self.add_source(vm, &source)?;
+        }
+        Ok(self)
+    }
+
+    pub fn strip_dirs_(&self) {
+        let entries = self.entries.replace(HashMap::new());
+        let strip = |key: &FunctionKey| {
+            let file = match Path::new(&key.0).file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => key.0.clone(),
+            };
+            (file, key.1, key.2.clone())
+        };
+        let mut stripped = HashMap::new();
+        for (key, stats) in entries {
+            let mut stats = stats;
+            stats.callers = stats
+                .callers
+                .iter()
+                .map(|(caller, count)| (strip(caller), *count))
+                .collect();
+            merge(&mut stripped, strip(&key), &stats);
+        }
+        self.entries.replace(stripped);
+        // The old order could be based on the directories.
+        self.order.borrow_mut().clear();
+    }
+
+    fn strip_dirs(self, _vm: &VirtualMachine) -> Self {
+        self.strip_dirs_();
+        self
+    }
+
+    pub fn sort_stats_(&self, vm: &VirtualMachine, keys: Vec<PyObjectRef>) -> PyResult<()> {
+        let mut names = vec![];
+        for key in keys {
+            let name = if objtype::isinstance(&key, &vm.ctx.int_type()) {
+                let number = objint::get_value(&key).to_string().parse().unwrap_or(3);
+                match numeric_sort_key(number) {
+                    Some(name) => name.to_string(),
+                    None => return Err(vm.new_value_error("invalid sort key".to_string())),
+                }
+            } else {
+                vm.to_pystr(&key)?
+            };
+            names.push(name);
+        }
+        let mut order = vec![];
+        for name in names {
+            match SORT_KEYS.iter().find(|(alias, _, _)| *alias == name) {
+                Some((_, key, description)) => order.push((*key, *description)),
+                None => return Err(vm.new_key_error(vm.new_str(name))),
+            }
+        }
+        self.order.replace(order);
+        Ok(())
+    }
+
+    fn sort_stats(self, keys: Args, vm: &VirtualMachine) -> PyResult<Self> {
+        let keys: Vec<PyObjectRef> = keys.into_iter().collect();
+        if keys.is_empty() {
+            self.sort_stats_(vm, vec![vm.new_str("stdname".to_string())])?;
+        } else {
+            self.sort_stats_(vm, keys)?;
+        }
+        Ok(self)
+    }
+
+    /// Apply the restrictions of `print_stats`: a number of lines, a fraction
+    /// of them, or a regular expression the function must match. Returns the
+    /// remaining entries and a message for each restriction that removed some.
+    fn restrict(
+        vm: &VirtualMachine,
+        mut entries: Entries,
+        restrictions: &[PyObjectRef],
+    ) -> PyResult<(Entries, Vec<String>)> {
+        let mut messages = vec![];
+        for restriction in restrictions {
+            let before = entries.len();
+            if objtype::isinstance(restriction, &vm.ctx.str_type()) {
+                let pattern = objstr::get_value(restriction);
+                let regex =
+                    Regex::new(&pattern).map_err(|err| vm.new_value_error(err.to_string()))?;
+                entries.retain(|(key, _)| regex.is_match(&std_string(key)));
+            } else if objtype::isinstance(restriction, &vm.ctx.float_type()) {
+                let fraction = objfloat::get_value(restriction);
+                if fraction >= 0.0 && fraction < 1.0 {
+                    entries.truncate((before as f64 * fraction + 0.5) as usize);
+                }
+            } else if objtype::isinstance(restriction, &vm.ctx.int_type()) {
+                if let Some(count) = objint::get_value(restriction).to_usize() {
+                    entries.truncate(count);
+                }
+            }
+            if entries.len() != before {
+                messages.push(format!(
+                    "   List reduced from {} to {} due to restriction <{}>",
+                    before,
+                    entries.len(),
+                    vm.to_repr(restriction)?.value
+                ));
+            }
+        }
+        Ok((entries, messages))
+    }
+
+    pub fn print_stats_(
+        &self,
+        vm: &VirtualMachine,
+        restrictions: Vec<PyObjectRef>,
+    ) -> PyResult<()> {
+        let entries = self.sorted_entries();
+        let total_calls: usize = entries.iter().map(|(_, stats)| stats.calls).sum();
+        let primitive_calls: usize = entries.iter().map(|(_, stats)| stats.primitive_calls).sum();
+        let total_time = entries.iter().map(|(_, stats)| stats.total_time).sum();
+
+        let mut summary = format!("         {} function calls ", total_calls);
+        if total_calls != primitive_calls {
+            summary.push_str(&format!("({} primitive calls) ", primitive_calls));
+        }
+        summary.push_str(&format!("in {:.3} seconds", profiler::seconds(total_time)));
+        self.write(vm, summary)?;
+        self.write(vm, String::new())?;
+
+        let (entries, messages) = PyStatsRef::restrict(vm, entries, &restrictions)?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let order = self.order.borrow().clone();
+        if !order.is_empty() {
+            let descriptions: Vec<&str> =
+                order.iter().map(|(_, description)| *description).collect();
+            self.write(vm, format!("   Ordered by: {}", descriptions.join(", ")))?;
+        } else {
+            self.write(vm, "   Random listing order was used".to_string())?;
+        }
+        for message in messages {
+            self.write(vm, message)?;
+        }
+        self.write(vm, String::new())?;
+        self.write(
+            vm,
+            "   ncalls  tottime  percall  cumtime  percall filename:lineno(function)".to_string(),
+        )?;
+        for (key, stats) in &entries {
+            self.write(
+                vm,
+                format!(
+                    "{:>9} {} {} {} {} {}",
+                    format_calls(stats),
+                    format_time(stats.total_time),
+                    format_per_call(stats.total_time, stats.calls),
+                    format_time(stats.cumulative_time),
+                    format_per_call(stats.cumulative_time, stats.primitive_calls),
+                    std_string(key)
+                ),
+            )?;
+        }
+        self.write(vm, String::new())?;
+        self.write(vm, String::new())
+    }
+
+    fn print_stats(self, restrictions: Args, vm: &VirtualMachine) -> PyResult<Self> {
+        self.print_stats_(vm, restrictions.into_iter().collect())?;
+        Ok(self)
+    }
+
+    fn total_calls(self, _vm: &VirtualMachine) -> usize {
+        self.entries
+            .borrow()
+            .values()
+            .map(|stats| stats.calls)
+            .sum()
+    }
+
+    fn prim_calls(self, _vm: &VirtualMachine) -> usize {
+        self.entries
+            .borrow()
+            .values()
+            .map(|stats| stats.primitive_calls)
+            .sum()
+    }
+
+    fn total_tt(self, _vm: &VirtualMachine) -> f64 {
+        let total = self
+            .entries
+            .borrow()
+            .values()
+            .map(|stats| stats.total_time)
+            .sum();
+        profiler::seconds(total)
+    }
+
+    /// The statistics of each function, keyed by the function name, like
+    /// `Stats.get_stats_profile()` of CPython.
+    fn get_stats_profile(self, vm: &VirtualMachine) -> PyResult {
+        let round = |seconds: f64| (seconds * 1000.0).round() / 1000.0;
+        let func_profiles = vm.ctx.new_dict();
+        let function_profile = vm.class("pstats", "FunctionProfile");
+        for (key, stats) in self.sorted_entries() {
+            let per_call = |time, calls| {
+                if calls == 0 {
+                    0.0
+                } else {
+                    round(profiler::seconds(time) / calls as f64)
+                }
+            };
+            let profile = vm
+                .ctx
+                .new_instance(function_profile.clone(), Some(vm.ctx.new_dict()));
+            let attributes = vec![
+                ("ncalls", vm.new_str(format_calls(&stats))),
+                (
+                    "tottime",
+                    vm.ctx.new_float(round(profiler::seconds(stats.total_time))),
+                ),
+                (
+                    "percall_tottime",
+                    vm.ctx.new_float(per_call(stats.total_time, stats.calls)),
+                ),
+                (
+                    "cumtime",
+                    vm.ctx
+                        .new_float(round(profiler::seconds(stats.cumulative_time))),
+                ),
+                (
+                    "percall_cumtime",
+                    vm.ctx
+                        .new_float(per_call(stats.cumulative_time, stats.primitive_calls)),
+                ),
+                ("file_name", vm.new_str(key.0.clone())),
+                ("line_number", vm.ctx.new_int(key.1)),
+            ];
+            for (name, value) in attributes {
+                vm.ctx.set_attr(&profile, name, value);
+            }
+            func_profiles.set_item(&vm.ctx, &key.2, profile);
+        }
+        let stats_profile = vm
+            .ctx
+            .new_instance(vm.class("pstats", "StatsProfile"), Some(vm.ctx.new_dict()));
+        let total_tt = round(self.total_tt(vm));
+        vm.ctx
+            .set_attr(&stats_profile, "total_tt", vm.ctx.new_float(total_tt));
+        vm.ctx
+            .set_attr(&stats_profile, "func_profiles", func_profiles.into_object());
+        Ok(stats_profile)
+    }
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    let stats = py_class!(ctx, "Stats", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyStatsRef::new),
+        "add" => ctx.new_rustfunc(PyStatsRef::add),
+        "strip_dirs" => ctx.new_rustfunc(PyStatsRef::strip_dirs),
+        "sort_stats" => ctx.new_rustfunc(PyStatsRef::sort_stats),
+        "print_stats" => ctx.new_rustfunc(PyStatsRef::print_stats),
+        "get_stats_profile" => ctx.new_rustfunc(PyStatsRef::get_stats_profile),
+        "total_calls" => ctx.new_property(PyStatsRef::total_calls),
+        "prim_calls" => ctx.new_property(PyStatsRef::prim_calls),
+        "total_tt" => ctx.new_property(PyStatsRef::total_tt),
+    });
+
+    let sort_key = py_class!(ctx, "SortKey", ctx.object(), {
+        "CALLS" => ctx.new_str("calls".to_string()),
+        "CUMULATIVE" => ctx.new_str("cumulative".to_string()),
+        "FILENAME" => ctx.new_str("filename".to_string()),
+        "LINE" => ctx.new_str("line".to_string()),
+        "NAME" => ctx.new_str("name".to_string()),
+        "NFL" => ctx.new_str("nfl".to_string()),
+        "PCALLS" => ctx.new_str("pcalls".to_string()),
+        "STDNAME" => ctx.new_str("stdname".to_string()),
+        "TIME" => ctx.new_str("tottime".to_string()),
+    });
+
+    py_module!(ctx, "pstats", {
+        "Stats" => stats,
+        "SortKey" => sort_key,
+        "StatsProfile" => ctx.new_class("StatsProfile", ctx.object()),
+        "FunctionProfile" => ctx.new_class("FunctionProfile", ctx.object()),
+    })
+}
@@ -1,4 +1,4 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::io;
 use std::io::Read;
 use std::io::Write;
@@ -13,6 +13,7 @@ use crate::obj::objstr;
 use crate::pyobject::{
     PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject, TypeProtocol,
 };
+use crate::signal;
 use crate::vm::VirtualMachine;
 
 use crate::obj::objtype::PyClassRef;
@@ -67,6 +68,15 @@ impl Connection {
         }
     }
 
+    /// Another handle to the same OS socket.
+    fn try_clone(&self) -> io::Result<Connection> {
+        Ok(match self {
+            Connection::TcpListener(con) => Connection::TcpListener(con.try_clone()?),
+            Connection::UdpSocket(con) => Connection::UdpSocket(con.try_clone()?),
+            Connection::TcpStream(con) => Connection::TcpStream(con.try_clone()?),
+        })
+    }
+
     fn local_addr(&self) -> io::Result<SocketAddr> {
         match self {
             Connection::TcpListener(con) => con.local_addr(),
@@ -82,6 +92,39 @@ impl Connection {
         }
     }
 
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Connection::TcpListener(con) => con.set_nonblocking(nonblocking),
+            Connection::UdpSocket(con) => con.set_nonblocking(nonblocking),
+            Connection::TcpStream(con) => con.set_nonblocking(nonblocking),
+        }
+    }
+
+    #[cfg(unix)]
+    fn fileno(&self) -> i64 {
+        use std::os::unix::io::AsRawFd;
+        i64::from(match self {
+            Connection::TcpListener(con) => con.as_raw_fd(),
+            Connection::UdpSocket(con) => con.as_raw_fd(),
+            Connection::TcpStream(con) => con.as_raw_fd(),
+        })
+    }
+
+    #[cfg(windows)]
+    fn fileno(&self) -> i64 {
+        use std::os::windows::io::AsRawSocket;
+        (match self {
+            Connection::TcpListener(con) => con.as_raw_socket(),
+            Connection::UdpSocket(con) => con.as_raw_socket(),
+            Connection::TcpStream(con) => con.as_raw_socket(),
+        }) as i64
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn fileno(&self) -> i64 {
+        -1
+    }
+
     fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
         match self {
             Connection::UdpSocket(con) => con.send_to(buf, addr),
@@ -118,6 +161,7 @@ pub struct Socket {
     address_family: AddressFamily,
     socket_kind: SocketKind,
     con: RefCell<Option<Connection>>,
+    blocking: Cell<bool>,
 }
 
 impl PyValue for Socket {
@@ -132,7 +176,83 @@ impl Socket {
             address_family,
             socket_kind,
             con: RefCell::new(None),
+            blocking: Cell::new(true),
+        }
+    }
+
+    /// Use a new connection, in the blocking mode that was asked for.
+    fn connect(&self, con: Connection) -> io::Result<()> {
+        if !self.blocking.get() {
+            con.set_nonblocking(true)?;
         }
+        self.con.borrow_mut().replace(con);
+        Ok(())
+    }
+
+    fn with_connection<T, F>(&self, f: F) -> io::Result<T>
+    where
+        F: FnOnce(&mut Connection) -> io::Result<T>,
+    {
+        match self.con.borrow_mut().as_mut() {
+            Some(con) => f(con),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "socket is not connected",
+            )),
+        }
+    }
+
+    /// Run `f`, which may block, on the connection with the GIL released.
+    /// It gets a handle of its own, so that other threads can keep using the
+    /// socket meanwhile.
+    fn blocking_io<T, F>(&self, vm: &VirtualMachine, f: F) -> PyResult<T>
+    where
+        F: FnOnce(&mut Connection) -> io::Result<T>,
+    {
+        let mut con = match self.con.borrow().as_ref() {
+            Some(con) => con.try_clone().map_err(|err| vm.new_io_error(&err))?,
+            None => return Err(vm.new_type_error("".to_string())),
+        };
+        vm.allow_threads(|| f(&mut con)).or_else(|err| {
+            // The call may have given up to let an interrupt be raised.
+            signal::check_signals(vm)?;
+            Err(vm.new_io_error(&err))
+        })
+    }
+
+    /// The OS level file descriptor of the socket, if it has one yet.
+    pub fn fileno(&self) -> Option<i64> {
+        self.con.borrow().as_ref().map(Connection::fileno)
+    }
+
+    /// Receive at most `bufsize` bytes, without waiting for all of them.
+    pub fn recv_some(&self, bufsize: usize) -> io::Result<Vec<u8>> {
+        self.with_connection(|con| {
+            let mut buffer = vec![0u8; bufsize];
+            let size = con.read(&mut buffer)?;
+            buffer.truncate(size);
+            Ok(buffer)
+        })
+    }
+
+    /// Send as much of `data` as possible, returning how much was sent.
+    pub fn send_some(&self, data: &[u8]) -> io::Result<usize> {
+        self.with_connection(|con| con.write(data))
+    }
+
+    /// Accept a connection as a new socket, which gets the blocking mode
+    /// of this one.
+    pub fn accept_socket(&self) -> io::Result<(Socket, SocketAddr)> {
+        let (stream, addr) = self.with_connection(Connection::accept)?;
+        Ok((self.accepted(stream)?, addr))
+    }
+
+    /// Wrap a connection accepted by this socket.
+    fn accepted(&self, stream: TcpStream) -> io::Result<Socket> {
+        let socket = Socket::new(self.address_family, self.socket_kind);
+        socket.blocking.set(self.blocking.get());
+        socket.connect(Connection::TcpStream(stream))?;
+        Ok(socket)
     }
 }
 
@@ -140,7 +260,7 @@ fn get_socket<'a>(obj: &'a PyObjectRef) -> impl Deref<Target = Socket> + 'a {
     obj.payload::<Socket>().unwrap()
 }
 
-type SocketRef = PyRef<Socket>;
+pub type SocketRef = PyRef<Socket>;
 
 fn socket_new(
     cls: PyClassRef,
@@ -163,21 +283,23 @@ fn socket_connect(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let socket = get_socket(zelf);
 
     match socket.socket_kind {
-        SocketKind::Stream => match TcpStream::connect(address_string) {
-            Ok(stream) => {
-                socket
-                    .con
-                    .borrow_mut()
-                    .replace(Connection::TcpStream(stream));
-                Ok(vm.get_none())
+        SocketKind::Stream => {
+            match vm
+                .allow_threads(|| TcpStream::connect(address_string))
+                .and_then(|stream| socket.connect(Connection::TcpStream(stream)))
+            {
+                Ok(()) => Ok(vm.get_none()),
+                Err(s) => {
+                    signal::check_signals(vm)?;
+                    Err(vm.new_io_error(&s))
+                }
             }
-            Err(s) => Err(vm.new_os_error(s.to_string())),
-        },
+        }
         SocketKind::Dgram => {
             if let Some(Connection::UdpSocket(con)) = socket.con.borrow().as_ref() {
                 match con.connect(address_string) {
                     Ok(_) => Ok(vm.get_none()),
-                    Err(s) => Err(vm.new_os_error(s.to_string())),
+                    Err(s) => Err(vm.new_io_error(&s)),
                 }
             } else {
                 Err(vm.new_type_error("".to_string()))
@@ -198,25 +320,17 @@ fn socket_bind(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let socket = get_socket(zelf);
 
     match socket.socket_kind {
-        SocketKind::Stream => match TcpListener::bind(address_string) {
-            Ok(stream) => {
-                socket
-                    .con
-                    .borrow_mut()
-                    .replace(Connection::TcpListener(stream));
-                Ok(vm.get_none())
-            }
-            Err(s) => Err(vm.new_os_error(s.to_string())),
+        SocketKind::Stream => match TcpListener::bind(address_string)
+            .and_then(|listener| socket.connect(Connection::TcpListener(listener)))
+        {
+            Ok(()) => Ok(vm.get_none()),
+            Err(s) => Err(vm.new_io_error(&s)),
         },
-        SocketKind::Dgram => match UdpSocket::bind(address_string) {
-            Ok(dgram) => {
-                socket
-                    .con
-                    .borrow_mut()
-                    .replace(Connection::UdpSocket(dgram));
-                Ok(vm.get_none())
-            }
-            Err(s) => Err(vm.new_os_error(s.to_string())),
+        SocketKind::Dgram => match UdpSocket::bind(address_string)
+            .and_then(|dgram| socket.connect(Connection::UdpSocket(dgram)))
+        {
+            Ok(()) => Ok(vm.get_none()),
+            Err(s) => Err(vm.new_io_error(&s)),
         },
     }
 }
@@ -256,23 +370,12 @@ fn socket_accept(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
 
     let socket = get_socket(zelf);
 
-    let ret = match socket.con.borrow_mut().as_mut() {
-        Some(v) => v.accept(),
-        None => return Err(vm.new_type_error("".to_string())),
+    let (stream, addr) = socket.blocking_io(vm, Connection::accept)?;
+    let socket = match socket.accepted(stream) {
+        Ok(socket) => socket.into_ref(vm),
+        Err(s) => return Err(vm.new_io_error(&s)),
     };
 
-    let (tcp_stream, addr) = match ret {
-        Ok((socket, addr)) => (socket, addr),
-        Err(s) => return Err(vm.new_os_error(s.to_string())),
-    };
-
-    let socket = Socket {
-        address_family: socket.address_family,
-        socket_kind: socket.socket_kind,
-        con: RefCell::new(Some(Connection::TcpStream(tcp_stream))),
-    }
-    .into_ref(vm);
-
     let addr_tuple = get_addr_tuple(vm, addr)?;
 
     Ok(vm.ctx.new_tuple(vec![socket.into_object(), addr_tuple]))
@@ -287,13 +390,7 @@ fn socket_recv(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let socket = get_socket(zelf);
 
     let mut buffer = vec![0u8; objint::get_value(bufsize).to_usize().unwrap()];
-    match socket.con.borrow_mut().as_mut() {
-        Some(v) => match v.read_exact(&mut buffer) {
-            Ok(_) => (),
-            Err(s) => return Err(vm.new_os_error(s.to_string())),
-        },
-        None => return Err(vm.new_type_error("".to_string())),
-    };
+    socket.blocking_io(vm, |con| con.read_exact(&mut buffer))?;
     Ok(vm.ctx.new_bytes(buffer))
 }
 
@@ -307,15 +404,7 @@ fn socket_recvfrom(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let socket = get_socket(zelf);
 
     let mut buffer = vec![0u8; objint::get_value(bufsize).to_usize().unwrap()];
-    let ret = match socket.con.borrow().as_ref() {
-        Some(v) => v.recv_from(&mut buffer),
-        None => return Err(vm.new_type_error("".to_string())),
-    };
-
-    let addr = match ret {
-        Ok((_size, addr)) => addr,
-        Err(s) => return Err(vm.new_os_error(s.to_string())),
-    };
+    let (_size, addr) = socket.blocking_io(vm, |con| con.recv_from(&mut buffer))?;
 
     let addr_tuple = get_addr_tuple(vm, addr)?;
 
@@ -330,13 +419,8 @@ fn socket_send(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     );
     let socket = get_socket(zelf);
 
-    match socket.con.borrow_mut().as_mut() {
-        Some(v) => match v.write(&objbytes::get_value(&bytes)) {
-            Ok(_) => (),
-            Err(s) => return Err(vm.new_os_error(s.to_string())),
-        },
-        None => return Err(vm.new_type_error("".to_string())),
-    };
+    let data = objbytes::get_value(&bytes);
+    socket.blocking_io(vm, |con| con.write(&data))?;
     Ok(vm.get_none())
 }
 
@@ -359,7 +443,7 @@ fn socket_sendto(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
             if let Some(v) = socket.con.borrow().as_ref() {
                 return match v.send_to(&objbytes::get_value(&bytes), address_string) {
                     Ok(_) => Ok(vm.get_none()),
-                    Err(s) => Err(vm.new_os_error(s.to_string())),
+                    Err(s) => Err(vm.new_io_error(&s)),
                 };
             }
             // Doing implicit bind
@@ -372,9 +456,9 @@ fn socket_sendto(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
                             .replace(Connection::UdpSocket(dgram));
                         Ok(vm.get_none())
                     }
-                    Err(s) => Err(vm.new_os_error(s.to_string())),
+                    Err(s) => Err(vm.new_io_error(&s)),
                 },
-                Err(s) => Err(vm.new_os_error(s.to_string())),
+                Err(s) => Err(vm.new_io_error(&s)),
             }
         }
         _ => Err(vm.new_not_implemented_error("".to_string())),
@@ -400,11 +484,28 @@ fn socket_getsockname(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
 
     match addr {
         Ok(addr) => get_addr_tuple(vm, addr),
-        Err(s) => Err(vm.new_os_error(s.to_string())),
+        Err(s) => Err(vm.new_io_error(&s)),
     }
 }
 
-fn get_addr_tuple(vm: &VirtualMachine, addr: SocketAddr) -> PyResult {
+fn socket_setblocking(zelf: SocketRef, flag: bool, vm: &VirtualMachine) -> PyResult<()> {
+    zelf.blocking.set(flag);
+    if let Some(con) = zelf.con.borrow().as_ref() {
+        con.set_nonblocking(!flag)
+            .map_err(|err| vm.new_io_error(&err))?;
+    }
+    Ok(())
+}
+
+fn socket_getblocking(zelf: SocketRef, _vm: &VirtualMachine) -> bool {
+    zelf.blocking.get()
+}
+
+fn socket_fileno(zelf: SocketRef, _vm: &VirtualMachine) -> i64 {
+    zelf.fileno().unwrap_or(-1)
+}
+
+pub fn get_addr_tuple(vm: &VirtualMachine, addr: SocketAddr) -> PyResult {
     let port = vm.ctx.new_int(addr.port());
     let ip = vm.ctx.new_str(addr.ip().to_string());
 
@@ -424,6 +525,9 @@ pub fn make_module(ctx: &PyContext) -> PyObjectRef {
          "getsockname" => ctx.new_rustfunc(socket_getsockname),
          "sendto" => ctx.new_rustfunc(socket_sendto),
          "recvfrom" => ctx.new_rustfunc(socket_recvfrom),
+         "setblocking" => ctx.new_rustfunc(socket_setblocking),
+         "getblocking" => ctx.new_rustfunc(socket_getblocking),
+         "fileno" => ctx.new_rustfunc(socket_fileno),
     });
 
     py_module!(ctx, "socket", {
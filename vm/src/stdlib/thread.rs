@@ -0,0 +1,586 @@
+//! The python `_thread` module: low level threads and locks.
+//!
+//! Threads are real OS threads, which take turns running python code by
+//! holding the GIL of the `VirtualMachine` (see `crate::gil`). Blocking
+//! operations such as waiting for a lock release the GIL.
+
+// The mutexes here guard state that is waited for with a `Condvar`.
+#![allow(clippy::mutex_atomic)]
+
+use std::cmp;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::exceptions;
+use crate::function::{Args, ArgumentError, FromArgs, OptionalArg, PyFuncArgs};
+use crate::gil::Gil;
+use crate::globals::globals;
+use crate::obj::objdict::{self, PyDictRef};
+use crate::obj::objfloat;
+use crate::obj::objtuple::PyTupleRef;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::pyobject::{
+    IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject,
+};
+use crate::signal;
+use crate::sysmodule;
+use crate::vm::{VirtualMachine, VmState, THREAD_STACK_SIZE};
+
+/// The largest timeout accepted by the blocking functions, in seconds.
+pub const TIMEOUT_MAX: f64 = 4_294_967.0;
+
+static NEXT_IDENT: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    static IDENT: u64 = NEXT_IDENT.fetch_add(1, Ordering::SeqCst) as u64;
+}
+
+/// The non-zero identifier python code knows the current thread by.
+pub fn get_ident() -> u64 {
+    (globals().get_ident)()
+}
+
+pub(crate) fn own_ident() -> u64 {
+    IDENT.with(|ident| *ident)
+}
+
+/// Bind a parameter that can be passed either by position or by keyword.
+pub fn take_arg(args: &mut PyFuncArgs, name: &str) -> Option<PyObjectRef> {
+    args.next_positional().or_else(|| args.take_keyword(name))
+}
+
+/// Convert a python timeout in seconds, where `None` or `-1` means forever.
+#[allow(clippy::float_cmp)]
+pub fn get_timeout(
+    vm: &VirtualMachine,
+    timeout: Option<PyObjectRef>,
+) -> PyResult<Option<Duration>> {
+    let seconds = match timeout {
+        Some(ref timeout) if !timeout.is(&vm.get_none()) => objfloat::make_float(vm, timeout)?,
+        _ => return Ok(None),
+    };
+    if seconds == -1.0 {
+        Ok(None)
+    } else if seconds < 0.0 {
+        Err(vm.new_value_error("timeout value must be positive".to_string()))
+    } else if seconds > TIMEOUT_MAX {
+        Err(vm.new_overflow_error("timeout value is too large".to_string()))
+    } else {
+        Ok(Some(Duration::from_micros((seconds * 1e6) as u64)))
+    }
+}
+
+/// Wait on `cond` as long as `blocked` holds for the state guarded by `lock`.
+/// Returns `None` if the timeout ran out first, or if `interruptible` and an
+/// interrupt became pending, see `signal::check_signals`. Must be called with
+/// the GIL released.
+pub fn wait_while<'a, T, F>(
+    lock: &'a Mutex<T>,
+    cond: &Condvar,
+    timeout: Option<Duration>,
+    interruptible: bool,
+    mut blocked: F,
+) -> Option<MutexGuard<'a, T>>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    // Signals don't wake up condition variables, so look for interrupts now
+    // and then.
+    let poll = interruptible && signal::receives_interrupts();
+    let mut state = lock.lock().unwrap();
+    while blocked(&mut state) {
+        if poll && signal::interrupt_pending() {
+            return None;
+        }
+        let mut wait = match deadline {
+            None => None,
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                Some(deadline - now)
+            }
+        };
+        if poll {
+            wait = Some(wait.map_or(signal::POLL_INTERVAL, |wait| {
+                cmp::min(wait, signal::POLL_INTERVAL)
+            }));
+        }
+        state = match wait {
+            None => cond.wait(state).unwrap(),
+            Some(wait) => cond.wait_timeout(state, wait).unwrap().0,
+        };
+    }
+    Some(state)
+}
+
+/// Values which may be handed to another thread running python code on the
+/// same VM.
+///
+/// # Safety
+///
+/// Implementors must not share state with other threads except through
+/// python objects, which are only touched while holding the GIL.
+pub unsafe trait GilShared {}
+
+unsafe impl GilShared for PyObjectRef {}
+unsafe impl<T: PyValue> GilShared for PyRef<T> {}
+unsafe impl GilShared for PyFuncArgs {}
+unsafe impl<A: GilShared, B: GilShared> GilShared for (A, B) {}
+
+/// Moves python objects into a new thread. They are only ever touched while
+/// holding the GIL, which makes sharing them between threads sound.
+struct GilBound<T>(T);
+
+unsafe impl<T: GilShared> Send for GilBound<T> {}
+
+/// The state of the VM a thread runs python code on. The thread only
+/// dereferences it while holding the GIL, which makes sure it's still there
+/// (see `crate::gil`).
+struct StatePtr(*const VmState);
+
+unsafe impl Send for StatePtr {}
+
+/// Releases the GIL when a thread is done, even if it panicked.
+struct GilGuard<'a>(&'a Gil);
+
+impl<'a> Drop for GilGuard<'a> {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+/// Start a new OS thread which runs `run(vm, data)` while holding the GIL,
+/// and return its identifier.
+///
+/// The thread runs on a `VirtualMachine` of its own, sharing the state of
+/// `vm`. It stops for good when it next waits for the GIL after `vm` was
+/// dropped.
+pub fn start_thread<T>(vm: &VirtualMachine, data: T, run: fn(&VirtualMachine, T)) -> PyResult<u64>
+where
+    T: GilShared + 'static,
+{
+    let gil = vm.gil.clone();
+    let state = StatePtr(&**vm);
+    let data = GilBound(data);
+    let (ident_sender, ident_receiver) = mpsc::channel();
+    let spawned = thread::Builder::new()
+        .stack_size(THREAD_STACK_SIZE)
+        .spawn(move || {
+            signal::block_interrupt_signals();
+            let _ = ident_sender.send(get_ident());
+            let StatePtr(state) = state;
+            gil.acquire();
+            let _guard = GilGuard(&gil);
+            let GilBound(data) = data;
+            // Dropped before the guard, while holding the GIL.
+            let vm = unsafe { VirtualMachine::new_thread(state) };
+            run(&vm, data);
+            // The queue of finalizers of the thread goes away with it, so
+            // empty it while holding the GIL.
+            vm.run_finalizers();
+        });
+    if let Err(err) = spawned {
+        return Err(vm.new_runtime_error(format!("can't start new thread: {}", err)));
+    }
+    vm.allow_threads(|| ident_receiver.recv())
+        .map_err(|_| vm.new_runtime_error("can't start new thread".to_string()))
+}
+
+/// Report an exception which ended a thread.
+pub fn print_thread_exception(vm: &VirtualMachine, header: &str, exc: &PyObjectRef) {
+    let mut report = format!("{}\n", header).into_bytes();
+    let _ = exceptions::write_exception(&mut report, vm, exc);
+    sysmodule::write_stderr(vm, &String::from_utf8_lossy(&report));
+}
+
+struct AcquireArgs {
+    blocking: bool,
+    timeout: Option<Duration>,
+}
+
+impl FromArgs for AcquireArgs {
+    fn arity() -> RangeInclusive<usize> {
+        0..=2
+    }
+
+    #[allow(clippy::float_cmp)]
+    fn from_args(vm: &VirtualMachine, args: &mut PyFuncArgs) -> Result<Self, ArgumentError> {
+        let blocking = match take_arg(args, "blocking") {
+            Some(blocking) => bool::try_from_object(vm, blocking)?,
+            None => true,
+        };
+        let timeout = take_arg(args, "timeout");
+        let has_timeout = match timeout {
+            Some(ref timeout) => {
+                !timeout.is(&vm.get_none()) && { objfloat::make_float(vm, timeout)? != -1.0 }
+            }
+            None => false,
+        };
+        if !blocking && has_timeout {
+            return Err(vm
+                .new_value_error("can't specify a timeout for a non-blocking call".to_string())
+                .into());
+        }
+        let timeout = get_timeout(vm, timeout)?;
+        Ok(AcquireArgs { blocking, timeout })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PyLock {
+    locked: Mutex<bool>,
+    unlocked: Condvar,
+}
+
+pub type PyLockRef = PyRef<PyLock>;
+
+impl PyValue for PyLock {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("_thread", "lock")
+    }
+}
+
+impl PyLock {
+    /// Raises `KeyboardInterrupt` if interrupted while waiting.
+    pub fn lock(
+        &self,
+        vm: &VirtualMachine,
+        blocking: bool,
+        timeout: Option<Duration>,
+    ) -> PyResult<bool> {
+        if self.try_lock() {
+            return Ok(true);
+        }
+        if !blocking {
+            return Ok(false);
+        }
+        let acquired = vm.allow_threads(|| {
+            match wait_while(&self.locked, &self.unlocked, timeout, true, |locked| {
+                *locked
+            }) {
+                Some(mut locked) => {
+                    *locked = true;
+                    true
+                }
+                None => false,
+            }
+        });
+        if !acquired {
+            signal::check_signals(vm)?;
+        }
+        Ok(acquired)
+    }
+
+    fn try_lock(&self) -> bool {
+        let mut locked = self.locked.lock().unwrap();
+        if *locked {
+            false
+        } else {
+            *locked = true;
+            true
+        }
+    }
+
+    pub fn unlock(&self, vm: &VirtualMachine) -> PyResult<()> {
+        let mut locked = self.locked.lock().unwrap();
+        if !*locked {
+            return Err(vm.new_runtime_error("release unlocked lock".to_string()));
+        }
+        *locked = false;
+        self.unlocked.notify_one();
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        *self.locked.lock().unwrap()
+    }
+}
+
+impl PyLockRef {
+    fn new(cls: PyClassRef, vm: &VirtualMachine) -> PyResult<PyLockRef> {
+        PyLock::default().into_ref_with_type(vm, cls)
+    }
+
+    fn acquire(self, args: AcquireArgs, vm: &VirtualMachine) -> PyResult<bool> {
+        self.lock(vm, args.blocking, args.timeout)
+    }
+
+    fn release(self, vm: &VirtualMachine) -> PyResult<()> {
+        self.unlock(vm)
+    }
+
+    fn locked(self, _vm: &VirtualMachine) -> bool {
+        self.is_locked()
+    }
+
+    fn enter(self, vm: &VirtualMachine) -> PyResult<bool> {
+        self.lock(vm, true, None)
+    }
+
+    fn exit(self, _args: Args, vm: &VirtualMachine) -> PyResult<()> {
+        self.unlock(vm)
+    }
+
+    fn repr(self, _vm: &VirtualMachine) -> String {
+        let state = if self.is_locked() {
+            "locked"
+        } else {
+            "unlocked"
+        };
+        format!(
+            "<{} _thread.lock object at 0x{:x}>",
+            state,
+            self.as_object().get_id()
+        )
+    }
+}
+
+#[derive(Debug)]
+struct RLockState {
+    /// Identifier of the thread holding the lock, 0 if it is free.
+    owner: u64,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct PyRLock {
+    state: Mutex<RLockState>,
+    unlocked: Condvar,
+}
+
+pub type PyRLockRef = PyRef<PyRLock>;
+
+impl PyValue for PyRLock {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("_thread", "RLock")
+    }
+}
+
+impl Default for PyRLock {
+    fn default() -> Self {
+        PyRLock {
+            state: Mutex::new(RLockState { owner: 0, count: 0 }),
+            unlocked: Condvar::new(),
+        }
+    }
+}
+
+impl PyRLock {
+    /// Take the lock `count` times at once.
+    fn lock_count(
+        &self,
+        vm: &VirtualMachine,
+        blocking: bool,
+        timeout: Option<Duration>,
+        count: usize,
+        interruptible: bool,
+    ) -> bool {
+        let me = get_ident();
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.owner == me {
+                state.count += count;
+                return true;
+            }
+            if state.owner == 0 {
+                state.owner = me;
+                state.count = count;
+                return true;
+            }
+        }
+        if !blocking {
+            return false;
+        }
+        vm.allow_threads(|| {
+            match wait_while(
+                &self.state,
+                &self.unlocked,
+                timeout,
+                interruptible,
+                |state| state.owner != 0,
+            ) {
+                Some(mut state) => {
+                    state.owner = me;
+                    state.count = count;
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+
+    /// Raises `KeyboardInterrupt` if interrupted while waiting.
+    pub fn lock(
+        &self,
+        vm: &VirtualMachine,
+        blocking: bool,
+        timeout: Option<Duration>,
+    ) -> PyResult<bool> {
+        let acquired = self.lock_count(vm, blocking, timeout, 1, true);
+        if !acquired && blocking {
+            signal::check_signals(vm)?;
+        }
+        Ok(acquired)
+    }
+
+    pub fn unlock(&self, vm: &VirtualMachine) -> PyResult<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.owner != get_ident() {
+            return Err(vm.new_runtime_error("cannot release un-acquired lock".to_string()));
+        }
+        state.count -= 1;
+        if state.count == 0 {
+            state.owner = 0;
+            self.unlocked.notify_one();
+        }
+        Ok(())
+    }
+
+    pub fn is_held_by_current_thread(&self) -> bool {
+        self.state.lock().unwrap().owner == get_ident()
+    }
+
+    /// Fully release the lock, returning how many times it was held.
+    fn unlock_all(&self, vm: &VirtualMachine) -> PyResult<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.owner != get_ident() {
+            return Err(vm.new_runtime_error("cannot release un-acquired lock".to_string()));
+        }
+        let count = state.count;
+        state.owner = 0;
+        state.count = 0;
+        self.unlocked.notify_one();
+        Ok(count)
+    }
+}
+
+impl PyRLockRef {
+    fn new(cls: PyClassRef, vm: &VirtualMachine) -> PyResult<PyRLockRef> {
+        PyRLock::default().into_ref_with_type(vm, cls)
+    }
+
+    fn acquire(self, args: AcquireArgs, vm: &VirtualMachine) -> PyResult<bool> {
+        self.lock(vm, args.blocking, args.timeout)
+    }
+
+    fn release(self, vm: &VirtualMachine) -> PyResult<()> {
+        self.unlock(vm)
+    }
+
+    fn is_owned(self, _vm: &VirtualMachine) -> bool {
+        self.is_held_by_current_thread()
+    }
+
+    fn release_save(self, vm: &VirtualMachine) -> PyResult<usize> {
+        self.unlock_all(vm)
+    }
+
+    fn acquire_restore(self, count: usize, vm: &VirtualMachine) {
+        // Like CPython, `Condition.wait` gets the lock back even if
+        // interrupted, the interrupt is raised after.
+        self.lock_count(vm, true, None, count, false);
+    }
+
+    fn enter(self, vm: &VirtualMachine) -> PyResult<bool> {
+        self.lock(vm, true, None)
+    }
+
+    fn exit(self, _args: Args, vm: &VirtualMachine) -> PyResult<()> {
+        self.unlock(vm)
+    }
+
+    fn repr(self, _vm: &VirtualMachine) -> String {
+        let (owner, count) = {
+            let state = self.state.lock().unwrap();
+            (state.owner, state.count)
+        };
+        let state = if owner == 0 { "unlocked" } else { "locked" };
+        format!(
+            "<{} _thread.RLock object owner={} count={} at 0x{:x}>",
+            state,
+            owner,
+            count,
+            self.as_object().get_id()
+        )
+    }
+}
+
+fn thread_allocate_lock(vm: &VirtualMachine) -> PyLockRef {
+    PyLock::default().into_ref(vm)
+}
+
+fn thread_get_ident(_vm: &VirtualMachine) -> u64 {
+    get_ident()
+}
+
+fn thread_start_new_thread(
+    func: PyObjectRef,
+    args: PyTupleRef,
+    kwargs: OptionalArg<PyDictRef>,
+    vm: &VirtualMachine,
+) -> PyResult<u64> {
+    let mut call_args = PyFuncArgs::from(args.elements.borrow().clone());
+    if let OptionalArg::Present(kwargs) = kwargs {
+        for (key, value) in objdict::get_key_value_pairs(kwargs.as_object()) {
+            let key = vm.to_pystr(&key)?;
+            call_args.kwargs.push((key, value));
+        }
+    }
+    start_thread(vm, (func, call_args), run_new_thread)
+}
+
+fn run_new_thread(vm: &VirtualMachine, (func, args): (PyObjectRef, PyFuncArgs)) {
+    if let Err(exc) = vm.invoke(func.clone(), args) {
+        // SystemExit just ends the thread quietly.
+        if objtype::isinstance(&exc, &vm.ctx.exceptions.system_exit) {
+            return;
+        }
+        let header = match vm.to_repr(&func) {
+            Ok(repr) => format!("Unhandled exception in thread started by {}", repr.value),
+            Err(_) => "Unhandled exception in thread".to_string(),
+        };
+        print_thread_exception(vm, &header, &exc);
+    }
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    let lock = py_class!(ctx, "lock", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyLockRef::new),
+        "acquire" => ctx.new_rustfunc(PyLockRef::acquire),
+        "release" => ctx.new_rustfunc(PyLockRef::release),
+        "locked" => ctx.new_rustfunc(PyLockRef::locked),
+        "__enter__" => ctx.new_rustfunc(PyLockRef::enter),
+        "__exit__" => ctx.new_rustfunc(PyLockRef::exit),
+        "__repr__" => ctx.new_rustfunc(PyLockRef::repr),
+    });
+
+    let rlock = py_class!(ctx, "RLock", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyRLockRef::new),
+        "acquire" => ctx.new_rustfunc(PyRLockRef::acquire),
+        "release" => ctx.new_rustfunc(PyRLockRef::release),
+        "_is_owned" => ctx.new_rustfunc(PyRLockRef::is_owned),
+        "_release_save" => ctx.new_rustfunc(PyRLockRef::release_save),
+        "_acquire_restore" => ctx.new_rustfunc(PyRLockRef::acquire_restore),
+        "__enter__" => ctx.new_rustfunc(PyRLockRef::enter),
+        "__exit__" => ctx.new_rustfunc(PyRLockRef::exit),
+        "__repr__" => ctx.new_rustfunc(PyRLockRef::repr),
+    });
+
+    py_module!(ctx, "_thread", {
+        "allocate_lock" => ctx.new_rustfunc(thread_allocate_lock),
+        "get_ident" => ctx.new_rustfunc(thread_get_ident),
+        "start_new_thread" => ctx.new_rustfunc(thread_start_new_thread),
+        "error" => ctx.exceptions.runtime_error.clone().into_object(),
+        "LockType" => lock.clone().into_object(),
+        "lock" => lock.into_object(),
+        "RLock" => rlock.into_object(),
+        "TIMEOUT_MAX" => ctx.new_float(TIMEOUT_MAX),
+    })
+}
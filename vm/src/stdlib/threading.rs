@@ -0,0 +1,642 @@
+//! The python `threading` module, built on top of `_thread`.
+
+// The mutexes here guard state that is waited for with a `Condvar`.
+#![allow(clippy::mutex_atomic)]
+
+use std::cell::{Cell, RefCell};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use super::thread::{self, PyLock, PyLockRef, PyRLock, PyRLockRef};
+use crate::function::{Args, ArgumentError, FromArgs, KwArgs, OptionalArg, PyFuncArgs};
+use crate::globals::globals;
+use crate::obj::objbool;
+use crate::obj::objdict::{self, PyDictRef};
+use crate::obj::objlist::PyListRef;
+use crate::obj::objproperty::PropertyBuilder;
+use crate::obj::objstr::PyStringRef;
+use crate::obj::objtuple::PyTupleRef;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::pyobject::{
+    DictProtocol, IdProtocol, PyContext, PyObject, PyObjectRef, PyRef, PyResult, PyValue,
+    TryFromObject, TypeProtocol,
+};
+use crate::signal;
+use crate::vm::VirtualMachine;
+
+pub(crate) static THREAD_COUNTER: AtomicUsize = AtomicUsize::new(1);
+
+fn new_thread_name(prefix: &str) -> String {
+    format!(
+        "{}-{}",
+        prefix,
+        globals().thread_counter.fetch_add(1, Ordering::SeqCst)
+    )
+}
+
+/// A `timeout=None` parameter, which may also be given by position.
+struct TimeoutArgs {
+    timeout: Option<Duration>,
+}
+
+impl FromArgs for TimeoutArgs {
+    fn arity() -> RangeInclusive<usize> {
+        0..=1
+    }
+
+    fn from_args(vm: &VirtualMachine, args: &mut PyFuncArgs) -> Result<Self, ArgumentError> {
+        let timeout = thread::take_arg(args, "timeout");
+        Ok(TimeoutArgs {
+            timeout: thread::get_timeout(vm, timeout)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ThreadState {
+    Initial,
+    Started,
+    Stopped,
+}
+
+#[derive(Debug)]
+pub struct PyThread {
+    target: RefCell<Option<PyObjectRef>>,
+    args: RefCell<PyFuncArgs>,
+    name: RefCell<String>,
+    daemon: Cell<bool>,
+    ident: Cell<Option<u64>>,
+    state: Mutex<ThreadState>,
+    stopped: Condvar,
+}
+
+pub type PyThreadRef = PyRef<PyThread>;
+
+impl PyValue for PyThread {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("threading", "Thread")
+    }
+}
+
+impl PyThread {
+    fn new(name: String, daemon: bool) -> PyThread {
+        PyThread {
+            target: RefCell::new(None),
+            args: RefCell::new(PyFuncArgs::default()),
+            name: RefCell::new(name),
+            daemon: Cell::new(daemon),
+            ident: Cell::new(None),
+            state: Mutex::new(ThreadState::Initial),
+            stopped: Condvar::new(),
+        }
+    }
+
+    /// A thread object for a thread that was not started by this module.
+    fn new_running(name: String, daemon: bool) -> PyThread {
+        let thread = PyThread::new(name, daemon);
+        thread.ident.set(Some(thread::get_ident()));
+        *thread.state.lock().unwrap() = ThreadState::Started;
+        thread
+    }
+
+    fn get_state(&self) -> ThreadState {
+        *self.state.lock().unwrap()
+    }
+
+    fn set_state(&self, state: ThreadState) {
+        *self.state.lock().unwrap() = state;
+        self.stopped.notify_all();
+    }
+
+    fn is_alive(&self) -> bool {
+        self.get_state() == ThreadState::Started
+    }
+
+    /// Wait for the thread to stop, returns false on timeout.
+    fn wait_stopped(&self, vm: &VirtualMachine, timeout: Option<Duration>) -> PyResult<bool> {
+        let stopped = vm.allow_threads(|| {
+            thread::wait_while(&self.state, &self.stopped, timeout, true, |state| {
+                *state != ThreadState::Stopped
+            })
+            .is_some()
+        });
+        if !stopped {
+            signal::check_signals(vm)?;
+        }
+        Ok(stopped)
+    }
+}
+
+#[derive(FromArgs)]
+#[pyarg(name = "__init__")]
+struct ThreadInitArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    group: Option<PyObjectRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    target: Option<PyObjectRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    name: Option<PyStringRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    args: Option<PyTupleRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    kwargs: Option<PyDictRef>,
+    #[pyarg(keyword_only, default = "None")]
+    daemon: Option<bool>,
+}
+
+impl PyThreadRef {
+    fn new(cls: PyClassRef, _args: Args, _kwargs: KwArgs, vm: &VirtualMachine) -> PyResult<Self> {
+        PyThread::new(new_thread_name("Thread"), false).into_ref_with_type(vm, cls)
+    }
+
+    fn init(self, args: ThreadInitArgs, vm: &VirtualMachine) -> PyResult<()> {
+        if args.group.is_some() {
+            return Err(vm.new_value_error("group argument must be None for now".to_string()));
+        }
+        let mut call_args = PyFuncArgs::default();
+        if let Some(args) = args.args {
+            call_args.args = args.elements.borrow().clone();
+        }
+        if let Some(kwargs) = args.kwargs {
+            for (key, value) in objdict::get_key_value_pairs(kwargs.as_object()) {
+                call_args.kwargs.push((vm.to_pystr(&key)?, value));
+            }
+        }
+        *self.target.borrow_mut() = args.target;
+        *self.args.borrow_mut() = call_args;
+        if let Some(name) = args.name {
+            *self.name.borrow_mut() = name.value.clone();
+        }
+        let daemon = match args.daemon {
+            Some(daemon) => daemon,
+            None => current_thread(vm)?.daemon.get(),
+        };
+        self.daemon.set(daemon);
+        Ok(())
+    }
+
+    fn start(self, vm: &VirtualMachine) -> PyResult<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if *state != ThreadState::Initial {
+                return Err(vm.new_runtime_error("threads can only be started once".to_string()));
+            }
+            *state = ThreadState::Started;
+        }
+        let active = active_threads(vm)?;
+        active.append(self.as_object().clone(), vm);
+
+        let started = thread::start_thread(vm, self.clone(), run_thread);
+        match started {
+            Ok(ident) => {
+                self.ident.set(Some(ident));
+                Ok(())
+            }
+            Err(err) => {
+                forget_thread(vm, &self);
+                self.set_state(ThreadState::Stopped);
+                Err(err)
+            }
+        }
+    }
+
+    fn run(self, vm: &VirtualMachine) -> PyResult<()> {
+        let target = self.target.borrow_mut().take();
+        let args = std::mem::replace(&mut *self.args.borrow_mut(), PyFuncArgs::default());
+        if let Some(target) = target {
+            vm.invoke(target, args)?;
+        }
+        Ok(())
+    }
+
+    fn join(self, args: TimeoutArgs, vm: &VirtualMachine) -> PyResult<()> {
+        match self.get_state() {
+            ThreadState::Initial => {
+                Err(vm.new_runtime_error("cannot join thread before it is started".to_string()))
+            }
+            _ if self.ident.get() == Some(thread::get_ident()) => {
+                Err(vm.new_runtime_error("cannot join current thread".to_string()))
+            }
+            _ => {
+                self.wait_stopped(vm, args.timeout)?;
+                Ok(())
+            }
+        }
+    }
+
+    fn is_alive_(self, _vm: &VirtualMachine) -> bool {
+        self.is_alive()
+    }
+
+    fn name(self, _vm: &VirtualMachine) -> String {
+        self.name.borrow().clone()
+    }
+
+    fn set_name(self, name: PyStringRef, vm: &VirtualMachine) -> PyResult {
+        *self.name.borrow_mut() = name.value.clone();
+        Ok(vm.get_none())
+    }
+
+    fn ident(self, _vm: &VirtualMachine) -> Option<u64> {
+        self.ident.get()
+    }
+
+    fn daemon(self, _vm: &VirtualMachine) -> bool {
+        self.daemon.get()
+    }
+
+    fn set_daemon(self, daemon: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if self.get_state() != ThreadState::Initial {
+            return Err(
+                vm.new_runtime_error("cannot set daemon status of active thread".to_string())
+            );
+        }
+        self.daemon.set(objbool::boolval(vm, daemon)?);
+        Ok(vm.get_none())
+    }
+
+    fn repr(self, _vm: &VirtualMachine) -> String {
+        let mut status = match self.get_state() {
+            ThreadState::Initial => "initial".to_string(),
+            ThreadState::Started => "started".to_string(),
+            ThreadState::Stopped => "stopped".to_string(),
+        };
+        if self.daemon.get() {
+            status.push_str(" daemon");
+        }
+        if let Some(ident) = self.ident.get() {
+            status.push_str(&format!(" {}", ident));
+        }
+        format!(
+            "<{}({}, {})>",
+            self.as_object().class().name,
+            self.name.borrow(),
+            status
+        )
+    }
+}
+
+/// The threads that are currently running, as kept in `threading._active`.
+fn active_threads(vm: &VirtualMachine) -> PyResult<PyListRef> {
+    let module = vm.import("threading")?;
+    PyListRef::try_from_object(vm, vm.get_attribute(module, "_active")?)
+}
+
+/// What a thread started by `Thread.start` runs.
+fn run_thread(vm: &VirtualMachine, zelf: PyThreadRef) {
+    zelf.ident.set(Some(thread::get_ident()));
+    if let Err(exc) = vm.call_method(zelf.as_object(), "run", vec![]) {
+        if !objtype::isinstance(&exc, &vm.ctx.exceptions.system_exit) {
+            let header = format!("Exception in thread {}:", zelf.name.borrow());
+            thread::print_thread_exception(vm, &header, &exc);
+        }
+    }
+    forget_thread(vm, &zelf);
+    zelf.set_state(ThreadState::Stopped);
+}
+
+fn forget_thread(vm: &VirtualMachine, thread: &PyThreadRef) {
+    if let Ok(active) = active_threads(vm) {
+        active
+            .elements
+            .borrow_mut()
+            .retain(|other| !other.is(thread.as_object()));
+    }
+}
+
+fn current_thread(vm: &VirtualMachine) -> PyResult<PyThreadRef> {
+    let ident = thread::get_ident();
+    let active = active_threads(vm)?;
+    let found = active.elements.borrow().iter().find_map(|thread| {
+        thread
+            .clone()
+            .downcast::<PyThread>()
+            .ok()
+            .filter(|thread| thread.ident.get() == Some(ident))
+    });
+    match found {
+        Some(thread) => Ok(thread),
+        None => {
+            // A thread started through `_thread`, which gets a stand-in.
+            let thread = PyThread::new_running(new_thread_name("Dummy"), true).into_ref(vm);
+            active.append(thread.as_object().clone(), vm);
+            Ok(thread)
+        }
+    }
+}
+
+fn threading_current_thread(vm: &VirtualMachine) -> PyResult<PyThreadRef> {
+    current_thread(vm)
+}
+
+fn threading_main_thread(vm: &VirtualMachine) -> PyResult {
+    let module = vm.import("threading")?;
+    vm.get_attribute(module, "_main_thread")
+}
+
+fn threading_active_count(vm: &VirtualMachine) -> PyResult<usize> {
+    Ok(active_threads(vm)?.elements.borrow().len())
+}
+
+fn threading_enumerate(vm: &VirtualMachine) -> PyResult {
+    let threads = active_threads(vm)?.elements.borrow().clone();
+    Ok(vm.ctx.new_list(threads))
+}
+
+fn threading_get_ident(_vm: &VirtualMachine) -> u64 {
+    thread::get_ident()
+}
+
+fn threading_lock(vm: &VirtualMachine) -> PyLockRef {
+    PyLock::default().into_ref(vm)
+}
+
+fn threading_rlock(vm: &VirtualMachine) -> PyRLockRef {
+    PyRLock::default().into_ref(vm)
+}
+
+/// Wait for all non-daemon threads to finish, which the interpreter does
+/// before it exits.
+fn threading_shutdown(vm: &VirtualMachine) -> PyResult<()> {
+    let main_ident = thread::get_ident();
+    loop {
+        let pending = active_threads(vm)?
+            .elements
+            .borrow()
+            .iter()
+            .filter_map(|thread| thread.clone().downcast::<PyThread>().ok())
+            .find(|thread| {
+                !thread.daemon.get() && thread.is_alive() && thread.ident.get() != Some(main_ident)
+            });
+        match pending {
+            Some(thread) => {
+                thread.wait_stopped(vm, None)?;
+            }
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Run `threading._shutdown()` if the module was imported.
+pub fn shutdown(vm: &VirtualMachine) -> PyResult<()> {
+    let modules = vm.get_attribute(vm.sys_module.clone(), "modules")?;
+    if PyDictRef::try_from_object(vm, modules)?.contains_key("threading") {
+        threading_shutdown(vm)
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PyCondition {
+    lock: PyObjectRef,
+    waiters: RefCell<Vec<PyLockRef>>,
+}
+
+pub type PyConditionRef = PyRef<PyCondition>;
+
+impl PyValue for PyCondition {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("threading", "Condition")
+    }
+}
+
+impl PyConditionRef {
+    fn new(
+        cls: PyClassRef,
+        lock: OptionalArg<Option<PyObjectRef>>,
+        vm: &VirtualMachine,
+    ) -> PyResult<Self> {
+        let lock = match lock.into_option().and_then(|lock| lock) {
+            Some(lock) => lock,
+            None => threading_rlock(vm).into_object(),
+        };
+        PyCondition {
+            lock,
+            waiters: RefCell::new(vec![]),
+        }
+        .into_ref_with_type(vm, cls)
+    }
+
+    fn acquire(self, args: Args, kwargs: KwArgs, vm: &VirtualMachine) -> PyResult {
+        vm.call_method(&self.lock, "acquire", PyFuncArgs::from((&args, &kwargs)))
+    }
+
+    fn release(self, vm: &VirtualMachine) -> PyResult {
+        vm.call_method(&self.lock, "release", vec![])
+    }
+
+    fn enter(self, vm: &VirtualMachine) -> PyResult {
+        vm.call_method(&self.lock, "__enter__", vec![])
+    }
+
+    fn exit(self, args: Args, vm: &VirtualMachine) -> PyResult {
+        vm.call_method(&self.lock, "__exit__", args.into_iter().collect::<Vec<_>>())
+    }
+
+    fn is_owned(&self, vm: &VirtualMachine) -> PyResult<bool> {
+        if let Ok(is_owned) = vm.get_attribute(self.lock.clone(), "_is_owned") {
+            return objbool::boolval(vm, vm.invoke(is_owned, vec![])?);
+        }
+        // Return True if the lock is held, by this thread or any other.
+        let acquired = vm.call_method(&self.lock, "acquire", vec![vm.new_bool(false)])?;
+        if objbool::boolval(vm, acquired)? {
+            vm.call_method(&self.lock, "release", vec![])?;
+            Ok(false)
+        } else {
+            Ok(true)
+        }
+    }
+
+    fn wait(self, args: TimeoutArgs, vm: &VirtualMachine) -> PyResult<bool> {
+        if !self.is_owned(vm)? {
+            return Err(vm.new_runtime_error("cannot wait on un-acquired lock".to_string()));
+        }
+        let waiter = threading_lock(vm);
+        waiter.lock(vm, true, None)?;
+        self.waiters.borrow_mut().push(waiter.clone());
+
+        let saved = match vm.get_attribute(self.lock.clone(), "_release_save") {
+            Ok(release_save) => Some(vm.invoke(release_save, vec![])?),
+            Err(_) => {
+                vm.call_method(&self.lock, "release", vec![])?;
+                None
+            }
+        };
+        // An interrupt is raised once the lock is taken back.
+        let notified = waiter.lock(vm, true, args.timeout);
+        match saved {
+            Some(saved) => vm.call_method(&self.lock, "_acquire_restore", vec![saved])?,
+            None => vm.call_method(&self.lock, "acquire", vec![])?,
+        };
+        if notified.as_ref().map_or(true, |notified| !notified) {
+            self.waiters
+                .borrow_mut()
+                .retain(|other| !other.as_object().is(waiter.as_object()));
+        }
+        notified
+    }
+
+    fn wait_for(
+        self,
+        predicate: PyObjectRef,
+        timeout: OptionalArg<Option<PyObjectRef>>,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        let timeout = thread::get_timeout(vm, timeout.into_option().and_then(|t| t))?;
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut result = vm.invoke(predicate.clone(), vec![])?;
+        while !objbool::boolval(vm, result.clone())? {
+            let timeout = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    Some(deadline - now)
+                }
+                None => None,
+            };
+            self.clone().wait(TimeoutArgs { timeout }, vm)?;
+            result = vm.invoke(predicate.clone(), vec![])?;
+        }
+        Ok(result)
+    }
+
+    fn notify(self, n: OptionalArg<usize>, vm: &VirtualMachine) -> PyResult<()> {
+        self.notify_waiters(n.into_option().unwrap_or(1), vm)
+    }
+
+    fn notify_all(self, vm: &VirtualMachine) -> PyResult<()> {
+        let n = self.waiters.borrow().len();
+        self.notify_waiters(n, vm)
+    }
+
+    fn notify_waiters(&self, n: usize, vm: &VirtualMachine) -> PyResult<()> {
+        if !self.is_owned(vm)? {
+            return Err(vm.new_runtime_error("cannot notify on un-acquired lock".to_string()));
+        }
+        let woken: Vec<PyLockRef> = {
+            let mut waiters = self.waiters.borrow_mut();
+            let n = n.min(waiters.len());
+            waiters.drain(..n).collect()
+        };
+        for waiter in woken {
+            waiter.unlock(vm)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PyEvent {
+    flag: Mutex<bool>,
+    set: Condvar,
+}
+
+pub type PyEventRef = PyRef<PyEvent>;
+
+impl PyValue for PyEvent {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("threading", "Event")
+    }
+}
+
+impl PyEventRef {
+    fn new(cls: PyClassRef, vm: &VirtualMachine) -> PyResult<Self> {
+        PyEvent::default().into_ref_with_type(vm, cls)
+    }
+
+    fn is_set(self, _vm: &VirtualMachine) -> bool {
+        *self.flag.lock().unwrap()
+    }
+
+    fn set(self, _vm: &VirtualMachine) {
+        *self.flag.lock().unwrap() = true;
+        self.set.notify_all();
+    }
+
+    fn clear(self, _vm: &VirtualMachine) {
+        *self.flag.lock().unwrap() = false;
+    }
+
+    fn wait(self, args: TimeoutArgs, vm: &VirtualMachine) -> PyResult<bool> {
+        if *self.flag.lock().unwrap() {
+            return Ok(true);
+        }
+        let set = vm.allow_threads(|| {
+            thread::wait_while(&self.flag, &self.set, args.timeout, true, |flag| !*flag).is_some()
+        });
+        if !set {
+            signal::check_signals(vm)?;
+        }
+        Ok(set)
+    }
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    let thread = py_class!(ctx, "Thread", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyThreadRef::new),
+        "__init__" => ctx.new_rustfunc(PyThreadRef::init),
+        "__repr__" => ctx.new_rustfunc(PyThreadRef::repr),
+        "start" => ctx.new_rustfunc(PyThreadRef::start),
+        "run" => ctx.new_rustfunc(PyThreadRef::run),
+        "join" => ctx.new_rustfunc(PyThreadRef::join),
+        "is_alive" => ctx.new_rustfunc(PyThreadRef::is_alive_),
+        "name" => PropertyBuilder::new(ctx)
+            .add_getter(PyThreadRef::name)
+            .add_setter(PyThreadRef::set_name)
+            .create(),
+        "ident" => ctx.new_property(PyThreadRef::ident),
+        "daemon" => PropertyBuilder::new(ctx)
+            .add_getter(PyThreadRef::daemon)
+            .add_setter(PyThreadRef::set_daemon)
+            .create(),
+    });
+
+    let condition = py_class!(ctx, "Condition", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyConditionRef::new),
+        "__enter__" => ctx.new_rustfunc(PyConditionRef::enter),
+        "__exit__" => ctx.new_rustfunc(PyConditionRef::exit),
+        "acquire" => ctx.new_rustfunc(PyConditionRef::acquire),
+        "release" => ctx.new_rustfunc(PyConditionRef::release),
+        "wait" => ctx.new_rustfunc(PyConditionRef::wait),
+        "wait_for" => ctx.new_rustfunc(PyConditionRef::wait_for),
+        "notify" => ctx.new_rustfunc(PyConditionRef::notify),
+        "notify_all" => ctx.new_rustfunc(PyConditionRef::notify_all),
+    });
+
+    let event = py_class!(ctx, "Event", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyEventRef::new),
+        "is_set" => ctx.new_rustfunc(PyEventRef::is_set),
+        "set" => ctx.new_rustfunc(PyEventRef::set),
+        "clear" => ctx.new_rustfunc(PyEventRef::clear),
+        "wait" => ctx.new_rustfunc(PyEventRef::wait),
+    });
+
+    // The thread importing this module is the one the interpreter started in.
+    let main_thread = PyObject::new(
+        PyThread::new_running("MainThread".to_string(), false),
+        thread.clone(),
+        None,
+    );
+
+    py_module!(ctx, "threading", {
+        "Thread" => thread.into_object(),
+        "Condition" => condition.into_object(),
+        "Event" => event.into_object(),
+        "Lock" => ctx.new_rustfunc(threading_lock),
+        "RLock" => ctx.new_rustfunc(threading_rlock),
+        "current_thread" => ctx.new_rustfunc(threading_current_thread),
+        "main_thread" => ctx.new_rustfunc(threading_main_thread),
+        "active_count" => ctx.new_rustfunc(threading_active_count),
+        "enumerate" => ctx.new_rustfunc(threading_enumerate),
+        "get_ident" => ctx.new_rustfunc(threading_get_ident),
+        "TIMEOUT_MAX" => ctx.new_float(thread::TIMEOUT_MAX),
+        "_shutdown" => ctx.new_rustfunc(threading_shutdown),
+        "_main_thread" => main_thread.clone(),
+        "_active" => ctx.new_list(vec![main_thread]),
+    })
+}
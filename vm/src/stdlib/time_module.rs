@@ -1,11 +1,11 @@
 //! The python `time` module.
 
-use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::function::PyFuncArgs;
 use crate::obj::objfloat;
 use crate::pyobject::{PyContext, PyObjectRef, PyResult, TypeProtocol};
+use crate::signal;
 use crate::vm::VirtualMachine;
 
 fn time_sleep(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
@@ -13,9 +13,17 @@ fn time_sleep(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let seconds = objfloat::get_value(seconds);
     let secs: u64 = seconds.trunc() as u64;
     let nanos: u32 = (seconds.fract() * 1e9) as u32;
-    let duration = Duration::new(secs, nanos);
-    thread::sleep(duration);
-    Ok(vm.get_none())
+    let deadline = Instant::now() + Duration::new(secs, nanos);
+    // An interrupt cuts the sleep short to be raised here. Should it be gone
+    // by then, the sleep goes on.
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(vm.get_none());
+        }
+        vm.allow_threads(|| signal::sleep(deadline - now));
+        signal::check_signals(vm)?;
+    }
 }
 
 fn duration_to_f64(d: Duration) -> f64 {
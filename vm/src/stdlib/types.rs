@@ -2,7 +2,7 @@
  * Dynamic type creation and names for built in types.
  */
 
-use crate::function::OptionalArg;
+use crate::function::{KwArgs, OptionalArg};
 use crate::obj::objdict::PyDict;
 use crate::obj::objstr::PyStringRef;
 use crate::obj::objtype;
@@ -22,15 +22,23 @@ fn types_new_class(
         OptionalArg::Missing => PyIterable::try_from_object(vm, vm.ctx.new_tuple(vec![]))?,
     };
     let dict = PyDict::default().into_ref(vm);
-    objtype::type_new_class(vm, vm.ctx.type_type(), name, bases, dict)
+    objtype::type_new_class(vm, vm.ctx.type_type(), name, bases, dict, KwArgs::default())
+}
+
+/// Mark a generator function as a coroutine. Generators already work as
+/// coroutines here, so this returns the function unchanged.
+fn types_coroutine(func: PyObjectRef, _vm: &VirtualMachine) -> PyObjectRef {
+    func
 }
 
 pub fn make_module(ctx: &PyContext) -> PyObjectRef {
     py_module!(ctx, "types", {
         "new_class" => ctx.new_rustfunc(types_new_class),
+        "coroutine" => ctx.new_rustfunc(types_coroutine),
         "FunctionType" => ctx.function_type(),
         "LambdaType" => ctx.function_type(),
         "CodeType" => ctx.code_type(),
-        "FrameType" => ctx.frame_type()
+        "FrameType" => ctx.frame_type(),
+        "GeneratorType" => ctx.generator_type()
     })
 }
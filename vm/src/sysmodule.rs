@@ -1,19 +1,109 @@
+use std::io::Write;
 use std::rc::Rc;
 use std::{env, mem};
 
+use crate::exceptions;
 use crate::frame::FrameRef;
 use crate::function::{OptionalArg, PyFuncArgs};
-use crate::pyobject::{DictProtocol, PyContext, PyObjectRef, PyResult, TypeProtocol};
+use crate::interpreter::Settings;
+use crate::obj::objstdio::{PyStdStream, StdStream};
+use crate::obj::objstr::{PyString, PyStringRef};
+use crate::obj::objstructseq::{new_struct_sequence, new_struct_sequence_type};
+use crate::pyobject::{
+    DictProtocol, IdProtocol, PyContext, PyObject, PyObjectRef, PyResult, TypeProtocol,
+};
 use crate::vm::VirtualMachine;
 
 /*
  * The magic sys module.
  */
 
-fn argv(ctx: &PyContext) -> PyObjectRef {
-    let mut argv: Vec<PyObjectRef> = env::args().map(|x| ctx.new_str(x)).collect();
-    argv.remove(0);
-    ctx.new_list(argv)
+/// The version of the python language this interpreter implements.
+pub const MAJOR: usize = 3;
+pub const MINOR: usize = 6;
+pub const MICRO: usize = 0;
+pub const RELEASELEVEL: &str = "alpha";
+pub const SERIAL: usize = 0;
+
+const VERSION_INFO_FIELDS: &[&str] = &["major", "minor", "micro", "releaselevel", "serial"];
+
+/// The version as `platform.python_version` gives it, like "3.6.0".
+pub fn get_version_number() -> String {
+    format!("{}.{}.{}", MAJOR, MINOR, MICRO)
+}
+
+fn get_version() -> String {
+    format!(
+        "{} (rustpython {}) [rustc {}]",
+        get_version_number(),
+        env!("CARGO_PKG_VERSION"),
+        rustc_version_runtime::version()
+    )
+}
+
+/// The version encoded in a single number, the way `sys.hexversion` is.
+fn hexversion(
+    major: usize,
+    minor: usize,
+    micro: usize,
+    releaselevel: &str,
+    serial: usize,
+) -> usize {
+    let level = match releaselevel {
+        "alpha" => 0xa,
+        "beta" => 0xb,
+        "candidate" => 0xc,
+        _ => 0xf,
+    };
+    (major << 24) | (minor << 16) | (micro << 8) | (level << 4) | serial
+}
+
+fn get_platform() -> &'static str {
+    match env::consts::OS {
+        "macos" => "darwin",
+        "windows" => "win32",
+        os => os,
+    }
+}
+
+fn get_executable() -> String {
+    env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_string))
+        .unwrap_or_else(String::new)
+}
+
+fn sys_exc_info(vm: &VirtualMachine) -> PyObjectRef {
+    let info = match vm.current_exception() {
+        Some(exc) => {
+            let traceback = vm
+                .get_attribute(exc.clone(), "__traceback__")
+                .unwrap_or_else(|_| vm.get_none());
+            vec![exc.class().into_object(), exc, traceback]
+        }
+        None => vec![vm.get_none(), vm.get_none(), vm.get_none()],
+    };
+    vm.ctx.new_tuple(info)
+}
+
+fn sys_exit(code: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult {
+    let args = code.into_option().into_iter().collect::<Vec<_>>();
+    let exc = vm.invoke(vm.ctx.exceptions.system_exit.clone().into_object(), args)?;
+    Err(exc)
+}
+
+/// Print the repr of a result in the interactive shell, and keep it as
+/// `builtins._`.
+fn sys_displayhook(obj: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+    if obj.is(&vm.get_none()) {
+        return Ok(());
+    }
+    // The repr may refer to the previous result.
+    vm.ctx.set_attr(&vm.builtins, "_", vm.get_none());
+    let repr = vm.to_repr(&obj)?;
+    write_stdout(vm, &format!("{}\n", repr.value))?;
+    vm.ctx.set_attr(&vm.builtins, "_", obj);
+    Ok(())
 }
 
 fn getframe(offset: OptionalArg<usize>, vm: &VirtualMachine) -> PyResult<FrameRef> {
@@ -26,6 +116,20 @@ fn getframe(offset: OptionalArg<usize>, vm: &VirtualMachine) -> PyResult<FrameRe
     Ok(frame.clone())
 }
 
+fn sys_getrecursionlimit(vm: &VirtualMachine) -> usize {
+    vm.recursion_limit.get()
+}
+
+fn sys_setrecursionlimit(limit: i32, vm: &VirtualMachine) -> PyResult<()> {
+    if limit < 1 {
+        return Err(
+            vm.new_value_error("recursion limit must be greater or equal than 1".to_string())
+        );
+    }
+    vm.recursion_limit.set(limit as usize);
+    Ok(())
+}
+
 fn sys_getrefcount(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(vm, args, required = [(object, None)]);
     let size = Rc::strong_count(&object);
@@ -39,8 +143,219 @@ fn sys_getsizeof(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     Ok(vm.ctx.new_int(size))
 }
 
-pub fn make_module(ctx: &PyContext, builtins: PyObjectRef) -> PyObjectRef {
-    let path_list = match env::var_os("PYTHONPATH") {
+fn sys_intern(string: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyStringRef> {
+    let cls = string.class();
+    let string = string.downcast::<PyString>().map_err(|string| {
+        vm.new_type_error(format!(
+            "intern() argument must be str, not {}",
+            string.class().name
+        ))
+    })?;
+    if !cls.is(&vm.ctx.str_type) {
+        return Err(vm.new_type_error(format!("can't intern {}", cls.name)));
+    }
+    Ok(vm.ctx.intern_string(string))
+}
+
+/// The file object `sys.<name>` currently is, or `None` if it was set to
+/// `None`, which discards what is written to it.
+pub fn get_stream(vm: &VirtualMachine, name: &str) -> PyResult<Option<PyObjectRef>> {
+    let stream = vm
+        .get_attribute(vm.sys_module.clone(), name)
+        .map_err(|_| vm.new_runtime_error(format!("lost sys.{}", name)))?;
+    if stream.is(&vm.get_none()) {
+        Ok(None)
+    } else {
+        Ok(Some(stream))
+    }
+}
+
+/// Write `text` to `sys.stdout`.
+pub fn write_stdout(vm: &VirtualMachine, text: &str) -> PyResult<()> {
+    if let Some(stdout) = get_stream(vm, "stdout")? {
+        vm.call_method(&stdout, "write", vec![vm.new_str(text.to_string())])?;
+    }
+    Ok(())
+}
+
+/// Write a report to `sys.stderr`. If that fails, the report goes to the
+/// stderr of the VM, as there is nowhere to raise the error to.
+pub fn write_stderr(vm: &VirtualMachine, text: &str) {
+    let result = get_stream(vm, "stderr").and_then(|stderr| match stderr {
+        Some(stderr) => vm
+            .call_method(&stderr, "write", vec![vm.new_str(text.to_string())])
+            .map(|_| ()),
+        None => Ok(()),
+    });
+    if result.is_err() {
+        let _ = vm.stderr().write_all(text.as_bytes());
+    }
+}
+
+fn sys_excepthook(
+    _exc_type: PyObjectRef,
+    exc: PyObjectRef,
+    _traceback: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<()> {
+    let mut report = vec![];
+    let _ = exceptions::write_exception(&mut report, vm, &exc);
+    if let Some(stderr) = get_stream(vm, "stderr")? {
+        let report = String::from_utf8_lossy(&report).into_owned();
+        vm.call_method(&stderr, "write", vec![vm.new_str(report)])?;
+    }
+    Ok(())
+}
+
+fn make_version_info(ctx: &PyContext) -> PyObjectRef {
+    let version_info_type = new_struct_sequence_type(ctx, "sys.version_info", VERSION_INFO_FIELDS);
+    new_struct_sequence(
+        &version_info_type,
+        vec![
+            ctx.new_int(MAJOR),
+            ctx.new_int(MINOR),
+            ctx.new_int(MICRO),
+            ctx.new_str(RELEASELEVEL.to_string()),
+            ctx.new_int(SERIAL),
+        ],
+    )
+}
+
+/// `sys.implementation`, which describes RustPython itself.
+fn make_implementation(ctx: &PyContext) -> PyObjectRef {
+    let mut parts = env!("CARGO_PKG_VERSION")
+        .split('.')
+        .map(|part| part.parse::<usize>().unwrap_or(0));
+    let mut part = || parts.next().unwrap_or(0);
+    let (major, minor, micro) = (part(), part(), part());
+    let version_type = new_struct_sequence_type(ctx, "sys.version_info", VERSION_INFO_FIELDS);
+    let version = new_struct_sequence(
+        &version_type,
+        vec![
+            ctx.new_int(major),
+            ctx.new_int(minor),
+            ctx.new_int(micro),
+            ctx.new_str("alpha".to_string()),
+            ctx.new_int(0),
+        ],
+    );
+
+    let namespace_type = ctx.new_class("SimpleNamespace", ctx.object());
+    let implementation = ctx.new_instance(namespace_type, Some(ctx.new_dict()));
+    ctx.set_attr(
+        &implementation,
+        "name",
+        ctx.new_str("rustpython".to_string()),
+    );
+    // Nothing is cached, compiled code isn't written to disk.
+    ctx.set_attr(&implementation, "cache_tag", ctx.none());
+    ctx.set_attr(&implementation, "version", version);
+    ctx.set_attr(
+        &implementation,
+        "hexversion",
+        ctx.new_int(hexversion(major, minor, micro, "alpha", 0)),
+    );
+    implementation
+}
+
+fn make_flags(ctx: &PyContext, settings: &Settings) -> PyObjectRef {
+    let flags_type = new_struct_sequence_type(
+        ctx,
+        "sys.flags",
+        &[
+            "debug",
+            "inspect",
+            "interactive",
+            "optimize",
+            "dont_write_bytecode",
+            "no_user_site",
+            "no_site",
+            "ignore_environment",
+            "verbose",
+            "bytes_warning",
+            "quiet",
+            "hash_randomization",
+            "isolated",
+            "dev_mode",
+            "utf8_mode",
+        ],
+    );
+    let flag = |value: bool| ctx.new_int(value as u8);
+    new_struct_sequence(
+        &flags_type,
+        vec![
+            flag(false),
+            flag(false),
+            flag(false),
+            ctx.new_int(settings.optimize),
+            // Compiled code is never written.
+            flag(true),
+            flag(false),
+            flag(false),
+            flag(settings.ignore_environment),
+            flag(false),
+            flag(false),
+            flag(false),
+            flag(false),
+            flag(false),
+            ctx.new_bool(false),
+            // Text is always utf-8.
+            flag(true),
+        ],
+    )
+}
+
+fn make_float_info(ctx: &PyContext) -> PyObjectRef {
+    use std::f64;
+
+    let float_info_type = new_struct_sequence_type(
+        ctx,
+        "sys.float_info",
+        &[
+            "max",
+            "max_exp",
+            "max_10_exp",
+            "min",
+            "min_exp",
+            "min_10_exp",
+            "dig",
+            "mant_dig",
+            "epsilon",
+            "radix",
+            "rounds",
+        ],
+    );
+    new_struct_sequence(
+        &float_info_type,
+        vec![
+            ctx.new_float(f64::MAX),
+            ctx.new_int(f64::MAX_EXP),
+            ctx.new_int(f64::MAX_10_EXP),
+            ctx.new_float(f64::MIN_POSITIVE),
+            ctx.new_int(f64::MIN_EXP),
+            ctx.new_int(f64::MIN_10_EXP),
+            ctx.new_int(f64::DIGITS),
+            ctx.new_int(f64::MANTISSA_DIGITS),
+            ctx.new_float(f64::EPSILON),
+            ctx.new_int(f64::RADIX),
+            // Rounding to nearest.
+            ctx.new_int(1),
+        ],
+    )
+}
+
+pub fn make_module(
+    ctx: &PyContext,
+    builtins: PyObjectRef,
+    settings: &Settings,
+    module_names: &[String],
+) -> PyObjectRef {
+    let pythonpath = if settings.ignore_environment {
+        None
+    } else {
+        env::var_os("PYTHONPATH")
+    };
+    let mut path_list: Vec<_> = match pythonpath {
         Some(paths) => env::split_paths(&paths)
             .map(|path| {
                 ctx.new_str(
@@ -52,7 +367,13 @@ pub fn make_module(ctx: &PyContext, builtins: PyObjectRef) -> PyObjectRef {
             .collect(),
         None => vec![],
     };
+    path_list.extend(settings.path.iter().map(|path| ctx.new_str(path.clone())));
     let path = ctx.new_list(path_list);
+    let argv = settings
+        .argv
+        .iter()
+        .map(|arg| ctx.new_str(arg.clone()))
+        .collect();
 
     let sys_doc = "This module provides access to some objects used or maintained by the
 interpreter and to functions that interact strongly with the interpreter.
@@ -88,10 +409,8 @@ exec_prefix -- prefix used to find the machine-specific Python library
 executable -- absolute path of the executable binary of the Python interpreter
 float_info -- a struct sequence with information about the float implementation.
 float_repr_style -- string indicating the style of repr() output for floats
-hash_info -- a struct sequence with information about the hash algorithm.
 hexversion -- version information encoded as a single integer
 implementation -- Python implementation information.
-int_info -- a struct sequence with information about the int implementation.
 maxsize -- the largest supported length of containers.
 maxunicode -- the value of the largest Unicode code point
 platform -- platform identifier
@@ -123,17 +442,65 @@ setprofile() -- set the global profiling function
 setrecursionlimit() -- set the max recursion depth for the interpreter
 settrace() -- set the global debug tracing function
 ";
+    let std_stream = |stream| PyObject::new(PyStdStream::new(stream), ctx.std_stream_type(), None);
+    let stdin = std_stream(StdStream::Stdin);
+    let stdout = std_stream(StdStream::Stdout);
+    let stderr = std_stream(StdStream::Stderr);
+    let excepthook = ctx.new_rustfunc(sys_excepthook);
+    let displayhook = ctx.new_rustfunc(sys_displayhook);
+
+    let mut module_names: Vec<_> = module_names.iter().map(String::as_str).collect();
+    module_names.extend(&["builtins", "sys"]);
+    module_names.sort();
+    let builtin_module_names = ctx.new_tuple(
+        module_names
+            .into_iter()
+            .map(|name| ctx.new_str(name.to_string()))
+            .collect(),
+    );
+    let byteorder = if cfg!(target_endian = "little") {
+        "little"
+    } else {
+        "big"
+    };
+
     let modules = ctx.new_dict();
     let sys_name = "sys";
     let sys_mod = py_module!(ctx, sys_name, {
-      "argv" => argv(ctx),
+      "argv" => ctx.new_list(argv),
+      "builtin_module_names" => builtin_module_names,
+      "byteorder" => ctx.new_str(byteorder.to_string()),
+      "displayhook" => displayhook.clone(),
+      "__displayhook__" => displayhook,
+      "excepthook" => excepthook.clone(),
+      "__excepthook__" => excepthook,
+      "exc_info" => ctx.new_rustfunc(sys_exc_info),
+      "executable" => ctx.new_str(get_executable()),
+      "exit" => ctx.new_rustfunc(sys_exit),
+      "flags" => make_flags(ctx, settings),
+      "float_info" => make_float_info(ctx),
+      "hexversion" => ctx.new_int(hexversion(MAJOR, MINOR, MICRO, RELEASELEVEL, SERIAL)),
+      "implementation" => make_implementation(ctx),
+      "platform" => ctx.new_str(get_platform().to_string()),
+      "version" => ctx.new_str(get_version()),
+      "version_info" => make_version_info(ctx),
+      "warnoptions" => ctx.new_list(vec![]),
+      "getrecursionlimit" => ctx.new_rustfunc(sys_getrecursionlimit),
       "getrefcount" => ctx.new_rustfunc(sys_getrefcount),
       "getsizeof" => ctx.new_rustfunc(sys_getsizeof),
+      "intern" => ctx.new_rustfunc(sys_intern),
+      "setrecursionlimit" => ctx.new_rustfunc(sys_setrecursionlimit),
       "maxsize" => ctx.new_int(std::usize::MAX),
       "path" => path,
       "ps1" => ctx.new_str(">>>>> ".to_string()),
       "ps2" => ctx.new_str("..... ".to_string()),
       "__doc__" => ctx.new_str(sys_doc.to_string()),
+      "stdin" => stdin.clone(),
+      "stdout" => stdout.clone(),
+      "stderr" => stderr.clone(),
+      "__stdin__" => stdin,
+      "__stdout__" => stdout,
+      "__stderr__" => stderr,
       "_getframe" => ctx.new_rustfunc(getframe),
     });
 
@@ -6,36 +6,49 @@
 
 extern crate rustpython_parser;
 
-use std::cell::{Ref, RefCell};
+use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::collections::hash_map::HashMap;
 use std::collections::hash_set::HashSet;
+use std::io::{self, BufRead, BufReader, Write};
+use std::mem;
+use std::ops::Deref;
 use std::rc::Rc;
-use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use crate::builtins;
 use crate::bytecode;
+use crate::debugger::Debugger;
+use crate::exceptions;
 use crate::frame::{ExecutionResult, Frame, FrameRef, Scope};
 use crate::function::PyFuncArgs;
+use crate::gil::Gil;
+use crate::globals::globals;
+use crate::interpreter::Settings;
 use crate::obj::objbool;
 use crate::obj::objbuiltinfunc::PyBuiltinFunction;
 use crate::obj::objcode::PyCodeRef;
 use crate::obj::objdict::PyDictRef;
 use crate::obj::objfunction::{PyFunction, PyMethod};
-use crate::obj::objgenerator::PyGeneratorRef;
+use crate::obj::objgenerator::{PyGenerator, PyGeneratorRef};
 use crate::obj::objiter;
 use crate::obj::objlist::PyList;
 use crate::obj::objsequence;
-use crate::obj::objstr::{PyString, PyStringRef};
+use crate::obj::objstdio::StdinReader;
+use crate::obj::objstr::{self, PyString, PyStringRef};
 use crate::obj::objtuple::PyTuple;
 use crate::obj::objtype;
 use crate::obj::objtype::PyClassRef;
+use crate::profiler::Profiler;
 use crate::pyobject::{
-    DictProtocol, IdProtocol, PyContext, PyObjectRef, PyResult, PyValue, TryFromObject, TryIntoRef,
-    TypeProtocol,
+    self, DictProtocol, IdProtocol, PyContext, PyObjectRef, PyResult, PyValue, TryFromObject,
+    TryIntoRef, TypeProtocol,
 };
+use crate::signal;
 use crate::stdlib;
 use crate::sysmodule;
 use num_bigint::BigInt;
+use num_traits::ToPrimitive;
 
 // use objects::objects;
 
@@ -43,35 +56,175 @@ use num_bigint::BigInt;
 
 /// Top level container of a python virtual machine. In theory you could
 /// create more instances of this struct and have them operate fully isolated.
+///
+/// Every thread running python code on the VM has a `VirtualMachine` of its
+/// own, holding the state of that thread, like its stack of frames. The
+/// state all of them share is in a `VmState` they dereference to.
 pub struct VirtualMachine {
+    state: StateRef,
+    pub frames: RefCell<Vec<FrameRef>>,
+    pub wasm_id: Option<String>,
+    /// Instructions executed since the GIL was last offered to other threads.
+    switch_ticks: Cell<u32>,
+    pub debugger: Debugger,
+    /// Collects timings of the python code that runs while it is set, see
+    /// `set_profiler`.
+    pub(crate) profiler: RefCell<Option<Rc<Profiler>>>,
+    /// Whether this thread holds a request for `EVAL_BREAKER` to trace it.
+    traced: Cell<bool>,
+}
+
+/// The state of a VM shared by all threads running python code on it. It is
+/// boxed, so that moving the `VirtualMachine` which owns it doesn't move it
+/// away from under the other threads.
+pub struct VmState {
     pub builtins: PyObjectRef,
     pub sys_module: PyObjectRef,
     pub stdlib_inits: RefCell<HashMap<String, stdlib::StdlibInitFunc>>,
     pub ctx: PyContext,
-    pub frames: RefCell<Vec<FrameRef>>,
-    pub wasm_id: Option<String>,
+    /// Held by whichever thread is running python code on this VM.
+    pub(crate) gil: Arc<Gil>,
+    pub settings: Settings,
+    /// What `sys.__stdin__` reads from.
+    pub stdin: RefCell<Box<dyn BufRead>>,
+    /// Makes the threads reading from `stdin` take turns.
+    pub(crate) stdin_reader: StdinReader,
+    /// Where `sys.__stdout__` writes to.
+    pub stdout: RefCell<Box<dyn Write>>,
+    /// Where `sys.__stderr__` writes to, and where errors are reported when
+    /// `sys.stderr` fails.
+    pub stderr: RefCell<Box<dyn Write>>,
+    /// The functions registered with `atexit.register`, with their arguments.
+    pub(crate) atexit_callbacks: RefCell<Vec<(PyObjectRef, PyFuncArgs)>>,
+    /// How deep the stack of python frames of a thread may get, see
+    /// `sys.setrecursionlimit`.
+    pub recursion_limit: Cell<usize>,
+}
+
+enum StateRef {
+    /// The VM created by `VirtualMachine::new`.
+    Owned(Box<VmState>),
+    /// The VM of a thread started by python code, which may only touch the
+    /// state while holding the GIL, when it is sure to be alive.
+    Thread(*const VmState),
+}
+
+impl Deref for VirtualMachine {
+    type Target = VmState;
+
+    fn deref(&self) -> &VmState {
+        match &self.state {
+            StateRef::Owned(state) => state,
+            StateRef::Thread(state) => unsafe { &**state },
+        }
+    }
+}
+
+/// Number of instructions a thread runs before it lets waiting threads in.
+const SWITCH_INTERVAL: u32 = 100;
+
+/// How many reasons there are for the frames of any thread to look up from
+/// their instructions: objects waiting to be finalized, a pending interrupt,
+/// threads waiting for the GIL and threads being traced. While it is zero,
+/// which is almost always the case, the evaluation loop checks nothing else.
+pub(crate) static EVAL_BREAKER: AtomicUsize = AtomicUsize::new(0);
+
+/// Make the frames check what needs their attention until the matching
+/// `release_eval_break`.
+pub(crate) fn request_eval_break() {
+    globals().eval_breaker.fetch_add(1, Ordering::SeqCst);
+}
+
+pub(crate) fn release_eval_break() {
+    globals().eval_breaker.fetch_sub(1, Ordering::SeqCst);
+}
+
+/// Whether the evaluation loop has to do more than execute the next
+/// instruction.
+pub fn eval_break_requested() -> bool {
+    globals().eval_breaker.load(Ordering::Relaxed) != 0
 }
 
+/// The recursion limit a VM starts with, the same as CPython's.
+pub const DEFAULT_RECURSION_LIMIT: usize = 1000;
+
+/// The stack size threads running python code need to reach the default
+/// recursion limit without overflowing, even in a debug build.
+pub const THREAD_STACK_SIZE: usize = 64 * 1024 * 1024;
+
 impl VirtualMachine {
     /// Create a new `VirtualMachine` structure.
     pub fn new() -> VirtualMachine {
+        VirtualMachine::new_with_settings(Settings::default())
+    }
+
+    /// Create a `VirtualMachine` configured by `settings`, see also
+    /// `InterpreterBuilder`.
+    pub fn new_with_settings(settings: Settings) -> VirtualMachine {
         let ctx = PyContext::new();
 
         // Hard-core modules:
         let builtins = builtins::make_module(&ctx);
-        let sysmod = sysmodule::make_module(&ctx, builtins.clone());
+        ctx.set_attr(&builtins, "__debug__", ctx.new_bool(settings.optimize == 0));
 
-        let stdlib_inits = RefCell::new(stdlib::get_module_inits());
-        VirtualMachine {
+        let mut stdlib_inits = stdlib::get_module_inits();
+        if let Some(ref enabled) = settings.stdlib_modules {
+            stdlib_inits.retain(|name, _| enabled.contains(name));
+        }
+        let module_names: Vec<_> = stdlib_inits.keys().cloned().collect();
+        let sysmod = sysmodule::make_module(&ctx, builtins.clone(), &settings, &module_names);
+        let stdlib_inits = RefCell::new(stdlib_inits);
+        let state = VmState {
             builtins,
             sys_module: sysmod,
             stdlib_inits,
             ctx,
+            gil: Arc::new(Gil::new_locked()),
+            settings,
+            stdin: RefCell::new(Box::new(BufReader::new(signal::Interruptible(io::stdin())))),
+            stdin_reader: StdinReader::default(),
+            stdout: RefCell::new(Box::new(io::stdout())),
+            stderr: RefCell::new(Box::new(io::stderr())),
+            atexit_callbacks: RefCell::new(vec![]),
+            recursion_limit: Cell::new(DEFAULT_RECURSION_LIMIT),
+        };
+        VirtualMachine::with_state(StateRef::Owned(Box::new(state)))
+    }
+
+    fn with_state(state: StateRef) -> VirtualMachine {
+        VirtualMachine {
+            state,
             frames: RefCell::new(vec![]),
             wasm_id: None,
+            switch_ticks: Cell::new(0),
+            debugger: Debugger::default(),
+            profiler: RefCell::new(None),
+            traced: Cell::new(false),
         }
     }
 
+    /// The VM of another thread running python code on this one, which
+    /// starts out without frames, tracing or profiling.
+    ///
+    /// # Safety
+    ///
+    /// The VM returned may only be used, and dropped, while holding the GIL,
+    /// and as long as the VM that owns the state is alive, see `crate::gil`.
+    pub(crate) unsafe fn new_thread(state: *const VmState) -> VirtualMachine {
+        VirtualMachine::with_state(StateRef::Thread(state))
+    }
+
+    /// The output `sys.__stdout__` writes to. Don't keep it borrowed while
+    /// running python code, which may print as well.
+    pub fn stdout(&self) -> RefMut<Box<dyn Write>> {
+        self.stdout.borrow_mut()
+    }
+
+    /// The output `sys.__stderr__` writes to.
+    pub fn stderr(&self) -> RefMut<Box<dyn Write>> {
+        self.stderr.borrow_mut()
+    }
+
     pub fn run_code_obj(&self, code: PyCodeRef, scope: Scope) -> PyResult {
         let frame = Frame::new(code, scope).into_ref(self);
         self.run_frame_full(frame)
@@ -85,12 +238,244 @@ impl VirtualMachine {
     }
 
     pub fn run_frame(&self, frame: FrameRef) -> PyResult<ExecutionResult> {
-        self.frames.borrow_mut().push(frame.clone());
+        self.push_frame(frame.clone())?;
         let result = frame.run(self);
+        self.pop_frame(&frame);
+        result
+    }
+
+    /// Resume a suspended generator frame by raising `exc` in it.
+    pub fn throw_into_frame(&self, frame: FrameRef, exc: PyObjectRef) -> PyResult<ExecutionResult> {
+        self.push_frame(frame.clone())?;
+        let result = frame.throw(self, exc);
+        self.pop_frame(&frame);
+        result
+    }
+
+    fn push_frame(&self, frame: FrameRef) -> PyResult<()> {
+        if self.frames.borrow().len() >= self.recursion_limit.get() {
+            let recursion_error = self.ctx.exceptions.recursion_error.clone();
+            return Err(self.new_exception(
+                recursion_error,
+                "maximum recursion depth exceeded".to_string(),
+            ));
+        }
+        if let Some(profiler) = self.profiler.borrow().as_ref() {
+            profiler.call(&frame);
+        }
+        let mut frames = self.frames.borrow_mut();
+        frame.back.replace(frames.last().cloned());
+        frames.push(frame);
+        Ok(())
+    }
+
+    fn pop_frame(&self, frame: &FrameRef) {
         self.frames.borrow_mut().pop();
+        if let Some(profiler) = self.profiler.borrow().as_ref() {
+            profiler.ret(frame);
+        }
+        // A generator is resumed from wherever it is needed next, so it has no
+        // caller while it is suspended.
+        if frame.code.is_generator {
+            frame.back.replace(None);
+        }
+        self.run_finalizers();
+    }
+
+    /// The exception being handled by the innermost `except` clause that is
+    /// running, as `sys.exc_info` gives it.
+    pub fn current_exception(&self) -> Option<PyObjectRef> {
+        self.frames
+            .borrow()
+            .iter()
+            .rev()
+            .find_map(|frame| frame.handled_exception())
+    }
+
+    /// Call `__del__` on all instances that became unreachable since the last
+    /// time this ran. Exceptions raised by a finalizer are printed to stderr
+    /// and otherwise ignored, like CPython does.
+    pub fn run_finalizers(&self) {
+        loop {
+            let pending = pyobject::take_pending_finalizers();
+            if pending.is_empty() {
+                break;
+            }
+            for obj in pending {
+                self.finalize_object(obj);
+            }
+        }
+    }
+
+    /// Shut the interpreter down, like CPython's `Py_FinalizeEx`: wait for
+    /// the threads started by `threading`, run the `atexit` callbacks, flush
+    /// the standard streams and tear the modules down so that their objects
+    /// are finalized. No python code should run on the VM afterwards.
+    pub fn finalize(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Err(exc) = stdlib::threading::shutdown(self) {
+                exceptions::print_exception(self, &exc);
+            }
+        }
+        stdlib::atexit::run_exitfuncs(self);
+        self.flush_std_streams();
+        self.clear_modules();
+        self.run_finalizers();
+        self.flush_std_streams();
+        let _ = self.stdout().flush();
+        let _ = self.stderr().flush();
+    }
+
+    fn flush_std_streams(&self) {
+        for name in &["stdout", "stderr"] {
+            let result = sysmodule::get_stream(self, name).and_then(|stream| match stream {
+                Some(stream) => self.call_method(&stream, "flush", vec![]).map(|_| ()),
+                None => Ok(()),
+            });
+            if let Err(exc) = result {
+                // Errors flushing stderr have nowhere to go.
+                if *name == "stdout" {
+                    self.print_ignored_exception(None, &exc);
+                }
+            }
+        }
+    }
+
+    /// Empty the namespaces of the imported modules, `__main__` first, and
+    /// forget the modules. Functions and their globals keep each other
+    /// alive, this lets their objects be finalized. `builtins` and `sys` are
+    /// left for the finalizers to use.
+    fn clear_modules(&self) {
+        let modules = match self
+            .get_attribute(self.sys_module.clone(), "modules")
+            .and_then(|modules| PyDictRef::try_from_object(self, modules))
+        {
+            Ok(modules) => modules,
+            Err(_) => return,
+        };
+        let mut entries = modules.get_key_value_pairs();
+        entries.sort_by_key(|(name, _)| objstr::get_value(name) != "__main__");
+        for (name, module) in entries {
+            let name = objstr::get_value(&name);
+            if name == "builtins" || name == "sys" {
+                continue;
+            }
+            if let Some(dict) = &module.dict {
+                // Taken out first, as finalizers may look at the namespace.
+                let namespace = mem::replace(&mut *dict.entries_mut(), Default::default());
+                drop(namespace);
+                self.run_finalizers();
+            }
+            modules.del_item(&name);
+        }
+    }
+
+    fn finalize_object(&self, obj: PyObjectRef) {
+        if obj.payload_is::<PyGenerator>() {
+            if let Err(exc) = self.call_method(&obj, "close", vec![]) {
+                self.print_ignored_exception(Some(&obj), &exc);
+            }
+            return;
+        }
+        let del = match objtype::class_get_attr(&obj.class(), "__del__") {
+            Some(del) => del,
+            None => return,
+        };
+        let result = self
+            .call_get_descriptor(del.clone(), obj)
+            .and_then(|method| self.invoke(method, vec![]));
+        if let Err(exc) = result {
+            self.print_ignored_exception(Some(&del), &exc);
+        }
+    }
+
+    /// Report an exception that can't be propagated, for example one raised
+    /// by `__del__`, in the way CPython's `PyErr_WriteUnraisable` does.
+    pub fn print_ignored_exception(&self, origin: Option<&PyObjectRef>, exc: &PyObjectRef) {
+        let origin = origin
+            .and_then(|obj| self.to_repr(obj).ok())
+            .map(|repr| repr.value.clone())
+            .unwrap_or_else(|| "<unknown>".to_string());
+        let mut report = format!("Exception ignored in: {}\n", origin).into_bytes();
+        let _ = exceptions::write_exception(&mut report, self, exc);
+        sysmodule::write_stderr(self, &String::from_utf8_lossy(&report));
+    }
+
+    /// Release the GIL while running `f`, so that other threads can execute
+    /// python code in the meantime. `f` must not touch any python object.
+    pub fn allow_threads<T, F>(&self, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        // The state may be gone by the time `f` returns, the lock isn't.
+        let gil = self.gil.clone();
+        gil.release();
+        let result = f();
+        gil.acquire();
         result
     }
 
+    /// Called between instructions to give other threads a chance to run.
+    /// Waiting threads request an eval break, so the instructions are only
+    /// counted while there may be any.
+    pub fn check_thread_switch(&self) {
+        let ticks = self.switch_ticks.get() + 1;
+        if ticks < SWITCH_INTERVAL {
+            self.switch_ticks.set(ticks);
+            return;
+        }
+        self.switch_ticks.set(0);
+        if self.gil.has_waiters() {
+            self.allow_threads(|| ());
+        }
+    }
+
+    /// Whether frames need to report each line they start executing.
+    pub fn is_tracing(&self) -> bool {
+        self.debugger.is_enabled() || self.profiler.borrow().is_some()
+    }
+
+    /// Enable the debugger and stop at the next line that gets executed.
+    pub fn set_trace(&self) {
+        self.debugger.set_trace();
+        self.update_tracing();
+    }
+
+    /// Replace the profiler of this thread, returning the previous one.
+    pub fn set_profiler(&self, profiler: Option<Rc<Profiler>>) -> Option<Rc<Profiler>> {
+        let previous = self.profiler.replace(profiler);
+        self.update_tracing();
+        previous
+    }
+
+    /// Keep the frames looking for lines to trace exactly while the debugger
+    /// or a profiler is set.
+    fn update_tracing(&self) {
+        let tracing = self.debugger.may_trace() || self.profiler.borrow().is_some();
+        if tracing != self.traced.replace(tracing) {
+            if tracing {
+                request_eval_break();
+            } else {
+                release_eval_break();
+            }
+        }
+    }
+
+    /// Called by a frame when it starts executing a new line.
+    pub fn trace_line(&self, frame: &Frame, line: usize) -> PyResult<()> {
+        if let Some(profiler) = self.profiler.borrow().as_ref() {
+            profiler.line(frame, line);
+        }
+        if self.debugger.is_enabled() {
+            // The debugger may be turned off by the commands it reads.
+            let result = self.debugger.line_event(self, line);
+            self.update_tracing();
+            result?;
+        }
+        Ok(())
+    }
+
     pub fn current_frame(&self) -> Option<Ref<FrameRef>> {
         let frames = self.frames.borrow();
         if frames.is_empty() {
@@ -133,7 +518,7 @@ impl VirtualMachine {
     }
 
     /// Create a new python int object.
-    pub fn new_int<T: Into<BigInt>>(&self, i: T) -> PyObjectRef {
+    pub fn new_int<T: Into<BigInt> + ToPrimitive>(&self, i: T) -> PyObjectRef {
         self.ctx.new_int(i)
     }
 
@@ -183,11 +568,37 @@ impl VirtualMachine {
         ))
     }
 
+    pub fn new_runtime_error(&self, msg: String) -> PyObjectRef {
+        let runtime_error = self.ctx.exceptions.runtime_error.clone();
+        self.new_exception(runtime_error, msg)
+    }
+
+    pub fn new_system_error(&self, msg: String) -> PyObjectRef {
+        let system_error = self.ctx.exceptions.system_error.clone();
+        self.new_exception(system_error, msg)
+    }
+
     pub fn new_os_error(&self, msg: String) -> PyObjectRef {
         let os_error = self.ctx.exceptions.os_error.clone();
         self.new_exception(os_error, msg)
     }
 
+    /// Create an OSError, or the subclass matching the error kind, from an
+    /// I/O error, with its `errno` and `strerror` filled in.
+    pub fn new_io_error(&self, err: &io::Error) -> PyObjectRef {
+        let exc_type = exceptions::io_error_type(self, err);
+        let message = err.to_string();
+        let args = match err.raw_os_error() {
+            Some(errno) => {
+                let suffix = format!(" (os error {})", errno);
+                let strerror = message.trim_end_matches(suffix.as_str()).to_string();
+                vec![self.new_int(errno), self.new_str(strerror)]
+            }
+            None => vec![self.new_str(message)],
+        };
+        exceptions::new_os_error(self, exc_type, args)
+    }
+
     /// Create a new python ValueError object. Useful for raising errors from
     /// python functions implemented in rust.
     pub fn new_value_error(&self, msg: String) -> PyObjectRef {
@@ -195,9 +606,9 @@ impl VirtualMachine {
         self.new_exception(value_error, msg)
     }
 
-    pub fn new_key_error(&self, msg: String) -> PyObjectRef {
+    pub fn new_key_error(&self, key: PyObjectRef) -> PyObjectRef {
         let key_error = self.ctx.exceptions.key_error.clone();
-        self.new_exception(key_error, msg)
+        exceptions::new_builtin_exception(self, key_error, vec![key])
     }
 
     pub fn new_index_error(&self, msg: String) -> PyObjectRef {
@@ -348,6 +759,25 @@ impl VirtualMachine {
         self.call_method(&func_ref, "__call__", args)
     }
 
+    /// Like `invoke`, converting the result to `R` the way the arguments of a
+    /// native function are, e.g. `vm.invoke_as::<Vec<String>, _>(f, args)`.
+    pub fn invoke_as<R, T>(&self, func_ref: PyObjectRef, args: T) -> PyResult<R>
+    where
+        R: TryFromObject,
+        T: Into<PyFuncArgs>,
+    {
+        let result = self.invoke(func_ref, args)?;
+        R::try_from_object(self, result)
+    }
+
+    /// Whether `invoke` can call `obj`.
+    pub fn is_callable(&self, obj: &PyObjectRef) -> bool {
+        obj.payload_is::<PyFunction>()
+            || obj.payload_is::<PyMethod>()
+            || obj.payload_is::<PyBuiltinFunction>()
+            || objtype::class_has_attr(&obj.class(), "__call__")
+    }
+
     fn invoke_python_function(
         &self,
         code: &PyCodeRef,
@@ -830,6 +1260,17 @@ impl VirtualMachine {
         })
     }
 
+    /// Call the `__hash__` of `obj`, which classes set to `None` when their
+    /// instances can't be hashed.
+    pub fn _hash(&self, obj: &PyObjectRef) -> PyResult {
+        match objtype::class_get_attr(&obj.class(), "__hash__") {
+            Some(ref hash) if !hash.is(&self.get_none()) => {
+                self.call_method(obj, "__hash__", vec![])
+            }
+            _ => Err(self.new_type_error(format!("unhashable type: '{}'", obj.class().name))),
+        }
+    }
+
     pub fn _eq(&self, a: PyObjectRef, b: PyObjectRef) -> PyResult {
         self.call_or_reflection(a, b, "__eq__", "__eq__", |vm, a, b| {
             Ok(vm.new_bool(a.is(&b)))
@@ -874,8 +1315,22 @@ impl Default for VirtualMachine {
     }
 }
 
+impl Drop for VirtualMachine {
+    /// Shut the other threads out: the ones still running python code on
+    /// this VM stop when they next wait for the GIL. `finalize` waits for
+    /// the non-daemon ones to end first.
+    fn drop(&mut self) {
+        if self.traced.get() {
+            release_eval_break();
+        }
+        if let StateRef::Owned(state) = &self.state {
+            state.gil.finalize();
+        }
+    }
+}
+
 lazy_static! {
-    static ref REPR_GUARDS: Mutex<HashSet<usize>> = { Mutex::new(HashSet::new()) };
+    pub(crate) static ref REPR_GUARDS: Mutex<HashSet<usize>> = { Mutex::new(HashSet::new()) };
 }
 
 pub struct ReprGuard {
@@ -885,7 +1340,10 @@ pub struct ReprGuard {
 /// A guard to protect repr methods from recursion into itself,
 impl ReprGuard {
     fn get_guards<'a>() -> MutexGuard<'a, HashSet<usize>> {
-        REPR_GUARDS.lock().expect("ReprGuard lock poisoned")
+        globals()
+            .repr_guards
+            .lock()
+            .expect("ReprGuard lock poisoned")
     }
 
     /// Returns None if the guard against 'obj' is still held otherwise returns the guard. The guard
@@ -912,10 +1370,36 @@ impl Drop for ReprGuard {
 
 #[cfg(test)]
 mod tests {
+    use std::rc::Rc;
+    use std::thread;
+    use std::time::Duration;
+
     use super::VirtualMachine;
+    use crate::compile;
+    use crate::frame::Scope;
+    use crate::obj::objlist::PyListRef;
     use crate::obj::{objint, objstr};
+    use crate::profiler::Profiler;
+    use crate::pyobject::{DictProtocol, TryFromObject};
     use num_bigint::ToBigInt;
 
+    fn run(vm: &VirtualMachine, scope: &Scope, source: &str) {
+        let code =
+            compile::compile(vm, source, &compile::Mode::Exec, "<test>".to_string()).unwrap();
+        vm.run_code_obj(code, scope.clone()).unwrap();
+    }
+
+    /// Python source starting a thread which keeps appending to `ticks`.
+    const TICKER: &str = "import _thread, time
+ticks = []
+def tick():
+    while True:
+        ticks.append(1)
+        time.sleep(0.001)
+_thread.start_new_thread(tick, ())
+time.sleep(0.02)
+";
+
     #[test]
     fn test_add_py_integers() {
         let vm = VirtualMachine::new();
@@ -935,4 +1419,45 @@ mod tests {
         let value = objstr::get_value(&res);
         assert_eq!(value, String::from("Hello Hello Hello Hello "))
     }
+
+    #[test]
+    fn test_profiler_requests_eval_break() {
+        let vm = VirtualMachine::new();
+        vm.set_profiler(Some(Rc::new(Profiler::new())));
+        assert!(vm.traced.get());
+        assert!(super::eval_break_requested());
+        vm.set_profiler(None);
+        assert!(!vm.traced.get());
+    }
+
+    #[test]
+    fn test_drop_with_running_thread() {
+        let vm = VirtualMachine::new();
+        let scope = vm.ctx.new_scope();
+        run(&vm, &scope, TICKER);
+        drop(scope);
+        drop(vm);
+        // The thread wakes up from its sleep, and must not touch the VM.
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_move_with_running_thread() {
+        let vm = VirtualMachine::new();
+        let scope = vm.ctx.new_scope();
+        run(&vm, &scope, TICKER);
+        let ticks = scope.globals.get_item("ticks").unwrap();
+        let ticks = PyListRef::try_from_object(&vm, ticks).unwrap();
+        assert!(!ticks.elements.borrow().is_empty());
+
+        let vm = Box::new(vm);
+        let count = ticks.elements.borrow().len();
+        run(&vm, &scope, "time.sleep(0.02)\n");
+        // The state the thread runs on stayed where it was.
+        assert!(ticks.elements.borrow().len() > count);
+        drop(ticks);
+        drop(scope);
+        drop(vm);
+        thread::sleep(Duration::from_millis(20));
+    }
 }
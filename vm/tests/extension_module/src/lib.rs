@@ -0,0 +1,30 @@
+//! The extension module `vm/tests/extension_module/tests/import.rs` builds
+//! and imports.
+
+#[macro_use]
+extern crate rustpython_vm;
+
+use rustpython_vm::pyobject::{PyContext, PyObjectRef};
+use rustpython_vm::VirtualMachine;
+
+fn double(value: i32, _vm: &VirtualMachine) -> i32 {
+    value * 2
+}
+
+/// Drop the last reference to `value` in the extension's copy of the crate.
+fn release(_value: PyObjectRef, _vm: &VirtualMachine) {}
+
+fn fail(_vm: &VirtualMachine) {
+    panic!("failing on purpose");
+}
+
+fn make_module(ctx: &PyContext) -> PyObjectRef {
+    py_module!(ctx, "answers", {
+        "everything" => ctx.new_int(42),
+        "double" => ctx.new_rustfunc(double),
+        "fail" => ctx.new_rustfunc(fail),
+        "release" => ctx.new_rustfunc(release),
+    })
+}
+
+py_extension_module!(make_module);
@@ -0,0 +1,60 @@
+//! Import the extension module of this crate, which cargo builds along
+//! with the test, against the same build of rustpython_vm.
+#![cfg(unix)]
+
+use std::env;
+use std::fs;
+
+use rustpython_vm::compile;
+use rustpython_vm::pyobject::{DictProtocol, TypeProtocol};
+use rustpython_vm::VirtualMachine;
+
+#[test]
+fn test_import_extension_module() {
+    // The test and the library are both in target/<profile>/deps.
+    let deps_dir = env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    let module_dir = env::temp_dir().join(format!("rustpython_extension_{}", std::process::id()));
+    fs::create_dir_all(&module_dir).unwrap();
+    fs::copy(
+        deps_dir.join("librustpython_extension_module_test.so"),
+        module_dir.join("answers.so"),
+    )
+    .unwrap();
+
+    let vm = VirtualMachine::new();
+    let sys_path = vm.get_attribute(vm.sys_module.clone(), "path").unwrap();
+    vm.call_method(
+        &sys_path,
+        "append",
+        vec![vm.new_str(module_dir.to_string_lossy().into_owned())],
+    )
+    .unwrap();
+    let answers = vm.import("answers");
+    fs::remove_dir_all(&module_dir).unwrap();
+    let answers = answers.unwrap();
+    let scope = vm.ctx.new_scope();
+    scope.globals.set_item(&vm.ctx, "answers", answers);
+    let run = |source: &str, mode: compile::Mode| {
+        let source = format!("{}\n", source);
+        let code = compile::compile(&vm, &source, &mode, "<test>".to_string())
+            .expect("the test code compiles");
+        match vm.run_code_obj(code, scope.clone()) {
+            Ok(value) => vm.to_pystr(&value).unwrap(),
+            Err(exc) => format!("{}: {}", exc.class().name, vm.to_pystr(&exc).unwrap()),
+        }
+    };
+    let eval = |source: &str| run(source, compile::Mode::Eval);
+    assert_eq!(eval("answers.everything"), "42");
+    assert_eq!(eval("answers.double(21)"), "42");
+    assert!(eval("answers.fail()")
+        .starts_with("SystemError: native code panicked at 'failing on purpose'"));
+    // The module is made once, and the objects it made work as any other.
+    assert_eq!(eval("__import__('answers') is answers"), "True");
+    assert_eq!(eval("[answers.double(x) for x in range(3)]"), "[0, 2, 4]");
+
+    // The extension's copy of the crate queues finalizers where the VM runs
+    // them.
+    let source = "class Probe:\n    def __del__(self):\n        finalized.append(True)\nfinalized = []\nanswers.release(Probe())";
+    run(source, compile::Mode::Exec);
+    assert_eq!(eval("finalized"), "[True]");
+}
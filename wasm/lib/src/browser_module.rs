@@ -13,9 +13,9 @@ use rustpython_vm::obj::{
     objtype::PyClassRef,
 };
 use rustpython_vm::pyobject::{
-    PyContext, PyObject, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
+    PyClassImpl, PyContext, PyObject, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
 };
-use rustpython_vm::VirtualMachine;
+use rustpython_vm::{pyclass, pymethods, VirtualMachine};
 
 use crate::{convert, vm_class::AccessibleVM, wasm_builtins::window};
 
@@ -158,6 +158,7 @@ fn browser_cancel_animation_frame(vm: &VirtualMachine, args: PyFuncArgs) -> PyRe
     Ok(vm.get_none())
 }
 
+#[pyclass(name = "Promise")]
 #[derive(Debug)]
 pub struct PyPromise {
     value: Promise,
@@ -170,6 +171,7 @@ impl PyValue for PyPromise {
     }
 }
 
+#[pymethods]
 impl PyPromise {
     pub fn new(value: Promise) -> PyPromise {
         PyPromise { value }
@@ -184,6 +186,7 @@ impl PyPromise {
         self.value.clone()
     }
 
+    #[pymethod]
     fn then(
         zelf: PyPromiseRef,
         on_fulfill: PyRef<PyFunction>,
@@ -217,6 +220,7 @@ impl PyPromise {
         PyPromise::from_future(ret_future).into_ref(vm)
     }
 
+    #[pymethod]
     fn catch(
         zelf: PyPromiseRef,
         on_reject: PyRef<PyFunction>,
@@ -240,6 +244,7 @@ impl PyPromise {
     }
 }
 
+#[pyclass]
 #[derive(Debug)]
 struct Document {
     doc: web_sys::Document,
@@ -252,7 +257,9 @@ impl PyValue for Document {
     }
 }
 
+#[pymethods]
 impl Document {
+    #[pymethod]
     fn query(zelf: DocumentRef, query: PyStringRef, vm: &VirtualMachine) -> PyResult {
         let elem = zelf
             .doc
@@ -266,6 +273,7 @@ impl Document {
     }
 }
 
+#[pyclass]
 #[derive(Debug)]
 struct Element {
     elem: web_sys::Element,
@@ -278,7 +286,9 @@ impl PyValue for Element {
     }
 }
 
+#[pymethods]
 impl Element {
+    #[pymethod]
     fn get_attr(
         zelf: ElementRef,
         attr: PyStringRef,
@@ -291,6 +301,7 @@ impl Element {
         }
     }
 
+    #[pymethod]
     fn set_attr(
         zelf: ElementRef,
         attr: PyStringRef,
@@ -349,14 +360,9 @@ fn browser_prompt(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
 }
 
 pub fn make_module(ctx: &PyContext) -> PyObjectRef {
-    let promise = py_class!(ctx, "Promise", ctx.object(), {
-        "then" => ctx.new_rustfunc(PyPromise::then),
-        "catch" => ctx.new_rustfunc(PyPromise::catch),
-    });
+    let promise = PyPromise::make_class(ctx);
 
-    let document_class = py_class!(ctx, "Document", ctx.object(), {
-        "query" => ctx.new_rustfunc(Document::query),
-    });
+    let document_class = Document::make_class(ctx);
 
     let document = PyObject::new(
         Document {
@@ -366,10 +372,7 @@ pub fn make_module(ctx: &PyContext) -> PyObjectRef {
         None,
     );
 
-    let element = py_class!(ctx, "Element", ctx.object(), {
-        "get_attr" => ctx.new_rustfunc(Element::get_attr),
-        "set_attr" => ctx.new_rustfunc(Element::set_attr),
-    });
+    let element = Element::make_class(ctx);
 
     py_module!(ctx, "browser", {
         "fetch" => ctx.new_rustfunc(browser_fetch),
@@ -60,8 +60,8 @@ const TS_CMT_START: &'static str = "/*";
 /// -   `vars?`: `{ [key: string]: any }`: Variables passed to the VM that can be
 ///     accessed in Python with the variable `js_vars`. Functions do work, and
 ///     receive the Python kwargs as the `this` argument.
-/// -   `stdout?`: `(out: string) => void`: A function to replace the native print
-///     function, by default `console.log`.
+/// -   `stdout?`: `(out: string) => void`: A function that gets what is written to
+///     `sys.stdout`, by default `console.log`.
 pub fn eval_py(source: String, options: Option<Object>) -> Result<JsValue, JsValue> {
     let options = options.unwrap_or_else(Object::new);
     let js_vars = {
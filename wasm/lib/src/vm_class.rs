@@ -1,5 +1,6 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::io::{self, Write};
 use std::rc::{Rc, Weak};
 
 use js_sys::{Object, Reflect, SyntaxError, TypeError};
@@ -7,8 +8,7 @@ use wasm_bindgen::prelude::*;
 
 use rustpython_vm::compile;
 use rustpython_vm::frame::{NameProtocol, Scope};
-use rustpython_vm::function::PyFuncArgs;
-use rustpython_vm::pyobject::{PyContext, PyObject, PyObjectPayload, PyObjectRef, PyResult};
+use rustpython_vm::pyobject::{PyContext, PyObject, PyObjectPayload, PyObjectRef};
 use rustpython_vm::VirtualMachine;
 
 use crate::browser_module::setup_browser_module;
@@ -219,32 +219,19 @@ impl WASMVirtualMachine {
             fn error() -> JsValue {
                 TypeError::new("Unknown stdout option, please pass a function or 'console'").into()
             }
-            let print_fn: Box<Fn(&VirtualMachine, PyFuncArgs) -> PyResult> =
-                if let Some(s) = stdout.as_string() {
-                    match s.as_str() {
-                        "console" => Box::new(wasm_builtins::builtin_print_console),
-                        _ => return Err(error()),
-                    }
-                } else if stdout.is_function() {
-                    let func = js_sys::Function::from(stdout);
-                    Box::new(move |vm: &VirtualMachine, args: PyFuncArgs| -> PyResult {
-                        func.call1(
-                            &JsValue::UNDEFINED,
-                            &wasm_builtins::format_print_args(vm, args)?.into(),
-                        )
-                        .map_err(|err| convert::js_to_py(vm, err))?;
-                        Ok(vm.get_none())
-                    })
-                } else if stdout.is_undefined() || stdout.is_null() {
-                    fn noop(vm: &VirtualMachine, _args: PyFuncArgs) -> PyResult {
-                        Ok(vm.get_none())
-                    }
-                    Box::new(noop)
-                } else {
-                    return Err(error());
-                };
-            let rustfunc = vm.ctx.new_rustfunc(print_fn);
-            vm.ctx.set_attr(&vm.builtins, "print", rustfunc);
+            let output: Box<dyn Write> = if let Some(s) = stdout.as_string() {
+                match s.as_str() {
+                    "console" => Box::new(wasm_builtins::ConsoleOutput::default()),
+                    _ => return Err(error()),
+                }
+            } else if stdout.is_function() {
+                Box::new(wasm_builtins::JsOutput(js_sys::Function::from(stdout)))
+            } else if stdout.is_undefined() || stdout.is_null() {
+                Box::new(io::sink())
+            } else {
+                return Err(error());
+            };
+            vm.stdout.replace(output);
             Ok(())
         })?
     }
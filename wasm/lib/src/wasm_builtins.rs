@@ -4,75 +4,65 @@
 //! desktop.
 //! Implements functions listed here: https://docs.python.org/3/library/builtins.html.
 
+use std::io::{self, Write};
+
 use js_sys::{self, Array};
+use wasm_bindgen::JsValue;
 use web_sys::{self, console};
 
-use rustpython_vm::function::PyFuncArgs;
-use rustpython_vm::obj::{objstr, objtype};
-use rustpython_vm::pyobject::{IdProtocol, PyObjectRef, PyResult, TypeProtocol};
-use rustpython_vm::VirtualMachine;
-
 pub(crate) fn window() -> web_sys::Window {
     web_sys::window().expect("Window to be available")
 }
 
-pub fn format_print_args(vm: &VirtualMachine, args: PyFuncArgs) -> Result<String, PyObjectRef> {
-    // Handle 'sep' kwarg:
-    let sep_arg = args
-        .get_optional_kwarg("sep")
-        .filter(|obj| !obj.is(&vm.get_none()));
-    if let Some(ref obj) = sep_arg {
-        if !objtype::isinstance(obj, &vm.ctx.str_type()) {
-            return Err(vm.new_type_error(format!(
-                "sep must be None or a string, not {}",
-                obj.class().name
-            )));
-        }
+/// Passes what is written to it to a javascript function, as a string.
+pub struct JsOutput(pub js_sys::Function);
+
+impl Write for JsOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0
+            .call1(&JsValue::UNDEFINED, &text.as_ref().into())
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))?;
+        Ok(buf.len())
     }
-    let sep_str = sep_arg.as_ref().map(|obj| objstr::borrow_value(obj));
 
-    // Handle 'end' kwarg:
-    let end_arg = args
-        .get_optional_kwarg("end")
-        .filter(|obj| !obj.is(&vm.get_none()));
-    if let Some(ref obj) = end_arg {
-        if !objtype::isinstance(obj, &vm.ctx.str_type()) {
-            return Err(vm.new_type_error(format!(
-                "end must be None or a string, not {}",
-                obj.class().name
-            )));
-        }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
-    let end_str = end_arg.as_ref().map(|obj| objstr::borrow_value(obj));
+}
 
-    // No need to handle 'flush' kwarg, irrelevant when writing to String
+/// Logs what is written to it to the browser console, a line at a time.
+#[derive(Default)]
+pub struct ConsoleOutput {
+    line: Vec<u8>,
+}
 
-    let mut output = String::new();
-    let mut first = true;
-    for a in args.args {
-        if first {
-            first = false;
-        } else if let Some(ref sep_str) = sep_str {
-            output.push_str(sep_str);
-        } else {
-            output.push(' ');
-        }
-        output.push_str(&vm.to_pystr(&a)?);
+impl ConsoleOutput {
+    fn log_line(&mut self) {
+        let line = String::from_utf8_lossy(&self.line).into_owned();
+        let arr = Array::new();
+        arr.push(&line.into());
+        console::log(&arr);
+        self.line.clear();
     }
+}
 
-    if let Some(end_str) = end_str {
-        output.push_str(end_str.as_ref())
-    } else {
-        output.push('\n');
+impl Write for ConsoleOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            if byte == b'\n' {
+                self.log_line();
+            } else {
+                self.line.push(byte);
+            }
+        }
+        Ok(buf.len())
     }
-    Ok(output)
-}
 
-pub fn builtin_print_console(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    let arr = Array::new();
-    for arg in args.args {
-        arr.push(&vm.to_pystr(&arg)?.into());
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.log_line();
+        }
+        Ok(())
     }
-    console::log(&arr);
-    Ok(vm.get_none())
 }